@@ -26,10 +26,7 @@ async fn main() -> Result<(), anyhow::Error> {
 		let hash = methods.chain_get_block_hash(Some(NumberOrHex::Number(block_number))).await?.unwrap();
 		let runtime_version = methods.state_get_runtime_version(Some(hash)).await?;
 		let spec_version = runtime_version.spec_version;
-		let block = methods.chain_get_block(Some(hash)).await?.unwrap();
-
-		// Mangle the extrinsics into the correct byte format from the RPC call which returns a Vec:
-		let ext_bytes = make_extrinsic_bytes(block.block.extrinsics);
+		let block: serde_json::Value = rpc_client.request("chain_getBlock", rpc_params![hash]).await?;
 
 		if !decoder.has_version(spec_version) {
 			// download the relevant metadata bytes, since the decoder doesn't have it yet.
@@ -39,22 +36,11 @@ async fn main() -> Result<(), anyhow::Error> {
 		}
 
 		println!("# Decoding exts for block {block_number}");
-		let decoded_exts = decoder.decode_extrinsics(spec_version, &ext_bytes)?;
+		let decoded_exts = decoder.decode_rpc_block(spec_version, &block)?;
 
-		println!("{decoded_exts}");
+		println!("{}", serde_json::to_string_pretty(&decoded_exts)?);
 
 		// We'll decode every 10_000th block, just to make sure we span some spec versions.
 		block_number += 10_000;
 	}
 }
-
-// A hack because we get the exts back as a vec of bytes and
-// desub wants the whole block body as bytes.
-fn make_extrinsic_bytes(exts: Vec<Bytes>) -> Vec<u8> {
-	use subxt::ext::codec::Encode;
-	use subxt::utils::Encoded;
-	// The inner `Bytes` are already encoded and contain the compact length etc,
-	// so don't encode them again by wrapping them in `Encoded`.
-	let e: Vec<Encoded> = exts.into_iter().map(|ext| Encoded(ext.0)).collect();
-	e.encode()
-}
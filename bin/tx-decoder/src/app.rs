@@ -17,6 +17,7 @@
 use crate::queries::*;
 
 use desub::{runtimes, Chain, Decoder, SpecVersion};
+use desub_current::Metadata;
 
 use anyhow::{Context, Error};
 use argh::FromArgs;
@@ -57,6 +58,15 @@ pub struct App {
 	#[argh(option, short = 'u')]
 	/// decode all blocks up to a spec version.
 	to: Option<i32>,
+	#[argh(option, default = "0")]
+	/// skip this many extrinsics at the start of each block.
+	skip: usize,
+	#[argh(option)]
+	/// decode at most this many extrinsics from each block (after any skipped).
+	count: Option<usize>,
+	#[argh(switch)]
+	/// list the signature of every call in the spec version given by --spec, rather than decoding anything.
+	list_calls: bool,
 	#[argh(switch, short = 'v')]
 	/// extra information about the programs execution.
 	pub verbose: bool,
@@ -112,7 +122,7 @@ impl<'a> AppState<'a> {
 				version
 			};
 			let decoder = self.decoder.read();
-			if Self::decode(&decoder, block, version.try_into()?, errors).is_err() {
+			if Self::decode(self.app, &decoder, block, version.try_into()?, errors).is_err() {
 				error_count += 1;
 			}
 			len += 1;
@@ -123,9 +133,23 @@ impl<'a> AppState<'a> {
 		Ok((error_count, len))
 	}
 
-	fn decode(decoder: &Decoder, block: BlockModel, spec: SpecVersion, errors: &mut Vec<String>) -> Result<(), Error> {
+	fn decode(
+		app: &App,
+		decoder: &Decoder,
+		block: BlockModel,
+		spec: SpecVersion,
+		errors: &mut Vec<String>,
+	) -> Result<(), Error> {
 		log::debug!("Decoding block {}, spec_version {}, ext length {}", block.block_num, spec, block.ext.len());
-		match decoder.decode_extrinsics(spec, &block.ext) {
+		let decoded = if app.skip == 0 && app.count.is_none() {
+			decoder.decode_extrinsics(spec, &block.ext)
+		} else {
+			let count = app.count.unwrap_or(usize::MAX);
+			decoder
+				.decode_extrinsics_window(spec, &block.ext, app.skip, count)
+				.and_then(|ext| serde_json::to_value(ext).map_err(Into::into))
+		};
+		match decoded {
 			e @ Err(_) => {
 				let e = e.context(format!("Failed to decode block {}", block.block_num)).unwrap_err();
 				errors.push(format!("{}", e));
@@ -186,6 +210,19 @@ pub async fn app(app: App) -> Result<(), Error> {
 
 	let state = AppState::new(&app, &decoder, &pool, pb.as_ref());
 
+	if app.list_calls {
+		let spec = app.spec.context("--list-calls needs a spec version to be given with --spec")?;
+		state.register_metadata(&mut conn, spec.try_into()?).await?;
+		let metadata = decoder
+			.read()
+			.metadata(spec.try_into()?)
+			.with_context(|| format!("Spec version {} does not have V14+ metadata to list the calls of", spec))?;
+		for signature in call_signatures(&metadata) {
+			println!("{}", signature);
+		}
+		return Ok(());
+	}
+
 	if let Some(block) = &app.block {
 		let version = version_by_block(&mut conn, *block).await?;
 		let previous = state.register_metadata(&mut conn, version.try_into()?).await?;
@@ -195,7 +232,7 @@ pub async fn app(app: App) -> Result<(), Error> {
 		} else {
 			version as u32
 		};
-		AppState::decode(&decoder.read(), block, version, &mut errors)?;
+		AppState::decode(&app, &decoder.read(), block, version, &mut errors)?;
 	}
 
 	if let Some(spec) = app.spec {
@@ -254,6 +291,27 @@ fn construct_progress_bar(count: usize) -> ProgressBar {
 	bar
 }
 
+/// The signature of every call in the metadata, in the form `Pallet.call(arg: Type, ...)`.
+fn call_signatures(metadata: &Metadata) -> Vec<String> {
+	metadata
+		.calls()
+		.map(|(pallet, call)| {
+			let args = call
+				.fields
+				.iter()
+				.map(|field| {
+					let ty = metadata.type_name(field.ty.id).unwrap_or_else(|| "?".to_string());
+					match &field.name {
+						Some(name) => format!("{}: {}", name, ty),
+						None => ty,
+					}
+				})
+				.collect::<Vec<_>>();
+			format!("{}.{}({})", pallet, call.name, args.join(", "))
+		})
+		.collect()
+}
+
 fn get_upgrade_block(chain: &Chain, version: u32) -> Option<u64> {
 	match chain {
 		Chain::Kusama => runtimes::kusama_upgrade_block(&version),
@@ -262,3 +320,19 @@ fn get_upgrade_block(chain: &Chain, version: u32) -> Option<u64> {
 		_ => None,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lists_call_signatures() {
+		let metadata =
+			Metadata::from_bytes(include_bytes!("../../../desub-current/tests/data/v14_metadata_polkadot.scale"))
+				.unwrap();
+		let signatures = call_signatures(&metadata);
+		assert_eq!(signatures[0], "System.fill_block(ratio: Perbill)");
+		assert!(signatures
+			.contains(&"Balances.transfer(dest: MultiAddress<AccountId32, ()>, value: Compact<u128>)".to_string()));
+	}
+}
@@ -2,19 +2,34 @@ use clap::Parser;
 use desub_current::{decoder, Metadata};
 use std::path::PathBuf;
 
+mod rpc;
+
 #[derive(Debug, Parser)]
+#[command(allow_missing_positional = true)]
 struct Opts {
-	/// SCALE encoded V14 metadata blob
-	metadata: PathBuf,
+	/// SCALE encoded V14 metadata blob. Not needed if `--url` is given.
+	#[arg(required_unless_present = "url")]
+	metadata: Option<PathBuf>,
 	/// Extrinsic hash in the form 0x1a2b3c
 	extrinsic: String,
+	/// HTTP RPC URL of a node to fetch the metadata from, instead of reading it from disk
+	#[arg(long)]
+	url: Option<String>,
+	/// Block hash to fetch the metadata at. Defaults to the latest block.
+	#[arg(long, requires = "url")]
+	at_block: Option<String>,
 }
 
 fn main() -> Result<(), anyhow::Error> {
 	let opts = Opts::parse();
 	pretty_env_logger::init();
 
-	let metadata_bytes = std::fs::read(opts.metadata)?;
+	let metadata_bytes = match (opts.metadata, opts.url) {
+		(Some(_), Some(_)) => anyhow::bail!("Provide either a metadata file or `--url`, not both"),
+		(Some(path), None) => std::fs::read(path)?,
+		(None, Some(url)) => rpc::fetch_metadata(&url, opts.at_block.as_deref())?,
+		(None, None) => anyhow::bail!("Either a metadata file or `--url` must be provided"),
+	};
 
 	let meta = Metadata::from_bytes(&metadata_bytes)?;
 
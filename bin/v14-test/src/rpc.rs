@@ -0,0 +1,30 @@
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Fetch the SCALE encoded metadata from the node at `url` over HTTP, optionally at the given block hash.
+/// If no block hash is provided, the metadata at the latest block is returned.
+pub fn fetch_metadata(url: &str, at_block: Option<&str>) -> Result<Vec<u8>, anyhow::Error> {
+	let params = match at_block {
+		Some(hash) => json!([hash]),
+		None => json!([]),
+	};
+	let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "state_getMetadata", "params": params });
+
+	let response: Value = ureq::post(url)
+		.send_json(request)
+		.with_context(|| format!("Cannot request metadata from {}", url))?
+		.into_json()
+		.context("Cannot parse RPC response as JSON")?;
+
+	if let Some(err) = response.get("error") {
+		anyhow::bail!("RPC call to state_getMetadata failed: {}", err);
+	}
+
+	let metadata_hex = response
+		.get("result")
+		.and_then(Value::as_str)
+		.context("RPC response does not contain a metadata hex string")?;
+	let metadata_hex = metadata_hex.strip_prefix("0x").unwrap_or(metadata_hex);
+
+	hex::decode(metadata_hex).context("Cannot decode metadata hex string into bytes")
+}
@@ -0,0 +1,57 @@
+use std::process::Command;
+
+const METADATA_PATH: &str =
+	concat!(env!("CARGO_MANIFEST_DIR"), "/../../desub-current/tests/data/v14_metadata_polkadot.scale");
+
+/// `Balances.transfer` signed by Alice, as produced against the metadata at `METADATA_PATH`.
+const TRANSFER_EXTRINSIC: &str = "0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0";
+
+fn v14_test() -> Command {
+	Command::new(env!("CARGO_BIN_EXE_v14-test"))
+}
+
+#[test]
+fn decodes_with_metadata_file() {
+	let output = v14_test().arg(METADATA_PATH).arg(TRANSFER_EXTRINSIC).output().unwrap();
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	assert!(stdout.contains("Extrinsic version: 4"));
+	assert!(stdout.contains("Balances"));
+	assert!(stdout.contains("transfer"));
+}
+
+#[test]
+fn requires_metadata_file_or_url() {
+	let output = v14_test().arg(TRANSFER_EXTRINSIC).output().unwrap();
+	assert!(!output.status.success());
+}
+
+#[test]
+fn at_block_requires_url() {
+	let output = v14_test().arg(METADATA_PATH).arg(TRANSFER_EXTRINSIC).args(["--at-block", "0x00"]).output().unwrap();
+	assert!(!output.status.success());
+}
+
+#[test]
+fn rejects_metadata_file_and_url() {
+	let output = v14_test()
+		.arg(METADATA_PATH)
+		.arg(TRANSFER_EXTRINSIC)
+		.args(["--url", "http://localhost:9933"])
+		.output()
+		.unwrap();
+	assert!(!output.status.success());
+}
+
+/// Fetches the latest metadata from a live node and decodes an unsigned `System.remark(b"")`.
+/// This needs the network, so it only runs when asked for with `cargo test -- --ignored`. Set `V14_TEST_RPC_URL`
+/// to point it at a node other than the public Polkadot one.
+#[test]
+#[ignore = "fetches metadata from a live node"]
+fn decodes_with_metadata_from_rpc() {
+	let url = std::env::var("V14_TEST_RPC_URL").unwrap_or_else(|_| "https://rpc.polkadot.io".to_string());
+	let output = v14_test().args(["--url", &url, "0x1004000000"]).output().unwrap();
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	assert!(String::from_utf8_lossy(&output.stdout).contains("remark"));
+}
@@ -0,0 +1,112 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Convert [`Value`]s to and from CBOR, a compact binary format, for instance to persist decoded
+//! extrinsics in an indexer.
+//!
+//! Values are written out with [`crate::value_serde`], so 256 bit integers survive the round trip,
+//! but the context of each value (normally a type ID) is not kept. As with JSON, variants are written
+//! out as a composite of their `name` and `values`, and read back in as such, and chars read back in
+//! as strings.
+
+use crate::value_serde;
+use scale_value::Value;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The error returned from [`from_cbor`].
+#[derive(Debug, thiserror::Error)]
+#[error("Couldn't read CBOR encoded value: {0}")]
+pub struct FromCborError(#[from] ciborium::de::Error<std::io::Error>);
+
+/// Encode a value as CBOR.
+///
+/// # Example
+///
+/// ```rust
+/// use desub_current::{cbor, Value};
+///
+/// let value = Value::named_composite([("amount", Value::u128(100))]);
+/// let bytes = cbor::to_cbor(&value);
+/// assert_eq!(cbor::from_cbor(&bytes).unwrap(), value);
+/// ```
+pub fn to_cbor<T: Clone>(value: &Value<T>) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	ciborium::into_writer(&SerializeValue(value), &mut bytes).expect("values can always be written to a Vec; qed");
+	bytes
+}
+
+/// Decode a value from CBOR written by [`to_cbor`].
+pub fn from_cbor(bytes: &[u8]) -> Result<Value<()>, FromCborError> {
+	let DeserializeValue(value) = ciborium::from_reader(bytes)?;
+	Ok(value)
+}
+
+struct SerializeValue<'a, T>(&'a Value<T>);
+
+impl<T: Clone> Serialize for SerializeValue<'_, T> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		value_serde::serialize(self.0, serializer)
+	}
+}
+
+struct DeserializeValue(Value<()>);
+
+impl<'de> Deserialize<'de> for DeserializeValue {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		value_serde::deserialize(deserializer).map(DeserializeValue)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use scale_value::Primitive;
+
+	#[test]
+	fn values_survive_cbor_round_trip() {
+		let mut u256 = [0u8; 32];
+		u256[31] = 0xff;
+		let value = Value::named_composite([
+			("dest", Value::from_bytes([1u8; 32])),
+			("value", Value::u128(u128::MAX)),
+			("delta", Value::i128(-5)),
+			("memo", Value::string("hello")),
+			("keep_alive", Value::bool(true)),
+			("wide", Value::primitive(Primitive::U256(u256))),
+			("empty", Value::unnamed_composite([])),
+		]);
+
+		let bytes = to_cbor(&value);
+		assert_eq!(from_cbor(&bytes).unwrap(), value);
+		// Smaller than the equivalent JSON:
+		assert!(bytes.len() < serde_json::to_vec(&value).unwrap().len());
+	}
+
+	#[test]
+	fn variants_read_back_as_name_and_values() {
+		let value = Value::unnamed_variant("Id", [Value::u128(1)]);
+		let expected = Value::named_composite([
+			("name", Value::string("Id")),
+			("values", Value::unnamed_composite([Value::u128(1)])),
+		]);
+		assert_eq!(from_cbor(&to_cbor(&value)).unwrap(), expected);
+	}
+
+	#[test]
+	fn invalid_cbor_is_an_error() {
+		assert!(from_cbor(&[0xff, 0x00]).is_err());
+	}
+}
@@ -0,0 +1,154 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::DecodeError;
+use crate::{Metadata, ValueExt};
+use scale_value::Value;
+use serde::Serialize;
+
+/// The details of an account, as stored in `System.Account`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AccountInfo {
+	/// The number of transactions this account has sent.
+	pub nonce: u128,
+	/// The number of other modules that currently depend on this account's existence.
+	pub consumers: u128,
+	/// The number of other modules that allow this account to exist.
+	pub providers: u128,
+	/// The number of modules that allow this account to exist for their own purposes only.
+	pub sufficients: u128,
+	/// The balances of the account.
+	pub data: AccountData,
+}
+
+/// The balances of an account, from the `data` field of `AccountInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AccountData {
+	/// The balance which can be spent.
+	pub free: u128,
+	/// The balance which has been set aside, and can't be spent.
+	pub reserved: u128,
+	/// The balance which can't be transferred away. How this is stored depends on the runtime.
+	pub frozen: FrozenBalance,
+}
+
+/// The frozen part of an account's balance. Older runtimes store two amounts here, and newer ones just one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FrozenBalance {
+	/// The `misc_frozen` and `fee_frozen` fields of older runtimes.
+	Split { misc_frozen: u128, fee_frozen: u128 },
+	/// The single `frozen` field of newer runtimes.
+	Single(u128),
+}
+
+impl AccountInfo {
+	/// Read the details of an account from an already decoded `AccountInfo` value. The layout of the
+	/// balances is worked out from the names of the fields present. Returns `None` if the value doesn't
+	/// look like an `AccountInfo`.
+	pub fn from_value<T>(value: &Value<T>) -> Option<AccountInfo> {
+		let number = |path: &str| value.get(path)?.as_u128();
+
+		let frozen = match number("data.frozen") {
+			Some(frozen) => FrozenBalance::Single(frozen),
+			None => FrozenBalance::Split {
+				misc_frozen: number("data.misc_frozen")?,
+				fee_frozen: number("data.fee_frozen")?,
+			},
+		};
+
+		Some(AccountInfo {
+			nonce: number("nonce")?,
+			consumers: number("consumers")?,
+			providers: number("providers")?,
+			sufficients: number("sufficients")?,
+			data: AccountData { free: number("data.free")?, reserved: number("data.reserved")?, frozen },
+		})
+	}
+}
+
+/// Decode the SCALE encoded value of a `System.Account` storage entry into an [`AccountInfo`].
+pub fn decode_account_info(metadata: &Metadata, data: &mut &[u8]) -> Result<AccountInfo, DecodeError> {
+	let value = super::decode_storage_value_by_name(metadata, "System", "Account", data)?;
+	AccountInfo::from_value(&value).ok_or(DecodeError::UnrecognisedAccountInfo)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+
+	fn metadata() -> Metadata {
+		let bytes = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+		Metadata::from_bytes(bytes).expect("valid metadata")
+	}
+
+	#[test]
+	fn decodes_split_frozen_balances() {
+		// nonce, consumers, providers, sufficients, then free, reserved, misc_frozen and fee_frozen:
+		let bytes = (7u32, 1u32, 2u32, 0u32, 1000u128, 20u128, 30u128, 40u128).encode();
+		let cursor = &mut &*bytes;
+
+		let info = decode_account_info(&metadata(), cursor).unwrap();
+		assert!(cursor.is_empty());
+		assert_eq!(
+			info,
+			AccountInfo {
+				nonce: 7,
+				consumers: 1,
+				providers: 2,
+				sufficients: 0,
+				data: AccountData {
+					free: 1000,
+					reserved: 20,
+					frozen: FrozenBalance::Split { misc_frozen: 30, fee_frozen: 40 }
+				},
+			}
+		);
+	}
+
+	#[test]
+	fn reads_single_frozen_balance() {
+		let value = Value::named_composite([
+			("nonce", Value::u128(7)),
+			("consumers", Value::u128(1)),
+			("providers", Value::u128(2)),
+			("sufficients", Value::u128(0)),
+			(
+				"data",
+				Value::named_composite([
+					("free", Value::u128(1000)),
+					("reserved", Value::u128(20)),
+					("frozen", Value::u128(50)),
+					("flags", Value::u128(1 << 127)),
+				]),
+			),
+		]);
+
+		let info = AccountInfo::from_value(&value).unwrap();
+		assert_eq!(info.data, AccountData { free: 1000, reserved: 20, frozen: FrozenBalance::Single(50) });
+		assert_eq!((info.nonce, info.consumers, info.providers, info.sufficients), (7, 1, 2, 0));
+	}
+
+	#[test]
+	fn rejects_other_shapes() {
+		let value = Value::named_composite([("nonce", Value::u128(7)), ("data", Value::u128(1))]);
+		assert_eq!(AccountInfo::from_value(&value), None);
+		assert!(matches!(
+			decode_account_info(&metadata(), &mut &*7u32.encode()),
+			Err(DecodeError::DecodeValueError(_))
+		));
+	}
+}
@@ -0,0 +1,96 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of the `ApplyExtrinsicResult` handed back when applying (or dry running) an extrinsic.
+
+use super::{decode_value_by_id, DecodeError};
+use crate::{Metadata, TypeId};
+use parity_scale_codec::Decode;
+use scale_value::Value;
+use sp_runtime::transaction_validity::TransactionValidityError;
+
+/// The result of applying an extrinsic, as returned by [`decode_apply_extrinsic_result`]. The outer result says
+/// whether the extrinsic was valid, and the inner one whether the call it made succeeded, giving back the
+/// `DispatchError` it failed with otherwise.
+pub type ApplyExtrinsicResult = Result<Result<(), Value<TypeId>>, TransactionValidityError>;
+
+/// Decode the SCALE encoded `ApplyExtrinsicResult` (ie `Result<Result<(), DispatchError>, TransactionValidityError>`)
+/// returned by the `BlockBuilder_apply_extrinsic` runtime API, for instance when dry running a transaction. The
+/// `DispatchError` varies by runtime, so it's decoded as a [`Value`] according to the type found in the metadata.
+/// The names of a `DispatchError::Module` error can be found by passing it to [`super::resolve_module_errors`].
+pub fn decode_apply_extrinsic_result(
+	metadata: &Metadata,
+	data: &mut &[u8],
+) -> Result<ApplyExtrinsicResult, DecodeError> {
+	if u8::decode(data)? == 1 {
+		return Ok(Err(TransactionValidityError::decode(data)?));
+	}
+	match u8::decode(data)? {
+		0 => Ok(Ok(Ok(()))),
+		_ => {
+			let dispatch_error_ty = dispatch_error_type_id(metadata).ok_or(DecodeError::CannotFindDispatchErrorType)?;
+			Ok(Ok(Err(decode_value_by_id(metadata, dispatch_error_ty, data)?)))
+		}
+	}
+}
+
+/// Find the ID of the `sp_runtime` `DispatchError` type in the metadata, if it's there.
+fn dispatch_error_type_id(metadata: &Metadata) -> Option<TypeId> {
+	metadata.iter_types().find_map(|(id, ty)| (ty.path.segments == ["sp_runtime", "DispatchError"]).then_some(id))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::decoder::{resolve_module_errors, MODULE_ERROR_NAME_FIELD, MODULE_ERROR_PALLET_FIELD};
+	use crate::ValueExt;
+	use parity_scale_codec::Encode;
+	use sp_runtime::transaction_validity::InvalidTransaction;
+
+	fn metadata() -> Metadata {
+		Metadata::from_bytes(include_bytes!("../../tests/data/v14_metadata_polkadot.scale")).expect("valid metadata")
+	}
+
+	#[test]
+	fn decodes_successful_dry_run() {
+		let metadata = metadata();
+		let data = [0u8, 0];
+		let cursor = &mut &data[..];
+		assert!(matches!(decode_apply_extrinsic_result(&metadata, cursor), Ok(Ok(Ok(())))));
+		assert!(cursor.is_empty());
+	}
+
+	#[test]
+	fn decodes_failed_dry_runs() {
+		let metadata = metadata();
+
+		// The extrinsic was invalid, because it couldn't pay its fees:
+		let data = Err::<(), _>(TransactionValidityError::Invalid(InvalidTransaction::Payment)).encode();
+		let result = decode_apply_extrinsic_result(&metadata, &mut &*data).unwrap();
+		assert_eq!(result, Err(TransactionValidityError::Invalid(InvalidTransaction::Payment)));
+
+		// The call failed with `DispatchError::Module { index: 5, error: 2 }`, from the Balances pallet:
+		let data = [0u8, 1, 3, 5, 2];
+		let cursor = &mut &data[..];
+		let mut error = decode_apply_extrinsic_result(&metadata, cursor).unwrap().unwrap().unwrap_err();
+		assert!(cursor.is_empty());
+		assert_eq!(error.get(MODULE_ERROR_PALLET_FIELD), None);
+
+		resolve_module_errors(&metadata, &mut error);
+		assert_eq!(error.get(MODULE_ERROR_PALLET_FIELD).and_then(|v| v.as_str()), Some("Balances"));
+		assert_eq!(error.get(MODULE_ERROR_NAME_FIELD).and_then(|v| v.as_str()), Some("InsufficientBalance"));
+	}
+}
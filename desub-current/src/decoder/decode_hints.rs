@@ -0,0 +1,121 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::DecodeValueError;
+use crate::{Metadata, TypeId};
+use scale_info::{form::PortableForm, Field, TypeDef};
+use scale_value::{Composite, Value, ValueDef};
+
+/// Hints which change how the arguments of particular calls are decoded. Hand these to
+/// [`super::decode_extrinsic_with_hints`] or [`super::decode_call_data_with_hints`].
+///
+/// At the moment, the only hint is to leave certain fields of a call opaque. The bytes of these fields are
+/// skipped over without building up a value for them, and handed back as a byte sequence instead. This is useful
+/// for calls which carry large payloads that often aren't of interest, like the proofs given to
+/// `ParachainSystem.set_validation_data`.
+#[derive(Debug, Clone, Default)]
+pub struct DecodeHints {
+	opaque_fields: Vec<OpaqueFields>,
+}
+
+#[derive(Debug, Clone)]
+struct OpaqueFields {
+	pallet: String,
+	call: String,
+	fields: Vec<String>,
+}
+
+impl DecodeHints {
+	/// No hints; decode everything as normal.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Leave the given fields of some call opaque. A field name matches either an argument of the call, or a field of
+	/// an argument which is itself a struct with named fields.
+	pub fn opaque_fields<F: Into<String>>(
+		mut self,
+		pallet: impl Into<String>,
+		call: impl Into<String>,
+		fields: impl IntoIterator<Item = F>,
+	) -> Self {
+		self.opaque_fields.push(OpaqueFields {
+			pallet: pallet.into(),
+			call: call.into(),
+			fields: fields.into_iter().map(Into::into).collect(),
+		});
+		self
+	}
+
+	/// Leave the relay chain storage proof and the downward and horizontal messages given to the
+	/// `ParachainSystem.set_validation_data` inherent opaque, decoding only the rest of the inherent data.
+	pub fn parachain_validation_data() -> Self {
+		Self::new().opaque_fields(
+			"ParachainSystem",
+			"set_validation_data",
+			["relay_chain_state", "downward_messages", "horizontal_messages"],
+		)
+	}
+
+	/// The fields to leave opaque in the given call, if any.
+	pub(super) fn opaque_fields_of(&self, pallet: &str, call: &str) -> Option<&[String]> {
+		self.opaque_fields.iter().find(|o| o.pallet == pallet && o.call == call).map(|o| &*o.fields)
+	}
+}
+
+/// Decode a single call argument, leaving it (or any of its named fields) as bytes if its name is one of `opaque`.
+pub(super) fn decode_argument(
+	metadata: &Metadata,
+	field: &Field<PortableForm>,
+	opaque: &[String],
+	data: &mut &[u8],
+) -> Result<Value<TypeId>, DecodeValueError> {
+	let ty_id = field.ty.id;
+	if field.name.as_ref().is_some_and(|name| opaque.contains(name)) {
+		return skip_value(metadata, ty_id, data);
+	}
+
+	let fields = match metadata.types().resolve(ty_id).map(|ty| &ty.type_def) {
+		Some(TypeDef::Composite(composite))
+			if composite.fields.iter().any(|f| f.name.as_ref().is_some_and(|name| opaque.contains(name))) =>
+		{
+			&composite.fields
+		}
+		_ => return super::decode_value_by_id(metadata, ty_id, data),
+	};
+
+	let mut values = Vec::with_capacity(fields.len());
+	for field in fields {
+		let name = field.name.clone().unwrap_or_default();
+		let value = match opaque.contains(&name) {
+			true => skip_value(metadata, field.ty.id, data)?,
+			false => super::decode_value_by_id(metadata, field.ty.id, data)?,
+		};
+		values.push((name, value));
+	}
+	Ok(Value { value: ValueDef::Composite(Composite::Named(values)), context: ty_id })
+}
+
+/// Skip over a value of the given type, handing back the bytes that it was encoded to. These are given the type of a
+/// `Vec<u8>` as their context, since that's what they are now, rather than the type that they were encoded from.
+fn skip_value(metadata: &Metadata, ty_id: TypeId, data: &mut &[u8]) -> Result<Value<TypeId>, DecodeValueError> {
+	let start = *data;
+	super::decode_value::skip(data, ty_id, metadata.types())?;
+	let bytes = &start[..start.len() - data.len()];
+	let (vec_ty, u8_ty) = metadata.bytes_ty();
+	let bytes = bytes.iter().map(|&b| Value::u128(b as u128).map_context(|_| u8_ty));
+	Ok(Value { value: ValueDef::Composite(Composite::Unnamed(bytes.collect())), context: vec_ty })
+}
@@ -2,10 +2,11 @@ use super::Value;
 use crate::metadata::{Metadata, StorageLocation};
 use crate::{ScaleInfoTypeId, TypeId};
 use frame_metadata::v14::StorageEntryType as FrameStorageEntryType;
+use parity_scale_codec::{Compact, Decode};
 use serde::Serialize;
 use sp_core::twox_128;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
 
 /// This struct is capable of decoding SCALE encoded storage
 pub struct StorageDecoder {
@@ -33,33 +34,70 @@ pub enum StorageDecodeError {
 		hasher: frame_metadata::v14::StorageHasher,
 		decode_error: super::DecodeValueError,
 	},
+	#[error("Not enough bytes in the input data to decode key {key} ({hasher:?})")]
+	NotEnoughBytesForKey { key: usize, hasher: frame_metadata::v14::StorageHasher },
 	#[error("Couldn't find a storage entry corresponding to the prefix hash provided in the data")]
 	PrefixNotFound,
 	#[error("Couldn't find a storage entry corresponding to the name hash provided in the data")]
 	NameNotFound,
+	#[error("Couldn't decode the length of the storage key: {0}")]
+	CouldNotDecodeKeyLength(parity_scale_codec::Error),
+	#[error("The storage key is {expected} bytes long, but only {got} bytes were given")]
+	NotEnoughBytesForKeyLength { got: usize, expected: usize },
+	#[error("{0} bytes were left over after decoding the storage key")]
+	LeftoverKeyBytes(usize),
+	#[error("The storage prefixes '{first}' and '{second}' have the same twox128 hash, so keys under them can't be told apart")]
+	PrefixCollision { first: String, second: String },
+}
+
+/// The error returned from [`StorageDecoder::decode_key_checkpointed()`].
+#[derive(thiserror::Error, Debug)]
+#[error("{error}")]
+pub struct CheckpointedStorageDecodeError {
+	/// Why the key couldn't be decoded.
+	#[source]
+	pub error: StorageDecodeError,
+	/// The offset (from the start of the input we were given) of the key following the bad one.
+	/// Advance the input by this to skip the bad key and carry on decoding. This is `None` if
+	/// the length of the bad key couldn't be decoded, in which case we can't carry on.
+	pub next_key_offset: Option<usize>,
 }
 
 impl StorageDecoder {
 	/// Call [`super::decode_storage()`] to construct a [`StorageDecoder`].
-	pub(super) fn generate_from_metadata(metadata: &Metadata) -> StorageDecoder {
-		let entries_by_hashed_prefix = metadata
-			.storage_entries()
-			.enumerate()
-			.map(|(index, entries)| {
-				let prefix_hash = twox_128(entries.prefix().as_bytes());
-				let entry_by_hashed_name = entries
-					.entries()
-					.enumerate()
-					.map(|(entry_index, entry)| {
-						let name_hash = twox_128(entry.name.as_bytes());
-						(name_hash, entry_index)
-					})
-					.collect();
-				(prefix_hash, StorageEntries { index, entry_by_hashed_name })
-			})
-			.collect();
+	///
+	/// Keys are matched to pallets by the twox128 hash of their storage prefix, so if two pallets share
+	/// a prefix hash, we return [`StorageDecodeError::PrefixCollision`] rather than decode keys against
+	/// the wrong pallet.
+	pub(super) fn generate_from_metadata(metadata: &Metadata) -> Result<StorageDecoder, StorageDecodeError> {
+		let mut entries_by_hashed_prefix: HashMap<[u8; 16], StorageEntries> = HashMap::new();
+		for (index, entries) in metadata.storage_entries().enumerate() {
+			let prefix_hash = twox_128(entries.prefix().as_bytes());
+			let entry_by_hashed_name = entries
+				.entries()
+				.enumerate()
+				.map(|(entry_index, entry)| {
+					let name_hash = twox_128(entry.name.as_bytes());
+					(name_hash, entry_index)
+				})
+				.collect();
 
-		StorageDecoder { entries_by_hashed_prefix }
+			match entries_by_hashed_prefix.entry(prefix_hash) {
+				Entry::Occupied(existing) => {
+					let first =
+						metadata.storage_entries().nth(existing.get().index).expect("index came from here; qed");
+					return Err(StorageDecodeError::PrefixCollision {
+						first: first.prefix().to_owned(),
+						second: entries.prefix().to_owned(),
+					});
+				}
+				Entry::Vacant(slot) => {
+					slot.insert(StorageEntries { index, entry_by_hashed_name });
+				}
+			}
+		}
+
+		Ok(StorageDecoder { entries_by_hashed_prefix })
 	}
 
 	/// Decode the SCALE encoded bytes representing a storage entry lookup. These conceptually take the
@@ -94,7 +132,7 @@ impl StorageDecoder {
 			FrameStorageEntryType::Map { hashers, key, value } => {
 				// We'll consume some more data based on the hashers.
 				// First, get the type information that we need ready.
-				let keys = storage_map_key_to_type_id_vec(metadata, key);
+				let keys = storage_map_key_to_type_id_vec(metadata, key, hashers.len());
 				if keys.len() != hashers.len() {
 					panic!(
 						"Metadata inconsistency: keys and hashers for storage lookup {}.{} don't line up",
@@ -126,6 +164,10 @@ impl StorageDecoder {
 						_other => false,
 					};
 
+					if bytes.len() < initial_hash_bytes {
+						return Err(StorageDecodeError::NotEnoughBytesForKey { key: idx, hasher: hasher.clone() });
+					}
+
 					// Decode the value if so, and return the total bytes consumed so far and the resulting hasher.
 					let (hasher, bytes_consumed) = if is_value_next {
 						// Don't consume our `bytes` here; create a new cursor to consume and count the length
@@ -162,6 +204,47 @@ impl StorageDecoder {
 		}
 	}
 
+	/// Decode a single key from a stream of SCALE encoded storage keys (ie the `Vec<StorageKey>` returned
+	/// from `state_getKeysPaged`, without its outer length), each of which is prefixed with its compact
+	/// encoded length.
+	///
+	/// On success, the input is moved past the decoded key. On error, the input is left untouched, and the
+	/// error contains the offset of the next key, so that a bad key can be skipped over without losing any
+	/// of the progress made so far.
+	pub fn decode_key_checkpointed<'m, 'b>(
+		&self,
+		metadata: &'m Metadata,
+		bytes: &mut &'b [u8],
+	) -> Result<StorageEntry<'m, 'b>, CheckpointedStorageDecodeError> {
+		let mut cursor: &'b [u8] = bytes;
+		let len = Compact::<u32>::decode(&mut cursor).map_err(|e| CheckpointedStorageDecodeError {
+			error: StorageDecodeError::CouldNotDecodeKeyLength(e),
+			next_key_offset: None,
+		})?;
+		let len = len.0 as usize;
+		let len_bytes = bytes.len() - cursor.len();
+
+		if cursor.len() < len {
+			return Err(CheckpointedStorageDecodeError {
+				error: StorageDecodeError::NotEnoughBytesForKeyLength { got: cursor.len(), expected: len },
+				next_key_offset: None,
+			});
+		}
+
+		let next_key_offset = len_bytes + len;
+		let key_bytes = &mut &cursor[..len];
+		let entry = self
+			.decode_key(metadata, key_bytes)
+			.and_then(|entry| match key_bytes.len() {
+				0 => Ok(entry),
+				n => Err(StorageDecodeError::LeftoverKeyBytes(n)),
+			})
+			.map_err(|error| CheckpointedStorageDecodeError { error, next_key_offset: Some(next_key_offset) })?;
+
+		*bytes = &bytes[next_key_offset..];
+		Ok(entry)
+	}
+
 	// Reverse the prefix+name hashing (which takes the form of `twox_128(prefix) + twox_128(name)`)
 	// into a specific storage location, which we can lookup in the Metadata to decode the remaining
 	// bytes.
@@ -185,12 +268,17 @@ impl StorageDecoder {
 
 // Metadata info for maps/doublemaps contains a vec of hashers for each key type,
 // and a Type representing the key(s). We expect the number of keys and hashers to
-// line up, so let's resolve the keys into something easier to work with.
+// line up, so let's resolve the keys into something easier to work with. A single
+// hasher means a single key, even if that key happens to be a tuple (or any other
+// composite type), in which case the whole thing is decoded after the hash.
 //
 // See https://github.com/paritytech/subxt/blob/793c945fbd2de022f523c39a84ee02609ba423a9/codegen/src/api/storage.rs#L105
 // for another example of this being handled in code.
-fn storage_map_key_to_type_id_vec(metadata: &Metadata, key: &ScaleInfoTypeId) -> Vec<TypeId> {
+fn storage_map_key_to_type_id_vec(metadata: &Metadata, key: &ScaleInfoTypeId, num_hashers: usize) -> Vec<TypeId> {
 	let ty_id = key.id;
+	if num_hashers == 1 {
+		return vec![ty_id];
+	}
 	let ty = match metadata.resolve(ty_id) {
 		Some(ty) => ty,
 		None => panic!("Metadata inconsistency: type #{} not found", ty_id),
@@ -0,0 +1,810 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decoding of SCALE encoded bytes into [`Value`]s. Nesting is limited to [`MAX_DEPTH`] so that
+//! recursive types can't be used to overflow the stack, and types which are awkward to consume in
+//! their raw SCALE shape (like `BTreeMap`s) are presented in a more natural way.
+
+use super::DecodeValueError;
+use crate::TypeId;
+use scale_decode::visitor::{self, types as visit, DecodeAsTypeResult, Visitor};
+use scale_decode::IntoVisitor;
+use scale_info::{form::PortableForm, PortableRegistry, TypeDef};
+use scale_value::{Composite, Primitive, Value, ValueDef, Variant};
+use std::cell::Cell;
+
+/// The name of the field holding the key of each entry in a decoded map.
+pub const MAP_KEY_FIELD: &str = "key";
+/// The name of the field holding the value of each entry in a decoded map.
+pub const MAP_VALUE_FIELD: &str = "value";
+
+/// The maximum number of nested composites, variants, sequences, tuples and arrays that we'll decode
+/// before giving up. This is far deeper than any real runtime type, but shallow enough that decoding
+/// can't overflow the stack.
+pub const MAX_DEPTH: usize = 128;
+
+/// The error handed back when decoding a value that is nested more than [`MAX_DEPTH`] levels deep.
+#[derive(Debug, thiserror::Error)]
+#[error("Value is nested more than {0} levels deep")]
+pub struct TooDeepError(pub usize);
+
+/// The maximum number of bytes of the input shown in a [`DetailedDecodeValueError`].
+const WINDOW_LEN: usize = 16;
+
+/// A [`DecodeValueError`], along with where in the input it happened. This is handy for tracking down
+/// type information that doesn't match the bytes being decoded.
+#[derive(Debug, thiserror::Error)]
+#[error("{error} (at byte {offset}, where the input is 0x{window})")]
+pub struct DetailedDecodeValueError {
+	/// The error that decoding failed with.
+	#[source]
+	pub error: DecodeValueError,
+	/// The offset into the input of the start of the innermost value which failed to decode.
+	pub offset: usize,
+	/// Up to 16 hex encoded bytes of the input, starting at `offset`.
+	pub window: String,
+}
+
+/// Decode a [`Value`] of the given type from the bytes provided, moving the cursor forwards past it.
+pub fn decode(data: &mut &[u8], ty: TypeId, types: &PortableRegistry) -> Result<Value<TypeId>, DecodeValueError> {
+	decode_detailed(data, ty, types).map_err(|e| e.error)
+}
+
+/// Like [`decode`], but on error, also report where in the input the error happened.
+pub fn decode_detailed(
+	data: &mut &[u8],
+	ty: TypeId,
+	types: &PortableRegistry,
+) -> Result<Value<TypeId>, DetailedDecodeValueError> {
+	let input = *data;
+	let value_start = Cell::new(input.as_ptr() as usize);
+	let visitor = DepthLimitedVisitor { depth: 0, value_start: &value_start };
+	match visitor::decode_with_visitor(data, ty, types, visitor) {
+		Ok(value) => Ok(normalize(value, types)),
+		Err(error) => {
+			let offset = value_start.get().saturating_sub(input.as_ptr() as usize).min(input.len());
+			let window = hex::encode(&input[offset..(offset + WINDOW_LEN).min(input.len())]);
+			Err(DetailedDecodeValueError { error, offset, window })
+		}
+	}
+}
+
+/// Skip over a value of the given type without building it up, moving the cursor forwards past it. Like [`decode`],
+/// this gives up on values nested more than [`MAX_DEPTH`] levels deep.
+pub fn skip(data: &mut &[u8], ty: TypeId, types: &PortableRegistry) -> Result<(), DecodeValueError> {
+	visitor::decode_with_visitor(data, ty, types, SkippingVisitor { depth: 0 })
+}
+
+/// A [`Visitor`] that steps over the items nested in each value itself, rather than leaving `scale_decode` to skip
+/// them (which it does without any limit on how deeply they're nested), and errors beyond [`MAX_DEPTH`].
+#[derive(Debug, Clone, Copy)]
+struct SkippingVisitor {
+	depth: usize,
+}
+
+impl SkippingVisitor {
+	fn skip_items(
+		self,
+		mut decode_item: impl FnMut(Self) -> Option<Result<(), DecodeValueError>>,
+	) -> Result<(), DecodeValueError> {
+		let nested = SkippingVisitor { depth: self.depth + 1 };
+		while let Some(res) = decode_item(nested) {
+			res?;
+		}
+		Ok(())
+	}
+}
+
+impl Visitor for SkippingVisitor {
+	type Value<'scale, 'info> = ();
+	type Error = DecodeValueError;
+
+	fn unchecked_decode_as_type<'scale, 'info>(
+		self,
+		_input: &mut &'scale [u8],
+		_type_id: visitor::TypeId,
+		_types: &'info PortableRegistry,
+	) -> DecodeAsTypeResult<Self, Result<Self::Value<'scale, 'info>, Self::Error>> {
+		if self.depth > MAX_DEPTH {
+			DecodeAsTypeResult::Decoded(Err(DecodeValueError::custom(TooDeepError(MAX_DEPTH))))
+		} else {
+			DecodeAsTypeResult::Skipped(self)
+		}
+	}
+
+	// Anything which can't contain other values is ignored:
+	fn visit_unexpected<'scale, 'info>(
+		self,
+		_unexpected: visitor::Unexpected,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		Ok(())
+	}
+
+	fn visit_sequence<'scale, 'info>(
+		self,
+		value: &mut visit::Sequence<'scale, 'info>,
+		_type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.skip_items(|v| value.decode_item(v))
+	}
+
+	fn visit_tuple<'scale, 'info>(
+		self,
+		value: &mut visit::Tuple<'scale, 'info>,
+		_type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.skip_items(|v| value.decode_item(v))
+	}
+
+	fn visit_array<'scale, 'info>(
+		self,
+		value: &mut visit::Array<'scale, 'info>,
+		_type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.skip_items(|v| value.decode_item(v))
+	}
+
+	fn visit_composite<'scale, 'info>(
+		self,
+		value: &mut visit::Composite<'scale, 'info>,
+		_type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.skip_items(|v| value.decode_item(v))
+	}
+
+	fn visit_variant<'scale, 'info>(
+		self,
+		value: &mut visit::Variant<'scale, 'info>,
+		_type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		let fields = value.fields();
+		self.skip_items(|v| fields.decode_item(v))
+	}
+}
+
+/// A [`Visitor`] that decodes into [`Value`]s like [`scale_value`]'s own visitor does, but keeps track of how
+/// deeply nested it is and errors once that exceeds [`MAX_DEPTH`]. It also records the address of the start of
+/// each value it's about to decode, so that when decoding fails we know where the innermost value started.
+#[derive(Debug, Clone, Copy)]
+struct DepthLimitedVisitor<'a> {
+	depth: usize,
+	value_start: &'a Cell<usize>,
+}
+
+impl<'a> DepthLimitedVisitor<'a> {
+	/// The visitor to use for the items nested inside the current value.
+	fn nested(self) -> Self {
+		DepthLimitedVisitor { depth: self.depth + 1, value_start: self.value_start }
+	}
+
+	fn visit_items(
+		self,
+		mut decode_item: impl FnMut(Self) -> Option<Result<Value<TypeId>, DecodeValueError>>,
+		type_id: visitor::TypeId,
+	) -> Result<Value<TypeId>, DecodeValueError> {
+		let nested = self.nested();
+		let mut vals = Vec::new();
+		while let Some(val) = decode_item(nested) {
+			vals.push(val?);
+		}
+		Ok(Value { value: ValueDef::Composite(Composite::Unnamed(vals)), context: type_id.0 })
+	}
+
+	fn visit_fields(self, value: &mut visit::Composite<'_, '_>) -> Result<Composite<TypeId>, DecodeValueError> {
+		let nested = self.nested();
+		// If there are no fields, we'll always assume unnamed.
+		let named = value.remaining() > 0 && !value.has_unnamed_fields();
+		if named {
+			let mut vals = Vec::with_capacity(value.remaining());
+			let mut name = value.peek_name();
+			while let Some(val) = value.decode_item(nested) {
+				let field_name = name.expect("all fields should be named; we have checked").to_owned();
+				vals.push((field_name, val?));
+				name = value.peek_name();
+			}
+			Ok(Composite::Named(vals))
+		} else {
+			let mut vals = Vec::with_capacity(value.remaining());
+			while let Some(val) = value.decode_item(nested) {
+				vals.push(val?);
+			}
+			Ok(Composite::Unnamed(vals))
+		}
+	}
+}
+
+// Values which can't contain other values are decoded exactly as `scale_value` would decode them.
+macro_rules! delegate_visit {
+	($($method:ident($ty:ty)),* $(,)?) => {
+		$(
+			fn $method<'scale, 'info>(
+				self,
+				value: $ty,
+				type_id: visitor::TypeId,
+			) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+				Value::<TypeId>::into_visitor().$method(value, type_id).map_err(Into::into)
+			}
+		)*
+	};
+}
+
+impl<'a> Visitor for DepthLimitedVisitor<'a> {
+	type Value<'scale, 'info> = Value<TypeId>;
+	type Error = DecodeValueError;
+
+	// Bail before looking at any bytes if we're too deep. Erroring here (rather than before decoding
+	// the items of the parent) means that the parent won't then try to skip over the rest of the bytes.
+	fn unchecked_decode_as_type<'scale, 'info>(
+		self,
+		input: &mut &'scale [u8],
+		_type_id: visitor::TypeId,
+		_types: &'info PortableRegistry,
+	) -> DecodeAsTypeResult<Self, Result<Self::Value<'scale, 'info>, Self::Error>> {
+		self.value_start.set(input.as_ptr() as usize);
+		if self.depth > MAX_DEPTH {
+			DecodeAsTypeResult::Decoded(Err(DecodeValueError::custom(TooDeepError(MAX_DEPTH))))
+		} else {
+			DecodeAsTypeResult::Skipped(self)
+		}
+	}
+
+	delegate_visit!(
+		visit_bool(bool),
+		visit_char(char),
+		visit_u8(u8),
+		visit_u16(u16),
+		visit_u32(u32),
+		visit_u64(u64),
+		visit_u128(u128),
+		visit_i8(i8),
+		visit_i16(i16),
+		visit_i32(i32),
+		visit_i64(i64),
+		visit_i128(i128),
+		visit_str(&mut visit::Str<'scale>),
+		visit_bitsequence(&mut visit::BitSequence<'scale>),
+	);
+
+	fn visit_u256<'info>(
+		self,
+		value: &'_ [u8; 32],
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'_, 'info>, Self::Error> {
+		Ok(Value { value: ValueDef::Primitive(Primitive::U256(*value)), context: type_id.0 })
+	}
+
+	fn visit_i256<'info>(
+		self,
+		value: &'_ [u8; 32],
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'_, 'info>, Self::Error> {
+		Ok(Value { value: ValueDef::Primitive(Primitive::I256(*value)), context: type_id.0 })
+	}
+
+	fn visit_sequence<'scale, 'info>(
+		self,
+		value: &mut visit::Sequence<'scale, 'info>,
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.visit_items(|v| value.decode_item(v), type_id)
+	}
+
+	fn visit_tuple<'scale, 'info>(
+		self,
+		value: &mut visit::Tuple<'scale, 'info>,
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.visit_items(|v| value.decode_item(v), type_id)
+	}
+
+	fn visit_array<'scale, 'info>(
+		self,
+		value: &mut visit::Array<'scale, 'info>,
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		self.visit_items(|v| value.decode_item(v), type_id)
+	}
+
+	fn visit_composite<'scale, 'info>(
+		self,
+		value: &mut visit::Composite<'scale, 'info>,
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		Ok(Value { value: ValueDef::Composite(self.visit_fields(value)?), context: type_id.0 })
+	}
+
+	fn visit_variant<'scale, 'info>(
+		self,
+		value: &mut visit::Variant<'scale, 'info>,
+		type_id: visitor::TypeId,
+	) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+		let values = self.visit_fields(value.fields())?;
+		Ok(Value { value: ValueDef::Variant(Variant { name: value.name().to_owned(), values }), context: type_id.0 })
+	}
+}
+
+/// Collection types whose decoded representation we rewrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Collection {
+	/// `BTreeMap<K, V>`; a composite wrapping a sequence of `(K, V)` tuples.
+	Map,
+	/// `BTreeSet<T>`; a composite wrapping a sequence of `T`.
+	Set,
+	/// `BoundedBTreeMap`/`BoundedBTreeSet`, a composite wrapping one of the above, or `BoundedVec`/`WeakBoundedVec`,
+	/// a composite wrapping a sequence.
+	Bounded,
+}
+
+/// Walk a decoded value and rewrite any `BTreeMap`s and `BTreeSet`s (and their bounded counterparts) found in it.
+///
+/// Maps become an unnamed composite of entries, each of which is a named composite with a
+/// [`MAP_KEY_FIELD`] and a [`MAP_VALUE_FIELD`], in the order they were encoded (ie ordered by key).
+/// Sets become an unnamed composite of their elements. `BoundedVec`s and `WeakBoundedVec`s become the
+/// sequence that they wrap. The context of the rewritten value remains the type ID of the original, so
+/// it can still be encoded back to the same bytes. Presentation only rewrites (like turning `PalletId`s
+/// into strings) are left to [`super::humanize_value`].
+pub fn normalize(value: Value<TypeId>, types: &PortableRegistry) -> Value<TypeId> {
+	let Value { value, context } = value;
+	let value = match value {
+		ValueDef::Composite(composite) => ValueDef::Composite(normalize_composite(composite, types)),
+		ValueDef::Variant(Variant { name, values }) => {
+			ValueDef::Variant(Variant { name, values: normalize_composite(values, types) })
+		}
+		other => other,
+	};
+	let value = Value { value, context };
+
+	match collection(types, context) {
+		Some(Collection::Map) => into_map(unwrap_single_field(value)),
+		Some(Collection::Set) | Some(Collection::Bounded) => unwrap_single_field(value),
+		None => value,
+	}
+}
+
+/// Normalize each field of a composite.
+fn normalize_composite(composite: Composite<TypeId>, types: &PortableRegistry) -> Composite<TypeId> {
+	match composite {
+		Composite::Named(vals) => {
+			Composite::Named(vals.into_iter().map(|(name, val)| (name, normalize(val, types))).collect())
+		}
+		Composite::Unnamed(vals) => Composite::Unnamed(vals.into_iter().map(|val| normalize(val, types)).collect()),
+	}
+}
+
+/// Work out whether the given type is one of the collections that we rewrite.
+fn collection(types: &PortableRegistry, id: TypeId) -> Option<Collection> {
+	let ty = types.resolve(id)?;
+	if !matches!(ty.type_def, TypeDef::<PortableForm>::Composite(_)) {
+		return None;
+	}
+	match ty.path.segments.last().map(|s| s.as_str()) {
+		Some("BTreeMap") => Some(Collection::Map),
+		Some("BTreeSet") => Some(Collection::Set),
+		Some("BoundedBTreeMap") | Some("BoundedBTreeSet") | Some("BoundedVec") | Some("WeakBoundedVec") => {
+			Some(Collection::Bounded)
+		}
+		_ => None,
+	}
+}
+
+/// If the value is a composite with exactly one field, hand back that field with the context of
+/// the outer value. Otherwise, hand back the value untouched.
+fn unwrap_single_field(value: Value<TypeId>) -> Value<TypeId> {
+	let context = value.context;
+	match value.value {
+		ValueDef::Composite(composite) if composite.len() == 1 => {
+			let inner = composite.into_values().next().expect("composite has exactly one field; qed");
+			Value { value: inner.value, context }
+		}
+		other => Value { value: other, context },
+	}
+}
+
+/// Turn a sequence of `(key, value)` tuples into a sequence of named entries.
+fn into_map(value: Value<TypeId>) -> Value<TypeId> {
+	let context = value.context;
+	let entries = match value.value {
+		ValueDef::Composite(Composite::Unnamed(entries)) => entries,
+		other => return Value { value: other, context },
+	};
+	let entries = entries
+		.into_iter()
+		.map(|entry| match entry.value {
+			ValueDef::Composite(Composite::Unnamed(kv)) if kv.len() == 2 => {
+				let mut kv = kv.into_iter();
+				let (k, v) = (kv.next().expect("two fields; qed"), kv.next().expect("two fields; qed"));
+				let fields = vec![(MAP_KEY_FIELD.to_string(), k), (MAP_VALUE_FIELD.to_string(), v)];
+				Value { value: ValueDef::Composite(Composite::Named(fields)), context: entry.context }
+			}
+			other => Value { value: other, context: entry.context },
+		})
+		.collect();
+	Value { value: ValueDef::Composite(Composite::Unnamed(entries)), context }
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+	use scale_info::{meta_type, Registry, TypeInfo};
+	use std::collections::{BTreeMap, BTreeSet};
+
+	/// Build a registry containing `T`, and hand back its type ID along with the registry.
+	fn registry_with<T: TypeInfo + 'static>() -> (TypeId, PortableRegistry) {
+		let mut registry = Registry::new();
+		let id = registry.register_type(&meta_type::<T>()).id;
+		(id, registry.into())
+	}
+
+	fn decode<T: TypeInfo + Encode + 'static>(val: &T) -> Value<()> {
+		let (id, types) = registry_with::<T>();
+		let bytes = val.encode();
+		super::decode(&mut &*bytes, id, &types).unwrap().remove_context()
+	}
+
+	fn entry(key: u128, value: u128) -> Value<()> {
+		Value::named_composite(vec![(MAP_KEY_FIELD, Value::u128(key)), (MAP_VALUE_FIELD, Value::u128(value))])
+	}
+
+	#[test]
+	fn btree_map_is_decoded_as_entries() {
+		let map: BTreeMap<u32, u64> = [(3, 30), (1, 10), (2, 20)].into_iter().collect();
+		assert_eq!(decode(&map), Value::unnamed_composite(vec![entry(1, 10), entry(2, 20), entry(3, 30)]));
+	}
+
+	#[test]
+	fn empty_btree_map_is_decoded_as_no_entries() {
+		let map: BTreeMap<u32, u64> = BTreeMap::new();
+		assert_eq!(decode(&map), Value::unnamed_composite(vec![]));
+	}
+
+	#[test]
+	fn btree_set_is_decoded_as_elements() {
+		let set: BTreeSet<u32> = [5, 1, 3].into_iter().collect();
+		assert_eq!(decode(&set), Value::unnamed_composite(vec![Value::u128(1), Value::u128(3), Value::u128(5)]));
+	}
+
+	#[test]
+	fn bounded_collections_are_decoded_as_what_they_wrap() {
+		// These mirror the types of the same names in `sp_runtime`/`frame_support`, whose paths end the same way.
+		#[derive(Encode, TypeInfo)]
+		struct BoundedBTreeSet<T>(BTreeSet<T>);
+		#[derive(Encode, TypeInfo)]
+		struct BoundedBTreeMap<K, V>(BTreeMap<K, V>);
+		#[derive(Encode, TypeInfo)]
+		struct BoundedVec<T>(Vec<T>);
+		#[derive(Encode, TypeInfo)]
+		struct WeakBoundedVec<T>(Vec<T>);
+
+		let set = BoundedBTreeSet([3u32, 1].into_iter().collect());
+		assert_eq!(decode(&set), Value::unnamed_composite(vec![Value::u128(1), Value::u128(3)]));
+
+		let map = BoundedBTreeMap([(2u32, 20u64), (1, 10)].into_iter().collect());
+		assert_eq!(decode(&map), Value::unnamed_composite(vec![entry(1, 10), entry(2, 20)]));
+
+		let bytes = Value::unnamed_composite(vec![Value::u128(1), Value::u128(2)]);
+		assert_eq!(decode(&BoundedVec(vec![1u8, 2])), bytes);
+		assert_eq!(decode(&WeakBoundedVec(vec![1u8, 2])), bytes);
+		assert_eq!(decode(&WeakBoundedVec(Vec::<u8>::new())), Value::unnamed_composite(vec![]));
+	}
+
+	#[test]
+	fn nested_btree_maps_are_decoded() {
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			inner: BTreeMap<u32, BTreeSet<u8>>,
+		}
+		let foo = Foo { inner: [(1, [2u8].into_iter().collect())].into_iter().collect() };
+		let expected = Value::named_composite(vec![(
+			"inner",
+			Value::unnamed_composite(vec![Value::named_composite(vec![
+				(MAP_KEY_FIELD, Value::u128(1)),
+				(MAP_VALUE_FIELD, Value::unnamed_composite(vec![Value::u128(2)])),
+			])]),
+		)]);
+		assert_eq!(decode(&foo), expected);
+	}
+
+	#[test]
+	fn variants_are_found_by_their_codec_index() {
+		#[derive(Encode, TypeInfo)]
+		enum Foo {
+			#[codec(index = 5)]
+			A(u8),
+			#[codec(index = 1)]
+			B(u16),
+			#[codec(index = 200)]
+			C,
+		}
+
+		assert_eq!(Foo::A(7).encode()[0], 5);
+		assert_eq!(decode(&Foo::A(7)), Value::unnamed_variant("A", vec![Value::u128(7)]));
+		assert_eq!(decode(&Foo::B(300)), Value::unnamed_variant("B", vec![Value::u128(300)]));
+		assert_eq!(decode(&Foo::C), Value::unnamed_variant("C", vec![]));
+
+		// Positional indexes that no variant was given aren't decoded:
+		let (id, types) = registry_with::<Foo>();
+		assert!(super::decode(&mut &[0u8, 7][..], id, &types).is_err());
+	}
+
+	#[test]
+	fn big_integer_mode_sequence_lengths() {
+		let (id, types) = registry_with::<Vec<u32>>();
+
+		// 2^30 is the smallest length that takes the 5 byte big integer mode to encode.
+		let mut bytes = parity_scale_codec::Compact(1u32 << 30).encode();
+		assert_eq!(bytes.len(), 5);
+		bytes.extend(7u32.encode());
+		assert!(super::decode(&mut &*bytes, id, &types).is_err());
+
+		// The input runs out long before that many items are decoded. Shorter lengths decode as usual:
+		assert_eq!(decode(&vec![7u32; 64]), Value::unnamed_composite(vec![Value::u128(7); 64]));
+
+		// 2^30 items are too many to decode in a test, but that is how many are read from the length, and they
+		// start straight after its 5 bytes:
+		let seen = Cell::new(None);
+		let _ = visitor::decode_with_visitor(&mut &*bytes, id, &types, ItemCount(&seen));
+		assert_eq!(seen.get(), Some((1 << 30, 5)));
+	}
+
+	/// Note how many items a sequence has, and how many bytes its length took up.
+	struct ItemCount<'a>(&'a Cell<Option<(usize, usize)>>);
+
+	impl Visitor for ItemCount<'_> {
+		type Value<'scale, 'info> = ();
+		type Error = visitor::DecodeError;
+
+		fn visit_sequence<'scale, 'info>(
+			self,
+			value: &mut visit::Sequence<'scale, 'info>,
+			_type_id: visitor::TypeId,
+		) -> Result<Self::Value<'scale, 'info>, Self::Error> {
+			let prefix_len = value.bytes_from_start().len() - value.bytes_from_undecoded().len();
+			self.0.set(Some((value.remaining(), prefix_len)));
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn phantom_variants_are_never_decoded() {
+		// Like the origins of instanced pallets, which need a variant to make use of their generic parameter.
+		#[derive(Encode, TypeInfo)]
+		enum Origin<I> {
+			Members(u32, u32),
+			Member(u8),
+			#[codec(skip)]
+			_Phantom(std::marker::PhantomData<I>),
+		}
+
+		assert_eq!(
+			decode(&Origin::<()>::Members(2, 3)),
+			Value::unnamed_variant("Members", vec![Value::u128(2), Value::u128(3)])
+		);
+		assert_eq!(decode(&Origin::<()>::Member(7)), Value::unnamed_variant("Member", vec![Value::u128(7)]));
+
+		// The phantom variant isn't in the type information, so its index is rejected rather than decoded:
+		let (id, types) = registry_with::<Origin<()>>();
+		let err = super::decode(&mut &[2u8][..], id, &types).unwrap_err();
+		assert!(err.to_string().contains("Could not find variant with index 2"), "{err}");
+	}
+
+	#[test]
+	fn compact_struct_fields() {
+		#[derive(Encode, TypeInfo)]
+		struct Vote {
+			#[codec(compact)]
+			balance: u128,
+			conviction: u8,
+			#[codec(compact)]
+			count: u32,
+		}
+
+		let (id, types) = registry_with::<Vote>();
+		let bytes = Vote { balance: 1_000_000_000_000, conviction: 3, count: 1 }.encode();
+		// The compact fields take up fewer bytes than the full width integers would:
+		assert_eq!(bytes.len(), 6 + 1 + 1);
+		let cursor = &mut &*bytes;
+		let value = super::decode(cursor, id, &types).unwrap().remove_context();
+
+		assert!(cursor.is_empty());
+		assert_eq!(
+			value,
+			Value::named_composite(vec![
+				("balance", Value::u128(1_000_000_000_000)),
+				("conviction", Value::u128(3)),
+				("count", Value::u128(1)),
+			])
+		);
+	}
+
+	#[test]
+	fn compact_newtype_fields() {
+		// `HasCompact` newtypes are described as a `Compact` pointing at a single field composite, and encoded
+		// as the compact integer that they wrap.
+		#[derive(Encode, TypeInfo, parity_scale_codec::CompactAs)]
+		struct Balance(u128);
+
+		#[derive(Encode, TypeInfo)]
+		struct Transfer {
+			#[codec(compact)]
+			amount: Balance,
+			#[codec(compact)]
+			tip: Balance,
+		}
+
+		let (id, types) = registry_with::<Transfer>();
+		let bytes = Transfer { amount: Balance(1_000_000_000_000), tip: Balance(0) }.encode();
+		assert_eq!(bytes.len(), 6 + 1);
+		let cursor = &mut &*bytes;
+		let value = super::decode(cursor, id, &types).unwrap().remove_context();
+
+		assert!(cursor.is_empty());
+		assert_eq!(
+			value,
+			Value::named_composite(vec![
+				("amount", Value::unnamed_composite(vec![Value::u128(1_000_000_000_000)])),
+				("tip", Value::unnamed_composite(vec![Value::u128(0)])),
+			])
+		);
+	}
+
+	#[test]
+	fn strings_are_decoded_as_strings_rather_than_bytes() {
+		#[derive(Encode, TypeInfo)]
+		struct Named {
+			name: String,
+			bytes: Vec<u8>,
+		}
+
+		let (id, types) = registry_with::<Named>();
+		let bytes = Named { name: "Alice ✓".into(), bytes: b"Alice".to_vec() }.encode();
+		let cursor = &mut &*bytes;
+		let value = super::decode(cursor, id, &types).unwrap().remove_context();
+
+		assert!(cursor.is_empty());
+		assert_eq!(
+			value,
+			Value::named_composite(vec![("name", Value::string("Alice ✓")), ("bytes", Value::from_bytes(b"Alice"))])
+		);
+
+		// Strings must be valid UTF-8.
+		let (id, types) = registry_with::<String>();
+		let err = super::decode(&mut &*vec![0xffu8, 0xfe].encode(), id, &types).unwrap_err();
+		assert!(err.to_string().contains("Could not decode string: invalid utf-8"), "{err}");
+	}
+
+	#[test]
+	fn nested_byte_sequences() {
+		// The outer length prefix counts elements, and each element has a length prefix of its own (70 bytes
+		// needs a two byte compact prefix).
+		let val: Vec<Vec<u8>> = vec![vec![], vec![1], vec![2; 70]];
+		let (id, types) = registry_with::<Vec<Vec<u8>>>();
+		let bytes = val.encode();
+		let cursor = &mut &*bytes;
+		let value = super::decode(cursor, id, &types).unwrap().remove_context();
+
+		assert!(cursor.is_empty());
+		assert_eq!(
+			value,
+			Value::unnamed_composite(vec![Value::from_bytes([]), Value::from_bytes([1]), Value::from_bytes([2; 70])])
+		);
+	}
+
+	#[test]
+	fn zero_field_composites_consume_no_bytes() {
+		#[derive(Encode, TypeInfo)]
+		struct Marker;
+
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: Marker,
+			b: std::marker::PhantomData<u64>,
+			c: u32,
+		}
+
+		let (id, types) = registry_with::<Marker>();
+		let bytes = [1u8, 2, 3];
+		let cursor = &mut &bytes[..];
+		assert_eq!(super::decode(cursor, id, &types).unwrap().remove_context(), Value::unnamed_composite(vec![]));
+		assert_eq!(cursor.len(), 3);
+		assert!(super::decode(&mut &[][..], id, &types).is_ok());
+
+		let (id, types) = registry_with::<Foo>();
+		let mut bytes = Foo { a: Marker, b: std::marker::PhantomData, c: 7 }.encode();
+		assert_eq!(bytes.len(), 4);
+		bytes.push(0xff);
+		let cursor = &mut &*bytes;
+		let value = super::decode(cursor, id, &types).unwrap().remove_context();
+		assert_eq!(cursor, &[0xff]);
+		// `scale-info` leaves `PhantomData` fields out of the type altogether:
+		assert_eq!(value, Value::named_composite(vec![("a", Value::unnamed_composite(vec![])), ("c", Value::u128(7))]));
+	}
+
+	#[derive(Encode, TypeInfo)]
+	enum Tree {
+		Leaf,
+		Node(Box<Tree>),
+	}
+
+	fn tree(depth: usize) -> Tree {
+		(0..depth).fold(Tree::Leaf, |tree, _| Tree::Node(Box::new(tree)))
+	}
+
+	#[test]
+	fn recursive_type_is_decoded() {
+		let (id, types) = registry_with::<Tree>();
+		let bytes = tree(100).encode();
+		let cursor = &mut &*bytes;
+
+		let mut value = super::decode(cursor, id, &types).unwrap();
+		assert!(cursor.is_empty());
+
+		let mut depth = 0;
+		while let ValueDef::Variant(Variant { name, values: Composite::Unnamed(mut fields) }) = value.value {
+			if name == "Leaf" {
+				break;
+			}
+			value = fields.remove(0);
+			depth += 1;
+		}
+		assert_eq!(depth, 100);
+	}
+
+	#[test]
+	fn too_deeply_nested_value_is_an_error() {
+		let (id, types) = registry_with::<Tree>();
+		// Every level of the tree is a single byte, so this is cheap to build but would
+		// overflow the stack if we tried to decode it all.
+		let bytes: Vec<u8> = std::iter::repeat(1u8).take(1_000_000).chain(std::iter::once(0)).collect();
+
+		let err = super::decode(&mut &*bytes, id, &types).unwrap_err();
+		assert!(err.to_string().contains(&TooDeepError(MAX_DEPTH).to_string()), "unexpected error: {}", err);
+	}
+
+	#[test]
+	fn values_are_skipped_with_the_same_depth_limit() {
+		let (id, types) = registry_with::<Tree>();
+		let bytes = tree(100).encode();
+		let cursor = &mut &*bytes;
+		super::skip(cursor, id, &types).unwrap();
+		assert!(cursor.is_empty());
+
+		let bytes: Vec<u8> = std::iter::repeat(1u8).take(1_000_000).chain(std::iter::once(0)).collect();
+		let err = super::skip(&mut &*bytes, id, &types).unwrap_err();
+		assert!(err.to_string().contains(&TooDeepError(MAX_DEPTH).to_string()), "unexpected error: {}", err);
+	}
+
+	#[test]
+	fn errors_report_where_decoding_failed() {
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: u32,
+			b: Vec<u64>,
+			c: u16,
+		}
+		let (id, types) = registry_with::<Foo>();
+		let bytes = Foo { a: 1, b: vec![2, 3], c: 4 }.encode();
+
+		// Cut off partway through the second item of `b`, which starts after `a`, the length of `b` and its first item:
+		let err = decode_detailed(&mut &bytes[..17], id, &types).unwrap_err();
+		assert_eq!(err.offset, 4 + 1 + 8);
+		assert_eq!(err.window, "03000000");
+		assert!(err.to_string().contains("at byte 13"), "{err}");
+
+		// Nothing goes wrong when the input is complete:
+		assert!(decode_detailed(&mut &*bytes, id, &types).is_ok());
+	}
+}
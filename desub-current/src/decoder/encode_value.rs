@@ -0,0 +1,109 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Encoding of [`Value`]s back into SCALE bytes. Decoded values carry the ID of the type that they were
+//! decoded as, so this is all that we need in order to encode them exactly as they were; for instance, a
+//! value decoded from a fixed size array is encoded without a length prefix, but one decoded from a `Vec`
+//! is given one.
+
+use crate::TypeId;
+use scale_encode::EncodeAsType;
+use scale_info::PortableRegistry;
+use scale_value::Value;
+
+/// The error handed back if a [`Value`] can't be encoded as the type it claims to be.
+pub use scale_encode::Error as EncodeValueError;
+
+/// Encode a [`Value`] as the type given by its context.
+pub fn encode(value: &Value<TypeId>, types: &PortableRegistry) -> Result<Vec<u8>, EncodeValueError> {
+	value.encode_as_type(value.context, types)
+}
+
+/// Encode a [`Value`] as the given type, rather than the type given by its context. Compact encoded values are
+/// decoded with the context of the type that they wrap, so this is needed to encode them compactly again when the
+/// type they were decoded as is known, as it is for call arguments and signed extensions.
+pub fn encode_as(value: &Value<TypeId>, ty: TypeId, types: &PortableRegistry) -> Result<Vec<u8>, EncodeValueError> {
+	value.encode_as_type(ty, types)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::decoder::decode_value;
+	use parity_scale_codec::Encode;
+	use scale_info::{meta_type, Registry, TypeInfo};
+
+	fn round_trip<T: Encode + TypeInfo + 'static>(val: T) -> (Value<()>, Vec<u8>) {
+		let mut registry = Registry::new();
+		let id = registry.register_type(&meta_type::<T>()).id;
+		let types: PortableRegistry = registry.into();
+
+		let bytes = val.encode();
+		let value = decode_value::decode(&mut &*bytes, id, &types).unwrap();
+		let encoded = encode(&value, &types).unwrap();
+		assert_eq!(encoded, bytes);
+		(value.remove_context(), encoded)
+	}
+
+	#[test]
+	fn arrays_and_sequences_round_trip() {
+		let (array, array_bytes) = round_trip([1u8, 2, 3, 4]);
+		let (vec, vec_bytes) = round_trip(vec![1u8, 2, 3, 4]);
+
+		// Both decode to the same shape, but only the `Vec` is length prefixed when encoded again.
+		assert_eq!(array, vec);
+		assert_eq!(array_bytes, vec![1, 2, 3, 4]);
+		assert_eq!(vec_bytes, vec![16, 1, 2, 3, 4]);
+	}
+
+	#[test]
+	fn can_encode_as_a_different_type() {
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: u32,
+			b: Vec<u8>,
+		}
+		// The same fields in a different order, and with a wider integer:
+		#[derive(Encode, TypeInfo)]
+		struct Bar {
+			b: Vec<u8>,
+			a: u64,
+		}
+
+		let mut registry = Registry::new();
+		let foo_id = registry.register_type(&meta_type::<Foo>()).id;
+		let bar_id = registry.register_type(&meta_type::<Bar>()).id;
+		let types: PortableRegistry = registry.into();
+
+		let foo_bytes = Foo { a: 1, b: vec![2, 3] }.encode();
+		let value = decode_value::decode(&mut &*foo_bytes, foo_id, &types).unwrap();
+		let bar_bytes = value.encode_as_type(bar_id, &types).unwrap();
+
+		assert_eq!(bar_bytes, Bar { b: vec![2, 3], a: 1 }.encode());
+	}
+
+	#[test]
+	fn nested_values_round_trip() {
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: [u32; 2],
+			b: Vec<(u8, Option<bool>)>,
+			c: std::collections::BTreeMap<u16, String>,
+		}
+
+		round_trip(Foo { a: [1, 2], b: vec![(3, Some(true)), (4, None)], c: [(5, "five".to_string())].into() });
+	}
+}
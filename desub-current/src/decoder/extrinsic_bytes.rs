@@ -172,6 +172,40 @@ mod test {
 		assert_eq!(iter_result_to_bytes(exts.next()), None);
 	}
 
+	#[test]
+	fn compact_lengths_in_every_mode() {
+		// The largest and smallest lengths of each mode, and how many bytes each mode takes up:
+		let lengths = [(0, 1), (63, 1), (64, 2), ((1 << 14) - 1, 2), (1 << 14, 4), ((1 << 30) - 1, 4), (1 << 30, 5)];
+		for (length, prefix) in lengths.into_iter().chain([(u32::MAX, 5)]) {
+			let mut bytes = Compact(length).encode();
+			bytes.push(0xff);
+			assert_eq!(decode_compact_u32(&bytes), Some((length as usize, prefix)), "length {length}");
+		}
+	}
+
+	#[test]
+	fn big_integer_mode_lengths() {
+		// 2^30 entries in block (wrong), which takes the 5 byte big integer mode to encode:
+		let mut bytes = Compact(1u32 << 30).encode();
+		assert_eq!(bytes.len(), 5);
+
+		// First entry; 4 bytes long (correct):
+		bytes.extend_from_slice(&Compact(4u32).encode());
+		bytes.extend_from_slice(&[1, 2, 3, 4]);
+
+		// Second entry; 2^30 bytes long (wrong):
+		bytes.extend_from_slice(&Compact(1u32 << 30).encode());
+		bytes.extend_from_slice(&[1, 2]);
+
+		let exts = AllExtrinsicBytes::new(&bytes).unwrap();
+		assert_eq!(exts.len(), 1 << 30);
+
+		let mut exts = exts.iter();
+		assert_eq!(iter_result_to_bytes(exts.next()), Some(Ok(&[1, 2, 3, 4][..])));
+		assert_eq!(iter_result_to_bytes(exts.next()), Some(Err(ExtrinsicBytesError { index: 12 })));
+		assert_eq!(iter_result_to_bytes(exts.next()), None);
+	}
+
 	#[test]
 	fn malformed_extrinsics_length() {
 		let mut bytes: Vec<u8> = vec![];
@@ -0,0 +1,196 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! An opt-in pass over decoded [`Value`]s which makes them easier for people to read, at the cost of no
+//! longer being able to encode them back into the bytes they came from.
+
+use super::resolve_module_errors;
+use crate::value_ext::bytes_of;
+use crate::{Metadata, TypeId, ValueExt};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef};
+use scale_value::{Composite, Primitive, Value, ValueDef};
+
+/// Type names of fields holding 8 byte identifiers, which we turn into strings. `LockIdentifier` is a
+/// plain `[u8; 8]` alias, so the name a field was given is all that distinguishes it from other arrays.
+const IDENTIFIER_TYPE_NAMES: &[&str] = &["LockIdentifier", "PalletId"];
+
+/// Rewrite a decoded value (and everything inside it) into a friendlier shape for display:
+///
+/// - 8 byte identifiers (`PalletId`s and `LockIdentifier`s) made up of printable ASCII, like `"py/trsry"` or
+///   `"staking "`, become strings. Other identifiers are left as the bytes they hold.
+/// - Democracy (and conviction voting) `Vote`s, which are packed into a single byte, become a named composite
+///   of `{ aye: bool, conviction: u8 }`.
+/// - `DispatchError::Module` errors are given the names of their pallet and error; see [`resolve_module_errors`].
+///
+/// Values decoded by this crate are left exactly as they were encoded, so that they can be encoded again; this
+/// pass is never applied to them unless asked for. The rewritten values keep the context of the original.
+pub fn humanize_value(metadata: &Metadata, value: &mut Value<TypeId>) {
+	humanize_types(metadata.types(), value);
+	resolve_module_errors(metadata, value);
+}
+
+/// The part of [`humanize_value`] which only needs to look at the types of the values.
+fn humanize_types(types: &PortableRegistry, value: &mut Value<TypeId>) {
+	value.transform(&mut |v: &mut Value<TypeId>| {
+		match types.resolve(v.context).map(|ty| (&ty.path.segments[..], &ty.type_def)) {
+			Some(([.., last], TypeDef::Composite(_))) if last == "PalletId" => return identifier_string(types, v),
+			Some(([first, .., last], TypeDef::Composite(_)))
+				if last == "Vote" && (first == "pallet_democracy" || first == "pallet_conviction_voting") =>
+			{
+				return vote_value(v)
+			}
+			_ => {}
+		}
+
+		// `LockIdentifier`s can only be spotted by the type name of the field holding them:
+		let (fields, values) = match &mut v.value {
+			ValueDef::Composite(values) => (fields_of(types, v.context, None), values),
+			ValueDef::Variant(variant) => (fields_of(types, v.context, Some(&variant.name)), &mut variant.values),
+			ValueDef::BitSequence(_) | ValueDef::Primitive(_) => return,
+		};
+		let values: Box<dyn Iterator<Item = &mut Value<TypeId>>> = match values {
+			Composite::Named(vals) => Box::new(vals.iter_mut().map(|(_, val)| val)),
+			Composite::Unnamed(vals) => Box::new(vals.iter_mut()),
+		};
+		for (field, val) in fields.iter().zip(values) {
+			if field.type_name.as_deref().is_some_and(|name| IDENTIFIER_TYPE_NAMES.contains(&name)) {
+				identifier_string(types, val);
+			}
+		}
+	});
+}
+
+/// The fields of a composite type, or of the named variant of a variant type.
+fn fields_of<'a>(types: &'a PortableRegistry, id: TypeId, variant: Option<&str>) -> &'a [Field<PortableForm>] {
+	match (types.resolve(id).map(|ty| &ty.type_def), variant) {
+		(Some(TypeDef::Composite(c)), None) => &c.fields,
+		(Some(TypeDef::Variant(v)), Some(name)) => {
+			v.variants.iter().find(|v| v.name == name).map_or(&[], |v| &v.fields)
+		}
+		_ => &[],
+	}
+}
+
+/// Unpack a `Vote` into `{ aye: bool, conviction: u8 }`. Votes are encoded as a single byte, whose top bit is
+/// set for an aye vote and whose remaining bits hold the conviction (from 0, for `None`, up to 6, for
+/// `Locked6x`). Values which don't wrap a single byte are left untouched.
+fn vote_value(value: &mut Value<TypeId>) {
+	let byte = match &value.value {
+		ValueDef::Composite(Composite::Unnamed(vals)) if vals.len() == 1 => vals[0].as_u128(),
+		_ => None,
+	};
+	let Some(byte) = byte.and_then(|n| u8::try_from(n).ok()) else { return };
+	let context = value.context;
+	let aye = Value { value: ValueDef::Primitive(Primitive::Bool(byte & 0b1000_0000 != 0)), context };
+	let conviction = Value { value: ValueDef::Primitive(Primitive::U128((byte & 0b0111_1111) as u128)), context };
+	let fields = vec![("aye".to_string(), aye), ("conviction".to_string(), conviction)];
+	value.value = ValueDef::Composite(Composite::Named(fields));
+}
+
+/// Turn an 8 byte identifier made up of printable ASCII into a string value. Anything else is left untouched,
+/// so that a string always holds the identifier's bytes exactly.
+fn identifier_string(types: &PortableRegistry, value: &mut Value<TypeId>) {
+	let bytes = match bytes_of(value, types) {
+		Some(bytes) if bytes.len() == 8 && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') => bytes,
+		_ => return,
+	};
+	let s = String::from_utf8(bytes).expect("printable ASCII is valid UTF-8; qed");
+	value.value = ValueDef::Primitive(Primitive::String(s));
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::decoder::{decode_value, encode_value};
+	use parity_scale_codec::Encode;
+	use scale_info::{meta_type, Registry, TypeInfo};
+
+	/// Decode the value, check that it's left as it was encoded, and then humanize it.
+	fn humanize<T: TypeInfo + Encode + 'static>(val: &T) -> Value<()> {
+		let mut registry = Registry::new();
+		let id = registry.register_type(&meta_type::<T>()).id;
+		let types: PortableRegistry = registry.into();
+
+		let bytes = val.encode();
+		let mut value = decode_value::decode(&mut &*bytes, id, &types).unwrap();
+		assert_eq!(encode_value::encode(&value, &types).unwrap(), bytes);
+		humanize_types(&types, &mut value);
+		value.remove_context()
+	}
+
+	#[test]
+	fn pallet_ids_are_humanized_as_strings() {
+		#[derive(Encode, TypeInfo)]
+		struct PalletId([u8; 8]);
+
+		assert_eq!(humanize(&PalletId(*b"py/trsry")), Value::string("py/trsry"));
+
+		// Identifiers which aren't printable stay as bytes, so no string is ambiguous:
+		let bytes = [0, 1, 2, 3, 0xff, b'a', b' ', b'\n'];
+		let expected = Value::unnamed_composite(vec![Value::unnamed_composite(bytes.map(|b| Value::u128(b as u128)))]);
+		assert_eq!(humanize(&PalletId(bytes)), expected);
+		assert_eq!(humanize(&PalletId(*b"0x123456")), Value::string("0x123456"));
+	}
+
+	#[test]
+	fn lock_identifiers_are_humanized_as_strings() {
+		type LockIdentifier = [u8; 8];
+		#[derive(Encode, TypeInfo)]
+		struct BalanceLock {
+			id: LockIdentifier,
+			amount: u64,
+			other: [u8; 8],
+		}
+
+		let lock = BalanceLock { id: *b"staking ", amount: 10, other: *b"staking " };
+		let expected = Value::named_composite(vec![
+			("id", Value::string("staking ")),
+			("amount", Value::u128(10)),
+			("other", Value::unnamed_composite(b"staking ".iter().map(|&b| Value::u128(b as u128)))),
+		]);
+		assert_eq!(humanize(&lock), expected);
+
+		let id = [0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0];
+		let lock = BalanceLock { id, amount: 10, other: [0; 8] };
+		let expected = Value::unnamed_composite(id.map(|b| Value::u128(b as u128)));
+		assert_eq!(humanize(&lock).get("id"), Some(&expected));
+	}
+
+	#[test]
+	fn democracy_votes_are_unpacked() {
+		let metadata = crate::Metadata::from_bytes(include_bytes!("../../tests/data/v14_metadata_polkadot.scale"))
+			.expect("valid metadata");
+		let (vote_id, _) = metadata
+			.iter_types()
+			.find(|(_, ty)| ty.path.segments == ["pallet_democracy", "vote", "Vote"])
+			.expect("Vote is in the registry");
+		let decode_vote = |byte: u8| decode_value::decode(&mut &[byte][..], vote_id, metadata.types()).unwrap();
+
+		// Without being humanized, a vote is left as the byte it was encoded as:
+		let mut aye = decode_vote(0b1000_0011);
+		assert_eq!(aye.clone().remove_context(), Value::unnamed_composite(vec![Value::u128(0b1000_0011)]));
+
+		humanize_value(&metadata, &mut aye);
+		assert_eq!(aye.context, vote_id);
+		let expected = Value::named_composite(vec![("aye", Value::bool(true)), ("conviction", Value::u128(3))]);
+		assert_eq!(aye.remove_context(), expected);
+
+		let mut nay = decode_vote(0);
+		humanize_value(&metadata, &mut nay);
+		let expected = Value::named_composite(vec![("aye", Value::bool(false)), ("conviction", Value::u128(0))]);
+		assert_eq!(nay.remove_context(), expected);
+	}
+}
@@ -0,0 +1,96 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::DecodeError;
+use crate::{Metadata, ValueExt};
+use scale_value::Value;
+use serde::Serialize;
+
+/// The runtime version that was last upgraded to, as stored in `System.LastRuntimeUpgrade`. Comparing this
+/// from one block to the next tells an indexer when the runtime, and so the metadata needed to decode the
+/// chain, has changed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LastRuntimeUpgradeInfo {
+	/// The spec version of the runtime.
+	pub spec_version: u32,
+	/// The spec name of the runtime, e.g. `polkadot`.
+	pub spec_name: String,
+}
+
+impl LastRuntimeUpgradeInfo {
+	/// Read the runtime version from an already decoded `LastRuntimeUpgradeInfo` value. Returns `None` if the
+	/// value doesn't look like a `LastRuntimeUpgradeInfo`.
+	pub fn from_value<T>(value: &Value<T>) -> Option<LastRuntimeUpgradeInfo> {
+		let spec_version = value.get("spec_version")?.as_u128()?.try_into().ok()?;
+		let spec_name = value.get("spec_name")?.as_str()?.to_owned();
+		Some(LastRuntimeUpgradeInfo { spec_version, spec_name })
+	}
+
+	/// Has the runtime been upgraded between this and some `previous` runtime version? The spec name is
+	/// compared as well as the spec version, so that a change of runtime altogether is also noticed.
+	pub fn is_upgrade_from(&self, previous: &LastRuntimeUpgradeInfo) -> bool {
+		self.spec_version != previous.spec_version || self.spec_name != previous.spec_name
+	}
+}
+
+/// Decode the SCALE encoded value of the `System.LastRuntimeUpgrade` storage entry into a
+/// [`LastRuntimeUpgradeInfo`].
+pub fn decode_last_runtime_upgrade(
+	metadata: &Metadata,
+	data: &mut &[u8],
+) -> Result<LastRuntimeUpgradeInfo, DecodeError> {
+	let value = super::decode_storage_value_by_name(metadata, "System", "LastRuntimeUpgrade", data)?;
+	LastRuntimeUpgradeInfo::from_value(&value).ok_or(DecodeError::UnrecognisedLastRuntimeUpgrade)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::{Compact, Encode};
+
+	fn metadata() -> Metadata {
+		let bytes = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+		Metadata::from_bytes(bytes).expect("valid metadata")
+	}
+
+	#[test]
+	fn decodes_last_runtime_upgrade() {
+		// A compact spec version followed by the spec name:
+		let bytes = (Compact(9110u32), "polkadot").encode();
+		let cursor = &mut &*bytes;
+
+		let info = decode_last_runtime_upgrade(&metadata(), cursor).unwrap();
+		assert!(cursor.is_empty());
+		assert_eq!(info, LastRuntimeUpgradeInfo { spec_version: 9110, spec_name: "polkadot".to_owned() });
+	}
+
+	#[test]
+	fn notices_upgrades() {
+		let info = |spec_version, spec_name: &str| LastRuntimeUpgradeInfo { spec_version, spec_name: spec_name.into() };
+
+		assert!(info(9110, "polkadot").is_upgrade_from(&info(9100, "polkadot")));
+		assert!(info(9110, "polkadot").is_upgrade_from(&info(9110, "kusama")));
+		assert!(!info(9110, "polkadot").is_upgrade_from(&info(9110, "polkadot")));
+	}
+
+	#[test]
+	fn rejects_other_shapes() {
+		let value = Value::named_composite([("spec_version", Value::u128(1 << 40)), ("spec_name", Value::string("a"))]);
+		assert_eq!(LastRuntimeUpgradeInfo::from_value(&value), None);
+		let value = Value::named_composite([("spec_version", Value::u128(1))]);
+		assert_eq!(LastRuntimeUpgradeInfo::from_value(&value), None);
+	}
+}
@@ -0,0 +1,42 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Hooks to observe decoding as it happens, for instance to keep counters in an indexer. The means to plug an
+//! observer in, `decode_extrinsics_observed` and `decode_call_data_observed`, are only available with the
+//! `metrics` feature.
+
+use super::{CallData, DecodeError, Extrinsic};
+
+/// Told about each thing decoded by `decode_extrinsics_observed` (including the call of each extrinsic) and
+/// `decode_call_data_observed`, and about each failure. Every method does nothing by default, so only the
+/// interesting ones need implementing. Methods take `&self` so that one observer can be shared between threads;
+/// use atomics or similar to count.
+pub trait DecodeObserver {
+	/// An extrinsic was decoded from `bytes` bytes (not counting its length prefix).
+	fn extrinsic_decoded(&self, _extrinsic: &Extrinsic<'_>, _bytes: usize) {}
+
+	/// Some call data was decoded from `bytes` bytes.
+	fn call_decoded(&self, _call: &CallData<'_>, _bytes: usize) {}
+
+	/// Decoding failed with the given error. Match on it to count errors by kind.
+	fn decode_failed(&self, _error: &DecodeError) {}
+}
+
+/// A [`DecodeObserver`] which ignores everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopObserver;
+
+impl DecodeObserver for NoopObserver {}
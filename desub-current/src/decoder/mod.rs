@@ -19,30 +19,94 @@
 //!
 //! See [`decode_extrinsics`], [`decode_extrinsic`], and [`decode_unwrapped_extrinsic`] for the most
 //! common extrinsic decoding needs.
+//! See [`decode_block`] to decode each extrinsic in a block body along with its index and hash.
 //!
-//! See [`decode_storage()`] and then the documentation on [`StorageDecoder`] to decode storage lookups.
+//! See [`decode_storage()`] and then the documentation on [`StorageDecoder`] to decode storage lookups, and
+//! [`decode_well_known_storage`] for storage, like the runtime code, that lives under well known keys. See
+//! [`decode_account_info`] to decode the details of an account, and [`decode_last_runtime_upgrade`] to find the
+//! runtime version that the chain last upgraded to.
+//!
+//! See [`decode_events`] to decode the events stored in `System.Events`, and [`correlate_events`] to group them by
+//! the extrinsic which emitted them.
+//!
+//! See [`decode_timestamp_from_block`] to find the time at which a block was authored.
+//!
+//! See [`decode_apply_extrinsic_result`] to decode the result of dry running an extrinsic.
+//!
+//! Decoded values are left as they were encoded, so that they can be encoded again. See [`humanize_value`] to
+//! rewrite them into a friendlier shape for display.
 
+mod account_info;
+mod apply_extrinsic_result;
+mod decode_hints;
 mod decode_storage;
+mod decode_value;
+mod encode_value;
 mod extrinsic_bytes;
+mod humanize;
+mod last_runtime_upgrade;
+mod metrics;
+mod module_error;
+mod well_known_storage;
 
-use crate::metadata::Metadata;
+use crate::metadata::{CallLookupError, Metadata, SignerFormat};
 use crate::TypeId;
-use extrinsic_bytes::{AllExtrinsicBytes, ExtrinsicBytesError};
-use parity_scale_codec::{Compact, Decode};
-use scale_decode::DecodeAsType;
-use scale_value::Value;
+use extrinsic_bytes::AllExtrinsicBytes;
+use parity_scale_codec::{Compact, Decode, Encode, IoReader};
+use scale_info::PortableRegistry;
+use scale_value::{Composite, Value, ValueDef, Variant};
 use serde::Serialize;
+use sp_core::{ecdsa, ed25519, sr25519, H256};
 use sp_runtime::{AccountId32, MultiAddress, MultiSignature};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Read;
 
 // Re-export the DecodeValueError here, which we expose in our global `DecodeError` enum.
 pub use scale_decode::Error as DecodeValueError;
 
+// Re-export the hints which change how the arguments of particular calls are decoded.
+pub use decode_hints::DecodeHints;
+
+// Re-export the field names used to present decoded maps, and the limits on decoding values.
+pub use decode_value::{DetailedDecodeValueError, TooDeepError, MAP_KEY_FIELD, MAP_VALUE_FIELD, MAX_DEPTH};
+
+// Re-export the error returned when a value can't be encoded.
+pub use encode_value::EncodeValueError;
+
+// Re-export the error returned when a vector of extrinsics is malformed.
+pub use extrinsic_bytes::ExtrinsicBytesError;
+
+// Re-export the means to observe decoding.
+#[cfg(feature = "metrics")]
+pub use metrics::{DecodeObserver, NoopObserver};
+#[cfg(not(feature = "metrics"))]
+use metrics::{DecodeObserver, NoopObserver};
+
+// Re-export the opt-in pass which makes decoded values easier to read.
+pub use humanize::humanize_value;
+
+// Re-export the means to name `DispatchError::Module` errors, and the field names used to do so.
+pub use module_error::{resolve_module_errors, MODULE_ERROR_NAME_FIELD, MODULE_ERROR_PALLET_FIELD};
+
 // Re-export storage related types that are part of our public interface.
 pub use decode_storage::{
-	StorageDecodeError, StorageDecoder, StorageEntry, StorageEntryType, StorageHasher, StorageMapKey,
+	CheckpointedStorageDecodeError, StorageDecodeError, StorageDecoder, StorageEntry, StorageEntryType, StorageHasher,
+	StorageMapKey,
 };
 
+// Re-export the means to decode the details of an account stored in `System.Account`.
+pub use account_info::{decode_account_info, AccountData, AccountInfo, FrozenBalance};
+
+// Re-export the means to decode the result of applying (or dry running) an extrinsic.
+pub use apply_extrinsic_result::{decode_apply_extrinsic_result, ApplyExtrinsicResult};
+
+// Re-export the means to decode the runtime version stored in `System.LastRuntimeUpgrade`.
+pub use last_runtime_upgrade::{decode_last_runtime_upgrade, LastRuntimeUpgradeInfo};
+
+// Re-export the means to decode storage that lives under well known keys rather than in a pallet.
+pub use well_known_storage::{decode_well_known_storage, WellKnownStorage, WellKnownStorageValue};
+
 /// An enum of the possible errors that can be returned from attempting to decode bytes
 /// using the functions in this module.
 #[derive(Debug, thiserror::Error)]
@@ -51,6 +115,8 @@ pub enum DecodeError {
 	UnexpectedExtrinsicsShape(#[from] ExtrinsicBytesError),
 	#[error("Failed to decode: {0}")]
 	CodecError(#[from] parity_scale_codec::Error),
+	#[error("Failed to read: {0}")]
+	Io(#[from] std::io::Error),
 	#[error("Failed to decode type: {0}")]
 	DecodeValueError(#[from] DecodeValueError),
 	#[error("Failed to decode: expected more data")]
@@ -61,23 +127,209 @@ pub enum DecodeError {
 	CannotDecodeExtrinsicVersion(u8),
 	#[error("Cannot find call corresponding to extrinsic with pallet index {0} and call index {1}")]
 	CannotFindCall(u8, u8),
+	#[error("Cannot decode call: the pallet with index {0} has no calls")]
+	PalletHasNoCalls(u8),
+	#[error("Cannot decode call: the pallet with index {0} has no call with index {1}")]
+	CallIndexOutOfRange(u8, u8),
+	#[error("Cannot find event with pallet index {0} and event index {1}")]
+	CannotFindEvent(u8, u8),
 	#[error("Failed to decode extrinsic: cannot find type ID {0}")]
 	CannotFindType(u32),
+	#[error("Failed to decode extrinsics: {count} extrinsics were reported, but at most {max} are allowed")]
+	TooManyExtrinsics { count: usize, max: usize },
+	#[error("Cannot find extrinsic at index {0}; only {1} extrinsics were reported")]
+	ExtrinsicNotFound(usize, usize),
+	#[error("Cannot find storage entry {0}.{1}")]
+	CannotFindStorageEntry(String, String),
+	#[error("Cannot find custom value {0} in the metadata")]
+	CannotFindCustomValue(String),
+	#[error("Cannot decode account info: the value doesn't have the expected fields")]
+	UnrecognisedAccountInfo,
+	#[error("Cannot decode the last runtime upgrade: the value doesn't have the expected fields")]
+	UnrecognisedLastRuntimeUpgrade,
+	#[error("Cannot find the DigestItem type in the metadata")]
+	CannotFindDigestItemType,
+	#[error("Cannot find the DispatchError type in the metadata")]
+	CannotFindDispatchErrorType,
+	#[error("Cannot decode call: calls are nested more than {0} levels deep")]
+	CallTooDeep(usize),
+	#[error("Signed extension {extension:?} doesn't line up with the additional signed data for {additional:?}")]
+	ExtensionMismatch { extension: Option<String>, additional: Option<String> },
+}
+
+/// The maximum number of calls that we'll decode nested inside one another, whether passed directly as arguments
+/// or wrapped up in opaque ones, before giving up. Real calls are never nested anywhere near this deeply, but the
+/// limit stops crafted calls from overflowing the stack.
+pub const MAX_CALL_DEPTH: usize = 32;
+
+/// The errors that can be returned from [`encode_extrinsic`].
+#[derive(Debug, thiserror::Error)]
+pub enum EncodeExtrinsicError {
+	#[error("Cannot find pallet {0} in the metadata")]
+	CannotFindPallet(String),
+	#[error("The address and signature cannot be encoded in the {0:?} signer format of this chain")]
+	SignerFormatMismatch(SignerFormat),
+	#[error("The signature has no value for the signed extension {0}")]
+	MissingSignedExtension(String),
+	#[error("The call {call} takes {expected} arguments, but {got} were given")]
+	WrongArgumentCount { call: String, expected: usize, got: usize },
+	#[error("Failed to encode value: {0}")]
+	EncodeValue(#[from] EncodeValueError),
 }
 
 /// Decode a single [`Value`] from a piece of scale encoded data, given some metadata and the ID of the type that we
 /// are expecting it to decode into.
+///
+/// `BTreeMap`s are presented as an unnamed composite of entries, each of which is a named composite with
+/// a [`MAP_KEY_FIELD`] and a [`MAP_VALUE_FIELD`]. `BTreeSet`s are presented as an unnamed composite of their
+/// elements. The bounded versions of these collections are presented in the same way, and `BoundedVec`s and
+/// `WeakBoundedVec`s are presented as the sequence that they wrap.
+///
+/// Values nested more than [`MAX_DEPTH`] levels deep are rejected, so that recursive types can't overflow the stack.
 pub fn decode_value_by_id<Id: Into<TypeId>>(
 	metadata: &Metadata,
 	ty: Id,
 	data: &mut &[u8],
 ) -> Result<Value<TypeId>, DecodeValueError> {
-	Value::decode_as_type(data, ty.into(), metadata.types())
+	decode_value::decode(data, ty.into(), metadata.types())
+}
+
+/// Like [`decode_value_by_id`], but if decoding fails, the error also says where in the input the innermost value
+/// that couldn't be decoded starts, along with the bytes found there. This helps to track down type information
+/// which doesn't match the data being decoded.
+pub fn decode_value_by_id_detailed<Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	data: &mut &[u8],
+) -> Result<Value<TypeId>, DetailedDecodeValueError> {
+	decode_value::decode_detailed(data, ty.into(), metadata.types())
+}
+
+/// Decode a single [`Value`] from a piece of scale encoded data, given a type registry and the ID of the type that
+/// we are expecting it to decode into. Unlike [`decode_value_by_id`], this doesn't need any [`Metadata`], which is
+/// handy for decoding things like runtime API results, where all that's at hand is a [`PortableRegistry`].
+///
+/// Values are presented in the same way as they are by [`decode_value_by_id`].
+pub fn decode_value_with_registry<Id: Into<TypeId>>(
+	registry: &PortableRegistry,
+	ty: Id,
+	data: &mut &[u8],
+) -> Result<Value<TypeId>, DecodeValueError> {
+	decode_value::decode(data, ty.into(), registry)
+}
+
+/// Encode a [`Value`] that was decoded using the functions in this module back into SCALE bytes. The value is
+/// encoded as the type given by its context, which is the type that it was decoded as, so the bytes that it was
+/// decoded from are recovered exactly. This includes the length prefixes of sequences (which arrays don't have),
+/// even though both are decoded to an unnamed composite.
+pub fn encode_value(metadata: &Metadata, value: &Value<TypeId>) -> Result<Vec<u8>, EncodeValueError> {
+	encode_value::encode(value, metadata.types())
+}
+
+/// Encode an [`Extrinsic`] that was decoded using the functions in this module back into SCALE bytes, in the same
+/// length prefixed form that [`decode_extrinsic`] expects. The signature (if any) is encoded in the signer format of
+/// the chain, followed by the signed extensions in the order that the metadata lists them, and then the call data.
+/// Call arguments and signed extensions are encoded as the types that the metadata gives for them, much like
+/// [`encode_value`] does. An extrinsic can therefore be decoded, modified and encoded again, so long as its call is
+/// given as many arguments as the call takes.
+pub fn encode_extrinsic(metadata: &Metadata, extrinsic: &Extrinsic) -> Result<Vec<u8>, EncodeExtrinsicError> {
+	// We only decode V4 extrinsics, so that is what we encode too.
+	let mut out = vec![if extrinsic.is_signed() { 0b1000_0100 } else { 0b0000_0100 }];
+
+	if let Some(signature) = &extrinsic.signature {
+		if metadata.extrinsic().optional_signature() {
+			out.push(1);
+		}
+		let format = metadata.extrinsic().signer_format();
+		match (format, &signature.address, &signature.signature) {
+			(SignerFormat::Substrate, address, signature) => (address, signature).encode_to(&mut out),
+			(SignerFormat::AccountId32, MultiAddress::Id(id), signature) => (id, signature).encode_to(&mut out),
+			(SignerFormat::Ethereum, MultiAddress::Address20(address), MultiSignature::Ecdsa(signature)) => {
+				(address, AsRef::<[u8; 65]>::as_ref(signature)).encode_to(&mut out)
+			}
+			_ => return Err(EncodeExtrinsicError::SignerFormatMismatch(format)),
+		}
+		for ext in metadata.extrinsic().signed_extensions() {
+			let (_, value) = signature
+				.extensions
+				.iter()
+				.find(|(name, _)| *name == ext.identifier)
+				.ok_or_else(|| EncodeExtrinsicError::MissingSignedExtension(ext.identifier.clone()))?;
+			out.extend(encode_value::encode_as(value, ext.ty.id, metadata.types())?);
+		}
+	}
+
+	let call_data = &extrinsic.call_data;
+	let pallet_index = metadata
+		.pallet_call_index_by_name(&call_data.pallet_name)
+		.ok_or_else(|| EncodeExtrinsicError::CannotFindPallet(call_data.pallet_name.to_string()))?;
+	out.extend([pallet_index, call_data.ty.index]);
+	// Calls decoded leniently hand back only the arguments before their opaque remainder.
+	let (expected, got) = (call_data.ty.fields.len(), call_data.arguments.len());
+	let counts_match = if call_data.opaque_remainder.is_some() { got < expected } else { got == expected };
+	if !counts_match {
+		return Err(EncodeExtrinsicError::WrongArgumentCount { call: call_data.ty.name.clone(), expected, got });
+	}
+	for (value, field) in call_data.arguments.iter().zip(&call_data.ty.fields) {
+		out.extend(encode_value::encode_as(value, field.ty.id, metadata.types())?);
+	}
+	if let Some(remainder) = &call_data.opaque_remainder {
+		out.extend(remainder);
+	}
+
+	Ok(out.encode())
+}
+
+/// Decode a storage value, given the storage prefix of the pallet that it belongs to (normally the pallet name)
+/// and the name of the storage entry. This is useful when the storage entry is already known, so there's no
+/// need to decode the storage key (see [`decode_storage`] for that).
+///
+/// # Example
+///
+/// ```rust
+/// use desub_current::{Metadata, Value, decoder};
+/// use parity_scale_codec::Encode;
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// let value = decoder::decode_storage_value_by_name(&metadata, "Timestamp", "Now", &mut &*123u64.encode()).unwrap();
+/// assert_eq!(value.remove_context(), Value::u128(123));
+/// ```
+pub fn decode_storage_value_by_name(
+	metadata: &Metadata,
+	prefix: &str,
+	name: &str,
+	data: &mut &[u8],
+) -> Result<Value<TypeId>, DecodeError> {
+	let entry = metadata
+		.storage_entry_by_name(prefix, name)
+		.ok_or_else(|| DecodeError::CannotFindStorageEntry(prefix.to_string(), name.to_string()))?;
+	let ty = match &entry.ty {
+		frame_metadata::v14::StorageEntryType::Plain(ty) => ty,
+		frame_metadata::v14::StorageEntryType::Map { value, .. } => value,
+	};
+	Ok(decode_value_by_id(metadata, ty.id, data)?)
+}
+
+/// Decode one of the chain specific values in the `custom` section of V15+ metadata (see [`Metadata::custom`])
+/// by its name. All of the bytes of the value must be used up in decoding it.
+pub fn decode_custom_value(metadata: &Metadata, name: &str) -> Result<Value<TypeId>, DecodeError> {
+	let (ty, mut bytes) =
+		metadata.custom_value(name).ok_or_else(|| DecodeError::CannotFindCustomValue(name.to_string()))?;
+	let value = decode_value_by_id(metadata, ty, &mut bytes)?;
+	if !bytes.is_empty() {
+		return Err(DecodeError::ExcessBytes(bytes.len()));
+	}
+	Ok(value)
 }
 
 /// Generate a [`StorageDecoder`] struct which is capable of decoding SCALE encoded storage keys. It's advisable
 /// to cache this struct if you are decoding lots of storage entries, since it is non-trivial to create.
 ///
+/// This fails with [`StorageDecodeError::PrefixCollision`] if the storage prefixes of two pallets have the same
+/// hash, since we'd have no way to tell which of them a key belongs to.
+///
 /// # Example
 ///
 /// ```rust
@@ -95,7 +347,7 @@ pub fn decode_value_by_id<Id: Into<TypeId>>(
 /// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
 ///
 /// // With the help of our metadata, we can create a storage decoder:
-/// let storage_decoder = decoder::decode_storage(&metadata);
+/// let storage_decoder = decoder::decode_storage(&metadata).unwrap();
 ///
 /// // Hex representing a lookup like `System.BlockHash(1000)`
 /// // (which contains values of type `[u8; 32]`):
@@ -126,7 +378,7 @@ pub fn decode_value_by_id<Id: Into<TypeId>>(
 /// #     Value::unnamed_composite(vec![Value::from_bytes(vec![1u8; 32])])
 /// # );
 /// ```
-pub fn decode_storage(metadata: &Metadata) -> StorageDecoder {
+pub fn decode_storage(metadata: &Metadata) -> Result<StorageDecoder, StorageDecodeError> {
 	decode_storage::StorageDecoder::generate_from_metadata(metadata)
 }
 
@@ -135,6 +387,10 @@ pub fn decode_storage(metadata: &Metadata) -> StorageDecoder {
 /// start with a compact encoded count of how many extrinsics exist, and then each extrinsic is prefixed by
 /// a compact encoding of its byte length.
 ///
+/// There's no per-block state to set up or reuse here: [`Metadata`] indexes pallets and calls by their `u8`
+/// indexes in fixed size lookup tables when it's created, so finding the call for each extrinsic is a couple of
+/// array lookups, and decoding a block of extrinsics costs the same as decoding each of them in turn.
+///
 /// # Example
 ///
 /// ```rust
@@ -158,32 +414,78 @@ pub fn decode_extrinsics<'a>(
 	metadata: &'a Metadata,
 	data: &mut &[u8],
 ) -> Result<Vec<Extrinsic<'a>>, (Vec<Extrinsic<'a>>, DecodeError)> {
-	let extrinsic_bytes = AllExtrinsicBytes::new(data).map_err(|e| (Vec::new(), e.into()))?;
+	decode_extrinsics_with_max(metadata, data, usize::MAX)
+}
+
+/// Like [`decode_extrinsics`], but fail with [`DecodeError::TooManyExtrinsics`] before decoding anything if the
+/// vector reports more than `max_extrinsics` extrinsics. This is handy to reject block bodies from untrusted
+/// sources which claim absurd numbers of extrinsics.
+pub fn decode_extrinsics_with_max<'a>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	max_extrinsics: usize,
+) -> Result<Vec<Extrinsic<'a>>, (Vec<Extrinsic<'a>>, DecodeError)> {
+	decode_extrinsics_inner(metadata, data, max_extrinsics, &NoopObserver)
+}
+
+/// Like [`decode_extrinsics`], but tell `observer` about each extrinsic decoded and about any failure.
+#[cfg(feature = "metrics")]
+pub fn decode_extrinsics_observed<'a, O: DecodeObserver + ?Sized>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	observer: &O,
+) -> Result<Vec<Extrinsic<'a>>, (Vec<Extrinsic<'a>>, DecodeError)> {
+	decode_extrinsics_inner(metadata, data, usize::MAX, observer)
+}
+
+fn decode_extrinsics_inner<'a, O: DecodeObserver + ?Sized>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	max_extrinsics: usize,
+	observer: &O,
+) -> Result<Vec<Extrinsic<'a>>, (Vec<Extrinsic<'a>>, DecodeError)> {
+	let fail = |out, err| {
+		observer.decode_failed(&err);
+		Err((out, err))
+	};
+
+	let extrinsic_bytes = match AllExtrinsicBytes::new(data) {
+		Ok(extrinsic_bytes) => extrinsic_bytes,
+		Err(e) => return fail(Vec::new(), e.into()),
+	};
+	if extrinsic_bytes.len() > max_extrinsics {
+		let err = DecodeError::TooManyExtrinsics { count: extrinsic_bytes.len(), max: max_extrinsics };
+		return fail(Vec::new(), err);
+	}
 
 	log::trace!("Decoding {} Total Extrinsics.", extrinsic_bytes.len());
 
-	let mut out = Vec::with_capacity(extrinsic_bytes.len());
+	// The count is untrusted, so don't reserve more room than the data could hold; each extrinsic takes at least
+	// a byte for its length prefix, a version byte and two bytes for its pallet and call indexes.
+	const MIN_EXTRINSIC_LEN: usize = 4;
+	let mut out = Vec::with_capacity(extrinsic_bytes.len().min(data.len() / MIN_EXTRINSIC_LEN));
 	let mut extrinsics_iter = extrinsic_bytes.iter();
 	for res in &mut extrinsics_iter {
 		let single_extrinsic = match res {
 			Ok(bytes) => bytes,
-			Err(e) => return Err((out, e.into())),
+			Err(e) => return fail(out, e.into()),
 		};
 
 		log::trace!("Extrinsic:{:?}", single_extrinsic.bytes());
 
 		let bytes = &mut single_extrinsic.bytes();
-		let ext = match decode_unwrapped_extrinsic(metadata, bytes) {
+		let ext = match decode_unwrapped_extrinsic_inner(metadata, bytes, None, observer) {
 			Ok(ext) => ext,
-			Err(e) => return Err((out, e)),
+			Err(e) => return fail(out, e),
 		};
 
 		// If decoding didn't consume all extrinsic bytes, something went wrong.
 		// Hand back whatever we have but note the error.
 		if !bytes.is_empty() {
-			return Err((out, DecodeError::ExcessBytes(bytes.len())));
+			return fail(out, DecodeError::ExcessBytes(bytes.len()));
 		}
 
+		observer.extrinsic_decoded(&ext, single_extrinsic.bytes().len());
 		out.push(ext);
 	}
 
@@ -194,6 +496,196 @@ pub fn decode_extrinsics<'a>(
 	Ok(out)
 }
 
+/// Split a SCALE encoded vector of extrinsics, in the same shape as expected by [`decode_extrinsics`], into the
+/// bytes of each extrinsic (not including their length prefixes), without decoding them. Any bytes following
+/// the last extrinsic are ignored.
+///
+/// Returns an error containing the byte index at which things went wrong if a length prefix can't be decoded,
+/// or if there are fewer extrinsics than the vector reports.
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::decoder;
+///
+/// // the same extrinsic repeated 3 times:
+/// let extrinsics_hex = "0x0C2004480104080c10142004480104080c10142004480104080c1014";
+/// let extrinsics_bytes = hex::decode(extrinsics_hex.strip_prefix("0x").unwrap()).unwrap();
+///
+/// let extrinsics = decoder::split_extrinsics(&extrinsics_bytes).unwrap();
+///
+/// assert_eq!(extrinsics.len(), 3);
+/// assert_eq!(extrinsics[0], &extrinsics_bytes[2..10]);
+/// ```
+pub fn split_extrinsics(data: &[u8]) -> Result<Vec<&[u8]>, ExtrinsicBytesError> {
+	let extrinsic_bytes = AllExtrinsicBytes::new(data)?;
+	let extrinsics = extrinsic_bytes.iter().map(|res| res.map(|ext| ext.bytes())).collect::<Result<Vec<_>, _>>()?;
+
+	// The iterator stops early if it runs out of bytes, so make sure that we got everything we were told about.
+	if extrinsics.len() < extrinsic_bytes.len() {
+		return Err(ExtrinsicBytesError { index: data.len() });
+	}
+	Ok(extrinsics)
+}
+
+/// Decode a SCALE encoded vector of extrinsics (ie a block body), in the same shape as expected by
+/// [`decode_extrinsics`], handing back each extrinsic along with its index in the block and its hash (the
+/// blake2_256 hash of its length prefixed bytes, by which it's known on chain). Unlike [`decode_extrinsics`],
+/// failing to decode one extrinsic doesn't stop the rest from being decoded.
+///
+/// The block is split into extrinsics up front, and an error is returned if that fails; nothing is decoded until
+/// the iterator is advanced. If there are bytes left over after the last extrinsic, one more item is yielded
+/// after the extrinsics, with the next index, the blake2_256 hash of the leftover bytes and a
+/// [`DecodeError::ExcessBytes`] error.
+pub fn decode_block<'a: 'b, 'b>(
+	metadata: &'a Metadata,
+	data: &'b [u8],
+) -> Result<impl Iterator<Item = (usize, H256, Result<Extrinsic<'a>, DecodeError>)> + 'b, DecodeError> {
+	let extrinsic_bytes = AllExtrinsicBytes::new(data)?;
+	let mut extrinsics_iter = extrinsic_bytes.iter();
+	let extrinsics = (&mut extrinsics_iter).map(|res| res.map(|ext| ext.bytes())).collect::<Result<Vec<_>, _>>()?;
+	// The iterator stops early if it runs out of bytes, so make sure that we got everything we were told about.
+	if extrinsics.len() < extrinsic_bytes.len() {
+		return Err(ExtrinsicBytesError { index: data.len() }.into());
+	}
+
+	let remaining = extrinsics_iter.remaining_bytes();
+	let excess = (!remaining.is_empty()).then(|| {
+		(extrinsics.len(), H256(sp_core::blake2_256(remaining)), Err(DecodeError::ExcessBytes(remaining.len())))
+	});
+	let decoded = extrinsics.into_iter().enumerate().map(move |(index, bytes)| {
+		let hash = H256(sp_core::blake2_256(&bytes.encode()));
+		let cursor = &mut &*bytes;
+		let ext = decode_unwrapped_extrinsic(metadata, cursor).and_then(|ext| match cursor.is_empty() {
+			true => Ok(ext),
+			false => Err(DecodeError::ExcessBytes(cursor.len())),
+		});
+		(index, hash, ext)
+	});
+	Ok(decoded.chain(excess))
+}
+
+/// Return the number of extrinsics that a SCALE encoded vector of extrinsics (in the same shape as expected
+/// by [`decode_extrinsics`]) says that it contains. Only the compact count at the start is read, so this is
+/// cheap, but nothing is checked about the extrinsics themselves; use [`split_extrinsics`] for that.
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::decoder;
+///
+/// // the same extrinsic repeated 3 times:
+/// let extrinsics_hex = "0x0C2004480104080c10142004480104080c10142004480104080c1014";
+/// let extrinsics_bytes = hex::decode(extrinsics_hex.strip_prefix("0x").unwrap()).unwrap();
+///
+/// assert_eq!(decoder::count_extrinsics(&extrinsics_bytes).unwrap(), 3);
+/// ```
+pub fn count_extrinsics(data: &[u8]) -> Result<usize, ExtrinsicBytesError> {
+	AllExtrinsicBytes::new(data).map(|exts| exts.len())
+}
+
+/// Decode a SCALE encoded vector of extrinsics, in the same shape as expected by [`decode_extrinsics`], from
+/// a reader. Extrinsics are read and decoded one at a time, so only the bytes of the extrinsic being decoded
+/// are buffered. The reader is left positioned after the last extrinsic.
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::{ Metadata, decoder };
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// // the same extrinsic repeated 3 times:
+/// let extrinsics_hex = "0x0C2004480104080c10142004480104080c10142004480104080c1014";
+/// let extrinsics_bytes = hex::decode(extrinsics_hex.strip_prefix("0x").unwrap()).unwrap();
+///
+/// let extrinsics = decoder::decode_extrinsics_from_reader(&metadata, std::io::Cursor::new(extrinsics_bytes)).unwrap();
+///
+/// assert_eq!(extrinsics.len(), 3);
+/// ```
+pub fn decode_extrinsics_from_reader<'a>(
+	metadata: &'a Metadata,
+	mut reader: impl Read,
+) -> Result<Vec<Extrinsic<'a>>, (Vec<Extrinsic<'a>>, DecodeError)> {
+	let len = <Compact<u32>>::decode(&mut IoReader(&mut reader)).map_err(|e| (Vec::new(), e.into()))?.0;
+	log::trace!("Decoding {} Total Extrinsics.", len);
+
+	let mut out = Vec::new();
+	let mut buf = Vec::new();
+	for _ in 0..len {
+		let ext_len = match <Compact<u32>>::decode(&mut IoReader(&mut reader)) {
+			Ok(ext_len) => ext_len.0 as usize,
+			Err(e) => return Err((out, e.into())),
+		};
+
+		// Read through `take` rather than allocating the whole length up front, in case it's bogus:
+		buf.clear();
+		if let Err(e) = (&mut reader).take(ext_len as u64).read_to_end(&mut buf) {
+			return Err((out, e.into()));
+		}
+		if buf.len() < ext_len {
+			return Err((out, DecodeError::EarlyEof("extrinsic is shorter than its length prefix")));
+		}
+
+		let bytes = &mut &*buf;
+		let ext = match decode_unwrapped_extrinsic(metadata, bytes) {
+			Ok(ext) => ext,
+			Err(e) => return Err((out, e)),
+		};
+		if !bytes.is_empty() {
+			return Err((out, DecodeError::ExcessBytes(bytes.len())));
+		}
+
+		out.push(ext);
+	}
+
+	Ok(out)
+}
+
+/// Decode only the extrinsic at index `n` (starting from 0) from a SCALE encoded vector of extrinsics, in the same
+/// shape as expected by [`decode_extrinsics`]. Because each extrinsic is prefixed by its byte length, the preceding
+/// extrinsics are skipped over without being decoded.
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::{ Metadata, decoder };
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// // the same extrinsic repeated 3 times:
+/// let extrinsics_hex = "0x0C2004480104080c10142004480104080c10142004480104080c1014";
+/// let extrinsics_bytes = hex::decode(extrinsics_hex.strip_prefix("0x").unwrap()).unwrap();
+///
+/// let extrinsic = decoder::decode_nth_extrinsic(&metadata, &extrinsics_bytes, 2).unwrap();
+///
+/// assert_eq!(extrinsic.call_data.pallet_name, "Auctions");
+/// assert_eq!(&*extrinsic.call_data.ty.name(), "bid");
+/// ```
+pub fn decode_nth_extrinsic<'a>(metadata: &'a Metadata, data: &[u8], n: usize) -> Result<Extrinsic<'a>, DecodeError> {
+	let extrinsic_bytes = AllExtrinsicBytes::new(data)?;
+
+	let single_extrinsic = match extrinsic_bytes.iter().nth(n) {
+		Some(res) => res?,
+		None => return Err(DecodeError::ExtrinsicNotFound(n, extrinsic_bytes.len())),
+	};
+
+	let bytes = &mut single_extrinsic.bytes();
+	let ext = decode_unwrapped_extrinsic(metadata, bytes)?;
+
+	if !bytes.is_empty() {
+		return Err(DecodeError::ExcessBytes(bytes.len()));
+	}
+
+	Ok(ext)
+}
+
 /// Decode a SCALE encoded extrinsic against the metadata provided. Conceptually, an individual extrinsic is expected
 /// to be represented in terms of a compact encoded count of its length in bytes, and then the actual extrinsic
 /// information (the optional signature and call data).
@@ -228,6 +720,41 @@ pub fn decode_extrinsic<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<
 	decode_unwrapped_extrinsic(metadata, data)
 }
 
+/// Like [`decode_extrinsic`], but the [`DecodeHints`] given can change how the arguments of particular calls are
+/// decoded; for instance, to leave the large proofs given to a parachain's `set_validation_data` inherent opaque.
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::{ Metadata, Value, decoder::{ self, DecodeHints } };
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// let extrinsic_hex = "0x2004480104080c1014";
+/// let extrinsic_bytes = hex::decode(extrinsic_hex.strip_prefix("0x").unwrap()).unwrap();
+/// let extrinsic_cursor = &mut &*extrinsic_bytes;
+///
+/// let hints = DecodeHints::new().opaque_fields("Auctions", "bid", ["amount"]);
+/// let extrinsic = decoder::decode_extrinsic_with_hints(&metadata, extrinsic_cursor, &hints).unwrap();
+///
+/// assert_eq!(extrinsic_cursor.len(), 0);
+/// assert_eq!(&*extrinsic.call_data.ty.name(), "bid");
+/// // The amount is handed back as the bytes that it was encoded to:
+/// let amount = extrinsic.call_data.arguments[4].clone().remove_context();
+/// assert_eq!(amount, Value::unnamed_composite(vec![Value::u128(0x14)]));
+/// ```
+pub fn decode_extrinsic_with_hints<'a>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	hints: &DecodeHints,
+) -> Result<Extrinsic<'a>, DecodeError> {
+	let _len = <Compact<u32>>::decode(data)?;
+
+	decode_unwrapped_extrinsic_inner(metadata, data, Some(hints), &NoopObserver)
+}
+
 /// Decode a SCALE encoded extrinsic against the metadata provided. Unlike [`decode_extrinsic`], this
 /// assumes that the bytes provided do *not* start with a compact encoded count of the extrinsic byte length
 /// (ie, the extrinsic has been "unwrapped" already, and here we deal directly with the signature and call data).
@@ -257,6 +784,15 @@ pub fn decode_extrinsic<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<
 /// assert_eq!(&*extrinsic.call_data.ty.name(), "bid");
 /// ```
 pub fn decode_unwrapped_extrinsic<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<Extrinsic<'a>, DecodeError> {
+	decode_unwrapped_extrinsic_inner(metadata, data, None, &NoopObserver)
+}
+
+fn decode_unwrapped_extrinsic_inner<'a, O: DecodeObserver + ?Sized>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	hints: Option<&DecodeHints>,
+	observer: &O,
+) -> Result<Extrinsic<'a>, DecodeError> {
 	if data.is_empty() {
 		return Err(DecodeError::EarlyEof("unwrapped extrinsic byte length should be > 0"));
 	}
@@ -283,19 +819,28 @@ pub fn decode_unwrapped_extrinsic<'a>(metadata: &'a Metadata, data: &mut &[u8])
 	let version = data[0] & 0b0111_1111;
 	*data = &data[1..];
 
-	// We only know how to decode V4 extrinsics at the moment
+	// We only know how to decode V4 extrinsics at the moment. The version belongs to each extrinsic
+	// rather than to the block, so the blocks around a change of extrinsic format can contain a mix.
 	if version != 4 {
 		return Err(DecodeError::CannotDecodeExtrinsicVersion(version));
 	}
 
-	// If the extrinsic is signed, decode the signature next.
+	// If the extrinsic is signed, decode the signature next. Some chains wrap the signature in an `Option`,
+	// in which case a `None` leaves the extrinsic unsigned.
 	let signature = match is_signed {
+		true if metadata.extrinsic().optional_signature() => match u8::decode(data)? {
+			0 => None,
+			1 => Some(decode_signature(metadata, data)?),
+			_ => return Err(parity_scale_codec::Error::from("invalid Option index for the extrinsic signature").into()),
+		},
 		true => Some(decode_signature(metadata, data)?),
 		false => None,
 	};
 
-	// Finally, decode the call data.
-	let call_data = decode_call_data(metadata, data)?;
+	// Finally, decode the call data. Failures are left to the caller to report, since they fail the extrinsic.
+	let len = data.len();
+	let call_data = decode_call_data_inner(metadata, data, false, hints, 0)?;
+	observer.call_decoded(&call_data, len - data.len());
 
 	Ok(Extrinsic { call_data, signature })
 }
@@ -326,6 +871,63 @@ pub fn decode_unwrapped_extrinsic<'a>(metadata: &'a Metadata, data: &mut &[u8])
 /// assert_eq!(&*call_data.ty.name(), "bid");
 /// ```
 pub fn decode_call_data<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<CallData<'a>, DecodeError> {
+	decode_call_data_observing(metadata, data, &NoopObserver)
+}
+
+/// Like [`decode_call_data`], but tell `observer` about the call decoded, or about the failure to decode it.
+#[cfg(feature = "metrics")]
+pub fn decode_call_data_observed<'a, O: DecodeObserver + ?Sized>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	observer: &O,
+) -> Result<CallData<'a>, DecodeError> {
+	decode_call_data_observing(metadata, data, observer)
+}
+
+fn decode_call_data_observing<'a, O: DecodeObserver + ?Sized>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	observer: &O,
+) -> Result<CallData<'a>, DecodeError> {
+	let len = data.len();
+	let res = decode_call_data_inner(metadata, data, false, None, 0);
+	match &res {
+		Ok(call) => observer.call_decoded(call, len - data.len()),
+		Err(e) => observer.decode_failed(e),
+	}
+	res
+}
+
+/// Like [`decode_call_data`], but tolerant of partial or malformed metadata: if an argument refers to a type ID
+/// that isn't in the type registry, decoding stops at that argument rather than failing. The bytes from that
+/// argument onwards are then handed back undecoded in [`CallData::opaque_remainder`], and all of `data` is
+/// consumed, since there is no way to tell where the call ends.
+pub fn decode_call_data_lenient<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<CallData<'a>, DecodeError> {
+	decode_call_data_inner(metadata, data, true, None, 0)
+}
+
+/// Like [`decode_call_data`], but the [`DecodeHints`] given can change how the arguments of particular calls are
+/// decoded.
+pub fn decode_call_data_with_hints<'a>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	hints: &DecodeHints,
+) -> Result<CallData<'a>, DecodeError> {
+	decode_call_data_inner(metadata, data, false, Some(hints), 0)
+}
+
+/// Decode call data which is nested `depth` calls deep, failing once that exceeds [`MAX_CALL_DEPTH`].
+fn decode_call_data_inner<'a>(
+	metadata: &'a Metadata,
+	data: &mut &[u8],
+	lenient: bool,
+	hints: Option<&DecodeHints>,
+	depth: usize,
+) -> Result<CallData<'a>, DecodeError> {
+	if depth > MAX_CALL_DEPTH {
+		return Err(DecodeError::CallTooDeep(MAX_CALL_DEPTH));
+	}
+
 	// Pluck out the u8's representing the pallet and call enum next.
 	if data.len() < 2 {
 		return Err(DecodeError::EarlyEof("expected at least 2 more bytes for the pallet/call index"));
@@ -335,22 +937,295 @@ pub fn decode_call_data<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<
 	log::trace!("pallet index: {}, call index: {}", pallet_index, call_index);
 
 	// Work out which call the extrinsic data represents and get type info for it:
-	let (pallet_name, variant) = match metadata.call_variant_by_enum_index(pallet_index, call_index) {
-		Some(call) => call,
-		None => return Err(DecodeError::CannotFindCall(pallet_index, call_index)),
+	let (pallet_name, variant) =
+		metadata.call_variant_by_enum_index(pallet_index, call_index).map_err(|e| match e {
+			CallLookupError::PalletNotFound => DecodeError::CannotFindCall(pallet_index, call_index),
+			CallLookupError::PalletHasNoCalls => DecodeError::PalletHasNoCalls(pallet_index),
+			CallLookupError::CallNotFound => DecodeError::CallIndexOutOfRange(pallet_index, call_index),
+		})?;
+
+	// Decode each of the argument values in the extrinsic, and any calls nested in them:
+	let opaque_fields = hints.and_then(|hints| hints.opaque_fields_of(pallet_name, &variant.name)).unwrap_or_default();
+	let mut arguments = Vec::with_capacity(variant.fields.len());
+	let mut inner_calls = BTreeMap::new();
+	let mut opaque_calls = BTreeMap::new();
+	let mut opaque_remainder = None;
+	for (idx, field) in variant.fields.iter().enumerate() {
+		let arg_bytes = *data;
+		if metadata.call_ty() == Some(field.ty.id) {
+			// The argument is a call in its own right, so decode it as one, and then build its value from that
+			// rather than decoding its bytes (and those of any calls nested inside it) a second time.
+			let call = decode_call_data_inner(metadata, data, lenient, hints, depth + 1)?;
+			match call_value(metadata, &call, field.ty.id) {
+				Some(value) => arguments.push(value),
+				None => {
+					log::warn!("Leaving argument {idx} of {pallet_name}.{} undecoded", variant.name);
+					opaque_remainder = Some(arg_bytes.to_vec());
+					*data = &[];
+					break;
+				}
+			}
+			inner_calls.insert(idx, call);
+			continue;
+		}
+
+		let value = match decode_hints::decode_argument(metadata, field, opaque_fields, data) {
+			Ok(value) => value,
+			Err(e) if lenient && is_missing_type(&e) => {
+				log::warn!("Leaving argument {idx} of {pallet_name}.{} undecoded: {e}", variant.name);
+				opaque_remainder = Some(arg_bytes.to_vec());
+				*data = &[];
+				break;
+			}
+			Err(e) => return Err(DecodeError::DecodeValueError(e)),
+		};
+		if is_opaque_call(metadata, field) {
+			let arg_bytes = &arg_bytes[..arg_bytes.len() - data.len()];
+			if let Some(call) = decode_opaque_call(metadata, arg_bytes, depth + 1)? {
+				opaque_calls.insert(idx, call);
+			}
+		}
+		arguments.push(value);
+	}
+
+	Ok(CallData {
+		pallet_name: Cow::Borrowed(pallet_name),
+		ty: Cow::Borrowed(variant),
+		arguments,
+		inner_calls,
+		opaque_calls,
+		opaque_remainder,
+	})
+}
+
+/// Build the value that a call decodes to as an argument of the outer call type `call_ty`, ie a variant naming the
+/// pallet, which holds a variant naming the call, which holds the arguments. This is `None` if the call wasn't fully
+/// decoded, in which case there's no value for it.
+fn call_value(metadata: &Metadata, call: &CallData, call_ty: TypeId) -> Option<Value<TypeId>> {
+	if call.opaque_remainder.is_some() {
+		return None;
+	}
+	let pallet_index = metadata.pallet_call_index_by_name(&call.pallet_name)?;
+	let pallet_calls_ty = metadata.pallet_calls_ty(pallet_index)?;
+
+	// Like values, calls with no arguments, or with any unnamed ones, hold an unnamed composite:
+	let fields = &call.ty.fields;
+	let args = call.arguments.iter().cloned();
+	let values = if !fields.is_empty() && fields.iter().all(|f| f.name.is_some()) {
+		Composite::Named(fields.iter().filter_map(|f| f.name.clone()).zip(args).collect())
+	} else {
+		Composite::Unnamed(args.collect())
+	};
+	let call_variant = Variant { name: call.ty.name.clone(), values };
+	let call_value = Value { value: ValueDef::Variant(call_variant), context: pallet_calls_ty };
+	let pallet_variant = Variant { name: call.pallet_name.to_string(), values: Composite::Unnamed(vec![call_value]) };
+	Some(Value { value: ValueDef::Variant(pallet_variant), context: call_ty })
+}
+
+/// Did decoding fail because some type ID could not be found in the type registry?
+fn is_missing_type(err: &DecodeValueError) -> bool {
+	matches!(
+		err.kind(),
+		scale_decode::error::ErrorKind::VisitorDecodeError(scale_decode::visitor::DecodeError::TypeIdNotFound(_))
+	)
+}
+
+/// Is the given call argument an `OpaqueCall` or a `WrapperKeepOpaque<Call>`? Both are encoded like a `Vec<u8>`
+/// containing the SCALE encoded call. Older runtimes only give us the former via the type name of the argument.
+fn is_opaque_call(metadata: &Metadata, field: &scale_info::Field<scale_info::form::PortableForm>) -> bool {
+	let type_name = field.type_name.as_deref().unwrap_or_default();
+	if type_name == "OpaqueCall" || type_name.starts_with("OpaqueCall<") || type_name.starts_with("WrapperKeepOpaque<")
+	{
+		return true;
+	}
+	metadata
+		.types()
+		.resolve(field.ty.id)
+		.and_then(|ty| ty.path.segments.last())
+		.is_some_and(|ident| ident == "WrapperKeepOpaque")
+}
+
+/// Decode the call wrapped up in the bytes of an opaque call argument, which is nested `depth` calls deep. The
+/// wrapped call may not be decodable with the current metadata (for instance, if it was stored under a previous
+/// runtime), so rather than failing to decode the outer call, we hand back `None` in this case. Calls nested too
+/// deeply are still an error though.
+fn decode_opaque_call<'a>(
+	metadata: &'a Metadata,
+	mut arg_bytes: &[u8],
+	depth: usize,
+) -> Result<Option<CallData<'a>>, DecodeError> {
+	// The bytes are prefixed with their length, which we check rather than copying them out:
+	match <Compact<u32>>::decode(&mut arg_bytes) {
+		Ok(len) if len.0 as usize == arg_bytes.len() => {}
+		Ok(len) => {
+			log::warn!("Opaque call claims to be {} bytes long, but {} bytes were found", len.0, arg_bytes.len());
+			return Ok(None);
+		}
+		Err(e) => {
+			log::warn!("Cannot decode the bytes of an opaque call: {}", e);
+			return Ok(None);
+		}
+	}
+	let cursor = &mut arg_bytes;
+	match decode_call_data_inner(metadata, cursor, false, None, depth) {
+		Ok(call) if cursor.is_empty() => Ok(Some(call)),
+		Ok(_) => {
+			log::warn!("Opaque call did not consume all of its bytes; {} bytes were left over", cursor.len());
+			Ok(None)
+		}
+		Err(e @ DecodeError::CallTooDeep(_)) => Err(e),
+		Err(e) => {
+			log::warn!("Cannot decode opaque call: {}", e);
+			Ok(None)
+		}
+	}
+}
+
+/// Decode a SCALE encoded vector of event records, as stored in `System.Events`. Conceptually, this is expected
+/// to take the form `Vec<EventRecord>`, where each record is made up of the [`Phase`] in which the event was
+/// emitted, the event itself (see [`decode_event`]) and a vector of topics.
+///
+/// Exactly as many records as the compact count at the start of the data says are decoded, and the data must hold
+/// nothing else (as is the case for the value of `System.Events`); any bytes left over after the last record are a
+/// [`DecodeError::ExcessBytes`].
+///
+/// # Example
+///
+/// ```rust
+/// use hex;
+/// use desub_current::{ Metadata, decoder::{ self, Phase } };
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// // A single `Treasury.Spending(12345)` event, emitted during block finalization:
+/// let events_hex = "0x040113013930000000000000000000000000000000";
+/// let events_bytes = hex::decode(events_hex.strip_prefix("0x").unwrap()).unwrap();
+/// let events_cursor = &mut &*events_bytes;
+///
+/// let events = decoder::decode_events(&metadata, events_cursor).unwrap();
+///
+/// assert_eq!(events_cursor.len(), 0);
+/// assert_eq!(events.len(), 1);
+/// assert_eq!(events[0].phase, Phase::Finalization);
+/// assert_eq!(events[0].event.pallet_name, "Treasury");
+/// assert_eq!(&*events[0].event.ty.name(), "Spending");
+/// ```
+pub fn decode_events<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<Vec<EventRecord<'a>>, DecodeError> {
+	let len = <Compact<u32>>::decode(data)?.0;
+	log::trace!("Decoding {} Total Events.", len);
+
+	let events = (0..len)
+		.map(|_| {
+			let phase = Phase::decode(data)?;
+			let event = decode_event(metadata, data)?;
+			let topics = <Vec<H256>>::decode(data)?;
+			Ok(EventRecord { phase, event, topics })
+		})
+		.collect::<Result<Vec<_>, DecodeError>>()?;
+	if !data.is_empty() {
+		return Err(DecodeError::ExcessBytes(data.len()));
+	}
+	Ok(events)
+}
+
+/// Decode a single SCALE encoded event. Much like call data, this is expected to take the form of
+/// `(u8, u8, fields)`, where the pallet and event variant indexes are determined by the `u8`s, and
+/// then the fields according to the specific variant are expected to follow. `DispatchError::Module` errors in
+/// the fields are left as their indexes; see [`resolve_module_errors`] to give them names.
+pub fn decode_event<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<Event<'a>, DecodeError> {
+	if data.len() < 2 {
+		return Err(DecodeError::EarlyEof("expected at least 2 more bytes for the pallet/event index"));
+	}
+	let pallet_index = u8::decode(data)?;
+	let event_index = u8::decode(data)?;
+	log::trace!("pallet index: {}, event index: {}", pallet_index, event_index);
+
+	let (pallet_name, variant) = match metadata.event_variant_by_enum_index(pallet_index, event_index) {
+		Some(event) => event,
+		None => return Err(DecodeError::CannotFindEvent(pallet_index, event_index)),
 	};
 
-	// Decode each of the argument values in the extrinsic:
-	let arguments = variant
+	let fields = variant
 		.fields
 		.iter()
-		.map(|field| {
-			let id = field.ty.id;
-			decode_value_by_id(metadata, id, data).map_err(DecodeError::DecodeValueError)
-		})
-		.collect::<Result<Vec<_>, _>>()?;
+		.map(|field| decode_value_by_id(metadata, field.ty.id, data).map_err(DecodeError::DecodeValueError))
+		.collect::<Result<Vec<_>, DecodeError>>()?;
+
+	Ok(Event { pallet_name: Cow::Borrowed(pallet_name), ty: Cow::Borrowed(variant), fields })
+}
+
+/// Decode the SCALE encoded digest of a block header, which takes the form `Vec<DigestItem>`. Each log
+/// (for instance a `PreRuntime`, `Consensus` or `Seal` item) is decoded as a [`Value`] according to the
+/// `DigestItem` type found in the metadata, and the cursor is left just after the last of them.
+///
+/// # Example
+///
+/// ```rust
+/// use desub_current::{ Metadata, Value, ValueDef, decoder };
+///
+/// let metadata_scale_encoded = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+/// let metadata = Metadata::from_bytes(metadata_scale_encoded).unwrap();
+///
+/// // A single `PreRuntime(*b"BABE", vec![1, 2, 3])` log:
+/// let digest_hex = "0x0406424142450c010203";
+/// let digest_bytes = hex::decode(digest_hex.strip_prefix("0x").unwrap()).unwrap();
+///
+/// let logs = decoder::decode_digest(&metadata, &mut &*digest_bytes).unwrap();
+///
+/// assert_eq!(logs.len(), 1);
+/// assert!(matches!(&logs[0].value, ValueDef::Variant(v) if v.name == "PreRuntime"));
+/// ```
+pub fn decode_digest(metadata: &Metadata, data: &mut &[u8]) -> Result<Vec<Value<TypeId>>, DecodeError> {
+	let digest_item_ty = digest_item_type_id(metadata).ok_or(DecodeError::CannotFindDigestItemType)?;
+	let len = <Compact<u32>>::decode(data)?.0;
+	log::trace!("Decoding {} Total Digest Logs.", len);
+
+	(0..len).map(|_| Ok(decode_value_by_id(metadata, digest_item_ty, data)?)).collect()
+}
+
+/// Find the ID of the `sp_runtime` `DigestItem` type in the metadata, if it's there.
+fn digest_item_type_id(metadata: &Metadata) -> Option<u32> {
+	metadata.types().types.iter().find_map(|ty| {
+		let segments = &ty.ty.path.segments;
+		let is_digest_item =
+			segments.first().is_some_and(|s| s == "sp_runtime") && segments.last().is_some_and(|s| s == "DigestItem");
+		is_digest_item.then_some(ty.id)
+	})
+}
+
+/// Group some decoded event records by the index of the extrinsic that emitted them, ie by their
+/// [`Phase::ApplyExtrinsic`] index. Within each group, events keep the order they were given in.
+///
+/// Events emitted during [`Phase::Initialization`] or [`Phase::Finalization`] don't belong to any
+/// extrinsic, and so are left out.
+pub fn correlate_events<'a, 'b>(events: &'b [EventRecord<'a>]) -> BTreeMap<u32, Vec<&'b EventRecord<'a>>> {
+	let mut by_extrinsic: BTreeMap<u32, Vec<&'b EventRecord<'a>>> = BTreeMap::new();
+	for event in events {
+		if let Phase::ApplyExtrinsic(idx) = event.phase {
+			by_extrinsic.entry(idx).or_default().push(event);
+		}
+	}
+	by_extrinsic
+}
+
+/// Find the `Timestamp.set` inherent amongst the decoded extrinsics of a block, and return the moment that
+/// it set, in milliseconds since the UNIX epoch. Returns `None` if the block has no such extrinsic.
+pub fn decode_timestamp_from_block(extrinsics: &[Extrinsic]) -> Option<u64> {
+	extrinsics.iter().find_map(|ext| {
+		let call = &ext.call_data;
+		if call.pallet_name != "Timestamp" || call.ty.name != "set" {
+			return None;
+		}
+		call.arguments.first()?.as_u128()?.try_into().ok()
+	})
+}
 
-	Ok(CallData { pallet_name: Cow::Borrowed(pallet_name), ty: Cow::Borrowed(variant), arguments })
+/// Like [`decode_timestamp_from_block`], but returns the moment as a UTC [`chrono::DateTime`].
+#[cfg(feature = "chrono")]
+pub fn decode_datetime_from_block(extrinsics: &[Extrinsic]) -> Option<chrono::DateTime<chrono::Utc>> {
+	let millis = decode_timestamp_from_block(extrinsics)?;
+	chrono::DateTime::from_timestamp_millis(millis.try_into().ok()?)
 }
 
 /// Decode the SCALE encoded data that, once signed, is used to construct a signed extrinsic. The encoded payload has the following shape:
@@ -359,21 +1234,54 @@ pub fn decode_signer_payload<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Re
 	let call_data = decode_call_data(metadata, data)?;
 	let signed_extensions = decode_signed_extensions(metadata, data)?;
 	let additional_signed = decode_additional_signed(metadata, data)?;
-	let extensions = signed_extensions
-		.into_iter()
-		.zip(additional_signed)
-		.map(|((name, extension), (_, additional))| (name, SignedExtensionWithAdditional { additional, extension }))
-		.collect();
+	let extensions = pair_extensions(signed_extensions, additional_signed)?;
 
 	Ok(SignerPayload { call_data, extensions })
 }
 
-/// Decode the signature part of a SCALE encoded extrinsic.
+/// Pair each signed extension up with its additional signed data, checking that both lists name the same extensions
+/// in the same order, and returning a [`DecodeError::ExtensionMismatch`] at the first place they don't.
+#[allow(clippy::type_complexity)]
+fn pair_extensions<'a>(
+	signed_extensions: Vec<(Cow<'a, str>, Value<TypeId>)>,
+	additional_signed: Vec<(Cow<'a, str>, Value<TypeId>)>,
+) -> Result<Vec<(Cow<'a, str>, SignedExtensionWithAdditional)>, DecodeError> {
+	let mismatch = |extension: Option<&str>, additional: Option<&str>| DecodeError::ExtensionMismatch {
+		extension: extension.map(str::to_owned),
+		additional: additional.map(str::to_owned),
+	};
+	if signed_extensions.len() != additional_signed.len() {
+		let idx = signed_extensions.len().min(additional_signed.len());
+		let extension = signed_extensions.get(idx).map(|(name, _)| &**name);
+		let additional = additional_signed.get(idx).map(|(name, _)| &**name);
+		return Err(mismatch(extension, additional));
+	}
+
+	signed_extensions
+		.into_iter()
+		.zip(additional_signed)
+		.map(|((name, extension), (additional_name, additional))| match name == additional_name {
+			true => Ok((name, SignedExtensionWithAdditional { additional, extension })),
+			false => Err(mismatch(Some(&name), Some(&additional_name))),
+		})
+		.collect()
+}
+
+/// Decode the signature part of a SCALE encoded extrinsic. On chains using [`SignerFormat::Ethereum`], the 20 byte
+/// address is given back as a [`MultiAddress::Address20`], and the signature as a [`MultiSignature::Ecdsa`]. On chains
+/// using [`SignerFormat::AccountId32`], the bare 32 byte address is given back as a [`MultiAddress::Id`].
 ///
 /// Ordinarily, one should prefer to use [`decode_extrinsic`] directly to decode the entire extrinsic at once.
 pub fn decode_signature<'a>(metadata: &'a Metadata, data: &mut &[u8]) -> Result<ExtrinsicSignature<'a>, DecodeError> {
-	let address = <MultiAddress<AccountId32, u32>>::decode(data)?;
-	let signature = MultiSignature::decode(data)?;
+	let (address, signature) = match metadata.extrinsic().signer_format() {
+		SignerFormat::Substrate => (<MultiAddress<AccountId32, u32>>::decode(data)?, MultiSignature::decode(data)?),
+		SignerFormat::AccountId32 => (MultiAddress::Id(AccountId32::decode(data)?), MultiSignature::decode(data)?),
+		SignerFormat::Ethereum => {
+			let address = MultiAddress::Address20(<[u8; 20]>::decode(data)?);
+			let signature = MultiSignature::Ecdsa(ecdsa::Signature::from_raw(<[u8; 65]>::decode(data)?));
+			(address, signature)
+		}
+	};
 	let extensions = decode_signed_extensions(metadata, data)?;
 
 	Ok(ExtrinsicSignature { address, signature, extensions })
@@ -399,7 +1307,10 @@ pub fn decode_signed_extensions<'a>(
 		.collect()
 }
 
-/// Decode the additional signed data.
+/// Decode the additional signed data. This is decoded according to the type that each signed extension gives for
+/// it, so an extension without any additional data (ie `()`) gives back an empty composite, and one whose additional
+/// data is a tuple (for instance because it aggregates several other extensions) gives back a composite of the
+/// decoded elements, in order.
 ///
 /// Ordinarily, one should prefer to use [`decode_signer_payload`], to decode the entire signer payload at once.
 #[allow(clippy::type_complexity)]
@@ -430,6 +1341,20 @@ pub struct CallData<'a> {
 	pub ty: Cow<'a, scale_info::Variant<scale_info::form::PortableForm>>,
 	/// The decoded argument data
 	pub arguments: Vec<Value<TypeId>>,
+	/// Calls passed directly as arguments (ie a `Box<Call>`, as taken by `Proxy.proxy` or
+	/// `Multisig.as_multi_threshold_1`), keyed by the index of the argument that they were decoded from.
+	/// The arguments themselves are decoded as values too.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub inner_calls: BTreeMap<usize, CallData<'a>>,
+	/// Calls wrapped up in opaque arguments (ie `OpaqueCall` or `WrapperKeepOpaque<Call>`, as used by
+	/// multisig calls), keyed by the index of the argument that they were decoded from. The arguments
+	/// themselves are left as bytes.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub opaque_calls: BTreeMap<usize, CallData<'a>>,
+	/// Only set by [`decode_call_data_lenient`]: the undecoded bytes from the first argument whose type could
+	/// not be found onwards. In this case, `arguments` holds only the arguments before that one.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub opaque_remainder: Option<Vec<u8>>,
 }
 
 impl<'a> CallData<'a> {
@@ -438,8 +1363,45 @@ impl<'a> CallData<'a> {
 			pallet_name: Cow::Owned(self.pallet_name.into_owned()),
 			ty: Cow::Owned(self.ty.into_owned()),
 			arguments: self.arguments,
+			inner_calls: self.inner_calls.into_iter().map(|(idx, call)| (idx, call.into_owned())).collect(),
+			opaque_calls: self.opaque_calls.into_iter().map(|(idx, call)| (idx, call.into_owned())).collect(),
+			opaque_remainder: self.opaque_remainder,
 		}
 	}
+
+	/// The calls nested directly in the arguments of this call, whether passed as calls in their own right (see
+	/// [`CallData::inner_calls`]) or wrapped up in opaque arguments (see [`CallData::opaque_calls`]), in argument
+	/// order. Each of these may have calls nested within it in turn.
+	pub fn nested_calls(&self) -> Vec<&CallData<'a>> {
+		let mut calls: Vec<_> = self.inner_calls.iter().chain(&self.opaque_calls).collect();
+		calls.sort_by_key(|(idx, _)| **idx);
+		calls.into_iter().map(|(_, call)| call).collect()
+	}
+
+	/// A compact JSON representation of this call, of the form
+	/// `{ "pallet": "Balances", "call": "transfer", "args": { "dest": .., "value": .. } }`.
+	///
+	/// Unlike the [`Serialize`] impl, this leaves out the full type information of the call. Arguments
+	/// without a name are keyed by their position instead.
+	pub fn to_call_json(&self) -> serde_json::Value {
+		let args: serde_json::Map<String, serde_json::Value> = self
+			.ty
+			.fields
+			.iter()
+			.zip(&self.arguments)
+			.enumerate()
+			.map(|(idx, (field, value))| {
+				let name = field.name.as_ref().map(|n| n.to_string()).unwrap_or_else(|| idx.to_string());
+				// Values are always representable as JSON, so this won't fall back in practice.
+				(name, serde_json::to_value(value).unwrap_or_default())
+			})
+			.collect();
+		serde_json::json!({
+			"pallet": self.pallet_name,
+			"call": self.ty.name,
+			"args": args,
+		})
+	}
 }
 
 /// The result of successfully decoding an extrinsic.
@@ -457,8 +1419,54 @@ impl<'a> Extrinsic<'a> {
 	pub fn into_owned(self) -> Extrinsic<'static> {
 		Extrinsic { call_data: self.call_data.into_owned(), signature: self.signature.map(|s| s.into_owned()) }
 	}
+
+	/// Is this extrinsic signed?
+	pub fn is_signed(&self) -> bool {
+		self.signature.is_some()
+	}
+
+	/// Replace the signature (if any) with an all zero signature of the same kind, for instance before logging the
+	/// extrinsic. Values inside the call data or signed extensions can be redacted with [`crate::ValueExt::redact`].
+	pub fn redact_signature(&mut self) {
+		let Some(signature) = &mut self.signature else { return };
+		signature.signature = match signature.signature {
+			MultiSignature::Ed25519(_) => MultiSignature::Ed25519(ed25519::Signature::from_raw([0; 64])),
+			MultiSignature::Sr25519(_) => MultiSignature::Sr25519(sr25519::Signature::from_raw([0; 64])),
+			MultiSignature::Ecdsa(_) => MultiSignature::Ecdsa(ecdsa::Signature::from_raw([0; 65])),
+		};
+	}
+
+	/// Is this extrinsic an inherent, ie an unsigned call to one of the [`INHERENT_CALLS`] that block authors
+	/// put into each block? The pallet is identified by the type path of its calls in the given metadata, so
+	/// inherents are recognised even when a runtime names the pallet differently.
+	pub fn is_inherent(&self, metadata: &Metadata) -> bool {
+		if self.is_signed() {
+			return false;
+		}
+		let Some(calls_ty) = metadata
+			.pallet_call_index_by_name(&self.call_data.pallet_name)
+			.and_then(|index| metadata.pallet_calls_ty(index))
+			.and_then(|id| metadata.resolve(id))
+		else {
+			return false;
+		};
+		let path = calls_ty.path.segments.join("::");
+		INHERENT_CALLS.iter().any(|(module, call)| {
+			path.strip_prefix(module).is_some_and(|rest| rest.starts_with("::")) && self.call_data.ty.name == *call
+		})
+	}
 }
 
+/// The `(module path, call)` of well known inherents, as used by [`Extrinsic::is_inherent`]. A pallet provides
+/// an inherent if the type path of its calls starts with the module path.
+pub const INHERENT_CALLS: &[(&str, &str)] = &[
+	("pallet_timestamp", "set"),
+	("pallet_authorship", "set_uncles"),
+	("polkadot_runtime_parachains::paras_inherent", "enter"),
+	("cumulus_pallet_parachain_system", "set_validation_data"),
+	("pallet_finality_tracker", "final_hint"),
+];
+
 /// The signature information embedded in an extrinsic.
 #[derive(Serialize, Debug, Clone, PartialEq)]
 pub struct ExtrinsicSignature<'a> {
@@ -481,6 +1489,12 @@ impl<'a> ExtrinsicSignature<'a> {
 			extensions: self.extensions.into_iter().map(|(k, v)| (Cow::Owned(k.into_owned()), v)).collect(),
 		}
 	}
+
+	/// Return the signed extensions keyed by their name. If more than one extension shares
+	/// the same name, the later one overwrites the earlier one.
+	pub fn extensions_map(&self) -> BTreeMap<&str, &Value<TypeId>> {
+		self.extensions.iter().map(|(k, v)| (&**k, v)).collect()
+	}
 }
 
 /// The decoded signer payload.
@@ -501,6 +1515,12 @@ impl<'a> SignerPayload<'a> {
 			extensions: self.extensions.into_iter().map(|(k, v)| (Cow::Owned(k.into_owned()), v)).collect(),
 		}
 	}
+
+	/// Return the signed extensions (and their additional data) keyed by their name. If more than
+	/// one extension shares the same name, the later one overwrites the earlier one.
+	pub fn extensions_map(&self) -> BTreeMap<&str, &SignedExtensionWithAdditional> {
+		self.extensions.iter().map(|(k, v)| (&**k, v)).collect()
+	}
 }
 
 /// The decoded signed extensions and additional data.
@@ -511,3 +1531,252 @@ pub struct SignedExtensionWithAdditional {
 	/// The additional signed value at this position
 	pub additional: Value<TypeId>,
 }
+
+/// A decoded event record, as stored in `System.Events`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EventRecord<'a> {
+	/// The phase of block execution in which the event was emitted.
+	pub phase: Phase,
+	/// Decoded event data and associated type information about the event.
+	#[serde(borrow)]
+	pub event: Event<'a>,
+	/// Topics associated with the event.
+	pub topics: Vec<H256>,
+}
+
+impl<'a> EventRecord<'a> {
+	pub fn into_owned(self) -> EventRecord<'static> {
+		EventRecord { phase: self.phase, event: self.event.into_owned(), topics: self.topics }
+	}
+}
+
+/// Decoded event data and associated type information.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Event<'a> {
+	/// The name of the pallet
+	#[serde(borrow)]
+	pub pallet_name: Cow<'a, str>,
+	/// The type information for this event (including the name
+	/// of the event and information about each field)
+	pub ty: Cow<'a, scale_info::Variant<scale_info::form::PortableForm>>,
+	/// The decoded field data
+	pub fields: Vec<Value<TypeId>>,
+}
+
+impl<'a> Event<'a> {
+	pub fn into_owned(self) -> Event<'static> {
+		Event {
+			pallet_name: Cow::Owned(self.pallet_name.into_owned()),
+			ty: Cow::Owned(self.ty.into_owned()),
+			fields: self.fields,
+		}
+	}
+}
+
+/// The phase of block execution in which an event was emitted.
+#[derive(Serialize, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+	/// Applying an extrinsic, with the index of that extrinsic in the block.
+	ApplyExtrinsic(u32),
+	/// Finalizing the block.
+	Finalization,
+	/// Initializing the block.
+	Initialization,
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+	use scale_info::{meta_type, Registry, TypeInfo};
+
+	#[test]
+	fn can_decode_with_a_registry_alone() {
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: u8,
+			b: Vec<bool>,
+		}
+
+		let mut registry = Registry::new();
+		let id = registry.register_type(&meta_type::<Foo>()).id;
+		let registry: PortableRegistry = registry.into();
+
+		let bytes = Foo { a: 1, b: vec![true, false] }.encode();
+		let cursor = &mut &*bytes;
+		let value = decode_value_with_registry(&registry, id, cursor).unwrap();
+
+		assert!(cursor.is_empty());
+		assert_eq!(value.context, id);
+		assert_eq!(
+			value.remove_context(),
+			Value::named_composite(vec![
+				("a", Value::u128(1)),
+				("b", Value::unnamed_composite(vec![Value::bool(true), Value::bool(false)])),
+			])
+		);
+	}
+
+	#[test]
+	fn can_leave_parachain_validation_data_opaque() {
+		use frame_metadata::v14::{ExtrinsicMetadata, PalletCallMetadata, PalletMetadata, RuntimeMetadataV14};
+		use scale_value::{Composite, ValueDef};
+		use std::collections::BTreeSet;
+
+		// Roughly the shape of the cumulus `ParachainInherentData`.
+		#[derive(Encode, TypeInfo)]
+		struct InboundDownwardMessage {
+			sent_at: u32,
+			msg: Vec<u8>,
+		}
+		#[derive(Encode, TypeInfo)]
+		struct StorageProof {
+			trie_nodes: BTreeSet<Vec<u8>>,
+		}
+		#[derive(Encode, TypeInfo)]
+		struct ParachainInherentData {
+			validation_data: u32,
+			relay_chain_state: StorageProof,
+			downward_messages: Vec<InboundDownwardMessage>,
+			horizontal_messages: BTreeMap<u32, Vec<InboundDownwardMessage>>,
+		}
+		#[allow(dead_code, non_camel_case_types)]
+		#[derive(Encode, TypeInfo)]
+		enum Call {
+			#[codec(index = 0)]
+			set_validation_data { data: ParachainInherentData },
+		}
+
+		let pallet = PalletMetadata {
+			name: "ParachainSystem",
+			storage: None,
+			calls: Some(PalletCallMetadata { ty: meta_type::<Call>() }),
+			event: None,
+			constants: vec![],
+			error: None,
+			index: 1,
+		};
+		let extrinsic = ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+		let runtime_metadata = RuntimeMetadataV14::new(vec![pallet], extrinsic, meta_type::<()>());
+		let metadata = Metadata::from_runtime_metadata(frame_metadata::RuntimeMetadata::V14(runtime_metadata)).unwrap();
+
+		let data = ParachainInherentData {
+			validation_data: 7,
+			relay_chain_state: StorageProof { trie_nodes: (0..100u8).map(|n| vec![n; 1024]).collect() },
+			downward_messages: vec![InboundDownwardMessage { sent_at: 1, msg: vec![2; 2048] }],
+			horizontal_messages: BTreeMap::new(),
+		};
+		let mut call_bytes = vec![1, 0];
+		data.encode_to(&mut call_bytes);
+
+		let cursor = &mut &*call_bytes;
+		let hints = DecodeHints::parachain_validation_data();
+		let call = decode_call_data_with_hints(&metadata, cursor, &hints).unwrap();
+		assert!(cursor.is_empty());
+		assert_eq!(
+			call.arguments[0].clone().remove_context(),
+			Value::named_composite(vec![
+				("validation_data", Value::u128(7)),
+				("relay_chain_state", Value::from_bytes(data.relay_chain_state.encode())),
+				("downward_messages", Value::from_bytes(data.downward_messages.encode())),
+				("horizontal_messages", Value::from_bytes(data.horizontal_messages.encode())),
+			])
+		);
+
+		// The bytes left opaque are typed as a `Vec<u8>`, rather than as the field that they were decoded from:
+		let ValueDef::Composite(Composite::Named(fields)) = &call.arguments[0].value else {
+			panic!("expected the inherent data to be a named composite");
+		};
+		let proof_ty = metadata.resolve(fields[1].1.context).map(|ty| &ty.type_def);
+		assert!(matches!(proof_ty, Some(scale_info::TypeDef::Sequence(_))), "{proof_ty:?}");
+
+		// Without the hints, the proof is decoded into a set of byte sequences as normal.
+		let cursor = &mut &*call_bytes;
+		let call = decode_call_data(&metadata, cursor).unwrap();
+		assert!(cursor.is_empty());
+		let ValueDef::Composite(Composite::Named(fields)) = &call.arguments[0].value else {
+			panic!("expected the inherent data to be a named composite");
+		};
+		let ValueDef::Composite(Composite::Named(proof)) = &fields[1].1.value else {
+			panic!("expected the storage proof to be a named composite");
+		};
+		assert_eq!(proof[0].0, "trie_nodes");
+	}
+
+	#[test]
+	fn can_decode_custom_values() {
+		use frame_metadata::v15::{
+			CustomMetadata, CustomValueMetadata, ExtrinsicMetadata, OuterEnums, RuntimeMetadataV15,
+		};
+
+		#[derive(Encode, TypeInfo)]
+		struct Fee {
+			base: u32,
+			multiplier: u8,
+		}
+
+		let custom_value = |ty, value| CustomValueMetadata { ty, value };
+		let custom = CustomMetadata {
+			map: [
+				("fee", custom_value(meta_type::<Fee>(), Fee { base: 1000, multiplier: 2 }.encode())),
+				("too_long", custom_value(meta_type::<u8>(), vec![1, 2])),
+			]
+			.into_iter()
+			.collect(),
+		};
+		let extrinsic = ExtrinsicMetadata {
+			version: 4,
+			address_ty: meta_type::<()>(),
+			call_ty: meta_type::<()>(),
+			signature_ty: meta_type::<()>(),
+			extra_ty: meta_type::<()>(),
+			signed_extensions: vec![],
+		};
+		let outer_enums = OuterEnums {
+			call_enum_ty: meta_type::<()>(),
+			event_enum_ty: meta_type::<()>(),
+			error_enum_ty: meta_type::<()>(),
+		};
+		let runtime_metadata =
+			RuntimeMetadataV15::new(vec![], extrinsic, meta_type::<()>(), vec![], outer_enums, custom);
+		let metadata = Metadata::from_runtime_metadata(frame_metadata::RuntimeMetadata::V15(runtime_metadata)).unwrap();
+
+		let names: Vec<_> = metadata.custom().map(|(name, _, _)| name).collect();
+		assert_eq!(names, vec!["fee", "too_long"]);
+
+		let fee = decode_custom_value(&metadata, "fee").unwrap();
+		assert_eq!(
+			fee.remove_context(),
+			Value::named_composite(vec![("base", Value::u128(1000)), ("multiplier", Value::u128(2))])
+		);
+		assert!(matches!(decode_custom_value(&metadata, "too_long"), Err(DecodeError::ExcessBytes(1))));
+		assert!(matches!(
+			decode_custom_value(&metadata, "missing"),
+			Err(DecodeError::CannotFindCustomValue(name)) if name == "missing"
+		));
+	}
+
+	#[test]
+	fn mismatched_signed_extensions_are_an_error() {
+		let ext = |name: &'static str| (Cow::Borrowed(name), Value::u128(0).map_context(|_| 0));
+		let signed = vec![ext("CheckNonce"), ext("CheckWeight")];
+
+		let paired = pair_extensions(signed.clone(), vec![ext("CheckNonce"), ext("CheckWeight")]).unwrap();
+		assert_eq!(paired.iter().map(|(name, _)| &**name).collect::<Vec<_>>(), ["CheckNonce", "CheckWeight"]);
+
+		// The same extensions, but out of order:
+		let err = pair_extensions(signed.clone(), vec![ext("CheckWeight"), ext("CheckNonce")]).unwrap_err();
+		assert!(matches!(
+			err,
+			DecodeError::ExtensionMismatch { extension: Some(extension), additional: Some(additional) }
+				if extension == "CheckNonce" && additional == "CheckWeight"
+		));
+
+		// Additional signed data for fewer extensions:
+		let err = pair_extensions(signed, vec![ext("CheckNonce")]).unwrap_err();
+		assert!(matches!(
+			err,
+			DecodeError::ExtensionMismatch { extension: Some(extension), additional: None } if extension == "CheckWeight"
+		));
+	}
+}
@@ -0,0 +1,82 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Resolve the pallet and error names of any `DispatchError::Module` errors found in decoded values.
+
+use crate::{Metadata, TypeId, ValueExt};
+use scale_value::{Composite, Primitive, Value, ValueDef};
+
+/// The field added to a `DispatchError::Module` error by [`resolve_module_errors`], holding the name of the
+/// pallet that the error came from.
+pub const MODULE_ERROR_PALLET_FIELD: &str = "pallet_name";
+/// The field added to a `DispatchError::Module` error by [`resolve_module_errors`], holding the name of the error.
+pub const MODULE_ERROR_NAME_FIELD: &str = "error_name";
+
+/// Find every `DispatchError::Module` error in the value (for instance in the `Err` branch of a `DispatchResult`),
+/// and add the names of the pallet and error that its indexes refer to, as [`MODULE_ERROR_PALLET_FIELD`] and
+/// [`MODULE_ERROR_NAME_FIELD`], alongside the existing `index` and `error` fields. The added values have the
+/// context of the `DispatchError` type. Errors whose indexes can't be found in the metadata are left as they are.
+///
+/// Decoded values are never given these fields unless asked for, so that they can still be encoded again. This is
+/// also done by [`super::humanize_value`].
+pub fn resolve_module_errors(metadata: &Metadata, value: &mut Value<TypeId>) {
+	value.transform(&mut |v: &mut Value<TypeId>| {
+		let Value { value, context } = v;
+		let ValueDef::Variant(variant) = value else { return };
+		if variant.name != "Module" || !is_dispatch_error(metadata, *context) {
+			return;
+		}
+
+		// Older runtimes have `Module { index, error }`, and newer ones `Module(ModuleError { index, error })`:
+		let fields = match &mut variant.values {
+			Composite::Named(fields) => fields,
+			Composite::Unnamed(values) => match values.as_mut_slice() {
+				[Value { value: ValueDef::Composite(Composite::Named(fields)), .. }] => fields,
+				_ => return,
+			},
+		};
+		if fields.iter().any(|(name, _)| name == MODULE_ERROR_PALLET_FIELD) {
+			return;
+		}
+
+		let field = |name: &str| fields.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+		let Some(pallet_index) = field("index").and_then(first_byte) else { return };
+		// Newer runtimes encode the error as `[u8; 4]`, the first byte of which is the error index:
+		let Some(error_index) = field("error").and_then(first_byte) else { return };
+		let Some((pallet_name, error)) = metadata.error_variant_by_enum_index(pallet_index, error_index) else {
+			log::debug!("Cannot find error {} in pallet {}", error_index, pallet_index);
+			return;
+		};
+
+		let string =
+			|s: &str| Value { value: ValueDef::Primitive(Primitive::String(s.to_string())), context: *context };
+		fields.push((MODULE_ERROR_PALLET_FIELD.to_string(), string(pallet_name)));
+		fields.push((MODULE_ERROR_NAME_FIELD.to_string(), string(&error.name)));
+	});
+}
+
+/// Is the given type an `sp_runtime::DispatchError`?
+fn is_dispatch_error(metadata: &Metadata, ty: TypeId) -> bool {
+	metadata.resolve(ty).and_then(|ty| ty.path.ident()).is_some_and(|ident| ident == "DispatchError")
+}
+
+/// The value as a `u8`, or the first `u8` of it if it's a composite of them.
+fn first_byte(value: &Value<TypeId>) -> Option<u8> {
+	match &value.value {
+		ValueDef::Composite(composite) => composite.values().next().and_then(first_byte),
+		_ => value.as_u128()?.try_into().ok(),
+	}
+}
@@ -0,0 +1,107 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::DecodeError;
+use parity_scale_codec::Decode;
+use serde::Serialize;
+
+/// A well known storage location. These are written directly by the runtime under fixed keys, rather than by
+/// a pallet, and so don't appear in the metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum WellKnownStorage {
+	/// `:code`; the wasm blob of the runtime.
+	Code,
+	/// `:heappages`; the number of 64KiB pages of heap that the runtime is given, as a `u64`.
+	HeapPages,
+	/// `:extrinsic_index`; the index of the extrinsic currently being applied, as a `u32`.
+	ExtrinsicIndex,
+}
+
+/// The value stored at a [`WellKnownStorage`] location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum WellKnownStorageValue {
+	Code(Vec<u8>),
+	HeapPages(u64),
+	ExtrinsicIndex(u32),
+}
+
+impl WellKnownStorage {
+	/// The raw storage key of this location.
+	pub fn key(&self) -> &'static [u8] {
+		match self {
+			WellKnownStorage::Code => b":code",
+			WellKnownStorage::HeapPages => b":heappages",
+			WellKnownStorage::ExtrinsicIndex => b":extrinsic_index",
+		}
+	}
+
+	/// Decode the value stored at this location. The runtime code is not SCALE encoded, so every remaining
+	/// byte is taken to be a part of it.
+	pub fn decode_value(&self, data: &mut &[u8]) -> Result<WellKnownStorageValue, DecodeError> {
+		let value = match self {
+			WellKnownStorage::Code => {
+				let code = data.to_vec();
+				*data = &[];
+				WellKnownStorageValue::Code(code)
+			}
+			WellKnownStorage::HeapPages => WellKnownStorageValue::HeapPages(u64::decode(data)?),
+			WellKnownStorage::ExtrinsicIndex => WellKnownStorageValue::ExtrinsicIndex(u32::decode(data)?),
+		};
+		Ok(value)
+	}
+}
+
+/// Recognise the raw key of a [`WellKnownStorage`] location, returning `None` for any other key.
+pub fn decode_well_known_storage(key: &[u8]) -> Option<WellKnownStorage> {
+	[WellKnownStorage::Code, WellKnownStorage::HeapPages, WellKnownStorage::ExtrinsicIndex]
+		.into_iter()
+		.find(|storage| storage.key() == key)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+
+	#[test]
+	fn decodes_code() {
+		let storage = decode_well_known_storage(b":code").unwrap();
+		assert_eq!(storage, WellKnownStorage::Code);
+
+		let wasm = b"\0asm\x01\0\0\0";
+		let cursor = &mut &wasm[..];
+		assert_eq!(storage.decode_value(cursor).unwrap(), WellKnownStorageValue::Code(wasm.to_vec()));
+		assert!(cursor.is_empty());
+	}
+
+	#[test]
+	fn decodes_heap_pages() {
+		let storage = decode_well_known_storage(b":heappages").unwrap();
+		assert_eq!(storage, WellKnownStorage::HeapPages);
+
+		let bytes = 2048u64.encode();
+		assert_eq!(storage.decode_value(&mut &*bytes).unwrap(), WellKnownStorageValue::HeapPages(2048));
+		assert!(storage.decode_value(&mut &bytes[..4]).is_err());
+	}
+
+	#[test]
+	fn ignores_other_keys() {
+		assert_eq!(decode_well_known_storage(b":extrinsic_index"), Some(WellKnownStorage::ExtrinsicIndex));
+		assert_eq!(decode_well_known_storage(b":code:"), None);
+		assert_eq!(decode_well_known_storage(b":cod"), None);
+		assert_eq!(decode_well_known_storage(&[0u8; 32]), None);
+	}
+}
@@ -17,11 +17,20 @@
 //! A crate to decode extrinsics, signer payloads and storage keys for substrate nodes using V14+ metadata.
 //! See [`decoder`] for more information.
 
+#[cfg(feature = "cbor")]
+pub mod cbor;
 pub mod decoder;
 pub mod metadata;
+#[cfg(feature = "pretty")]
+pub mod pretty;
+mod value_builder;
+mod value_ext;
+pub mod value_serde;
 
 pub use metadata::Metadata;
 pub use scale_value::{Value, ValueDef};
+pub use value_builder::ValueBuilder;
+pub use value_ext::{ByteLenError, Hashable, Truncated, ValueExt};
 
 /// An ID that represents a type in a [`scale_info::PortableRegistry`].
 pub type TypeId = u32;
@@ -29,6 +38,15 @@ pub type TypeId = u32;
 /// A re-export of the [`scale_info`] crate, since we delegate much of the type inspection to it.
 pub use scale_info;
 
+/// A re-export of the [`scale_value`] crate. Decoded values are [`scale_value::Value`]s (which is what [`Value`]
+/// refers to), so they can be handed straight to tools expecting `scale-value` types without any conversion.
+pub use scale_value;
+
+/// A re-export of the [`scale_encode`] crate. [`Value`]s implement [`scale_encode::EncodeAsType`], so decoded
+/// values can be encoded as any compatible type in a registry (for instance, to hand them to `subxt`), and
+/// not just as the type that they were decoded as (see [`decoder::encode_value`] for that).
+pub use scale_encode;
+
 /// A re-export of [`scale_info::Type`] as used throughout this library.
 pub type Type = scale_info::Type<scale_info::form::PortableForm>;
 
@@ -20,12 +20,14 @@
 mod readonly_array;
 mod u8_map;
 mod version_14;
+mod version_15;
 
 use crate::{ScaleInfoTypeId, Type, TypeId};
-use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
 use parity_scale_codec::Decode;
 use readonly_array::ReadonlyArray;
 use scale_info::{form::PortableForm, PortableRegistry};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use u8_map::U8Map;
 
 // Some type aliases used below. `scale-info` is re-exported at the root,
@@ -47,6 +49,8 @@ pub enum MetadataError {
 	ExpectedVariantType { got: String },
 	#[error("could not find type with ID {0}")]
 	TypeNotFound(u32),
+	#[error("metadata should begin with the magic number {META_RESERVED:#x} ('meta'), but got {got:#x}")]
+	InvalidMagic { got: u32 },
 }
 
 /// This is a representation of the SCALE encoded metadata obtained from a substrate
@@ -59,11 +63,29 @@ pub struct Metadata {
 	/// Hash pallet calls by index, since when decoding, we'll have the pallet/call
 	/// `u8`'s available to us to look them up by.
 	pallet_calls_by_index: U8Map<MetadataPalletCalls>,
+	/// Hash pallet events by index, since when decoding, we'll have the pallet/event
+	/// `u8`'s available to us to look them up by.
+	pallet_events_by_index: U8Map<MetadataPalletEvents>,
+	/// Hash pallet errors by index, since `DispatchError::Module` errors give us the
+	/// pallet/error `u8`'s to look them up by.
+	pallet_errors_by_index: U8Map<MetadataPalletErrors>,
 	/// Store storage entry information as a readonly array, allowing us to look up a
 	/// specific storage entry using a key like `(usize,usize)`. Since the order of
 	/// entries in this array is not guaranteed between metadata versions, it should
 	/// not be exposed.
 	pallet_storage: ReadonlyArray<MetadataPalletStorage>,
+	/// The type ID of the outer call enum, whose variants wrap the calls of each pallet. Calls which take another call
+	/// as an argument (like `Proxy.proxy`) refer to this type.
+	call_ty: Option<TypeId>,
+	/// The type ID of the outer event enum, whose variants wrap the events of each pallet. Only V15+ metadata
+	/// tells us this.
+	outer_event_ty: Option<TypeId>,
+	/// The type IDs of the pallet constants. We don't decode constants, but [`Metadata::validate`] checks these.
+	constant_types: Vec<TypeId>,
+	/// The type IDs of `Vec<u8>` and `u8`, which values left as bytes are given as their context.
+	bytes_ty: (TypeId, TypeId),
+	/// Chain specific values published in the `custom` section of V15+ metadata, keyed by name.
+	custom: BTreeMap<String, frame_metadata::v15::CustomValueMetadata<PortableForm>>,
 	/// Type information lives inside this.
 	types: PortableRegistry,
 }
@@ -84,6 +106,11 @@ impl Metadata {
 	/// This file can then be read and passed directly to this method.
 	pub fn from_bytes(bytes: &[u8]) -> Result<Self, MetadataError> {
 		log::trace!("Decoding metadata");
+		// Check the magic number first, so that we can give a clear error if we've been handed something else.
+		let magic = u32::decode(&mut &*bytes)?;
+		if magic != META_RESERVED {
+			return Err(MetadataError::InvalidMagic { got: magic });
+		}
 		let meta = RuntimeMetadataPrefixed::decode(&mut &*bytes)?;
 		Self::from_runtime_metadata(meta.1)
 	}
@@ -95,6 +122,10 @@ impl Metadata {
 				log::trace!("V14 metadata found.");
 				version_14::decode(meta_v14)
 			}
+			RuntimeMetadata::V15(meta_v15) => {
+				log::trace!("V15 metadata found.");
+				version_15::decode(meta_v15)
+			}
 			unsupported_meta => Err(MetadataError::UnsupportedVersion(unsupported_meta.version())),
 		}
 	}
@@ -104,16 +135,133 @@ impl Metadata {
 		&self.extrinsic
 	}
 
+	/// The number of pallets described by this metadata, including those with no calls, events, errors or storage.
+	pub fn pallet_count(&self) -> usize {
+		self.pallet_calls_by_index.values().count()
+	}
+
+	/// Iterate over every call that the metadata knows about, giving the name of the pallet that it belongs to and its
+	/// type information (its name, index and arguments). Pallets are given in the order that the metadata lists them,
+	/// and their calls in the order of the pallet's call enum.
+	pub fn calls(&self) -> impl Iterator<Item = (&str, &scale_info::Variant<PortableForm>)> {
+		self.pallet_calls_by_index.values().flat_map(move |pallet| {
+			let variants = pallet.calls.as_ref().and_then(|calls| self.get_variant(calls.calls_type_id));
+			variants.into_iter().flat_map(|v| &v.variants).map(move |variant| (&*pallet.name, variant))
+		})
+	}
+
 	/// Given a [`crate::TypeId`], return the corresponding type from the type registry, if possible.
 	pub fn resolve<Id: Into<TypeId>>(&self, id: Id) -> Option<&Type> {
 		self.types.resolve(id.into())
 	}
 
+	/// Iterate over every type in the type registry along with its [`crate::TypeId`], for instance to generate
+	/// schemas for all of the types that some metadata refers to.
+	pub fn iter_types(&self) -> impl Iterator<Item = (TypeId, &Type)> {
+		self.types.types.iter().map(|ty| (ty.id, &ty.ty))
+	}
+
+	/// A human readable name for the given type, including the types that any generic parameters were instantiated
+	/// with, for instance `Option<u32>` or `BTreeMap<AccountId32, Vec<u8>>`. Named types are given by the last
+	/// segment of their path, and unnamed ones (like sequences, arrays and tuples) in Rust syntax. `None` is handed
+	/// back if the type, or one that its name refers to, can't be found.
+	pub fn type_name<Id: Into<TypeId>>(&self, id: Id) -> Option<String> {
+		type_name(&self.types, id.into(), &mut Vec::new())
+	}
+
+	/// The number of bytes that the given type always takes up when SCALE encoded, or `None` if that depends on
+	/// the value (or if the type can't be found). Fixed width integers, arrays, and tuples and composites made of
+	/// such things have a fixed length. Sequences, strings and compact encoded values don't, and nor do enums
+	/// unless all of their variants happen to have the same fixed length.
+	pub fn fixed_encoded_len<Id: Into<TypeId>>(&self, id: Id) -> Option<usize> {
+		fixed_encoded_len(&self.types, id.into(), &mut Vec::new())
+	}
+
+	/// Check that every type referred to by the pallet calls, events, errors, storage entries and constants, and by
+	/// the signed extensions, is in the type registry, along with every type that those refer to in turn. This is
+	/// worth doing once on metadata from an untrusted source, rather than finding out part way through decoding.
+	/// On failure, the IDs of the missing types are handed back in ascending order.
+	pub fn validate(&self) -> Result<(), Vec<TypeId>> {
+		let calls = self.pallet_calls_by_index.values().filter_map(|p| p.calls.as_ref()).map(|c| c.calls_type_id.id);
+		let events =
+			self.pallet_events_by_index.values().filter_map(|p| p.events.as_ref()).map(|e| e.events_type_id.id);
+		let errors =
+			self.pallet_errors_by_index.values().filter_map(|p| p.errors.as_ref()).map(|e| e.errors_type_id.id);
+		let storage = self.pallet_storage.iter().flat_map(|p| p.entries()).flat_map(|entry| match &entry.ty {
+			frame_metadata::v14::StorageEntryType::Plain(ty) => vec![ty.id],
+			frame_metadata::v14::StorageEntryType::Map { key, value, .. } => vec![key.id, value.id],
+		});
+		let extensions = self.extrinsic.signed_extensions.iter().flat_map(|ext| [ext.ty.id, ext.additional_signed.id]);
+
+		let mut to_visit: Vec<TypeId> = calls
+			.chain(events)
+			.chain(errors)
+			.chain(storage)
+			.chain(extensions)
+			.chain(self.constant_types.iter().copied())
+			.chain(self.call_ty)
+			.chain(self.outer_event_ty)
+			.collect();
+		let mut visited = HashSet::new();
+		let mut missing = BTreeSet::new();
+		while let Some(id) = to_visit.pop() {
+			if !visited.insert(id) {
+				continue;
+			}
+			match self.types.resolve(id) {
+				Some(ty) => to_visit.extend(referenced_types(ty)),
+				None => {
+					missing.insert(id);
+				}
+			}
+		}
+
+		if missing.is_empty() {
+			Ok(())
+		} else {
+			Err(missing.into_iter().collect())
+		}
+	}
+
+	/// Iterate over the chain specific values in the `custom` section of the metadata, giving the name, type ID and
+	/// SCALE encoded bytes of each, in order of name. Only V15+ metadata has this section. See
+	/// [`crate::decoder::decode_custom_value`] to decode one.
+	pub fn custom(&self) -> impl Iterator<Item = (&str, TypeId, &[u8])> {
+		self.custom.iter().map(|(name, custom)| (&**name, custom.ty.id, &*custom.value))
+	}
+
+	/// Find a custom value by name, giving its type ID and SCALE encoded bytes.
+	pub(crate) fn custom_value(&self, name: &str) -> Option<(TypeId, &[u8])> {
+		self.custom.get(name).map(|custom| (custom.ty.id, &*custom.value))
+	}
+
+	/// The type ID of the outer call enum, if the metadata tells us it.
+	pub(crate) fn call_ty(&self) -> Option<TypeId> {
+		self.call_ty
+	}
+
 	/// Return a reference to the [`scale_info`] type registry.
 	pub(crate) fn types(&self) -> &PortableRegistry {
 		&self.types
 	}
 
+	/// Some chains use a custom extrinsic format in which the signature of a signed extrinsic is SCALE encoded as an
+	/// `Option`. The metadata doesn't tell us about this, so set this to decode extrinsics from such chains. A `None`
+	/// signature is then decoded as an unsigned extrinsic.
+	pub fn set_optional_signature(&mut self, optional: bool) {
+		self.extrinsic.optional_signature = optional;
+	}
+
+	/// Add a type that isn't described by the metadata itself to the type registry, returning the ID that it
+	/// can be decoded by (for instance with [`crate::decoder::decode_value_by_id`]). Types are only ever
+	/// appended, so the IDs of existing types don't change. Any type IDs that the new type refers to should
+	/// already be in the registry, or be the ID that is returned here.
+	pub fn register_synthetic_type(&mut self, ty: Type) -> TypeId {
+		let id = self.types.types.len() as TypeId;
+		self.types.types.push(scale_info::PortableType { id, ty });
+		id
+	}
+
 	/// Retrieve the storage entry at the location provided. Locations are generated from
 	/// [`crate::decoder::StorageDecoder`] calls, and should always exist. It is a user error
 	/// to use a different [`Metadata`] instance for obtaining these locations from the instance
@@ -128,6 +276,18 @@ impl Metadata {
 		StorageEntry { prefix: &pallet.prefix, metadata: entry }
 	}
 
+	/// Find a storage entry given the storage prefix of its pallet (normally the pallet name) and its name.
+	pub(crate) fn storage_entry_by_name(&self, prefix: &str, name: &str) -> Option<&StorageEntryMetadata> {
+		self.pallet_storage.iter().find(|p| p.prefix == prefix)?.storage_entries.iter().find(|e| e.name == name)
+	}
+
+	/// Return the storage prefix of the pallet with the given name, or `None` if there's no such pallet or it has
+	/// no storage. Storage keys begin with the twox128 hash of this prefix rather than of the pallet name. The two
+	/// are normally identical, but a pallet can be given a different storage prefix than its name in the runtime.
+	pub fn pallet_storage_prefix(&self, pallet_name: &str) -> Option<&str> {
+		self.pallet_storage.iter().find(|p| p.pallet_name == pallet_name).map(|p| &*p.prefix)
+	}
+
 	/// In order to generate a lookup table to decode storage entries, we need to be able to
 	/// iterate over them.
 	pub(crate) fn storage_entries(&self) -> impl Iterator<Item = &MetadataPalletStorage> {
@@ -135,16 +295,85 @@ impl Metadata {
 	}
 
 	/// Given the `u8` variant index of a pallet and call, this returns the pallet name and the call Variant
-	/// if found, or `None` if no such call exists at those indexes, or we don't have suitable call data.
+	/// if found, or a [`CallLookupError`] describing why no such call exists at those indexes.
 	pub(crate) fn call_variant_by_enum_index(
 		&self,
 		pallet: u8,
 		call: u8,
+	) -> Result<(&str, &scale_info::Variant<PortableForm>), CallLookupError> {
+		let p = self.pallet_calls_by_index.get(pallet).ok_or(CallLookupError::PalletNotFound)?;
+		let calls = p.calls.as_ref().ok_or(CallLookupError::PalletHasNoCalls)?;
+		let variant = self
+			.get_variant(calls.calls_type_id)
+			.zip(calls.call_variant_indexes.get(call))
+			.and_then(|(type_def_variant, index)| type_def_variant.variants.get(*index))
+			.ok_or(CallLookupError::CallNotFound)?;
+		Ok((&*p.name, variant))
+	}
+
+	/// The type IDs of `Vec<u8>` and of the `u8`s in it, for values which are handed back as bytes.
+	pub(crate) fn bytes_ty(&self) -> (TypeId, TypeId) {
+		self.bytes_ty
+	}
+
+	/// The ID of the enum type holding the calls of the pallet with the given `u8` variant index, if it has any.
+	pub(crate) fn pallet_calls_ty(&self, pallet: u8) -> Option<TypeId> {
+		let calls = self.pallet_calls_by_index.get(pallet)?.calls.as_ref()?;
+		Some(calls.calls_type_id.id)
+	}
+
+	/// Find the `u8` variant index of the pallet with the given name, if it has any calls.
+	pub(crate) fn pallet_call_index_by_name(&self, pallet: &str) -> Option<u8> {
+		(0..=u8::MAX).find(|&idx| self.pallet_calls_by_index.get(idx).is_some_and(|p| p.name == pallet))
+	}
+
+	/// Given the `u8` variant index of a pallet and event, this returns the pallet name and the event Variant
+	/// if found, or `None` if no such event exists at those indexes, or we don't have suitable event data.
+	/// The outer event enum is used to find the event if the metadata declares one, and otherwise the
+	/// event types of each pallet are.
+	pub fn event_variant_by_enum_index(
+		&self,
+		pallet: u8,
+		event: u8,
 	) -> Option<(&str, &scale_info::Variant<PortableForm>)> {
-		self.pallet_calls_by_index.get(pallet).and_then(|p| {
-			p.calls.as_ref().and_then(|calls| {
-				let type_def_variant = self.get_variant(calls.calls_type_id)?;
-				let index = *calls.call_variant_indexes.get(call)?;
+		if let Some(outer_event_ty) = self.outer_event_ty {
+			return self.event_variant_by_outer_enum(outer_event_ty, pallet, event);
+		}
+		self.pallet_events_by_index.get(pallet).and_then(|p| {
+			p.events.as_ref().and_then(|events| {
+				let type_def_variant = self.get_variant(events.events_type_id)?;
+				let index = *events.event_variant_indexes.get(event)?;
+				let variant = type_def_variant.variants.get(index)?;
+				Some((&*p.name, variant))
+			})
+		})
+	}
+
+	/// Find an event through the outer event enum, which has a variant for each pallet (named after it)
+	/// wrapping that pallet's event enum.
+	fn event_variant_by_outer_enum(
+		&self,
+		outer_event_ty: TypeId,
+		pallet: u8,
+		event: u8,
+	) -> Option<(&str, &scale_info::Variant<PortableForm>)> {
+		let pallet_variant = self.get_variant_by_id(outer_event_ty)?.variants.iter().find(|v| v.index == pallet)?;
+		let [field] = pallet_variant.fields.as_slice() else { return None };
+		let variant = self.get_variant_by_id(field.ty.id)?.variants.iter().find(|v| v.index == event)?;
+		Some((&*pallet_variant.name, variant))
+	}
+
+	/// Given the `u8` variant index of a pallet and error, this returns the pallet name and the error Variant
+	/// if found, or `None` if no such error exists at those indexes, or we don't have suitable error data.
+	pub(crate) fn error_variant_by_enum_index(
+		&self,
+		pallet: u8,
+		error: u8,
+	) -> Option<(&str, &scale_info::Variant<PortableForm>)> {
+		self.pallet_errors_by_index.get(pallet).and_then(|p| {
+			p.errors.as_ref().and_then(|errors| {
+				let type_def_variant = self.get_variant(errors.errors_type_id)?;
+				let index = *errors.error_variant_indexes.get(error)?;
 				let variant = type_def_variant.variants.get(index)?;
 				Some((&*p.name, variant))
 			})
@@ -153,15 +382,32 @@ impl Metadata {
 
 	/// A helper function to get hold of a Variant given a type ID, or None if it's not found.
 	fn get_variant(&self, ty: ScaleInfoTypeId) -> Option<&TypeDefVariant> {
-		self.types.resolve(ty.id).and_then(|ty| match &ty.type_def {
+		self.get_variant_by_id(ty.id)
+	}
+
+	fn get_variant_by_id(&self, id: TypeId) -> Option<&TypeDefVariant> {
+		self.types.resolve(id).and_then(|ty| match &ty.type_def {
 			scale_info::TypeDef::Variant(variant) => Some(variant),
 			_ => None,
 		})
 	}
 }
 
+/// The reasons that [`Metadata::call_variant_by_enum_index`] can fail to find a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CallLookupError {
+	/// There is no pallet at the given index.
+	PalletNotFound,
+	/// The pallet exists, but has no calls.
+	PalletHasNoCalls,
+	/// The pallet has calls, but none at the given index.
+	CallNotFound,
+}
+
 #[derive(Debug)]
 pub(crate) struct MetadataPalletStorage {
+	/// The name of the pallet that this storage belongs to.
+	pallet_name: String,
 	/// The storage prefix (normally identical to the pallet name,
 	/// although they are distinct values in the metadata).
 	prefix: String,
@@ -199,14 +445,66 @@ struct MetadataCalls {
 	call_variant_indexes: U8Map<usize>,
 }
 
+#[derive(Debug)]
+struct MetadataPalletEvents {
+	/// The pallet name.
+	name: String,
+	/// Not every pallet emits events. If it does, the
+	/// event information will be here.
+	events: Option<MetadataEvents>,
+}
+
+#[derive(Debug)]
+struct MetadataEvents {
+	/// This allows us to find the type information corresponding to
+	/// the event in the [`PortableRegistry`].
+	events_type_id: ScaleInfoTypeId,
+	/// This allows us to map a u8 enum index to the correct event variant
+	/// from the events type, above.
+	event_variant_indexes: U8Map<usize>,
+}
+
+#[derive(Debug)]
+struct MetadataPalletErrors {
+	/// The pallet name.
+	name: String,
+	/// Not every pallet has errors. If it does, the
+	/// error information will be here.
+	errors: Option<MetadataErrors>,
+}
+
+#[derive(Debug)]
+struct MetadataErrors {
+	/// This allows us to find the type information corresponding to
+	/// the error in the [`PortableRegistry`].
+	errors_type_id: ScaleInfoTypeId,
+	/// This allows us to map a u8 enum index to the correct error variant
+	/// from the errors type, above.
+	error_variant_indexes: U8Map<usize>,
+}
+
 /// Information about the extrinsic format supported on the substrate node
 /// that the metadata was obtained from.
 #[derive(Debug, Clone)]
 pub struct MetadataExtrinsic {
 	version: u8,
+	signer_format: SignerFormat,
+	optional_signature: bool,
 	signed_extensions: Vec<SignedExtensionMetadata>,
 }
 
+/// The shape of the address and signature that a signed extrinsic starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignerFormat {
+	/// A `MultiAddress<AccountId32, _>` address and a `MultiSignature`, as used by most substrate based chains.
+	Substrate,
+	/// A 20 byte `AccountId20` address and a 65 byte ECDSA `EthereumSignature`, as used by EVM compatible
+	/// chains such as Moonbeam.
+	Ethereum,
+	/// A bare 32 byte public key or `AccountId32` address (rather than a `MultiAddress`) and a `MultiSignature`.
+	AccountId32,
+}
+
 impl MetadataExtrinsic {
 	/// The version of the extrinsic format in use by the node.
 	#[allow(unused)]
@@ -214,6 +512,18 @@ impl MetadataExtrinsic {
 		self.version
 	}
 
+	/// The shape of the address and signature of signed extrinsics, worked out from the `Address`
+	/// type parameter of the extrinsic type.
+	pub fn signer_format(&self) -> SignerFormat {
+		self.signer_format
+	}
+
+	/// Whether the signature of signed extrinsics is wrapped in an `Option`; see
+	/// [`Metadata::set_optional_signature`].
+	pub fn optional_signature(&self) -> bool {
+		self.optional_signature
+	}
+
 	/// Part of the extrinsic signature area can be varied to include whatever information
 	/// a node decides is important. This returns details about that part.
 	pub(crate) fn signed_extensions(&self) -> &[SignedExtensionMetadata] {
@@ -234,3 +544,435 @@ pub(crate) struct StorageEntry<'a> {
 	pub prefix: &'a str,
 	pub metadata: &'a StorageEntryMetadata,
 }
+
+/// The IDs of the types that the given type is built from; see [`Metadata::validate`].
+fn referenced_types(ty: &Type) -> Vec<TypeId> {
+	use scale_info::TypeDef;
+	match &ty.type_def {
+		TypeDef::Composite(composite) => composite.fields.iter().map(|f| f.ty.id).collect(),
+		TypeDef::Variant(variant) => variant.variants.iter().flat_map(|v| &v.fields).map(|f| f.ty.id).collect(),
+		TypeDef::Sequence(seq) => vec![seq.type_param.id],
+		TypeDef::Array(array) => vec![array.type_param.id],
+		TypeDef::Tuple(tuple) => tuple.fields.iter().map(|f| f.id).collect(),
+		TypeDef::Primitive(_) => vec![],
+		TypeDef::Compact(compact) => vec![compact.type_param.id],
+		TypeDef::BitSequence(bits) => vec![bits.bit_store_type.id, bits.bit_order_type.id],
+	}
+}
+
+/// See [`Metadata::fixed_encoded_len`]. `visiting` holds the types that we're part way through, so that a type
+/// which contains itself (and so can't have a fixed length anyway) doesn't send us round in circles.
+fn fixed_encoded_len(types: &PortableRegistry, id: TypeId, visiting: &mut Vec<TypeId>) -> Option<usize> {
+	use scale_info::{TypeDef, TypeDefPrimitive};
+
+	if visiting.contains(&id) {
+		return None;
+	}
+	let ty = types.resolve(id)?;
+	visiting.push(id);
+	let len = match &ty.type_def {
+		TypeDef::Composite(composite) => {
+			fixed_encoded_len_of_all(types, composite.fields.iter().map(|f| f.ty.id), visiting)
+		}
+		TypeDef::Tuple(tuple) => fixed_encoded_len_of_all(types, tuple.fields.iter().map(|f| f.id), visiting),
+		TypeDef::Array(array) => {
+			fixed_encoded_len(types, array.type_param.id, visiting).and_then(|len| len.checked_mul(array.len as usize))
+		}
+		TypeDef::Variant(variant) => {
+			let mut lens = variant
+				.variants
+				.iter()
+				.map(|v| fixed_encoded_len_of_all(types, v.fields.iter().map(|f| f.ty.id), visiting));
+			match lens.next() {
+				Some(Some(first)) if lens.all(|len| len == Some(first)) => first.checked_add(1),
+				_ => None,
+			}
+		}
+		TypeDef::Primitive(primitive) => match primitive {
+			TypeDefPrimitive::Bool | TypeDefPrimitive::U8 | TypeDefPrimitive::I8 => Some(1),
+			TypeDefPrimitive::U16 | TypeDefPrimitive::I16 => Some(2),
+			TypeDefPrimitive::Char | TypeDefPrimitive::U32 | TypeDefPrimitive::I32 => Some(4),
+			TypeDefPrimitive::U64 | TypeDefPrimitive::I64 => Some(8),
+			TypeDefPrimitive::U128 | TypeDefPrimitive::I128 => Some(16),
+			TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => Some(32),
+			TypeDefPrimitive::Str => None,
+		},
+		TypeDef::Sequence(_) | TypeDef::Compact(_) | TypeDef::BitSequence(_) => None,
+	};
+	visiting.pop();
+	len
+}
+
+/// The total fixed length of some types encoded one after the other, if they all have one.
+fn fixed_encoded_len_of_all(
+	types: &PortableRegistry,
+	ids: impl Iterator<Item = TypeId>,
+	visiting: &mut Vec<TypeId>,
+) -> Option<usize> {
+	let mut total = 0usize;
+	for id in ids {
+		total = total.checked_add(fixed_encoded_len(types, id, visiting)?)?;
+	}
+	Some(total)
+}
+
+/// A human readable name for a type; see [`Metadata::type_name`].
+fn type_name(types: &PortableRegistry, id: TypeId, visiting: &mut Vec<TypeId>) -> Option<String> {
+	use scale_info::{TypeDef, TypeDefPrimitive};
+
+	if visiting.contains(&id) {
+		return None;
+	}
+	let ty = types.resolve(id)?;
+	visiting.push(id);
+	let name = if let Some(ident) = ty.path.ident() {
+		if ty.type_params.is_empty() {
+			Some(ident)
+		} else {
+			// Parameters which aren't used by the type may not say what they were instantiated with.
+			let params = ty
+				.type_params
+				.iter()
+				.map(|param| match &param.ty {
+					Some(param_ty) => type_name(types, param_ty.id, visiting),
+					None => Some(param.name.clone()),
+				})
+				.collect::<Option<Vec<_>>>();
+			params.map(|params| format!("{ident}<{}>", params.join(", ")))
+		}
+	} else {
+		let mut names = |ids: &mut dyn Iterator<Item = TypeId>| {
+			ids.map(|id| type_name(types, id, visiting)).collect::<Option<Vec<_>>>().map(|names| names.join(", "))
+		};
+		match &ty.type_def {
+			TypeDef::Sequence(seq) => names(&mut std::iter::once(seq.type_param.id)).map(|t| format!("Vec<{t}>")),
+			TypeDef::Array(array) => {
+				names(&mut std::iter::once(array.type_param.id)).map(|t| format!("[{t}; {}]", array.len))
+			}
+			TypeDef::Tuple(tuple) => names(&mut tuple.fields.iter().map(|f| f.id)).map(|t| format!("({t})")),
+			TypeDef::Compact(compact) => {
+				names(&mut std::iter::once(compact.type_param.id)).map(|t| format!("Compact<{t}>"))
+			}
+			TypeDef::BitSequence(bits) => {
+				names(&mut [bits.bit_store_type.id, bits.bit_order_type.id].into_iter()).map(|t| format!("BitVec<{t}>"))
+			}
+			TypeDef::Primitive(primitive) => Some(
+				match primitive {
+					TypeDefPrimitive::Bool => "bool",
+					TypeDefPrimitive::Char => "char",
+					TypeDefPrimitive::Str => "str",
+					TypeDefPrimitive::U8 => "u8",
+					TypeDefPrimitive::U16 => "u16",
+					TypeDefPrimitive::U32 => "u32",
+					TypeDefPrimitive::U64 => "u64",
+					TypeDefPrimitive::U128 => "u128",
+					TypeDefPrimitive::U256 => "u256",
+					TypeDefPrimitive::I8 => "i8",
+					TypeDefPrimitive::I16 => "i16",
+					TypeDefPrimitive::I32 => "i32",
+					TypeDefPrimitive::I64 => "i64",
+					TypeDefPrimitive::I128 => "i128",
+					TypeDefPrimitive::I256 => "i256",
+				}
+				.to_string(),
+			),
+			// Composites and variants are always given a path by `scale-info`, so this shouldn't happen.
+			TypeDef::Composite(_) | TypeDef::Variant(_) => Some("_".to_string()),
+		}
+	};
+	visiting.pop();
+	name
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+	use scale_info::{
+		Field, Path, TypeDef, TypeDefArray, TypeDefComposite, TypeDefPrimitive, TypeDefSequence, Variant,
+	};
+	use scale_value::Value;
+
+	fn metadata() -> Metadata {
+		Metadata::from_bytes(include_bytes!("../../tests/data/v14_metadata_polkadot.scale")).expect("valid metadata")
+	}
+
+	fn synthetic_type(path: &[&str], type_def: TypeDef<PortableForm>) -> Type {
+		Type {
+			path: Path { segments: path.iter().map(|s| s.to_string()).collect() },
+			type_params: vec![],
+			type_def,
+			docs: vec![],
+		}
+	}
+
+	#[test]
+	fn finds_event_variants_by_index() {
+		let metadata = metadata();
+		let (pallet, event) = metadata.event_variant_by_enum_index(0, 0).expect("System.ExtrinsicSuccess exists");
+		assert_eq!(pallet, "System");
+		assert_eq!(event.name, "ExtrinsicSuccess");
+		assert_eq!(event.fields.len(), 1);
+
+		let (pallet, event) = metadata.event_variant_by_enum_index(5, 2).expect("Balances.Transfer exists");
+		assert_eq!((pallet, &*event.name), ("Balances", "Transfer"));
+
+		assert!(metadata.event_variant_by_enum_index(0, 200).is_none());
+		assert!(metadata.event_variant_by_enum_index(200, 0).is_none());
+	}
+
+	#[test]
+	fn validates_that_referenced_types_exist() {
+		assert_eq!(metadata().validate(), Ok(()));
+
+		// Point a storage entry and a constant at types that aren't in the registry:
+		let bytes = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+		let RuntimeMetadata::V14(mut meta) = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap().1 else {
+			panic!("expected V14 metadata")
+		};
+		let pallet = meta.pallets.iter_mut().find(|p| p.storage.is_some() && !p.constants.is_empty()).unwrap();
+		let entry = &mut pallet.storage.as_mut().unwrap().entries[0];
+		entry.ty = frame_metadata::v14::StorageEntryType::Plain(1_000_000.into());
+		pallet.constants[0].ty = 1_000_001.into();
+
+		let metadata = Metadata::from_runtime_metadata(RuntimeMetadata::V14(meta)).unwrap();
+		assert_eq!(metadata.validate(), Err(vec![1_000_000, 1_000_001]));
+	}
+
+	#[test]
+	fn pallet_storage_prefix_can_differ_from_pallet_name() {
+		let metadata = metadata();
+		assert_eq!(metadata.pallet_storage_prefix("System"), Some("System"));
+		assert_eq!(metadata.pallet_storage_prefix("Utility"), None, "Utility has no storage");
+		assert_eq!(metadata.pallet_storage_prefix("NotAPallet"), None);
+
+		let bytes = include_bytes!("../../tests/data/v14_metadata_polkadot.scale");
+		let RuntimeMetadata::V14(mut runtime_metadata) = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap().1
+		else {
+			panic!("expected V14 metadata")
+		};
+		let system = runtime_metadata.pallets.iter_mut().find(|p| p.name == "System").unwrap();
+		system.storage.as_mut().unwrap().prefix = "Frame".to_string();
+
+		let metadata = Metadata::from_runtime_metadata(RuntimeMetadata::V14(runtime_metadata)).unwrap();
+		assert_eq!(metadata.pallet_storage_prefix("System"), Some("Frame"));
+		assert_eq!(metadata.pallet_storage_prefix("Frame"), None);
+	}
+
+	#[test]
+	fn type_names_include_generic_parameters() {
+		let metadata = metadata();
+		let type_name = |id: u32| metadata.type_name(id).expect("type exists");
+
+		assert_eq!(type_name(3), "AccountInfo<u32, AccountData<u128>>");
+		assert_eq!(type_name(30), "Option<Vec<u8>>");
+		assert_eq!(type_name(31), "Result<(), DispatchError>");
+		assert_eq!(type_name(49), "(AccountId32, Exposure<AccountId32, u128>)");
+		assert_eq!(type_name(1), "[u8; 32]");
+		assert_eq!(type_name(51), "Compact<u128>");
+		// Pallet events don't say what their `T` and `I` parameters were instantiated with:
+		assert_eq!(type_name(34), "Event<T, I>");
+		assert_eq!(metadata.type_name(1_000_000u32), None);
+	}
+
+	#[test]
+	fn iterates_over_all_calls() {
+		let metadata = metadata();
+		let calls: Vec<_> = metadata.calls().map(|(pallet, call)| (pallet, &*call.name)).collect();
+		assert_eq!(calls[0], ("System", "fill_block"));
+		assert!(calls.contains(&("Balances", "transfer")));
+
+		for (pallet, call) in metadata.calls() {
+			let pallet_index = metadata.pallet_call_index_by_name(pallet).unwrap();
+			let (_, found) = metadata.call_variant_by_enum_index(pallet_index, call.index).unwrap();
+			assert_eq!(found, call);
+		}
+	}
+
+	#[test]
+	fn iterates_over_all_types() {
+		let metadata = metadata();
+		assert_eq!(metadata.iter_types().count(), metadata.types().types.len());
+
+		let (id, ty) = metadata.iter_types().find(|(_, ty)| ty.path.ident().as_deref() == Some("AccountId32")).unwrap();
+		assert_eq!(metadata.resolve(id), Some(ty));
+	}
+
+	#[test]
+	fn pallets_without_calls_storage_events_or_errors_are_fine() {
+		use frame_metadata::v14::{ExtrinsicMetadata, PalletCallMetadata, PalletMetadata, RuntimeMetadataV14};
+		use scale_info::{meta_type, TypeInfo};
+
+		#[allow(dead_code, non_camel_case_types)]
+		#[derive(TypeInfo)]
+		enum Call {
+			remark { remark: Vec<u8> },
+		}
+
+		let pallet = |name, index, calls| PalletMetadata {
+			name,
+			storage: None,
+			calls,
+			event: None,
+			constants: vec![],
+			error: None,
+			index,
+		};
+		let extrinsic = || ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+		let from_pallets = |pallets| {
+			let runtime_metadata = RuntimeMetadataV14::new(pallets, extrinsic(), meta_type::<()>());
+			Metadata::from_runtime_metadata(RuntimeMetadata::V14(runtime_metadata)).expect("valid metadata")
+		};
+
+		// No pallets at all:
+		let metadata = from_pallets(vec![]);
+		assert_eq!(metadata.call_variant_by_enum_index(0, 0).unwrap_err(), CallLookupError::PalletNotFound);
+		assert_eq!(metadata.storage_entries().count(), 0);
+
+		// An empty pallet alongside one with calls:
+		let metadata = from_pallets(vec![
+			pallet("Empty", 0, None),
+			pallet("Remarks", 1, Some(PalletCallMetadata { ty: meta_type::<Call>() })),
+		]);
+		assert_eq!(metadata.call_variant_by_enum_index(0, 0).unwrap_err(), CallLookupError::PalletHasNoCalls);
+		assert!(metadata.event_variant_by_enum_index(0, 0).is_none());
+		assert!(metadata.error_variant_by_enum_index(0, 0).is_none());
+		assert_eq!(metadata.pallet_storage_prefix("Empty"), None);
+
+		let call = crate::decoder::decode_call_data(&metadata, &mut &[1u8, 0, 8, b'h', b'i'][..]).unwrap();
+		assert_eq!(call.pallet_name, "Remarks");
+		assert_eq!(call.arguments[0].clone().remove_context(), Value::from_bytes(b"hi"));
+	}
+
+	#[test]
+	fn pallet_and_call_indexes_can_use_the_whole_u8_range() {
+		use frame_metadata::v14::{ExtrinsicMetadata, PalletCallMetadata, PalletMetadata, RuntimeMetadataV14};
+		use scale_info::{meta_type, TypeInfo};
+
+		#[allow(dead_code, non_camel_case_types)]
+		#[derive(TypeInfo)]
+		enum Call {
+			#[codec(index = 0)]
+			first { a: u8 },
+			#[codec(index = 200)]
+			middling { b: u16 },
+			#[codec(index = 255)]
+			last { c: u32 },
+		}
+
+		let pallet = |name, index| PalletMetadata {
+			name,
+			storage: None,
+			calls: Some(PalletCallMetadata { ty: meta_type::<Call>() }),
+			event: None,
+			constants: vec![],
+			error: None,
+			index,
+		};
+		let extrinsic = ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+		let runtime_metadata =
+			RuntimeMetadataV14::new(vec![pallet("Middling", 200), pallet("Last", 255)], extrinsic, meta_type::<()>());
+		let metadata = Metadata::from_runtime_metadata(RuntimeMetadata::V14(runtime_metadata)).expect("valid metadata");
+
+		for (pallet, call, name) in
+			[(200, 200, "middling"), (200, 255, "last"), (255, 0, "first"), (255, 200, "middling")]
+		{
+			let (_, variant) = metadata.call_variant_by_enum_index(pallet, call).unwrap();
+			assert_eq!((variant.index, &*variant.name), (call, name));
+		}
+		assert_eq!(metadata.call_variant_by_enum_index(200, 199).unwrap_err(), CallLookupError::CallNotFound);
+		assert_eq!(metadata.call_variant_by_enum_index(199, 200).unwrap_err(), CallLookupError::PalletNotFound);
+		assert_eq!(metadata.pallet_call_index_by_name("Last"), Some(255));
+
+		let mut data = &[200u8, 200, 0x37, 0x13][..];
+		let call = crate::decoder::decode_call_data(&metadata, &mut data).unwrap();
+		assert!(data.is_empty());
+		assert_eq!((&*call.pallet_name, &*call.ty.name), ("Middling", "middling"));
+		assert_eq!(call.arguments[0].clone().remove_context(), Value::u128(0x1337));
+
+		let call = crate::decoder::decode_call_data(&metadata, &mut &[255u8, 255, 1, 0, 0, 0][..]).unwrap();
+		assert_eq!((&*call.pallet_name, &*call.ty.name), ("Last", "last"));
+	}
+
+	#[test]
+	fn rejects_bytes_without_metadata_magic() {
+		let mut bytes = include_bytes!("../../tests/data/v14_metadata_polkadot.scale").to_vec();
+		bytes[..4].copy_from_slice(b"atem");
+
+		let err = Metadata::from_bytes(&bytes).unwrap_err();
+		assert!(matches!(err, MetadataError::InvalidMagic { got: 0x6d657461 }), "{err:?}");
+		// Too few bytes to contain a magic number at all:
+		assert!(matches!(Metadata::from_bytes(b"met"), Err(MetadataError::CodecError(_))));
+	}
+
+	#[test]
+	fn computes_fixed_encoded_lengths() {
+		let mut metadata = metadata();
+		let mut register = |type_def| metadata.register_synthetic_type(synthetic_type(&[], type_def));
+
+		let u8_id = register(TypeDef::Primitive(TypeDefPrimitive::U8));
+		let u32_id = register(TypeDef::Primitive(TypeDefPrimitive::U32));
+		let bytes32_id = register(TypeDef::Array(TypeDefArray::new(32, u8_id.into())));
+		let vec_id = register(TypeDef::Sequence(TypeDefSequence::new(u8_id.into())));
+		let field = |ty: TypeId| Field { name: None, ty: ty.into(), type_name: None, docs: vec![] };
+		let fixed_struct_id =
+			register(TypeDef::Composite(TypeDefComposite { fields: vec![field(u32_id), field(bytes32_id)] }));
+		let variable_struct_id =
+			register(TypeDef::Composite(TypeDefComposite { fields: vec![field(u32_id), field(vec_id)] }));
+		let variant = |name: &str, index: u8, fields: Vec<Field<PortableForm>>| Variant {
+			name: name.into(),
+			fields,
+			index,
+			docs: vec![],
+		};
+		let option_id = register(TypeDef::Variant(TypeDefVariant::new(vec![
+			variant("None", 0, vec![]),
+			variant("Some", 1, vec![field(u32_id)]),
+		])));
+		let fieldless_enum_id =
+			register(TypeDef::Variant(TypeDefVariant::new(vec![variant("A", 0, vec![]), variant("B", 1, vec![])])));
+		let self_id = metadata.types().types.len() as TypeId;
+		let recursive_id = metadata.register_synthetic_type(synthetic_type(
+			&[],
+			TypeDef::Composite(TypeDefComposite { fields: vec![field(self_id)] }),
+		));
+
+		assert_eq!(metadata.fixed_encoded_len(bytes32_id), Some(32));
+		assert_eq!(metadata.fixed_encoded_len(vec_id), None);
+		assert_eq!(metadata.fixed_encoded_len(fixed_struct_id), Some(36));
+		assert_eq!(metadata.fixed_encoded_len(variable_struct_id), None);
+		assert_eq!(metadata.fixed_encoded_len(option_id), None);
+		assert_eq!(metadata.fixed_encoded_len(fieldless_enum_id), Some(1));
+		assert_eq!(metadata.fixed_encoded_len(recursive_id), None);
+		assert_eq!(metadata.fixed_encoded_len(u32::MAX), None);
+	}
+
+	#[test]
+	fn can_decode_synthetic_types() {
+		let mut metadata = metadata();
+		let num_types = metadata.types().types.len() as TypeId;
+
+		let u16_id = metadata.register_synthetic_type(synthetic_type(&[], TypeDef::Primitive(TypeDefPrimitive::U16)));
+		let seq_id = metadata
+			.register_synthetic_type(synthetic_type(&[], TypeDef::Sequence(TypeDefSequence::new(u16_id.into()))));
+		let field =
+			|name: &str, ty: TypeId| Field { name: Some(name.into()), ty: ty.into(), type_name: None, docs: vec![] };
+		let foo_id = metadata.register_synthetic_type(synthetic_type(
+			&["synthetic", "Foo"],
+			TypeDef::Composite(TypeDefComposite { fields: vec![field("a", u16_id), field("b", seq_id)] }),
+		));
+
+		assert_eq!((u16_id, seq_id, foo_id), (num_types, num_types + 1, num_types + 2));
+		assert_eq!(metadata.resolve(foo_id).and_then(|ty| ty.path.ident()), Some("Foo".to_string()));
+
+		let bytes = (7u16, vec![1u16, 2]).encode();
+		let value = crate::decoder::decode_value_by_id(&metadata, foo_id, &mut &*bytes).expect("can decode");
+		assert_eq!(
+			value.remove_context(),
+			Value::named_composite(vec![
+				("a", Value::u128(7)),
+				("b", Value::unnamed_composite(vec![Value::u128(1), Value::u128(2)])),
+			])
+		);
+	}
+}
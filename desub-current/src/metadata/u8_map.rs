@@ -75,6 +75,11 @@ impl<V> U8Map<V> {
 			Some(item)
 		}
 	}
+
+	/// Iterate over the values in the map, in the order that they were first inserted.
+	pub fn values(&self) -> impl Iterator<Item = &V> {
+		self.items.iter()
+	}
 }
 
 impl<V> FromIterator<(u8, V)> for U8Map<V> {
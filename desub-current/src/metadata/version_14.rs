@@ -15,56 +15,249 @@
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::u8_map::U8Map;
-use super::{Metadata, MetadataCalls, MetadataError, MetadataExtrinsic, MetadataPalletCalls, MetadataPalletStorage};
-use frame_metadata::v14::RuntimeMetadataV14;
+use super::{
+	Metadata, MetadataCalls, MetadataError, MetadataErrors, MetadataEvents, MetadataExtrinsic, MetadataPalletCalls,
+	MetadataPalletErrors, MetadataPalletEvents, MetadataPalletStorage, SignerFormat,
+};
+use crate::ScaleInfoTypeId;
+use crate::TypeId;
+use frame_metadata::v14::{PalletMetadata, RuntimeMetadataV14, SignedExtensionMetadata};
+use scale_info::{
+	form::PortableForm, Path, PortableRegistry, PortableType, Type, TypeDef, TypeDefPrimitive, TypeDefSequence,
+};
 
 /// Decode V14 metadata into our general Metadata struct
 pub fn decode(meta: RuntimeMetadataV14) -> Result<Metadata, MetadataError> {
-	let registry = meta.types;
+	let address_ty = extrinsic_type_param(&meta.types, meta.extrinsic.ty, "Address");
+	let call_ty = extrinsic_type_param(&meta.types, meta.extrinsic.ty, "Call");
+	decode_parts(
+		meta.types,
+		meta.pallets,
+		meta.extrinsic.version,
+		address_ty,
+		call_ty,
+		meta.extrinsic.signed_extensions,
+		None,
+	)
+}
+
+/// Build our general Metadata struct from the parts that V14 and later metadata versions share.
+/// `address_ty` is the type ID of the `Address` used in signed extrinsics, `call_ty` the type ID of the enum of
+/// all calls, and `outer_event_ty` the type ID of the enum of all events, if known.
+pub(super) fn decode_parts(
+	mut registry: PortableRegistry,
+	pallets: Vec<PalletMetadata<PortableForm>>,
+	extrinsic_version: u8,
+	address_ty: Option<TypeId>,
+	call_ty: Option<TypeId>,
+	signed_extensions: Vec<SignedExtensionMetadata<PortableForm>>,
+	outer_event_ty: Option<TypeId>,
+) -> Result<Metadata, MetadataError> {
 	let mut pallet_calls_by_index = U8Map::new();
+	let mut pallet_events_by_index = U8Map::new();
+	let mut pallet_errors_by_index = U8Map::new();
 	let mut pallet_storage = Vec::new();
+	let mut constant_types = Vec::new();
 
 	// Gather some details about the extrinsic itself:
-	let extrinsic =
-		MetadataExtrinsic { version: meta.extrinsic.version, signed_extensions: meta.extrinsic.signed_extensions };
+	let extrinsic = MetadataExtrinsic {
+		version: extrinsic_version,
+		signer_format: signer_format(&registry, address_ty),
+		optional_signature: false,
+		signed_extensions,
+	};
 
 	// Gather information about the calls/storage in use:
-	for pallet in meta.pallets {
+	for pallet in pallets {
+		constant_types.extend(pallet.constants.iter().map(|constant| constant.ty.id));
+
 		// capture the call information in this pallet:
 		let calls = pallet
 			.calls
 			.map(|call_md| {
-				// Get the type representing the variant of available calls:
 				let calls_type_id = call_md.ty;
-				let calls_type =
-					registry.resolve(calls_type_id.id).ok_or_else(|| MetadataError::TypeNotFound(calls_type_id.id))?;
-
-				// Expect that type to be a variant:
-				let calls_type_def = &calls_type.type_def;
-				let calls_variant = match calls_type_def {
-					scale_info::TypeDef::Variant(variant) => variant,
-					_ => {
-						return Err(MetadataError::ExpectedVariantType { got: format!("{:?}", calls_type_def) });
-					}
-				};
-
-				// Store the mapping from u8 index to variant slice index for quicker decode lookup:
-				let call_variant_indexes =
-					calls_variant.variants.iter().enumerate().map(|(idx, v)| (v.index, idx)).collect();
-
-				Ok(MetadataCalls { calls_type_id, call_variant_indexes })
+				variant_indexes(&registry, calls_type_id)
+					.map(|call_variant_indexes| MetadataCalls { calls_type_id, call_variant_indexes })
+			})
+			.transpose()?;
+		pallet_calls_by_index.insert(pallet.index, MetadataPalletCalls { name: pallet.name.clone(), calls });
+
+		// capture the event information in this pallet:
+		let events = pallet
+			.event
+			.map(|event_md| {
+				let events_type_id = event_md.ty;
+				variant_indexes(&registry, events_type_id)
+					.map(|event_variant_indexes| MetadataEvents { events_type_id, event_variant_indexes })
 			})
 			.transpose()?;
-		pallet_calls_by_index.insert(pallet.index, MetadataPalletCalls { name: pallet.name, calls });
+		pallet_events_by_index.insert(pallet.index, MetadataPalletEvents { name: pallet.name.clone(), events });
+
+		// capture the error information in this pallet:
+		let errors = pallet
+			.error
+			.map(|error_md| {
+				let errors_type_id = error_md.ty;
+				variant_indexes(&registry, errors_type_id)
+					.map(|error_variant_indexes| MetadataErrors { errors_type_id, error_variant_indexes })
+			})
+			.transpose()?;
+		pallet_errors_by_index.insert(pallet.index, MetadataPalletErrors { name: pallet.name.clone(), errors });
 
 		// Capture the storage information in this pallet:
 		if let Some(storage_metadata) = pallet.storage {
 			pallet_storage.push(MetadataPalletStorage {
+				pallet_name: pallet.name,
 				prefix: storage_metadata.prefix,
 				storage_entries: storage_metadata.entries.into(),
 			});
 		}
 	}
 
-	Ok(Metadata { pallet_calls_by_index, pallet_storage: pallet_storage.into(), extrinsic, types: registry })
+	let bytes_ty = bytes_ty(&mut registry);
+	Ok(Metadata {
+		pallet_calls_by_index,
+		pallet_events_by_index,
+		pallet_errors_by_index,
+		pallet_storage: pallet_storage.into(),
+		call_ty,
+		outer_event_ty,
+		constant_types,
+		bytes_ty,
+		extrinsic,
+		custom: Default::default(),
+		types: registry,
+	})
+}
+
+/// Find the type IDs of `Vec<u8>` and of `u8` in the registry, adding them to the end of it if they aren't there
+/// already (which they are in any real runtime), so that bytes handed back undecoded have a type to point at.
+fn bytes_ty(registry: &mut PortableRegistry) -> (TypeId, TypeId) {
+	let mut find_or_add = |type_def: TypeDef<PortableForm>| {
+		if let Some(ty) = registry.types.iter().find(|ty| ty.ty.type_def == type_def && ty.ty.path.is_empty()) {
+			return ty.id;
+		}
+		let id = registry.types.len() as TypeId;
+		let ty = Type { path: Path { segments: vec![] }, type_params: vec![], type_def, docs: vec![] };
+		registry.types.push(PortableType { id, ty });
+		id
+	};
+	let u8_ty = find_or_add(TypeDef::Primitive(TypeDefPrimitive::U8));
+	let vec_ty = find_or_add(TypeDef::Sequence(TypeDefSequence::new(u8_ty.into())));
+	(vec_ty, u8_ty)
+}
+
+/// V14 metadata doesn't tell us the `Address` or `Call` types directly, so find them from the type parameters of the
+/// extrinsic type.
+fn extrinsic_type_param(registry: &PortableRegistry, extrinsic_ty: ScaleInfoTypeId, name: &str) -> Option<TypeId> {
+	registry
+		.resolve(extrinsic_ty.id)
+		.and_then(|ty| ty.type_params.iter().find(|param| param.name == name))
+		.and_then(|param| param.ty)
+		.map(|ty| ty.id)
+}
+
+/// Work out the shape of extrinsic signatures from the `Address` type used in them. Chains whose addresses
+/// are 20 byte `AccountId20`s (or `H160`s) sign extrinsics with Ethereum style signatures, and chains whose
+/// addresses are a bare 32 byte key use those directly; anything else is assumed to be the standard substrate
+/// format.
+fn signer_format(registry: &PortableRegistry, address_ty: Option<TypeId>) -> SignerFormat {
+	let address_ty = address_ty.and_then(|id| registry.resolve(id));
+	match address_ty.and_then(|ty| ty.path.ident()) {
+		Some(ident) if ident == "AccountId20" || ident == "H160" => SignerFormat::Ethereum,
+		_ if address_ty.is_some_and(|ty| is_32_byte_key(registry, ty)) => SignerFormat::AccountId32,
+		_ => SignerFormat::Substrate,
+	}
+}
+
+/// The most newtype wrappers we'll look through to find the key in an address type. Real keys are wrapped once or
+/// twice, and this stops a type which (wrongly) contains itself from recursing forever.
+const MAX_KEY_WRAPPERS: usize = 8;
+
+/// Is this type a `[u8; 32]`, or some newtype wrapper (like `AccountId32` or `sr25519::Public`) around one?
+fn is_32_byte_key(registry: &PortableRegistry, ty: &scale_info::Type<PortableForm>) -> bool {
+	let mut ty = ty;
+	for _ in 0..=MAX_KEY_WRAPPERS {
+		match &ty.type_def {
+			TypeDef::Array(array) => {
+				return array.len == 32
+					&& matches!(
+						registry.resolve(array.type_param.id).map(|ty| &ty.type_def),
+						Some(TypeDef::Primitive(TypeDefPrimitive::U8))
+					)
+			}
+			TypeDef::Composite(composite) => match &composite.fields[..] {
+				[field] => match registry.resolve(field.ty.id) {
+					Some(inner) => ty = inner,
+					None => return false,
+				},
+				_ => return false,
+			},
+			_ => return false,
+		}
+	}
+	false
+}
+
+/// Given the type ID of some pallet calls or events, return a mapping from the `u8` variant
+/// index to the index of the variant in the type's variant slice, for quicker decode lookup.
+fn variant_indexes(registry: &PortableRegistry, type_id: ScaleInfoTypeId) -> Result<U8Map<usize>, MetadataError> {
+	// Get the type representing the variant of available calls/events:
+	let ty = registry.resolve(type_id.id).ok_or(MetadataError::TypeNotFound(type_id.id))?;
+
+	// Expect that type to be a variant:
+	let variant = match &ty.type_def {
+		TypeDef::Variant(variant) => variant,
+		type_def => return Err(MetadataError::ExpectedVariantType { got: format!("{:?}", type_def) }),
+	};
+
+	Ok(variant.variants.iter().enumerate().map(|(idx, v)| (v.index, idx)).collect())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use scale_info::{Field, TypeDefArray, TypeDefComposite};
+
+	/// A registry of the given types, each of which has an ID matching its position.
+	fn registry(type_defs: Vec<TypeDef<PortableForm>>) -> PortableRegistry {
+		let types = type_defs
+			.into_iter()
+			.enumerate()
+			.map(|(id, type_def)| PortableType {
+				id: id as TypeId,
+				ty: Type { path: Path { segments: vec![] }, type_params: vec![], type_def, docs: vec![] },
+			})
+			.collect();
+		PortableRegistry { types }
+	}
+
+	/// A composite type with a single field of the given type.
+	fn newtype(inner: TypeId) -> TypeDef<PortableForm> {
+		let field = Field { name: None, ty: inner.into(), type_name: None, docs: vec![] };
+		TypeDef::Composite(TypeDefComposite { fields: vec![field] })
+	}
+
+	#[test]
+	fn finds_32_byte_keys_through_newtypes() {
+		let types = registry(vec![
+			TypeDef::Primitive(TypeDefPrimitive::U8),
+			TypeDef::Array(TypeDefArray { len: 32, type_param: 0.into() }),
+			newtype(1),
+			newtype(2),
+			TypeDef::Array(TypeDefArray { len: 20, type_param: 0.into() }),
+			newtype(4),
+		]);
+		let is_key = |id: u32| is_32_byte_key(&types, types.resolve(id).unwrap());
+		assert!(is_key(1) && is_key(2) && is_key(3));
+		assert!(!is_key(0) && !is_key(4) && !is_key(5));
+	}
+
+	#[test]
+	fn gives_up_on_types_that_contain_themselves() {
+		// A newtype around itself, and a pair of newtypes around each other:
+		let types = registry(vec![newtype(0), newtype(2), newtype(1)]);
+		assert!(!is_32_byte_key(&types, types.resolve(0).unwrap()));
+		assert!(!is_32_byte_key(&types, types.resolve(1).unwrap()));
+	}
 }
@@ -0,0 +1,59 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{version_14, Metadata, MetadataError};
+use frame_metadata::{v14, v15::RuntimeMetadataV15};
+
+/// Decode V15 metadata into our general Metadata struct. The pallet and signed extension details that
+/// we use are the same as in V14. Of the V15 additions, the outer event enum is used to look up events
+/// and the custom values are kept, and the rest (like the runtime APIs) are ignored.
+pub fn decode(meta: RuntimeMetadataV15) -> Result<Metadata, MetadataError> {
+	let pallets = meta
+		.pallets
+		.into_iter()
+		.map(|p| v14::PalletMetadata {
+			name: p.name,
+			storage: p.storage,
+			calls: p.calls,
+			event: p.event,
+			constants: p.constants,
+			error: p.error,
+			index: p.index,
+		})
+		.collect();
+	let signed_extensions = meta
+		.extrinsic
+		.signed_extensions
+		.into_iter()
+		.map(|e| v14::SignedExtensionMetadata {
+			identifier: e.identifier,
+			ty: e.ty,
+			additional_signed: e.additional_signed,
+		})
+		.collect();
+
+	let mut metadata = version_14::decode_parts(
+		meta.types,
+		pallets,
+		meta.extrinsic.version,
+		Some(meta.extrinsic.address_ty.id),
+		Some(meta.extrinsic.call_ty.id),
+		signed_extensions,
+		Some(meta.outer_enums.event_enum_ty.id),
+	)?;
+	metadata.custom = meta.custom.map;
+	Ok(metadata)
+}
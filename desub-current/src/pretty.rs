@@ -0,0 +1,177 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Render decoded [`Value`]s with ANSI colors, for printing to a terminal. Field names, primitive
+//! values and variant names are each given a different color, to make large values easier to read.
+
+use scale_value::{BitSequence, Composite, Primitive, Value, ValueDef};
+use std::fmt::Write;
+
+const FIELD_COLOR: &str = "\x1b[34m";
+const PRIMITIVE_COLOR: &str = "\x1b[32m";
+const VARIANT_COLOR: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// Render a value as a string containing ANSI color codes. Named composites are rendered like
+/// `{ name: value }`, unnamed ones like `(value, value)` and variants like `Name { .. }` or `Name(..)`.
+///
+/// # Example
+///
+/// ```rust
+/// use desub_current::{pretty, Value};
+///
+/// let value = Value::named_composite([("amount", Value::u128(100))]);
+/// assert_eq!(pretty::to_colored_string(&value), "{ \x1b[34mamount\x1b[0m: \x1b[32m100\x1b[0m }");
+/// ```
+pub fn to_colored_string<T>(value: &Value<T>) -> String {
+	let mut out = String::new();
+	write_value(&mut out, value);
+	out
+}
+
+fn write_value<T>(out: &mut String, value: &Value<T>) {
+	match &value.value {
+		ValueDef::Composite(composite) => write_composite(out, composite),
+		ValueDef::Variant(variant) => {
+			write!(out, "{VARIANT_COLOR}{}{RESET}", variant.name).expect("writing to a String can't fail");
+			if !variant.values.is_empty() {
+				// Separate `Name { .. }`, but not `Name(..)`:
+				if let Composite::Named(_) = variant.values {
+					out.push(' ');
+				}
+				write_composite(out, &variant.values);
+			}
+		}
+		ValueDef::BitSequence(bits) => write_primitive(out, format_bits(bits)),
+		ValueDef::Primitive(primitive) => write_primitive(out, format_primitive(primitive)),
+	}
+}
+
+fn write_composite<T>(out: &mut String, composite: &Composite<T>) {
+	match composite {
+		Composite::Named(fields) if fields.is_empty() => out.push_str("{}"),
+		Composite::Named(fields) => {
+			out.push_str("{ ");
+			for (idx, (name, value)) in fields.iter().enumerate() {
+				if idx > 0 {
+					out.push_str(", ");
+				}
+				write!(out, "{FIELD_COLOR}{name}{RESET}: ").expect("writing to a String can't fail");
+				write_value(out, value);
+			}
+			out.push_str(" }");
+		}
+		Composite::Unnamed(values) => {
+			out.push('(');
+			for (idx, value) in values.iter().enumerate() {
+				if idx > 0 {
+					out.push_str(", ");
+				}
+				write_value(out, value);
+			}
+			out.push(')');
+		}
+	}
+}
+
+fn write_primitive(out: &mut String, primitive: String) {
+	write!(out, "{PRIMITIVE_COLOR}{primitive}{RESET}").expect("writing to a String can't fail");
+}
+
+fn format_primitive(primitive: &Primitive) -> String {
+	match primitive {
+		Primitive::Bool(b) => b.to_string(),
+		Primitive::Char(c) => format!("{c:?}"),
+		Primitive::String(s) => format!("{s:?}"),
+		Primitive::U128(n) => n.to_string(),
+		Primitive::I128(n) => n.to_string(),
+		// These are stored as little endian bytes; show them as big endian hex, as one would write the number.
+		Primitive::U256(bytes) | Primitive::I256(bytes) => {
+			let mut bytes = *bytes;
+			bytes.reverse();
+			format!("0x{}", hex::encode(bytes))
+		}
+	}
+}
+
+fn format_bits(bits: &BitSequence) -> String {
+	let bits: String = bits.iter().map(|bit| if bit { '1' } else { '0' }).collect();
+	format!("<{bits}>")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn field(name: &str) -> String {
+		format!("{FIELD_COLOR}{name}{RESET}")
+	}
+
+	fn primitive(value: &str) -> String {
+		format!("{PRIMITIVE_COLOR}{value}{RESET}")
+	}
+
+	fn variant(name: &str) -> String {
+		format!("{VARIANT_COLOR}{name}{RESET}")
+	}
+
+	#[test]
+	fn colors_keys_primitives_and_variants() {
+		let value = Value::named_composite([
+			("dest", Value::unnamed_variant("Id", [Value::string("alice")])),
+			("value", Value::u128(1000)),
+			("keep_alive", Value::named_variant("Yes", [("really", Value::bool(true))])),
+		]);
+
+		let expected = format!(
+			"{{ {}: {}({}), {}: {}, {}: {} {{ {}: {} }} }}",
+			field("dest"),
+			variant("Id"),
+			primitive("\"alice\""),
+			field("value"),
+			primitive("1000"),
+			field("keep_alive"),
+			variant("Yes"),
+			field("really"),
+			primitive("true"),
+		);
+		assert_eq!(to_colored_string(&value), expected);
+	}
+
+	#[test]
+	fn renders_remaining_value_kinds() {
+		let mut u256 = [0; 32];
+		u256[0] = 1;
+		let value = Value::unnamed_composite([
+			Value::variant("None", Composite::Unnamed(vec![])),
+			Value::named_composite(Vec::<(String, _)>::new()),
+			Value::char('x'),
+			Value::i128(-5),
+			Value::bit_sequence(BitSequence::from_iter([true, false, true])),
+			Value::without_context(ValueDef::Primitive(Primitive::U256(u256))),
+		]);
+
+		let expected = format!(
+			"({}, {{}}, {}, {}, {}, {})",
+			variant("None"),
+			primitive("'x'"),
+			primitive("-5"),
+			primitive("<101>"),
+			primitive(&format!("0x{}01", "00".repeat(31))),
+		);
+		assert_eq!(to_colored_string(&value), expected);
+	}
+}
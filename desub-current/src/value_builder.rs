@@ -0,0 +1,168 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Concise constructors for [`Value`]s, to complement those that [`scale_value`] provides.
+
+use scale_value::{Primitive, Value, ValueDef};
+
+/// Constructors for [`Value`]s that [`scale_value`] doesn't provide itself. Along with the existing
+/// [`Value::named_composite`], [`Value::unnamed_composite`], [`Value::variant`], [`Value::named_variant`],
+/// [`Value::unnamed_variant`], [`Value::bit_sequence`], [`Value::string`], [`Value::bool`], [`Value::char`],
+/// [`Value::u128`] and [`Value::i128`], this makes it easy to build the values expected from decoding, or to
+/// be encoded.
+///
+/// # Example
+///
+/// ```rust
+/// use desub_current::{Value, ValueBuilder, TypeId};
+///
+/// let transfer = Value::named_variant("transfer", [("dest", Value::u8(1)), ("value", Value::u64(1000))]);
+/// assert_eq!(transfer, Value::named_variant("transfer", [("dest", Value::u128(1)), ("value", Value::u128(1000))]));
+///
+/// // Decoded values carry the ID of their type, so give built values one to compare against them:
+/// let typed: Value<TypeId> = Value::u32(1).in_context(5);
+/// assert_eq!(typed.context, 5);
+/// ```
+pub trait ValueBuilder: Sized {
+	/// Create a new unsigned integer from a `u8`.
+	fn u8(val: u8) -> Self;
+	/// Create a new unsigned integer from a `u16`.
+	fn u16(val: u16) -> Self;
+	/// Create a new unsigned integer from a `u32`.
+	fn u32(val: u32) -> Self;
+	/// Create a new unsigned integer from a `u64`.
+	fn u64(val: u64) -> Self;
+	/// Create a new signed integer from an `i8`.
+	fn i8(val: i8) -> Self;
+	/// Create a new signed integer from an `i16`.
+	fn i16(val: i16) -> Self;
+	/// Create a new signed integer from an `i32`.
+	fn i32(val: i32) -> Self;
+	/// Create a new signed integer from an `i64`.
+	fn i64(val: i64) -> Self;
+	/// Create a new unsigned 256 bit integer from its little endian bytes.
+	fn u256(val: [u8; 32]) -> Self;
+	/// Create a new signed 256 bit integer from its little endian bytes.
+	fn i256(val: [u8; 32]) -> Self;
+	/// Give this value, and every value nested in it, the same context.
+	fn in_context<T: Clone>(self, context: T) -> Value<T>;
+}
+
+impl ValueBuilder for Value<()> {
+	fn u8(val: u8) -> Self {
+		Value::u128(val.into())
+	}
+	fn u16(val: u16) -> Self {
+		Value::u128(val.into())
+	}
+	fn u32(val: u32) -> Self {
+		Value::u128(val.into())
+	}
+	fn u64(val: u64) -> Self {
+		Value::u128(val.into())
+	}
+	fn i8(val: i8) -> Self {
+		Value::i128(val.into())
+	}
+	fn i16(val: i16) -> Self {
+		Value::i128(val.into())
+	}
+	fn i32(val: i32) -> Self {
+		Value::i128(val.into())
+	}
+	fn i64(val: i64) -> Self {
+		Value::i128(val.into())
+	}
+	fn u256(val: [u8; 32]) -> Self {
+		Value::without_context(ValueDef::Primitive(Primitive::U256(val)))
+	}
+	fn i256(val: [u8; 32]) -> Self {
+		Value::without_context(ValueDef::Primitive(Primitive::I256(val)))
+	}
+	fn in_context<T: Clone>(self, context: T) -> Value<T> {
+		self.map_context(move |_| context.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::TypeId;
+	use parity_scale_codec::Encode;
+	use scale_decode::DecodeAsType;
+	use scale_info::{meta_type, PortableRegistry, Registry, TypeInfo};
+	use scale_value::BitSequence;
+
+	#[test]
+	fn builds_values_matching_decoded_ones() {
+		#[derive(Encode, TypeInfo)]
+		enum Foo {
+			Bar { a: u8, b: i16, c: (u32, i64) },
+			Baz(bool, Vec<u64>),
+		}
+
+		let mut registry = Registry::new();
+		let foo_id = registry.register_type(&meta_type::<Foo>()).id;
+		let types: PortableRegistry = registry.into();
+		let decode = |foo: Foo| {
+			let bytes = foo.encode();
+			Value::<TypeId>::decode_as_type(&mut &*bytes, foo_id, &types).unwrap().remove_context()
+		};
+
+		assert_eq!(
+			decode(Foo::Bar { a: 1, b: -2, c: (3, -4) }),
+			Value::named_variant(
+				"Bar",
+				[
+					("a", Value::u8(1)),
+					("b", Value::i16(-2)),
+					("c", Value::unnamed_composite([Value::u32(3), Value::i64(-4)]))
+				]
+			)
+		);
+		assert_eq!(
+			decode(Foo::Baz(true, vec![5, 6])),
+			Value::unnamed_variant(
+				"Baz",
+				[Value::bool(true), Value::unnamed_composite([Value::u64(5), Value::u64(6)])]
+			)
+		);
+	}
+
+	#[test]
+	fn builds_primitives() {
+		assert_eq!(Value::u16(u16::MAX), Value::u128(u16::MAX as u128));
+		assert_eq!(Value::i8(i8::MIN), Value::i128(i8::MIN as i128));
+		assert_eq!(Value::i32(-1), Value::i128(-1));
+		assert_eq!(Value::u256([1; 32]).value, ValueDef::Primitive(Primitive::U256([1; 32])));
+		assert_eq!(Value::i256([2; 32]).value, ValueDef::Primitive(Primitive::I256([2; 32])));
+	}
+
+	#[test]
+	fn gives_nested_values_a_context() {
+		let value = Value::named_composite([
+			("a", Value::bit_sequence(BitSequence::from_iter([true, false]))),
+			("b", Value::unnamed_composite([Value::u8(1)])),
+		])
+		.in_context(7u32);
+
+		assert_eq!(value.context, 7);
+		let ValueDef::Composite(composite) = &value.value else { panic!("expected a composite") };
+		for field in composite.values() {
+			assert_eq!(field.context, 7);
+		}
+	}
+}
@@ -0,0 +1,773 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Extra functionality for decoded [`Value`]s.
+
+use crate::{Metadata, TypeId};
+use scale_info::{PortableRegistry, TypeDef, TypeDefPrimitive};
+use scale_value::{At, Composite, Primitive, Value, ValueDef};
+use std::fmt::{self, Display, Formatter, Write};
+use std::hash::{Hash, Hasher};
+
+/// Extra functionality for decoded [`Value`]s: introspecting their size, for instance to reject
+/// absurdly large values before serializing them or passing them on, walking them to apply
+/// custom post-processing, and navigating into them.
+pub trait ValueExt {
+	/// The context attached to each value in the tree. Some functionality needs this to be the [`TypeId`] that the
+	/// value was decoded from.
+	type Context;
+
+	/// The number of values in the tree, including this one.
+	fn node_count(&self) -> usize;
+
+	/// A rough estimate of the number of bytes needed to hold this value in memory, including
+	/// any heap allocated strings, field names and bit sequences.
+	fn byte_size_estimate(&self) -> usize;
+
+	/// Visit every value in the tree depth first, handing each one to `f` to be modified in place.
+	/// A value is handed to `f` before its children, so any children that `f` adds are visited too.
+	fn transform<F: FnMut(&mut Self)>(&mut self, f: &mut F);
+
+	/// A copy of the value in which every unnamed composite holding exactly one value (as newtype wrappers like
+	/// `struct Balance(u128)` decode to) is replaced by the value that it holds, so that `((1000))` becomes `1000`.
+	/// The context of the innermost value is kept. Beware that this also collapses sequences and arrays which happen
+	/// to hold one item.
+	fn flatten_newtypes(&self) -> Self
+	where
+		Self: Clone;
+
+	/// Navigate into the value by a dot separated path like `"dest.Id.0"`, returning `None` if there's nothing there.
+	/// Numeric segments index into composite (and variant) fields by position, and other segments look up fields by
+	/// name. A segment naming the variant that a value holds leaves us at that value, so that the variant can be
+	/// checked on the way through; above, `dest` must be the `Id` variant for the path to resolve.
+	fn get(&self, path: &str) -> Option<&Self>;
+
+	/// Replace every value whose path matches `predicate` with a copy of `replacement`, for instance to keep addresses
+	/// and signatures out of logs. Paths are given in the form that [`ValueExt::get`] accepts, without variant names:
+	/// field names for named fields and positions otherwise, separated by dots (so `"dest.0"` in a transfer's arguments),
+	/// with `""` being the value itself. The contents of a replaced value aren't visited.
+	fn redact<P: Fn(&str) -> bool>(&mut self, predicate: P, replacement: Self)
+	where
+		Self: Clone;
+
+	/// The bytes held in this value, if the type it was decoded from is a sequence of `u8`s (ie a `Vec<u8>` or
+	/// `[u8; N]`), or a newtype wrapping one (like `AccountId32`). The type is looked up in `metadata`, so a sequence
+	/// of other integers is never mistaken for bytes, however small its values happen to be.
+	fn as_bytes(&self, metadata: &Metadata) -> Option<Vec<u8>>
+	where
+		Self::Context: Copy + Into<TypeId>;
+
+	/// Like [`ValueExt::as_bytes`], but also check that there are exactly `len` bytes. Decoding accepts a
+	/// `Vec<u8>` of any length, so this is handy to make sure that one holds, say, a 32 byte key.
+	fn expect_byte_len(&self, metadata: &Metadata, len: usize) -> Result<Vec<u8>, ByteLenError>
+	where
+		Self::Context: Copy + Into<TypeId>;
+
+	/// Wrap the value so that it's displayed (via `Debug` or `Display`) in the same format as a [`Value`] normally is,
+	/// except that any sequence of more than `max_bytes` bytes is cut short to `0x<first max_bytes bytes>...(N bytes)`.
+	/// This keeps things like a runtime upgrade's wasm blob from flooding the logs. Byte sequences are told apart by
+	/// their types, as in [`ValueExt::as_bytes`].
+	fn debug_truncated<'a>(&'a self, metadata: &'a Metadata, max_bytes: usize) -> Truncated<'a, Self>
+	where
+		Self: Sized,
+		Self::Context: Copy + Into<TypeId>;
+
+	/// Wrap the value so that it can be hashed and compared, for instance to deduplicate values in a `HashSet`.
+	/// The context of each value is included. If `canonical` is true, the fields of named composites are hashed
+	/// and compared in order of their names, so that structs whose fields are in different orders count as equal.
+	/// Values wrapped with different `canonical` flags shouldn't be mixed in the same set.
+	fn hashable(&self, canonical: bool) -> Hashable<'_, Self>
+	where
+		Self: Sized;
+}
+
+/// A value which can be hashed and compared; see [`ValueExt::hashable`].
+pub struct Hashable<'a, V> {
+	value: &'a V,
+	canonical: bool,
+}
+
+/// A value which is displayed with long byte sequences cut short; see [`ValueExt::debug_truncated`].
+pub struct Truncated<'a, V> {
+	value: &'a V,
+	metadata: &'a Metadata,
+	max_bytes: usize,
+}
+
+/// The error returned from [`ValueExt::expect_byte_len`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ByteLenError {
+	#[error("Value is not a sequence of bytes")]
+	NotBytes,
+	#[error("Expected {expected} bytes, but got {got}")]
+	WrongLength { expected: usize, got: usize },
+}
+
+impl<T> ValueExt for Value<T> {
+	type Context = T;
+
+	fn node_count(&self) -> usize {
+		let mut count = 0;
+		visit(self, |_| count += 1);
+		count
+	}
+
+	fn byte_size_estimate(&self) -> usize {
+		let mut size = 0;
+		visit(self, |value| {
+			size += std::mem::size_of::<Value<T>>();
+			size += match &value.value {
+				ValueDef::Composite(Composite::Named(fields)) => fields.iter().map(|(name, _)| name.len()).sum(),
+				ValueDef::Composite(Composite::Unnamed(_)) => 0,
+				ValueDef::Variant(variant) => {
+					let names = match &variant.values {
+						Composite::Named(fields) => fields.iter().map(|(name, _)| name.len()).sum(),
+						Composite::Unnamed(_) => 0,
+					};
+					variant.name.len() + names
+				}
+				ValueDef::BitSequence(bits) => (bits.len() + 7) / 8,
+				ValueDef::Primitive(Primitive::String(s)) => s.len(),
+				ValueDef::Primitive(_) => 0,
+			};
+		});
+		size
+	}
+
+	fn transform<F: FnMut(&mut Self)>(&mut self, f: &mut F) {
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			f(value);
+			let children = match &mut value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &mut variant.values,
+				ValueDef::BitSequence(_) | ValueDef::Primitive(_) => continue,
+			};
+			// Push in reverse, so that children are visited in order.
+			match children {
+				Composite::Named(fields) => stack.extend(fields.iter_mut().rev().map(|(_, val)| val)),
+				Composite::Unnamed(vals) => stack.extend(vals.iter_mut().rev()),
+			}
+		}
+	}
+
+	fn flatten_newtypes(&self) -> Self
+	where
+		Self: Clone,
+	{
+		let mut value = self.clone();
+		value.transform(&mut |v| {
+			while let ValueDef::Composite(Composite::Unnamed(vals)) = &mut v.value {
+				if vals.len() != 1 {
+					break;
+				}
+				*v = vals.pop().expect("one value");
+			}
+		});
+		value
+	}
+
+	fn get(&self, path: &str) -> Option<&Self> {
+		path.split('.').try_fold(self, |value, segment| match (&value.value, segment.parse::<usize>()) {
+			(_, Ok(index)) => value.at(index),
+			(ValueDef::Variant(variant), _) if variant.name == segment => Some(value),
+			_ => value.at(segment),
+		})
+	}
+
+	fn redact<P: Fn(&str) -> bool>(&mut self, predicate: P, replacement: Self)
+	where
+		Self: Clone,
+	{
+		let mut stack = vec![(self, String::new())];
+		while let Some((value, path)) = stack.pop() {
+			if predicate(&path) {
+				*value = replacement.clone();
+				continue;
+			}
+			let children = match &mut value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &mut variant.values,
+				ValueDef::BitSequence(_) | ValueDef::Primitive(_) => continue,
+			};
+			let child_path = |segment: &dyn Display| match path.is_empty() {
+				true => segment.to_string(),
+				false => format!("{path}.{segment}"),
+			};
+			match children {
+				Composite::Named(fields) => {
+					stack.extend(fields.iter_mut().map(|(name, val)| (val, child_path(name))));
+				}
+				Composite::Unnamed(vals) => {
+					stack.extend(vals.iter_mut().enumerate().map(|(idx, val)| (val, child_path(&idx))));
+				}
+			}
+		}
+	}
+
+	fn as_bytes(&self, metadata: &Metadata) -> Option<Vec<u8>>
+	where
+		T: Copy + Into<TypeId>,
+	{
+		bytes_of(self, metadata.types())
+	}
+
+	fn expect_byte_len(&self, metadata: &Metadata, len: usize) -> Result<Vec<u8>, ByteLenError>
+	where
+		T: Copy + Into<TypeId>,
+	{
+		let bytes = self.as_bytes(metadata).ok_or(ByteLenError::NotBytes)?;
+		if bytes.len() != len {
+			return Err(ByteLenError::WrongLength { expected: len, got: bytes.len() });
+		}
+		Ok(bytes)
+	}
+
+	fn debug_truncated<'a>(&'a self, metadata: &'a Metadata, max_bytes: usize) -> Truncated<'a, Self>
+	where
+		T: Copy + Into<TypeId>,
+	{
+		Truncated { value: self, metadata, max_bytes }
+	}
+
+	fn hashable(&self, canonical: bool) -> Hashable<'_, Self> {
+		Hashable { value: self, canonical }
+	}
+}
+
+impl<T: Hash> Hash for Hashable<'_, Value<T>> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		hash_value(self.value, self.canonical, state)
+	}
+}
+
+impl<T: PartialEq> PartialEq for Hashable<'_, Value<T>> {
+	fn eq(&self, other: &Self) -> bool {
+		match self.canonical || other.canonical {
+			true => values_eq_canonical(self.value, other.value),
+			false => self.value == other.value,
+		}
+	}
+}
+
+impl<T: Eq> Eq for Hashable<'_, Value<T>> {}
+
+impl<T: fmt::Debug> fmt::Debug for Hashable<'_, Value<T>> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.value, f)
+	}
+}
+
+fn hash_value<T: Hash, H: Hasher>(value: &Value<T>, canonical: bool, state: &mut H) {
+	match &value.value {
+		ValueDef::Composite(composite) => {
+			state.write_u8(0);
+			hash_composite(composite, canonical, state);
+		}
+		ValueDef::Variant(variant) => {
+			state.write_u8(1);
+			variant.name.hash(state);
+			hash_composite(&variant.values, canonical, state);
+		}
+		ValueDef::BitSequence(bits) => {
+			state.write_u8(2);
+			state.write_usize(bits.len());
+			bits.iter().for_each(|bit| bit.hash(state));
+		}
+		ValueDef::Primitive(primitive) => {
+			state.write_u8(3);
+			match primitive {
+				Primitive::Bool(b) => (0u8, b).hash(state),
+				Primitive::Char(c) => (1u8, c).hash(state),
+				Primitive::String(s) => (2u8, s).hash(state),
+				Primitive::U128(n) => (3u8, n).hash(state),
+				Primitive::I128(n) => (4u8, n).hash(state),
+				Primitive::U256(n) => (5u8, n).hash(state),
+				Primitive::I256(n) => (6u8, n).hash(state),
+			}
+		}
+	}
+	value.context.hash(state);
+}
+
+fn hash_composite<T: Hash, H: Hasher>(composite: &Composite<T>, canonical: bool, state: &mut H) {
+	match composite {
+		Composite::Named(fields) => {
+			state.write_u8(0);
+			state.write_usize(fields.len());
+			let fields = match canonical {
+				true => sorted_fields(fields),
+				false => fields.iter().collect(),
+			};
+			for (name, value) in fields {
+				name.hash(state);
+				hash_value(value, canonical, state);
+			}
+		}
+		Composite::Unnamed(values) => {
+			state.write_u8(1);
+			state.write_usize(values.len());
+			values.iter().for_each(|value| hash_value(value, canonical, state));
+		}
+	}
+}
+
+fn sorted_fields<T>(fields: &[(String, Value<T>)]) -> Vec<&(String, Value<T>)> {
+	let mut fields: Vec<_> = fields.iter().collect();
+	fields.sort_by(|a, b| a.0.cmp(&b.0));
+	fields
+}
+
+/// Like `==`, but with the fields of named composites compared in order of their names.
+fn values_eq_canonical<T: PartialEq>(a: &Value<T>, b: &Value<T>) -> bool {
+	let values_eq = match (&a.value, &b.value) {
+		(ValueDef::Composite(a), ValueDef::Composite(b)) => composites_eq_canonical(a, b),
+		(ValueDef::Variant(a), ValueDef::Variant(b)) => {
+			a.name == b.name && composites_eq_canonical(&a.values, &b.values)
+		}
+		(a, b) => a == b,
+	};
+	values_eq && a.context == b.context
+}
+
+fn composites_eq_canonical<T: PartialEq>(a: &Composite<T>, b: &Composite<T>) -> bool {
+	match (a, b) {
+		(Composite::Named(a), Composite::Named(b)) => {
+			a.len() == b.len()
+				&& sorted_fields(a)
+					.into_iter()
+					.zip(sorted_fields(b))
+					.all(|(a, b)| a.0 == b.0 && values_eq_canonical(&a.1, &b.1))
+		}
+		(Composite::Unnamed(a), Composite::Unnamed(b)) => {
+			a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_eq_canonical(a, b))
+		}
+		_ => false,
+	}
+}
+
+impl<T: Copy + Into<TypeId>> Display for Truncated<'_, Value<T>> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		fmt_truncated(self.value, self.metadata.types(), self.max_bytes, f)
+	}
+}
+
+impl<T: Copy + Into<TypeId>> fmt::Debug for Truncated<'_, Value<T>> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		fmt_truncated(self.value, self.metadata.types(), self.max_bytes, f)
+	}
+}
+
+/// The bytes held in a value whose type is a sequence or array of `u8`s, or a newtype wrapping one; see
+/// [`ValueExt::as_bytes`].
+pub(crate) fn bytes_of<T: Copy + Into<TypeId>>(mut value: &Value<T>, types: &PortableRegistry) -> Option<Vec<u8>> {
+	loop {
+		if let Some(bytes) = u8_sequence(value, types) {
+			return Some(bytes);
+		}
+		match (&value.value, types.resolve(value.context.into()).map(|ty| &ty.type_def)) {
+			(ValueDef::Composite(Composite::Unnamed(vals)), Some(TypeDef::Composite(c)))
+				if vals.len() == 1 && c.fields.len() == 1 =>
+			{
+				value = &vals[0]
+			}
+			_ => return None,
+		}
+	}
+}
+
+/// The bytes held in a value whose type is a sequence or array of `u8`s. Newtypes aren't seen through.
+fn u8_sequence<T: Copy + Into<TypeId>>(value: &Value<T>, types: &PortableRegistry) -> Option<Vec<u8>> {
+	let ValueDef::Composite(Composite::Unnamed(vals)) = &value.value else { return None };
+	let item_ty = match &types.resolve(value.context.into())?.type_def {
+		TypeDef::Sequence(seq) => seq.type_param.id,
+		TypeDef::Array(arr) => arr.type_param.id,
+		_ => return None,
+	};
+	if !matches!(types.resolve(item_ty).map(|ty| &ty.type_def), Some(TypeDef::Primitive(TypeDefPrimitive::U8))) {
+		return None;
+	}
+	vals.iter().map(|val| val.as_u128().and_then(|n| u8::try_from(n).ok())).collect()
+}
+
+/// Format a value like its `Display` impl does, but with byte sequences longer than `max_bytes` cut short.
+fn fmt_truncated<T: Copy + Into<TypeId>>(
+	value: &Value<T>,
+	types: &PortableRegistry,
+	max_bytes: usize,
+	f: &mut Formatter<'_>,
+) -> fmt::Result {
+	match &value.value {
+		ValueDef::Composite(Composite::Unnamed(vals)) if vals.len() > max_bytes => match u8_sequence(value, types) {
+			Some(bytes) => write!(f, "0x{}...({} bytes)", hex::encode(&bytes[..max_bytes]), bytes.len()),
+			None => fmt_unnamed_truncated(vals, types, max_bytes, f),
+		},
+		ValueDef::Composite(composite) => fmt_composite_truncated(composite, types, max_bytes, f),
+		ValueDef::Variant(variant) => {
+			fmt_name(&variant.name, f)?;
+			f.write_char(' ')?;
+			fmt_composite_truncated(&variant.values, types, max_bytes, f)
+		}
+		ValueDef::BitSequence(_) | ValueDef::Primitive(_) => value.fmt(f),
+	}
+}
+
+fn fmt_composite_truncated<T: Copy + Into<TypeId>>(
+	composite: &Composite<T>,
+	types: &PortableRegistry,
+	max_bytes: usize,
+	f: &mut Formatter<'_>,
+) -> fmt::Result {
+	match composite {
+		Composite::Named(fields) => {
+			f.write_str("{ ")?;
+			for (idx, (name, val)) in fields.iter().enumerate() {
+				if idx != 0 {
+					f.write_str(", ")?;
+				}
+				fmt_name(name, f)?;
+				f.write_str(": ")?;
+				fmt_truncated(val, types, max_bytes, f)?;
+			}
+			f.write_str(" }")
+		}
+		Composite::Unnamed(vals) => fmt_unnamed_truncated(vals, types, max_bytes, f),
+	}
+}
+
+fn fmt_unnamed_truncated<T: Copy + Into<TypeId>>(
+	vals: &[Value<T>],
+	types: &PortableRegistry,
+	max_bytes: usize,
+	f: &mut Formatter<'_>,
+) -> fmt::Result {
+	f.write_char('(')?;
+	for (idx, val) in vals.iter().enumerate() {
+		if idx != 0 {
+			f.write_str(", ")?;
+		}
+		fmt_truncated(val, types, max_bytes, f)?;
+	}
+	f.write_char(')')
+}
+
+/// Field and variant names are written as they are if they're valid identifiers, and quoted otherwise.
+fn fmt_name(name: &str, f: &mut Formatter<'_>) -> fmt::Result {
+	let mut chars = name.chars();
+	let is_ident =
+		chars.next().is_some_and(|c| c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_');
+	if is_ident {
+		f.write_str(name)
+	} else {
+		write!(f, "{name:?}")
+	}
+}
+
+/// Call `f` on every value in the tree. Like [`ValueExt::transform`], this uses an explicit stack rather than recursing,
+/// so that very deeply nested values can't overflow the call stack.
+fn visit<'a, T>(value: &'a Value<T>, mut f: impl FnMut(&'a Value<T>)) {
+	let mut stack = vec![value];
+	while let Some(value) = stack.pop() {
+		f(value);
+		match &value.value {
+			ValueDef::Composite(composite) => stack.extend(composite.values()),
+			ValueDef::Variant(variant) => stack.extend(variant.values.values()),
+			ValueDef::BitSequence(_) | ValueDef::Primitive(_) => {}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use parity_scale_codec::Encode;
+	use scale_info::{form::PortableForm, Field, Path, Type, TypeDefArray, TypeDefComposite, TypeDefSequence};
+	use scale_value::BitSequence;
+
+	fn metadata() -> Metadata {
+		Metadata::from_bytes(include_bytes!("../tests/data/v14_metadata_polkadot.scale")).expect("valid metadata")
+	}
+
+	fn register(metadata: &mut Metadata, type_def: TypeDef<PortableForm>) -> TypeId {
+		metadata.register_synthetic_type(Type {
+			path: Path { segments: vec![] },
+			type_params: vec![],
+			type_def,
+			docs: vec![],
+		})
+	}
+
+	fn decode<E: Encode>(metadata: &Metadata, ty: TypeId, val: &E) -> Value<TypeId> {
+		crate::decoder::decode_value_by_id(metadata, ty, &mut &*val.encode()).expect("can decode")
+	}
+
+	#[test]
+	fn counts_nodes_in_nested_value() {
+		let value = Value::named_composite(vec![
+			("a", Value::u128(1)),
+			("b", Value::unnamed_composite(vec![Value::bool(true), Value::string("hello")])),
+			("c", Value::unnamed_variant("Some", vec![Value::unnamed_composite(vec![])])),
+		]);
+		// outer + a + b + b.0 + b.1 + c + c.0
+		assert_eq!(value.node_count(), 7);
+		assert_eq!(Value::u128(1).node_count(), 1);
+	}
+
+	#[test]
+	fn estimates_byte_size_of_nested_value() {
+		let node = std::mem::size_of::<Value<()>>();
+
+		assert_eq!(Value::u128(1).byte_size_estimate(), node);
+		assert_eq!(Value::string("hello").byte_size_estimate(), node + 5);
+		assert_eq!(Value::bit_sequence(BitSequence::from_iter([true; 9])).byte_size_estimate(), node + 2);
+
+		let value = Value::named_composite(vec![
+			("ab", Value::string("xyz")),
+			("c", Value::named_variant("Foo", vec![("de", Value::bool(false))])),
+		]);
+		// 4 nodes, field names "ab", "c" and "de", variant name "Foo" and the string "xyz".
+		assert_eq!(value.byte_size_estimate(), 4 * node + 2 + 1 + 2 + 3 + 3);
+	}
+
+	#[test]
+	fn navigates_by_path() {
+		// The arguments of a `Balances.transfer` call, and a batch of them.
+		let transfer = Value::named_composite(vec![
+			("dest", Value::unnamed_variant("Id", vec![Value::from_bytes([1u8; 32])])),
+			("value", Value::u128(12345)),
+		]);
+		let batch = Value::unnamed_composite(vec![transfer.clone()]);
+
+		assert_eq!(transfer.get("value"), Some(&Value::u128(12345)));
+		assert_eq!(transfer.get("dest.Id"), transfer.get("dest"));
+		assert_eq!(transfer.get("dest.Id.0"), Some(&Value::from_bytes([1u8; 32])));
+		assert_eq!(transfer.get("dest.0.31"), Some(&Value::u128(1)));
+		assert_eq!(batch.get("0.value"), Some(&Value::u128(12345)));
+
+		assert_eq!(transfer.get("dest.Index"), None);
+		assert_eq!(transfer.get("amount"), None);
+		assert_eq!(batch.get("1.value"), None);
+		assert_eq!(batch.get("0.value.0"), None);
+	}
+
+	#[test]
+	fn handles_deeply_nested_values() {
+		let mut value = Value::u128(0);
+		for _ in 0..100_000 {
+			value = Value::unnamed_composite(vec![value]);
+		}
+		assert_eq!(value.node_count(), 100_001);
+		// Dropping a value this deep recurses, so leak it rather than overflow the test thread.
+		std::mem::forget(value);
+	}
+
+	#[test]
+	fn transforms_values_in_place() {
+		use crate::TypeId;
+		use parity_scale_codec::Encode;
+		use scale_decode::DecodeAsType;
+		use scale_info::{meta_type, PortableRegistry, Registry, TypeDef, TypeDefPrimitive, TypeInfo};
+
+		#[derive(Encode, TypeInfo)]
+		struct Foo {
+			a: u32,
+			b: (u32, u8),
+			c: Vec<u32>,
+		}
+
+		let mut registry = Registry::new();
+		let foo_id = registry.register_type(&meta_type::<Foo>()).id;
+		let u64_id = registry.register_type(&meta_type::<u64>()).id;
+		let types: PortableRegistry = registry.into();
+		let is_u32 = |id: TypeId| {
+			matches!(types.resolve(id).map(|ty| &ty.type_def), Some(TypeDef::Primitive(TypeDefPrimitive::U32)))
+		};
+
+		let bytes = Foo { a: 1, b: (2, 3), c: vec![4, 5] }.encode();
+		let mut value = Value::decode_as_type(&mut &*bytes, foo_id, &types).unwrap();
+
+		let mut seen = Vec::new();
+		value.transform(&mut |v: &mut Value<TypeId>| {
+			if let Some(n) = v.as_u128() {
+				seen.push(n);
+			}
+			if is_u32(v.context) {
+				v.context = u64_id;
+			}
+		});
+
+		// Values are visited depth first, in order.
+		assert_eq!(seen, vec![1, 2, 3, 4, 5]);
+		let mut u32_count = 0;
+		let mut u64_count = 0;
+		visit(&value, |v| {
+			u32_count += is_u32(v.context) as usize;
+			u64_count += (v.context == u64_id) as usize;
+		});
+		assert_eq!((u32_count, u64_count), (0, 4));
+	}
+
+	#[test]
+	fn redacts_values_by_path() {
+		// The arguments of a `Balances.transfer` call, and a batch of them.
+		let transfer = Value::named_composite(vec![
+			("dest", Value::unnamed_variant("Id", vec![Value::from_bytes([1u8; 32])])),
+			("value", Value::u128(12345)),
+		]);
+		let mut batch = Value::unnamed_composite(vec![transfer.clone(), transfer]);
+
+		batch.redact(|path| path.ends_with(".dest.0"), Value::string("<redacted>"));
+
+		let redacted = Value::named_composite(vec![
+			("dest", Value::unnamed_variant("Id", vec![Value::string("<redacted>")])),
+			("value", Value::u128(12345)),
+		]);
+		assert_eq!(batch, Value::unnamed_composite(vec![redacted.clone(), redacted]));
+
+		// The value itself has an empty path.
+		let mut value = Value::u128(1);
+		value.redact(str::is_empty, Value::bool(false));
+		assert_eq!(value, Value::bool(false));
+	}
+
+	#[test]
+	fn flattens_newtypes() {
+		// A `Balance(u128)` wrapped in another newtype.
+		let wrapped = Value::unnamed_composite(vec![Value::unnamed_composite(vec![Value::u128(1000)])]);
+		assert_eq!(wrapped.flatten_newtypes(), Value::u128(1000));
+
+		// Newtypes are flattened wherever they are, but other composites keep their shape.
+		let value = Value::named_composite(vec![
+			("amount", wrapped.clone()),
+			("pair", Value::unnamed_composite(vec![wrapped.clone(), Value::bool(true)])),
+			("dest", Value::unnamed_variant("Id", vec![Value::unnamed_composite(vec![Value::from_bytes([1u8; 4])])])),
+			("empty", Value::unnamed_composite(vec![])),
+		]);
+		assert_eq!(
+			value.flatten_newtypes(),
+			Value::named_composite(vec![
+				("amount", Value::u128(1000)),
+				("pair", Value::unnamed_composite(vec![Value::u128(1000), Value::bool(true)])),
+				("dest", Value::unnamed_variant("Id", vec![Value::from_bytes([1u8; 4])])),
+				("empty", Value::unnamed_composite(vec![])),
+			])
+		);
+		// The original is left alone.
+		assert_eq!(wrapped.node_count(), 3);
+	}
+
+	#[test]
+	fn extracts_bytes() {
+		let mut metadata = metadata();
+		let (vec_u8_id, u8_id) = metadata.bytes_ty();
+		let array_id = register(&mut metadata, TypeDef::Array(TypeDefArray::new(4, u8_id.into())));
+		let u32_id = register(&mut metadata, TypeDef::Primitive(TypeDefPrimitive::U32));
+		let vec_u32_id = register(&mut metadata, TypeDef::Sequence(TypeDefSequence::new(u32_id.into())));
+		let (account_id, _) = metadata
+			.iter_types()
+			.find(|(_, ty)| ty.path.segments == ["sp_core", "crypto", "AccountId32"])
+			.expect("AccountId32 is in the registry");
+
+		assert_eq!(decode(&metadata, vec_u8_id, &vec![1u8, 2, 3]).as_bytes(&metadata), Some(vec![1, 2, 3]));
+		assert_eq!(decode(&metadata, vec_u8_id, &Vec::<u8>::new()).as_bytes(&metadata), Some(vec![]));
+		assert_eq!(decode(&metadata, array_id, &[1u8, 2, 3, 4]).as_bytes(&metadata), Some(vec![1, 2, 3, 4]));
+		// Newtypes around bytes, like `AccountId32`, are seen through:
+		assert_eq!(decode(&metadata, account_id, &[7u8; 32]).as_bytes(&metadata), Some(vec![7; 32]));
+
+		// A sequence of other integers isn't bytes, however small they happen to be:
+		assert_eq!(decode(&metadata, vec_u32_id, &vec![1u32, 2, 3]).as_bytes(&metadata), None);
+		assert_eq!(decode(&metadata, u8_id, &1u8).as_bytes(&metadata), None);
+		// Nor is anything whose type isn't in the registry:
+		assert_eq!(Value::from_bytes([1u8, 2, 3]).map_context(|_| TypeId::MAX).as_bytes(&metadata), None);
+	}
+
+	#[test]
+	fn truncates_long_byte_sequences_when_displayed() {
+		let mut metadata = metadata();
+		let (vec_u8_id, u8_id) = metadata.bytes_ty();
+		let array_id = register(&mut metadata, TypeDef::Array(TypeDefArray::new(4, u8_id.into())));
+		let u32_id = register(&mut metadata, TypeDef::Primitive(TypeDefPrimitive::U32));
+		let vec_u32_id = register(&mut metadata, TypeDef::Sequence(TypeDefSequence::new(u32_id.into())));
+		let field =
+			|name: &str, ty: TypeId| Field { name: Some(name.into()), ty: ty.into(), type_name: None, docs: vec![] };
+		let fields = vec![field("code", vec_u8_id), field("hash", array_id), field("numbers", vec_u32_id)];
+		let struct_id = register(&mut metadata, TypeDef::Composite(TypeDefComposite { fields }));
+
+		let value = decode(&metadata, struct_id, &(vec![0xabu8; 10 * 1024], [1u8, 2, 3, 4], vec![1u32, 2, 3, 4, 5]));
+
+		// A sequence of `u32`s isn't cut short like bytes are, however small its values happen to be:
+		let expected = "{ code: 0xabababab...(10240 bytes), hash: (1, 2, 3, 4), numbers: (1, 2, 3, 4, 5) }";
+		assert_eq!(value.debug_truncated(&metadata, 4).to_string(), expected);
+		assert_eq!(format!("{:?}", value.debug_truncated(&metadata, 4)), expected);
+
+		// Nothing is cut short if there's nothing long enough:
+		assert_eq!(value.debug_truncated(&metadata, 1 << 20).to_string(), value.to_string());
+	}
+
+	#[test]
+	fn checks_byte_length() {
+		let mut metadata = metadata();
+		let (vec_u8_id, _) = metadata.bytes_ty();
+		let bool_id = register(&mut metadata, TypeDef::Primitive(TypeDefPrimitive::Bool));
+
+		assert_eq!(decode(&metadata, vec_u8_id, &vec![0u8; 32]).expect_byte_len(&metadata, 32), Ok(vec![0; 32]));
+		assert_eq!(
+			decode(&metadata, vec_u8_id, &vec![0u8; 31]).expect_byte_len(&metadata, 32),
+			Err(ByteLenError::WrongLength { expected: 32, got: 31 })
+		);
+		assert_eq!(decode(&metadata, bool_id, &true).expect_byte_len(&metadata, 1), Err(ByteLenError::NotBytes));
+	}
+
+	#[test]
+	fn hashes_values_stably() {
+		use std::collections::{hash_map::DefaultHasher, HashSet};
+
+		fn hash_of(value: &Value<()>, canonical: bool) -> u64 {
+			let mut hasher = DefaultHasher::new();
+			value.hashable(canonical).hash(&mut hasher);
+			hasher.finish()
+		}
+
+		let value = Value::named_composite(vec![
+			("a", Value::u128(1)),
+			("b", Value::unnamed_variant("Some", vec![Value::string("hi")])),
+			("c", Value::bit_sequence(BitSequence::from_iter([true, false]))),
+		]);
+		let reordered = Value::named_composite(vec![
+			("c", Value::bit_sequence(BitSequence::from_iter([true, false]))),
+			("a", Value::u128(1)),
+			("b", Value::unnamed_variant("Some", vec![Value::string("hi")])),
+		]);
+
+		assert_eq!(hash_of(&value, false), hash_of(&value.clone(), false));
+		assert_eq!(hash_of(&value, true), hash_of(&reordered, true));
+		assert_ne!(hash_of(&value, false), hash_of(&reordered, false));
+		assert_ne!(hash_of(&Value::u128(1), false), hash_of(&Value::i128(1), false));
+		assert_ne!(
+			hash_of(&Value::unnamed_composite(vec![]), false),
+			hash_of(&Value::named_composite::<&str, _>(vec![]), false)
+		);
+
+		assert_ne!(value.hashable(false), reordered.hashable(false));
+		assert_eq!(value.hashable(true), reordered.hashable(true));
+		let different = Value::named_composite(vec![("a", Value::u128(2))]);
+		assert_ne!(value.hashable(true), different.hashable(true));
+
+		let values = [value.clone(), reordered.clone(), value.clone()];
+		assert_eq!(values.iter().map(|v| v.hashable(false)).collect::<HashSet<_>>().len(), 2);
+		assert_eq!(values.iter().map(|v| v.hashable(true)).collect::<HashSet<_>>().len(), 1);
+	}
+}
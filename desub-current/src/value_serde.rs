@@ -0,0 +1,624 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Serialize and deserialize [`Value`]s in a way that preserves 256 bit integers.
+//!
+//! [`Value`]'s own serde implementation writes [`Primitive::U256`] and [`Primitive::I256`] out as a plain
+//! sequence of 32 bytes, which reads back as a composite of 32 small integers (or at best as a `U256`, losing
+//! the sign). The functions here write each of them out as a named composite with a single field,
+//! [`U256_FIELD`] or [`I256_FIELD`], holding the `0x` prefixed hex encoding of the (little endian) bytes, and
+//! restore them on the way back in. So that a composite which happens to look like this isn't mistaken for one
+//! of them, any field name starting with `__` is written out with one more `_` in front, which is taken off again
+//! on the way back in. They can be used with `#[serde(with = "desub_current::value_serde")]`.
+//!
+//! [`from_value`] deserializes a [`Value`] into some Rust type, reporting variant names that the target enum
+//! doesn't know about as a [`DeserializeError::UnknownVariant`], and numbers too big (or small) for the target
+//! integer type as a [`DeserializeError::NumberOutOfRange`].
+//!
+//! [`serialize_bounded`] serializes a [`Value`] to JSON in the same way as [`serialize`], but refuses values with
+//! more than a given number of nodes in them, for services which hand decoded values out over an API.
+
+use crate::ValueExt;
+use scale_value::{Composite, Primitive, Value, ValueDef, Variant};
+use serde::de::{
+	self,
+	value::{MapDeserializer, SeqDeserializer},
+	DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The name of the field that a [`Primitive::U256`] is serialized under.
+pub const U256_FIELD: &str = "__u256";
+/// The name of the field that a [`Primitive::I256`] is serialized under.
+pub const I256_FIELD: &str = "__i256";
+/// Field names starting with this are escaped (by adding another `_` to the front) when serialized, so that they
+/// can't be confused with [`U256_FIELD`] and [`I256_FIELD`].
+const ESCAPED_PREFIX: &str = "__";
+
+/// An error deserializing a [`Value`] into some type with [`from_value`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DeserializeError {
+	#[error("Unknown variant `{got}`; expected one of {expected:?}")]
+	UnknownVariant { got: String, expected: Vec<String> },
+	#[error("Number {value} is out of range for {expected}")]
+	NumberOutOfRange { value: String, expected: String },
+	#[error(transparent)]
+	Other(#[from] scale_value::serde::DeserializerError),
+}
+
+/// An error serializing a [`Value`] to JSON with [`serialize_bounded`].
+#[derive(Debug, thiserror::Error)]
+pub enum SerializeBoundedError {
+	#[error("Value has {count} nodes, which is more than the {max} allowed")]
+	TooManyNodes { count: usize, max: usize },
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+}
+
+/// Deserialize a [`Value`] into some type `T`.
+///
+/// This behaves like [`scale_value::serde::from_value`], except that a [`ValueDef::Variant`] whose name isn't one
+/// of the target enum's variants is reported as a [`DeserializeError::UnknownVariant`], and a number which doesn't
+/// fit in the target integer type (say, a decoded `u128` balance going into a `u32`) as a
+/// [`DeserializeError::NumberOutOfRange`], rather than as opaque messages.
+pub fn from_value<'de, Ctx, T: Deserialize<'de>>(value: Value<Ctx>) -> Result<T, DeserializeError> {
+	T::deserialize(ValueDeserializer(value.value))
+}
+
+impl de::Error for DeserializeError {
+	fn custom<T: std::fmt::Display>(msg: T) -> Self {
+		DeserializeError::Other(de::Error::custom(msg))
+	}
+
+	fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+		DeserializeError::UnknownVariant {
+			got: variant.to_string(),
+			expected: expected.iter().map(ToString::to_string).collect(),
+		}
+	}
+}
+
+/// Deserializes values the same way that `scale_value` does, but reports errors as a [`DeserializeError`]. Serde
+/// hands variant names that an enum doesn't know about to [`de::Error::unknown_variant`] on that error type, and
+/// the integer methods check the range themselves, since `scale_value` would otherwise hand an oversized number
+/// to the visitor and leave it to complain. Composites and variants are taken apart here so that the same holds
+/// for everything nested inside them; primitives and bit sequences are left to `scale_value`.
+struct ValueDeserializer<Ctx>(ValueDef<Ctx>);
+
+impl<'de, Ctx> IntoDeserializer<'de, DeserializeError> for ValueDeserializer<Ctx> {
+	type Deserializer = Self;
+	fn into_deserializer(self) -> Self {
+		self
+	}
+}
+
+/// Integers that don't fit the requested type are reported, and any others are handed to `scale_value`.
+macro_rules! deserialize_integer {
+	($($method:ident $ty:ident)*) => {$(
+		fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+			let value = match &self.0 {
+				ValueDef::Primitive(Primitive::U128(n)) => $ty::try_from(*n).is_err().then(|| n.to_string()),
+				ValueDef::Primitive(Primitive::I128(n)) => $ty::try_from(*n).is_err().then(|| n.to_string()),
+				ValueDef::Composite(_) | ValueDef::Variant(_) => return self.deserialize_any(visitor),
+				_ => None,
+			};
+			match value {
+				Some(value) => Err(DeserializeError::NumberOutOfRange { value, expected: stringify!($ty).to_string() }),
+				None => Ok(self.0.$method(visitor)?),
+			}
+		}
+	)*};
+}
+
+/// Composites and variants are taken apart by [`Deserializer::deserialize_any`] in these cases, like `scale_value`
+/// does, and anything else is handed to `scale_value`.
+macro_rules! deserialize_leaf {
+	($($method:ident)*) => {$(
+		fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+			match self.0 {
+				ValueDef::Composite(_) | ValueDef::Variant(_) => self.deserialize_any(visitor),
+				value => Ok(value.$method(visitor)?),
+			}
+		}
+	)*};
+}
+
+impl<'de, Ctx> Deserializer<'de> for ValueDeserializer<Ctx> {
+	type Error = DeserializeError;
+
+	fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(Composite::Named(fields)) => visitor.visit_map(MapDeserializer::new(
+				fields.into_iter().map(|(name, v)| (name, ValueDeserializer(v.value))),
+			)),
+			ValueDef::Composite(Composite::Unnamed(values)) => visit_values(values, visitor),
+			ValueDef::Variant(variant) => visitor.visit_enum(VariantDeserializer(variant)),
+			value => Ok(value.deserialize_any(visitor)?),
+		}
+	}
+
+	deserialize_integer! {
+		deserialize_u8 u8 deserialize_u16 u16 deserialize_u32 u32 deserialize_u64 u64 deserialize_u128 u128
+		deserialize_i8 i8 deserialize_i16 i16 deserialize_i32 i32 deserialize_i64 i64 deserialize_i128 i128
+	}
+
+	deserialize_leaf! {
+		deserialize_bool deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string
+		deserialize_identifier deserialize_ignored_any
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		// Like `scale_value`, a `Some` or `None` variant is taken to be an option:
+		match self.0 {
+			ValueDef::Variant(Variant { name, values: Composite::Unnamed(mut values) })
+				if name == "Some" && values.len() == 1 =>
+			{
+				visitor.visit_some(ValueDeserializer(values.pop().expect("length checked; qed").value))
+			}
+			ValueDef::Variant(Variant { name, values: Composite::Unnamed(values) })
+				if name == "None" && values.is_empty() =>
+			{
+				visitor.visit_none()
+			}
+			value => ValueDeserializer(value).deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::BitSequence(_) => Ok(self.0.deserialize_newtype_struct(name, visitor)?),
+			value => visitor.visit_seq(SeqDeserializer::new(std::iter::once(ValueDeserializer(value)))),
+		}
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(composite) | ValueDef::Variant(Variant { values: composite, .. }) => {
+				visit_values(composite.into_values().collect(), visitor)
+			}
+			value => Ok(value.deserialize_seq(visitor)?),
+		}
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(composite) | ValueDef::Variant(Variant { values: composite, .. })
+				if composite.len() == len =>
+			{
+				visit_values(composite.into_values().collect(), visitor)
+			}
+			// `scale_value` reports the length mismatch (or handles the primitive):
+			value => Ok(value.deserialize_tuple(len, visitor)?),
+		}
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		len: usize,
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(composite) | ValueDef::Variant(Variant { values: composite, .. })
+				if composite.is_empty() =>
+			{
+				visitor.visit_unit()
+			}
+			value => Ok(value.deserialize_unit(visitor)?),
+		}
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Variant(Variant { values, .. }) => {
+				ValueDeserializer(ValueDef::Composite(values)).deserialize_any(visitor)
+			}
+			value => ValueDeserializer(value).deserialize_leaf_map(visitor),
+		}
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		_fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		self.deserialize_map(visitor)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(_) | ValueDef::Variant(_) => self.deserialize_any(visitor),
+			value => Ok(value.deserialize_enum(name, variants, visitor)?),
+		}
+	}
+
+	fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		self.deserialize_byte_buf(visitor)
+	}
+
+	fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		// `scale_value` gathers a composite of `u8`s up into bytes, so only variants are taken apart here:
+		match self.0 {
+			ValueDef::Variant(_) => self.deserialize_any(visitor),
+			value => Ok(value.deserialize_byte_buf(visitor)?),
+		}
+	}
+}
+
+impl<Ctx> ValueDeserializer<Ctx> {
+	/// A map is deserialized from a composite in the same way as anything else, and `scale_value` deals with the
+	/// rest (notably bit sequences, which it hands out as a map).
+	fn deserialize_leaf_map<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+		match self.0 {
+			ValueDef::Composite(_) => self.deserialize_any(visitor),
+			value => Ok(value.deserialize_map(visitor)?),
+		}
+	}
+}
+
+fn visit_values<'de, Ctx, V: Visitor<'de>>(values: Vec<Value<Ctx>>, visitor: V) -> Result<V::Value, DeserializeError> {
+	visitor.visit_seq(SeqDeserializer::new(values.into_iter().map(|v| ValueDeserializer(v.value))))
+}
+
+/// A [`Variant`] as a serde enum: its name picks the variant and its fields are deserialized like a composite.
+struct VariantDeserializer<Ctx>(Variant<Ctx>);
+
+impl<'de, Ctx> EnumAccess<'de> for VariantDeserializer<Ctx> {
+	type Error = DeserializeError;
+	type Variant = ValueDeserializer<Ctx>;
+
+	fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self::Variant), DeserializeError> {
+		let Variant { name, values } = self.0;
+		let name = seed.deserialize(IntoDeserializer::<DeserializeError>::into_deserializer(name))?;
+		Ok((name, ValueDeserializer(ValueDef::Composite(values))))
+	}
+}
+
+impl<'de, Ctx> VariantAccess<'de> for ValueDeserializer<Ctx> {
+	type Error = DeserializeError;
+
+	fn unit_variant(self) -> Result<(), DeserializeError> {
+		<()>::deserialize(self)
+	}
+
+	fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, DeserializeError> {
+		seed.deserialize(self)
+	}
+
+	fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, DeserializeError> {
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn struct_variant<V: Visitor<'de>>(
+		self,
+		_fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, DeserializeError> {
+		self.deserialize_any(visitor)
+	}
+}
+
+/// Serialize a [`Value`], writing out any 256 bit integers in a form that [`deserialize`] can restore.
+pub fn serialize<T: Clone, S: Serializer>(value: &Value<T>, serializer: S) -> Result<S::Ok, S::Error> {
+	let mut value = value.clone();
+	value.transform(&mut |v: &mut Value<T>| {
+		let (field, bytes) = match &mut v.value {
+			ValueDef::Primitive(Primitive::U256(bytes)) => (U256_FIELD, bytes),
+			ValueDef::Primitive(Primitive::I256(bytes)) => (I256_FIELD, bytes),
+			value => {
+				for (name, _) in named_fields(value) {
+					if name.starts_with(ESCAPED_PREFIX) {
+						name.insert(0, '_');
+					}
+				}
+				return;
+			}
+		};
+		let hex = Value { value: ValueDef::Primitive(Primitive::String(hex_bytes(bytes))), context: v.context.clone() };
+		v.value = ValueDef::Composite(Composite::Named(vec![(field.to_string(), hex)]));
+	});
+	value.serialize(serializer)
+}
+
+/// Serialize a [`Value`] to JSON as [`serialize`] would, unless it has more than `max_nodes` nodes in it (as counted
+/// by [`ValueExt::node_count`]), in which case a [`SerializeBoundedError::TooManyNodes`] is handed back instead. The
+/// count is taken before anything is serialized, so a huge value is turned away without building any output.
+pub fn serialize_bounded<T: Clone>(
+	value: &Value<T>,
+	max_nodes: usize,
+) -> Result<serde_json::Value, SerializeBoundedError> {
+	let count = value.node_count();
+	if count > max_nodes {
+		return Err(SerializeBoundedError::TooManyNodes { count, max: max_nodes });
+	}
+	Ok(serialize(value, serde_json::value::Serializer)?)
+}
+
+/// Deserialize a [`Value`] that was serialized with [`serialize`], restoring any 256 bit integers.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Value<()>, D::Error> {
+	let mut value = Value::<()>::deserialize(deserializer)?;
+	let mut err = None;
+	value.transform(&mut |v: &mut Value<()>| match serialized_wide_integer(&v.value) {
+		Some((false, Some(bytes))) => v.value = ValueDef::Primitive(Primitive::U256(bytes)),
+		Some((true, Some(bytes))) => v.value = ValueDef::Primitive(Primitive::I256(bytes)),
+		Some((is_signed, None)) => {
+			let field = if is_signed { I256_FIELD } else { U256_FIELD };
+			err = Some(format!("expected `{field}` to hold 32 hex encoded bytes"));
+		}
+		None => {
+			for (name, _) in named_fields(&mut v.value) {
+				if name.starts_with(ESCAPED_PREFIX) {
+					name.remove(0);
+				}
+			}
+		}
+	});
+	match err {
+		Some(e) => Err(serde::de::Error::custom(e)),
+		None => Ok(value),
+	}
+}
+
+/// If the value is a composite that [`serialize`] wrote a 256 bit integer out as, whether the integer is signed,
+/// along with its bytes (or `None` if they're malformed).
+fn serialized_wide_integer(value: &ValueDef<()>) -> Option<(bool, Option<[u8; 32]>)> {
+	let ValueDef::Composite(Composite::Named(fields)) = value else { return None };
+	let [(field, hex)] = fields.as_slice() else { return None };
+	let is_signed = match field.as_str() {
+		U256_FIELD => false,
+		I256_FIELD => true,
+		_ => return None,
+	};
+	let bytes = match &hex.value {
+		ValueDef::Primitive(Primitive::String(s)) => parse_hex_bytes(s),
+		_ => None,
+	};
+	Some((is_signed, bytes))
+}
+
+/// The named fields of a composite or variant value, if it has any.
+fn named_fields<T>(value: &mut ValueDef<T>) -> &mut [(String, Value<T>)] {
+	match value {
+		ValueDef::Composite(Composite::Named(fields)) => fields,
+		ValueDef::Variant(variant) => match &mut variant.values {
+			Composite::Named(fields) => fields,
+			Composite::Unnamed(_) => &mut [],
+		},
+		_ => &mut [],
+	}
+}
+
+fn hex_bytes(bytes: &[u8; 32]) -> String {
+	format!("0x{}", hex::encode(bytes))
+}
+
+fn parse_hex_bytes(s: &str) -> Option<[u8; 32]> {
+	let bytes = hex::decode(s.strip_prefix("0x")?).ok()?;
+	bytes.try_into().ok()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Wrapper {
+		#[serde(with = "super")]
+		value: Value<()>,
+	}
+
+	fn wide_integers() -> Value<()> {
+		let mut u256 = [0u8; 32];
+		u256[0] = 1;
+		u256[31] = 0xff;
+		Value::named_composite(vec![
+			("unsigned", Value::primitive(Primitive::U256(u256))),
+			("signed", Value::primitive(Primitive::I256([0xff; 32]))),
+			("nested", Value::unnamed_composite(vec![Value::primitive(Primitive::U256([7; 32]))])),
+			("bytes", Value::from_bytes([1u8, 2, 3])),
+		])
+	}
+
+	#[test]
+	fn wide_integers_survive_json_round_trip() {
+		let original = Wrapper { value: wide_integers() };
+		let json = serde_json::to_string(&original).unwrap();
+		let decoded: Wrapper = serde_json::from_str(&json).unwrap();
+		assert_eq!(decoded, original);
+	}
+
+	#[test]
+	fn wide_integers_survive_value_round_trip() {
+		let original = Wrapper { value: wide_integers() };
+		let value = scale_value::serde::to_value(&original).unwrap();
+		let decoded: Wrapper = scale_value::serde::from_value(value).unwrap();
+		assert_eq!(decoded, original);
+	}
+
+	#[test]
+	fn lookalike_composites_are_not_mistaken_for_wide_integers() {
+		let hex = Value::string(hex_bytes(&[1; 32]));
+		let original = Wrapper {
+			value: Value::named_composite(vec![
+				("lookalike", Value::named_composite(vec![(U256_FIELD, hex.clone())])),
+				("signed_lookalike", Value::unnamed_composite(vec![Value::named_composite(vec![(I256_FIELD, hex)])])),
+				("escaped", Value::named_composite(vec![("___x", Value::u128(1)), ("_y", Value::u128(2))])),
+				("real", Value::primitive(Primitive::U256([1; 32]))),
+			]),
+		};
+
+		let json = serde_json::to_value(&original).unwrap();
+		assert_eq!(json["value"]["lookalike"], serde_json::json!({ "___u256": hex_bytes(&[1; 32]) }));
+		assert_eq!(json["value"]["real"], serde_json::json!({ "__u256": hex_bytes(&[1; 32]) }));
+
+		let decoded: Wrapper = serde_json::from_value(json).unwrap();
+		assert_eq!(decoded, original);
+	}
+
+	#[test]
+	fn malformed_wide_integer_is_an_error() {
+		let json = format!(r#"{{"value": {{"{U256_FIELD}": "0x1234"}}}}"#);
+		assert!(serde_json::from_str::<Wrapper>(&json).is_err());
+	}
+
+	#[test]
+	fn serialization_can_be_bounded() {
+		let value = wide_integers();
+		let count = value.node_count();
+		let json = serialize_bounded(&value, count).unwrap();
+		assert_eq!(json, serde_json::to_value(Wrapper { value }).unwrap()["value"]);
+
+		let big = Value::from_bytes(vec![0u8; 10_000]);
+		assert!(matches!(
+			serialize_bounded(&big, 1000),
+			Err(SerializeBoundedError::TooManyNodes { count: 10_001, max: 1000 })
+		));
+	}
+
+	#[derive(Debug, PartialEq, Deserialize)]
+	enum Call {
+		Transfer { dest: u32 },
+		Remark(String),
+		Noop,
+	}
+
+	#[test]
+	fn known_variants_deserialize() {
+		let value = Value::named_variant("Transfer", vec![("dest", Value::u128(1))]);
+		assert_eq!(from_value::<_, Call>(value), Ok(Call::Transfer { dest: 1 }));
+		assert_eq!(from_value::<_, Call>(Value::unnamed_variant("Noop", vec![])), Ok(Call::Noop));
+	}
+
+	#[test]
+	fn unknown_variant_is_reported() {
+		let value = Value::unnamed_variant("Batch", vec![Value::u128(1)]);
+		assert_eq!(
+			from_value::<_, Call>(value),
+			Err(DeserializeError::UnknownVariant {
+				got: "Batch".to_string(),
+				expected: vec!["Transfer".to_string(), "Remark".to_string(), "Noop".to_string()],
+			})
+		);
+	}
+
+	#[test]
+	fn numbers_are_range_checked() {
+		assert_eq!(from_value::<_, u32>(Value::u128(1234)), Ok(1234));
+		assert_eq!(from_value::<_, i8>(Value::i128(-5)), Ok(-5));
+		assert_eq!(
+			from_value::<_, u32>(Value::u128(5_000_000_000)),
+			Err(DeserializeError::NumberOutOfRange { value: "5000000000".to_string(), expected: "u32".to_string() })
+		);
+		assert_eq!(
+			from_value::<_, u64>(Value::u128(u128::MAX)),
+			Err(DeserializeError::NumberOutOfRange { value: u128::MAX.to_string(), expected: "u64".to_string() })
+		);
+		assert_eq!(
+			from_value::<_, u64>(Value::i128(-1)),
+			Err(DeserializeError::NumberOutOfRange { value: "-1".to_string(), expected: "u64".to_string() })
+		);
+		// A number where a string is expected is a type mismatch rather than a range problem:
+		assert!(matches!(from_value::<_, String>(Value::u128(1)), Err(DeserializeError::Other(_))));
+	}
+
+	#[test]
+	fn nested_problems_are_reported() {
+		let batch = Value::unnamed_variant(
+			"Some",
+			vec![Value::unnamed_composite(vec![
+				Value::named_variant("Transfer", vec![("dest", Value::u128(1))]),
+				Value::named_variant("Transfer", vec![("dest", Value::u128(5_000_000_000))]),
+			])],
+		);
+		assert_eq!(
+			from_value::<_, Option<Vec<Call>>>(batch),
+			Err(DeserializeError::NumberOutOfRange { value: "5000000000".to_string(), expected: "u32".to_string() })
+		);
+		let batch = Value::named_composite(vec![(
+			"calls",
+			Value::unnamed_composite(vec![Value::unnamed_variant("Batch", vec![])]),
+		)]);
+		#[derive(Debug, Deserialize)]
+		struct Batch {
+			#[allow(dead_code)]
+			calls: Vec<Call>,
+		}
+		assert!(
+			matches!(from_value::<_, Batch>(batch), Err(DeserializeError::UnknownVariant { got, .. }) if got == "Batch")
+		);
+	}
+
+	#[test]
+	fn other_errors_are_passed_through() {
+		let value = Value::named_variant("Transfer", vec![("dest", Value::string("alice"))]);
+		assert!(matches!(from_value::<_, Call>(value), Err(DeserializeError::Other(_))));
+	}
+
+	/// The argument of an unsigned `System.remark(b"hello")` call, decoded as a `Vec<u8>`.
+	fn decoded_remark() -> Value<crate::TypeId> {
+		let metadata = crate::Metadata::from_bytes(include_bytes!("../tests/data/v14_metadata_polkadot.scale"))
+			.expect("valid metadata");
+		let call = crate::decoder::decode_call_data(&metadata, &mut &[0u8, 1, 20, b'h', b'e', b'l', b'l', b'o'][..])
+			.expect("can decode remark");
+		call.arguments.into_iter().next().expect("remark has an argument")
+	}
+
+	#[test]
+	fn byte_sequences_deserialize_into_vec_u8() {
+		assert_eq!(from_value::<_, Vec<u8>>(decoded_remark()), Ok(b"hello".to_vec()));
+	}
+
+	#[test]
+	fn byte_sequences_deserialize_into_serde_bytes() {
+		#[derive(Debug, PartialEq, Deserialize)]
+		struct Remark {
+			#[serde(with = "serde_bytes")]
+			remark: Vec<u8>,
+		}
+
+		let value = Value::named_composite(vec![("remark", decoded_remark().remove_context())]);
+		assert_eq!(from_value::<_, Remark>(value), Ok(Remark { remark: b"hello".to_vec() }));
+		assert_eq!(
+			from_value::<_, serde_bytes::ByteBuf>(decoded_remark()),
+			Ok(serde_bytes::ByteBuf::from(b"hello".to_vec()))
+		);
+		// Numbers too big to be bytes are an error rather than being truncated:
+		let not_bytes = Value::unnamed_composite(vec![Value::u128(1), Value::u128(256)]);
+		assert!(from_value::<_, serde_bytes::ByteBuf>(not_bytes).is_err());
+	}
+}
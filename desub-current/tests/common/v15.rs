@@ -0,0 +1,111 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Re-encoding V14 metadata as V15, shared with the `desub` tests (which include this file by path).
+
+use frame_metadata::{v15, RuntimeMetadata, RuntimeMetadataPrefixed};
+use parity_scale_codec::Decode;
+use scale_info::{form::PortableForm, Field, Path, PortableType, Type, TypeDef, TypeDefVariant, Variant};
+
+/// Decode some SCALE encoded V14 metadata and re-express it as V15, which carries the same pallets and types.
+///
+/// V14 metadata has no outer error enum, so one is added to the type registry with a variant for each pallet
+/// that has errors, in the same shape as the `RuntimeError` enum that V15 runtimes declare.
+pub fn v14_as_v15(v14_bytes: &[u8]) -> v15::RuntimeMetadataV15 {
+	let RuntimeMetadataPrefixed(_, RuntimeMetadata::V14(mut v14)) =
+		RuntimeMetadataPrefixed::decode(&mut &*v14_bytes).expect("valid metadata")
+	else {
+		panic!("expected V14 metadata")
+	};
+	let param = |name: &str| {
+		let ext_ty = v14.types.resolve(v14.extrinsic.ty.id).expect("extrinsic type exists");
+		ext_ty.type_params.iter().find(|p| p.name == name).and_then(|p| p.ty).expect("extrinsic type param exists")
+	};
+	let [call_enum_ty, address_ty, signature_ty, extra_ty] = ["Call", "Address", "Signature", "Extra"].map(param);
+
+	// The outer event enum lives next to the outer call enum, in the runtime crate:
+	let mut event_path = v14.types.resolve(call_enum_ty.id).expect("call type exists").path.segments.clone();
+	*event_path.last_mut().expect("call type has a path") = "Event".to_string();
+	let event_enum_ty =
+		v14.types.types.iter().find(|ty| ty.ty.path.segments == event_path).expect("event type exists").id;
+
+	let mut error_path = event_path;
+	*error_path.last_mut().expect("checked above") = "RuntimeError".to_string();
+	let error_variants = v14
+		.pallets
+		.iter()
+		.filter_map(|p| {
+			let error = p.error.as_ref()?;
+			let field = Field { name: None, ty: error.ty, type_name: None, docs: vec![] };
+			Some(Variant { name: p.name.clone(), fields: vec![field], index: p.index, docs: vec![] })
+		})
+		.collect();
+	let error_enum_ty = v14.types.types.len() as u32;
+	v14.types.types.push(PortableType {
+		id: error_enum_ty,
+		ty: Type::<PortableForm> {
+			path: Path { segments: error_path },
+			type_params: vec![],
+			type_def: TypeDef::Variant(TypeDefVariant { variants: error_variants }),
+			docs: vec![],
+		},
+	});
+
+	let extrinsic = v15::ExtrinsicMetadata {
+		version: v14.extrinsic.version,
+		address_ty,
+		call_ty: call_enum_ty,
+		signature_ty,
+		extra_ty,
+		signed_extensions: v14
+			.extrinsic
+			.signed_extensions
+			.iter()
+			.map(|e| v15::SignedExtensionMetadata {
+				identifier: e.identifier.clone(),
+				ty: e.ty,
+				additional_signed: e.additional_signed,
+			})
+			.collect(),
+	};
+	let pallets = v14
+		.pallets
+		.into_iter()
+		.map(|p| v15::PalletMetadata {
+			name: p.name,
+			storage: p.storage,
+			calls: p.calls,
+			event: p.event,
+			constants: p.constants,
+			error: p.error,
+			index: p.index,
+			docs: vec![],
+		})
+		.collect();
+	v15::RuntimeMetadataV15 {
+		types: v14.types,
+		pallets,
+		extrinsic,
+		ty: v14.ty,
+		apis: vec![],
+		outer_enums: v15::OuterEnums {
+			call_enum_ty,
+			event_enum_ty: event_enum_ty.into(),
+			error_enum_ty: error_enum_ty.into(),
+		},
+		custom: v15::CustomMetadata { map: Default::default() },
+	}
+}
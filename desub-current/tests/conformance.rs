@@ -0,0 +1,398 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Decode a corpus of extrinsics with `desub-current` and with `subxt`, and check that both agree.
+//!
+//! `subxt` is the reference: its metadata gives the types making up an extrinsic, and each part is decoded with
+//! its dynamic decoding (`scale_value::scale::decode_as_type`). Both outputs are then mapped to a common form for
+//! comparison:
+//!
+//! - the call becomes a `Pallet(call { args.. })` value with the type IDs removed,
+//! - the address and signature are compared by their SCALE encoding, since `desub` hands them back as
+//!   `sp_runtime` types rather than values,
+//! - the signed extensions become the values of each extension, in order.
+//!
+//! `desub` rewrites maps, sets and bounded collections (see `decoder::decode_value::normalize`), and
+//! `decoder::humanize_value` can turn 8 byte identifiers into strings. The values decoded by `subxt` are presented
+//! in the same way here, working from the shape that each collection is SCALE encoded in.
+
+use desub_current::{
+	decoder::{self, Extrinsic, MAP_KEY_FIELD, MAP_VALUE_FIELD},
+	Metadata, Value, ValueDef,
+};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use parity_scale_codec::{Decode, Encode};
+use scale_info::{
+	form::PortableForm, Field, Path, PortableType, Type, TypeDef, TypeDefComposite, TypeDefPrimitive, TypeDefSequence,
+	TypeDefTuple, TypeParameter,
+};
+use scale_value::{Composite, Primitive, Variant};
+use subxt::ext::scale_value::scale::decode_as_type;
+
+static V14_METADATA_POLKADOT_SCALE: &[u8] = include_bytes!("data/v14_metadata_polkadot.scale");
+static CORPUS: &str = include_str!("data/conformance_extrinsics.txt");
+
+/// The type name of fields holding lock identifiers, which are presented as strings when humanized.
+const LOCK_IDENTIFIER: &str = "LockIdentifier";
+
+/// An extrinsic in the form that we compare.
+#[derive(Debug, PartialEq)]
+struct Comparable {
+	signature: Option<ComparableSignature>,
+	call: Value<()>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ComparableSignature {
+	address: Vec<u8>,
+	signature: Vec<u8>,
+	extensions: Vec<Value<()>>,
+}
+
+/// The reference decoder, built on `subxt`.
+struct Reference {
+	metadata: subxt::Metadata,
+	/// Present printable 8 byte identifiers as strings, as `decoder::humanize_value` does.
+	humanize: bool,
+}
+
+impl Reference {
+	fn new(mut meta: RuntimeMetadata, humanize: bool) -> Reference {
+		let RuntimeMetadata::V14(v14) = &mut meta else { panic!("expected V14 metadata") };
+
+		// `subxt` looks for the outer call and event enums by the names that runtimes have given them since
+		// late 2022, so rename them in older metadata. This only changes type paths, not how anything decodes.
+		let types = &mut v14.types.types;
+		let extrinsic = &types[v14.extrinsic.ty.id as usize].ty;
+		let call = extrinsic.type_params.iter().find(|p| p.name == "Call").and_then(|p| p.ty).expect("call type");
+		let runtime = types[call.id as usize].ty.path.segments[0].clone();
+		for ty in types.iter_mut() {
+			if let [krate, ident] = &mut ty.ty.path.segments[..] {
+				if *krate == runtime && (ident == "Call" || ident == "Event") {
+					*ident = format!("Runtime{ident}");
+				}
+			}
+		}
+
+		let meta = RuntimeMetadataPrefixed(META_RESERVED, meta);
+		Reference { metadata: subxt::Metadata::try_from(meta).expect("subxt can use the metadata"), humanize }
+	}
+
+	fn decode(&self, data: &mut &[u8]) -> Comparable {
+		let is_signed = data[0] & 0b1000_0000 != 0;
+		*data = &data[1..];
+
+		let extrinsic = self.metadata.extrinsic();
+		let signature = is_signed.then(|| {
+			let address = self.decode_bytes(extrinsic.address_ty(), data);
+			let signature = self.decode_bytes(extrinsic.signature_ty(), data);
+			let extensions =
+				extrinsic.signed_extensions().iter().map(|ext| self.decode_value(ext.extra_ty(), data)).collect();
+			ComparableSignature { address, signature, extensions }
+		});
+		let call = self.decode_value(extrinsic.call_ty(), data);
+
+		Comparable { signature, call }
+	}
+
+	fn decode_value(&self, id: u32, data: &mut &[u8]) -> Value<()> {
+		let value = decode_as_type(data, id, self.metadata.types())
+			.unwrap_or_else(|e| panic!("subxt can't decode a value of type {id}: {e}"));
+		self.present(value)
+	}
+
+	/// Decode a value, handing back the bytes that it was decoded from.
+	fn decode_bytes(&self, ty: u32, data: &mut &[u8]) -> Vec<u8> {
+		let before = *data;
+		self.decode_value(ty, data);
+		before[..before.len() - data.len()].to_vec()
+	}
+
+	/// Present a value decoded by `subxt` as `desub` would.
+	fn present(&self, value: Value<u32>) -> Value<()> {
+		let ty = self.metadata.types().resolve(value.context).expect("decoded values have types in the registry");
+		let ident = ty.path.segments.last().map(String::as_str);
+		if let (Some("PalletId"), true, Some(id)) = (ident, self.humanize, identifier(&value)) {
+			return Value::string(id);
+		}
+		let value = match (ident, value.value) {
+			// Maps are encoded as a sequence of `(key, value)` tuples:
+			(Some("BTreeMap"), ValueDef::Composite(map)) => {
+				let entries = items(only_value(map)).map(|entry| {
+					let mut entry = items(entry);
+					let (key, value) = (entry.next().expect("map key"), entry.next().expect("map value"));
+					Value::named_composite([(MAP_KEY_FIELD, self.present(key)), (MAP_VALUE_FIELD, self.present(value))])
+				});
+				return Value::unnamed_composite(entries.collect::<Vec<_>>());
+			}
+			(Some("BTreeSet"), ValueDef::Composite(set)) => {
+				return Value::unnamed_composite(items(only_value(set)).map(|v| self.present(v)).collect::<Vec<_>>());
+			}
+			(Some("BoundedVec" | "WeakBoundedVec" | "BoundedBTreeMap" | "BoundedBTreeSet"), ValueDef::Composite(c)) => {
+				return self.present(only_value(c));
+			}
+			(_, ValueDef::Composite(c)) => match &ty.type_def {
+				TypeDef::Composite(def) => ValueDef::Composite(self.present_fields(&def.fields, c)),
+				_ => ValueDef::Composite(Composite::Unnamed(c.into_values().map(|v| self.present(v)).collect())),
+			},
+			(_, ValueDef::Variant(Variant { name, values })) => {
+				let TypeDef::Variant(def) = &ty.type_def else { panic!("variant values should have variant types") };
+				let variant = def.variants.iter().find(|v| v.name == name).expect("decoded variants exist");
+				let values = self.present_fields(&variant.fields, values);
+				ValueDef::Variant(Variant { name, values })
+			}
+			(_, ValueDef::Primitive(p)) => ValueDef::Primitive(p),
+			(_, ValueDef::BitSequence(b)) => ValueDef::BitSequence(b),
+		};
+		Value { value, context: () }
+	}
+
+	/// Present the fields of a composite or variant, humanizing any lock identifiers among them.
+	fn present_fields(&self, fields: &[Field<PortableForm>], values: Composite<u32>) -> Composite<()> {
+		let present = |field: &Field<PortableForm>, value: Value<u32>| {
+			let is_lock_identifier = field.type_name.as_deref() == Some(LOCK_IDENTIFIER);
+			match identifier(&value) {
+				Some(id) if self.humanize && is_lock_identifier => Value::string(id),
+				_ => self.present(value),
+			}
+		};
+		match values {
+			Composite::Named(values) => {
+				Composite::Named(fields.iter().zip(values).map(|(f, (name, v))| (name, present(f, v))).collect())
+			}
+			Composite::Unnamed(values) => {
+				Composite::Unnamed(fields.iter().zip(values).map(|(f, v)| present(f, v)).collect())
+			}
+		}
+	}
+}
+
+/// The values in a composite value, such as the items of a sequence or the fields of a tuple.
+fn items(value: Value<u32>) -> impl Iterator<Item = Value<u32>> {
+	match value.value {
+		ValueDef::Composite(c) => c.into_values(),
+		other => panic!("expected a composite value, got {other:?}"),
+	}
+}
+
+/// The value wrapped by a newtype.
+fn only_value(composite: Composite<u32>) -> Value<u32> {
+	let mut values = composite.into_values();
+	match (values.next(), values.next()) {
+		(Some(value), None) => value,
+		_ => panic!("expected a newtype with exactly one field"),
+	}
+}
+
+/// The string in an 8 byte identifier (or a newtype around one), if all of it is printable ASCII.
+fn identifier(value: &Value<u32>) -> Option<String> {
+	let ValueDef::Composite(composite) = &value.value else { return None };
+	let values: Vec<_> = composite.values().collect();
+	if let [inner @ Value { value: ValueDef::Composite(_), .. }] = &values[..] {
+		return identifier(inner);
+	}
+	let bytes = values
+		.iter()
+		.map(|v| match v.value {
+			ValueDef::Primitive(Primitive::U128(byte)) => u8::try_from(byte).ok(),
+			_ => None,
+		})
+		.collect::<Option<Vec<u8>>>()?;
+	let printable = bytes.len() == 8 && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ');
+	printable.then(|| String::from_utf8(bytes).expect("printable ASCII is valid UTF-8"))
+}
+
+fn comparable(meta: &Metadata, ext: Extrinsic, humanize: bool) -> Comparable {
+	let signature = ext.signature.map(|sig| ComparableSignature {
+		address: sig.address.encode(),
+		signature: sig.signature.encode(),
+		extensions: sig.extensions.into_iter().map(|(_, val)| val.remove_context()).collect(),
+	});
+
+	let call_data = ext.call_data;
+	let args = call_data.arguments.into_iter().map(|mut arg| {
+		if humanize {
+			decoder::humanize_value(meta, &mut arg);
+		}
+		arg.remove_context()
+	});
+	let args = if call_data.ty.fields.iter().any(|f| f.name.is_some()) {
+		let names = call_data.ty.fields.iter().map(|f| f.name.clone().unwrap_or_default());
+		Composite::Named(names.zip(args).collect())
+	} else {
+		Composite::Unnamed(args.collect())
+	};
+	let call =
+		Value { value: ValueDef::Variant(Variant { name: call_data.ty.name.clone(), values: args }), context: () };
+	let call = Value::unnamed_variant(call_data.pallet_name, vec![call]);
+
+	Comparable { signature, call }
+}
+
+/// Decode each extrinsic with `desub` and with `subxt`, and check that they agree, handing back how many
+/// extrinsics were checked.
+fn check_agreement(
+	meta: fn() -> RuntimeMetadata,
+	humanize: bool,
+	extrinsics: impl IntoIterator<Item = (usize, Vec<u8>)>,
+) -> usize {
+	let desub_meta = Metadata::from_runtime_metadata(meta()).expect("valid metadata");
+	let reference = Reference::new(meta(), humanize);
+
+	let mut checked = 0;
+	for (line, bytes) in extrinsics {
+		let cursor = &mut &*bytes;
+		let ext = decoder::decode_unwrapped_extrinsic(&desub_meta, cursor)
+			.unwrap_or_else(|e| panic!("desub can't decode the extrinsic on line {line}: {e}"));
+		assert!(cursor.is_empty(), "desub left bytes over decoding the extrinsic on line {line}");
+
+		let reference_cursor = &mut &*bytes;
+		let expected = reference.decode(reference_cursor);
+		assert!(reference_cursor.is_empty(), "subxt left bytes over decoding the extrinsic on line {line}");
+
+		assert_eq!(comparable(&desub_meta, ext, humanize), expected, "desub disagrees with subxt on line {line}");
+		checked += 1;
+	}
+	checked
+}
+
+fn polkadot_metadata() -> RuntimeMetadata {
+	RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata").1
+}
+
+fn corpus() -> impl Iterator<Item = (usize, Vec<u8>)> {
+	CORPUS.lines().enumerate().filter(|(_, line)| !line.is_empty() && !line.starts_with('#')).map(|(idx, line)| {
+		let hex_str = line.strip_prefix("0x").expect("0x should prefix hex encoded bytes");
+		(idx + 1, hex::decode(hex_str).expect("valid bytes from hex"))
+	})
+}
+
+#[test]
+fn desub_agrees_with_subxt() {
+	assert!(check_agreement(polkadot_metadata, false, corpus()) > 0, "the corpus should not be empty");
+	// None of the corpus is changed by humanizing it:
+	check_agreement(polkadot_metadata, true, corpus());
+}
+
+/// The index of the call added to `System` by [`metadata_with_collections_call`].
+const COLLECTIONS_CALL_INDEX: u8 = 200;
+
+/// Polkadot metadata, altered so that `System` has a call taking a map, a set and a couple of identifiers:
+///
+/// ```text
+/// collections { map: BTreeMap<u32, bool>, set: BTreeSet<u32>, ids: Ids { lock: LockIdentifier, pallet: PalletId } }
+/// ```
+///
+/// Polkadot has no calls taking these, but they are used in other runtimes and the types exist in its registry.
+fn metadata_with_collections_call() -> RuntimeMetadata {
+	let mut meta = polkadot_metadata();
+	let RuntimeMetadata::V14(meta_v14) = &mut meta else { panic!("expected V14 metadata") };
+	let types = &mut meta_v14.types.types;
+
+	let find = |types: &[PortableType], f: &dyn Fn(&Type<PortableForm>) -> bool| {
+		types.iter().find(|t| f(&t.ty)).expect("type exists").id
+	};
+	let u32_id = find(types, &|ty| matches!(ty.type_def, TypeDef::Primitive(TypeDefPrimitive::U32)));
+	let bool_id = find(types, &|ty| matches!(ty.type_def, TypeDef::Primitive(TypeDefPrimitive::Bool)));
+	let lock_id = find(types, &|ty| matches!(&ty.type_def, TypeDef::Array(a) if a.len == 8));
+	let pallet_id = find(types, &|ty| ty.path.ident().as_deref() == Some("PalletId"));
+
+	let mut push = |path: &[&str], params: &[(&str, u32)], type_def: TypeDef<PortableForm>| {
+		let id = types.len() as u32;
+		let path = Path { segments: path.iter().map(|s| s.to_string()).collect() };
+		let type_params =
+			params.iter().map(|(name, ty)| TypeParameter { name: name.to_string(), ty: Some((*ty).into()) }).collect();
+		types.push(PortableType { id, ty: Type { path, type_params, type_def, docs: vec![] } });
+		id
+	};
+	let field = |name: &str, ty: u32, type_name: &str| Field {
+		name: (!name.is_empty()).then(|| name.to_string()),
+		ty: ty.into(),
+		type_name: Some(type_name.to_string()),
+		docs: vec![],
+	};
+
+	let entry = push(&[], &[], TypeDef::Tuple(TypeDefTuple { fields: vec![u32_id.into(), bool_id.into()] }));
+	let entries = push(&[], &[], TypeDef::Sequence(TypeDefSequence { type_param: entry.into() }));
+	let map = push(
+		&["BTreeMap"],
+		&[("K", u32_id), ("V", bool_id)],
+		TypeDef::Composite(TypeDefComposite { fields: vec![field("", entries, "Vec<(K, V)>")] }),
+	);
+	let items = push(&[], &[], TypeDef::Sequence(TypeDefSequence { type_param: u32_id.into() }));
+	let set = push(
+		&["BTreeSet"],
+		&[("T", u32_id)],
+		TypeDef::Composite(TypeDefComposite { fields: vec![field("", items, "Vec<T>")] }),
+	);
+	let ids = push(
+		&["conformance", "Ids"],
+		&[],
+		TypeDef::Composite(TypeDefComposite {
+			fields: vec![field("lock", lock_id, LOCK_IDENTIFIER), field("pallet", pallet_id, "PalletId")],
+		}),
+	);
+
+	let system = meta_v14.pallets.iter().find(|p| p.name == "System").expect("System pallet exists");
+	let call_ty = system.calls.as_ref().expect("System has calls").ty.id as usize;
+	let TypeDef::Variant(calls) = &mut meta_v14.types.types[call_ty].ty.type_def else {
+		panic!("System calls should be a variant")
+	};
+	calls.variants.push(scale_info::Variant {
+		name: "collections".into(),
+		fields: vec![
+			field("map", map, "BTreeMap<u32, bool>"),
+			field("set", set, "BTreeSet<u32>"),
+			field("ids", ids, "Ids"),
+		],
+		index: COLLECTIONS_CALL_INDEX,
+		docs: vec![],
+	});
+
+	meta
+}
+
+#[test]
+fn desub_agrees_with_subxt_on_collections_and_identifiers() {
+	// An unsigned `System.collections` call:
+	let mut ext = vec![4u8, 0, COLLECTIONS_CALL_INDEX];
+	ext.extend(vec![(1u32, true), (2u32, false)].encode());
+	ext.extend(vec![1u32, 2, 3].encode());
+	ext.extend(b"staking ");
+	ext.extend(b"py/trsry");
+
+	// Identifiers which aren't printable are left as bytes:
+	let mut unprintable = ext.clone();
+	let len = unprintable.len();
+	unprintable[len - 16..].copy_from_slice(&[0xff; 16]);
+
+	for humanize in [false, true] {
+		check_agreement(metadata_with_collections_call, humanize, [(1, ext.clone()), (2, unprintable.clone())]);
+	}
+
+	// And to be sure that the reference is checking what we think it is:
+	let reference = Reference::new(metadata_with_collections_call(), true);
+	let Comparable { call, .. } = reference.decode(&mut &*ext);
+	let entry = |k: u32, v: bool| {
+		Value::named_composite([(MAP_KEY_FIELD, Value::u128(k.into())), (MAP_VALUE_FIELD, Value::bool(v))])
+	};
+	let args = vec![
+		("map", Value::unnamed_composite([entry(1, true), entry(2, false)])),
+		("set", Value::unnamed_composite([Value::u128(1), Value::u128(2), Value::u128(3)])),
+		("ids", Value::named_composite([("lock", Value::string("staking ")), ("pallet", Value::string("py/trsry"))])),
+	];
+	assert_eq!(call, Value::unnamed_variant("System", [Value::named_variant("collections", args)]));
+}
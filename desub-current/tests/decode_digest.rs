@@ -0,0 +1,67 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use desub_current::{decoder, Metadata, ValueDef, ValueExt};
+use parity_scale_codec::Encode;
+use sp_runtime::generic::{Digest, DigestItem};
+
+static V14_METADATA_POLKADOT_SCALE: &[u8] = include_bytes!("data/v14_metadata_polkadot.scale");
+
+fn metadata() -> Metadata {
+	Metadata::from_bytes(V14_METADATA_POLKADOT_SCALE).expect("valid metadata")
+}
+
+fn variant_name<T: std::fmt::Debug>(value: &desub_current::Value<T>) -> &str {
+	match &value.value {
+		ValueDef::Variant(v) => &v.name,
+		other => panic!("expected a variant, got {other:?}"),
+	}
+}
+
+#[test]
+fn decode_header_digest_logs() {
+	let meta = metadata();
+
+	// The sort of digest that a BABE authored block carries:
+	let digest = Digest {
+		logs: vec![
+			DigestItem::PreRuntime(*b"BABE", vec![1, 2, 3, 4]),
+			DigestItem::Consensus(*b"FRNK", vec![5, 6]),
+			DigestItem::Seal(*b"BABE", vec![7; 64]),
+		],
+	};
+	let mut encoded = digest.encode();
+	encoded.extend(b"rest of the header");
+	let cursor = &mut &*encoded;
+
+	let logs = decoder::decode_digest(&meta, cursor).unwrap();
+	assert_eq!(cursor, b"rest of the header");
+
+	let names: Vec<_> = logs.iter().map(variant_name).collect();
+	assert_eq!(names, ["PreRuntime", "Consensus", "Seal"]);
+
+	let ValueDef::Variant(seal) = &logs[2].value else { panic!("seal should be a variant") };
+	let fields: Vec<_> = seal.values.values().collect();
+	assert_eq!(fields[0].as_bytes(&meta).unwrap(), b"BABE");
+	assert_eq!(fields[1].as_bytes(&meta).unwrap(), vec![7; 64]);
+}
+
+#[test]
+fn decode_empty_digest() {
+	let meta = metadata();
+	let logs = decoder::decode_digest(&meta, &mut &*Digest::default().encode()).unwrap();
+	assert!(logs.is_empty());
+}
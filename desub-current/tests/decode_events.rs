@@ -0,0 +1,224 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::v15::v14_as_v15;
+use desub_current::{
+	decoder::{self, Phase},
+	Metadata, Value,
+};
+use frame_metadata::RuntimeMetadata;
+use parity_scale_codec::{Compact, Encode};
+use sp_core::H256;
+use sp_keyring::AccountKeyring;
+
+static V14_METADATA_POLKADOT_SCALE: &[u8] = include_bytes!("data/v14_metadata_polkadot.scale");
+
+fn metadata() -> Metadata {
+	Metadata::from_bytes(V14_METADATA_POLKADOT_SCALE).expect("valid metadata")
+}
+
+/// Encode an event record given the phase, the pallet/event indexes and the encoded fields.
+fn event_record(phase: (u8, Option<u32>), pallet: u8, event: u8, fields: Vec<u8>, topics: Vec<H256>) -> Vec<u8> {
+	let mut bytes = vec![phase.0];
+	if let Some(idx) = phase.1 {
+		bytes.extend(idx.encode());
+	}
+	bytes.push(pallet);
+	bytes.push(event);
+	bytes.extend(fields);
+	bytes.extend(topics.encode());
+	bytes
+}
+
+#[test]
+fn can_decode_events() {
+	let meta = metadata();
+
+	let alice = AccountKeyring::Alice.to_account_id();
+	let bob = AccountKeyring::Bob.to_account_id();
+
+	// System.ExtrinsicSuccess (DispatchInfo { weight: 1000, class: Normal, pays_fee: Yes })
+	let success = event_record((0, Some(0)), 0, 0, (1000u64, 0u8, 0u8).encode(), vec![]);
+	// Balances.Transfer (Alice, Bob, 12345), with a topic attached
+	let transfer = event_record((0, Some(1)), 5, 2, (&alice, &bob, 12345u128).encode(), vec![H256::repeat_byte(1)]);
+
+	let mut events_bytes = Compact(2u32).encode();
+	events_bytes.extend(success);
+	events_bytes.extend(transfer);
+
+	let cursor = &mut &*events_bytes;
+	let events = decoder::decode_events(&meta, cursor).expect("can decode events");
+
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(events.len(), 2);
+
+	assert_eq!(events[0].phase, Phase::ApplyExtrinsic(0));
+	assert_eq!(events[0].event.pallet_name, "System");
+	assert_eq!(&*events[0].event.ty.name, "ExtrinsicSuccess");
+	assert!(events[0].topics.is_empty());
+
+	assert_eq!(events[1].phase, Phase::ApplyExtrinsic(1));
+	assert_eq!(events[1].event.pallet_name, "Balances");
+	assert_eq!(&*events[1].event.ty.name, "Transfer");
+	assert_eq!(events[1].event.fields.len(), 3);
+	assert_eq!(events[1].event.fields[2].clone().remove_context(), Value::u128(12345));
+	assert_eq!(events[1].topics, vec![H256::repeat_byte(1)]);
+}
+
+#[test]
+fn decodes_the_number_of_events_given() {
+	let meta = metadata();
+
+	// System.ExtrinsicSuccess (DispatchInfo { weight: 1000, class: Normal, pays_fee: Yes })
+	let success = event_record((0, Some(0)), 0, 0, (1000u64, 0u8, 0u8).encode(), vec![]);
+
+	let mut events_bytes = Compact(2u32).encode();
+	events_bytes.extend(&success);
+	events_bytes.extend(&success);
+	events_bytes.extend([1, 2, 3]);
+
+	let events =
+		decoder::decode_events(&meta, &mut &events_bytes[..events_bytes.len() - 3]).expect("can decode events");
+	assert_eq!(events.len(), 2);
+
+	// Bytes left over after the events are an error:
+	let res = decoder::decode_events(&meta, &mut &*events_bytes);
+	assert!(matches!(res, Err(decoder::DecodeError::ExcessBytes(3))));
+
+	// Claiming more events than there are is an error:
+	let mut too_few = Compact(3u32).encode();
+	too_few.extend(&success);
+	too_few.extend(&success);
+	assert!(decoder::decode_events(&meta, &mut &*too_few).is_err());
+}
+
+#[test]
+fn unknown_event_is_an_error() {
+	let meta = metadata();
+
+	let mut events_bytes = Compact(1u32).encode();
+	events_bytes.extend(event_record((1, None), 0, 200, vec![], vec![]));
+
+	let res = decoder::decode_events(&meta, &mut &*events_bytes);
+	assert!(matches!(res, Err(decoder::DecodeError::CannotFindEvent(0, 200))));
+}
+
+#[test]
+fn can_correlate_events_to_extrinsics() {
+	let meta = metadata();
+
+	let alice = AccountKeyring::Alice.to_account_id();
+	let bob = AccountKeyring::Bob.to_account_id();
+	let success = || (1000u64, 0u8, 0u8).encode();
+
+	let records = vec![
+		// Treasury.Spending (12345), emitted during initialization
+		event_record((2, None), 19, 1, 12345u128.encode(), vec![]),
+		// Balances.Transfer (Alice, Bob, 1) followed by System.ExtrinsicSuccess, for extrinsic 1
+		event_record((0, Some(1)), 5, 2, (&alice, &bob, 1u128).encode(), vec![]),
+		event_record((0, Some(1)), 0, 0, success(), vec![]),
+		// System.ExtrinsicSuccess, for extrinsic 0
+		event_record((0, Some(0)), 0, 0, success(), vec![]),
+		// Treasury.Spending (1), emitted during finalization
+		event_record((1, None), 19, 1, 1u128.encode(), vec![]),
+	];
+	let mut events_bytes = Compact(records.len() as u32).encode();
+	events_bytes.extend(records.into_iter().flatten());
+
+	let events = decoder::decode_events(&meta, &mut &*events_bytes).expect("can decode events");
+	let by_extrinsic = decoder::correlate_events(&events);
+
+	assert_eq!(by_extrinsic.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+
+	let names = |idx: u32| by_extrinsic[&idx].iter().map(|e| e.event.ty.name.as_str()).collect::<Vec<_>>();
+	assert_eq!(names(0), vec!["ExtrinsicSuccess"]);
+	assert_eq!(names(1), vec!["Transfer", "ExtrinsicSuccess"]);
+	assert!(by_extrinsic.values().flatten().all(|e| matches!(e.phase, Phase::ApplyExtrinsic(_))));
+}
+
+#[test]
+fn module_errors_are_resolved_to_names() {
+	let meta = metadata();
+
+	// Multisig.MultisigExecuted (approving, timepoint, multisig, call hash, Err(DispatchError::Module { index: 5, error: 2 }))
+	let mut fields = ([1u8; 32], (1u32, 2u32), [2u8; 32], [3u8; 32]).encode();
+	fields.extend([1, 3, 5, 2]);
+	let executed = event_record((0, Some(0)), 30, 2, fields, vec![]);
+
+	let mut events_bytes = Compact(1u32).encode();
+	events_bytes.extend(executed);
+
+	let events = decoder::decode_events(&meta, &mut &*events_bytes).expect("can decode events");
+	let mut result = events[0].event.fields[4].clone();
+
+	// The error is decoded exactly as it was encoded, and only named when asked for:
+	let unresolved = Value::unnamed_variant(
+		"Err",
+		vec![Value::named_variant("Module", vec![("index", Value::u128(5)), ("error", Value::u128(2))])],
+	);
+	assert_eq!(result.clone().remove_context(), unresolved);
+
+	decoder::resolve_module_errors(&meta, &mut result);
+	let expected = Value::unnamed_variant(
+		"Err",
+		vec![Value::named_variant(
+			"Module",
+			vec![
+				("index", Value::u128(5)),
+				("error", Value::u128(2)),
+				(decoder::MODULE_ERROR_PALLET_FIELD, Value::string("Balances")),
+				(decoder::MODULE_ERROR_NAME_FIELD, Value::string("InsufficientBalance")),
+			],
+		)],
+	);
+	assert_eq!(result.remove_context(), expected);
+}
+
+/// Re-encode the V14 Polkadot metadata as V15, declaring the outer event enum but leaving out the event types
+/// of each pallet, so that events can only be found through the outer enum.
+fn v15_metadata_without_pallet_events() -> Metadata {
+	let mut v15 = v14_as_v15(V14_METADATA_POLKADOT_SCALE);
+	for pallet in &mut v15.pallets {
+		pallet.event = None;
+	}
+	Metadata::from_runtime_metadata(RuntimeMetadata::V15(v15)).expect("valid metadata")
+}
+
+#[test]
+fn events_are_found_through_the_v15_outer_event_enum() {
+	let meta = v15_metadata_without_pallet_events();
+	let alice = AccountKeyring::Alice.to_account_id();
+	let bob = AccountKeyring::Bob.to_account_id();
+
+	// Balances.Transfer (Alice, Bob, 12345)
+	let mut events_bytes = Compact(1u32).encode();
+	events_bytes.extend(event_record((0, Some(1)), 5, 2, (&alice, &bob, 12345u128).encode(), vec![]));
+
+	let cursor = &mut &*events_bytes;
+	let events = decoder::decode_events(&meta, cursor).expect("can decode events");
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(events[0].event.pallet_name, "Balances");
+	assert_eq!(&*events[0].event.ty.name, "Transfer");
+	assert_eq!(events[0].event.fields[2].clone().remove_context(), Value::u128(12345));
+
+	// Indexes that the outer enum doesn't know about are still an error:
+	assert!(matches!(
+		decoder::decode_event(&meta, &mut &[5u8, 200][..]),
+		Err(decoder::DecodeError::CannotFindEvent(5, 200))
+	));
+}
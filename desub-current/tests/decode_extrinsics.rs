@@ -16,8 +16,9 @@
 
 use desub_current::{
 	decoder::{self, SignedExtensionWithAdditional},
-	Metadata, Value, ValueDef,
+	Metadata, Value, ValueDef, ValueExt,
 };
+use parity_scale_codec::{Compact, Encode};
 use scale_value::{Composite, Variant};
 
 static V14_METADATA_POLKADOT_SCALE: &[u8] = include_bytes!("data/v14_metadata_polkadot.scale");
@@ -91,6 +92,66 @@ fn balance_transfer_signed() {
 	assert_eq!(ext.call_data.arguments[1].clone().remove_context(), Value::u128(12345));
 }
 
+#[test]
+fn balance_transfer_call_json() {
+	let meta = metadata();
+
+	// Balances.transfer (amount: 12345)
+	let ext_bytes = &mut &*to_bytes("0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+
+	let dest = [
+		28, 189, 45, 67, 83, 10, 68, 112, 90, 208, 136, 175, 49, 62, 24, 248, 11, 83, 239, 22, 179, 97, 119, 205, 75,
+		119, 184, 70, 242, 165, 240, 124,
+	];
+	assert_eq!(
+		ext.call_data.to_call_json(),
+		serde_json::json!({
+			"pallet": "Balances",
+			"call": "transfer",
+			"args": {
+				"dest": { "name": "Id", "values": [[dest]] },
+				"value": 12345
+			}
+		})
+	);
+}
+
+#[test]
+fn redacting_signature_and_address() {
+	let meta = metadata();
+
+	// Balances.transfer (amount: 12345)
+	let ext_bytes = &mut &*to_bytes("0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let mut ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+
+	ext.redact_signature();
+	let signature = &ext.signature.as_ref().expect("extrinsic is signed").signature;
+	assert_eq!(signature.encode(), [&[1u8][..], &[0; 64]].concat(), "still an sr25519 signature, but zeroed");
+
+	// The `dest` argument is a `MultiAddress::Id`, so the account ID is its first field.
+	let dest = &mut ext.call_data.arguments[0];
+	dest.redact(|path| path == "0", Value::with_context(ValueDef::Primitive("<redacted>".into()), 0));
+	let dest = dest.clone().remove_context();
+	assert_eq!(dest, Value::unnamed_variant("Id", vec![Value::string("<redacted>")]));
+	assert_eq!(ext.call_data.arguments[1].clone().remove_context(), Value::u128(12345));
+}
+
+#[test]
+fn signed_extensions_by_name() {
+	let meta = metadata();
+
+	// Balances.transfer (amount: 12345)
+	let ext_bytes = &mut &*to_bytes("0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+	let signature = ext.signature.expect("extrinsic is signed");
+
+	let extensions = signature.extensions_map();
+	assert_eq!(extensions.len(), signature.extensions.len());
+	assert_eq!(extensions["CheckNonce"].clone().remove_context(), singleton_value(Value::u128(0)));
+	assert!(!extensions.contains_key("NotAnExtension"));
+}
+
 #[test]
 fn balance_transfer_all_signed() {
 	let meta = metadata();
@@ -188,6 +249,185 @@ fn technical_committee_execute_unsigned() {
 	assert_eq!(ext.call_data.arguments[1].clone().remove_context(), Value::u128(500));
 }
 
+#[test]
+fn multisig_as_multi_with_opaque_call_unsigned() {
+	let meta = metadata();
+
+	// Balances.transfer (Alice -> Bob, 12345), to be wrapped up as an `OpaqueCall`.
+	let transfer = to_bytes("0x0500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let bob: [u8; 32] =
+		to_bytes("0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48").try_into().unwrap();
+
+	// Multisig.as_multi (threshold: 2, other_signatories: [Bob], maybe_timepoint: None, call: transfer,
+	// store_call: false, max_weight: 1000).
+	let mut call = vec![30u8, 1];
+	call.extend(2u16.encode());
+	call.extend(vec![bob].encode());
+	call.push(0);
+	call.extend(transfer.encode());
+	call.extend((false, 1000u64).encode());
+
+	// An unsigned V4 extrinsic containing the call.
+	let ext = [vec![4u8], call].concat().encode();
+
+	let ext_bytes = &mut &*ext;
+	let ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+
+	assert!(ext_bytes.is_empty(), "No more bytes expected");
+	assert_eq!(ext.call_data.pallet_name, "Multisig");
+	assert_eq!(&*ext.call_data.ty.name, "as_multi");
+	assert_eq!(ext.call_data.arguments.len(), 6);
+	// The opaque argument is left as bytes..
+	assert_eq!(ext.call_data.arguments[3].clone().remove_context(), Value::from_bytes(&transfer));
+
+	// ..but the call inside it is decoded too.
+	assert_eq!(ext.call_data.opaque_calls.keys().copied().collect::<Vec<_>>(), vec![3]);
+	let inner = &ext.call_data.opaque_calls[&3];
+	assert_eq!(inner.pallet_name, "Balances");
+	assert_eq!(&*inner.ty.name, "transfer");
+	assert_eq!(inner.arguments[1].clone().remove_context(), Value::u128(12345));
+	assert_eq!(ext.call_data.nested_calls(), vec![inner]);
+}
+
+#[test]
+fn proxy_proxy_inner_call() {
+	let meta = metadata();
+
+	// Balances.transfer (Alice -> Bob, 12345), to be made on behalf of Bob.
+	let transfer = to_bytes("0x0500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let bob = to_bytes("0x8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48");
+
+	// Proxy.proxy (real: Bob, force_proxy_type: None, call: transfer), wrapped in another Proxy.proxy.
+	let proxy = |call: &[u8]| [&[29u8, 0][..], &bob, &[0], call].concat();
+	let ext = [vec![4u8], proxy(&proxy(&transfer))].concat().encode();
+
+	let ext_bytes = &mut &*ext;
+	let ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+
+	assert!(ext_bytes.is_empty(), "No more bytes expected");
+	assert_eq!(ext.call_data.pallet_name, "Proxy");
+	assert_eq!(&*ext.call_data.ty.name, "proxy");
+	assert_eq!(ext.call_data.arguments.len(), 3);
+
+	// The call argument is decoded as a value as usual, and as call data:
+	assert!(matches!(&ext.call_data.arguments[2].value, ValueDef::Variant(Variant { name, .. }) if name == "Proxy"));
+	assert_eq!(decoder::encode_value(&meta, &ext.call_data.arguments[2]).unwrap(), proxy(&transfer));
+	assert_eq!(ext.call_data.inner_calls.keys().copied().collect::<Vec<_>>(), vec![2]);
+	let nested = ext.call_data.nested_calls();
+	assert_eq!(nested.len(), 1);
+	assert_eq!((&*nested[0].pallet_name, &*nested[0].ty.name), ("Proxy", "proxy"));
+
+	// Calls nested further in are decoded too.
+	let innermost = nested[0].nested_calls();
+	assert_eq!(innermost.len(), 1);
+	assert_eq!((&*innermost[0].pallet_name, &*innermost[0].ty.name), ("Balances", "transfer"));
+	assert_eq!(innermost[0].arguments[1].clone().remove_context(), Value::u128(12345));
+	assert!(innermost[0].nested_calls().is_empty());
+}
+
+#[test]
+fn deeply_nested_calls_are_rejected() {
+	let meta = metadata();
+	let remark = [0u8, 1, 0];
+	let bob = [0u8; 32];
+
+	// Proxy.proxy (real: Bob, force_proxy_type: None, call), with the call passed directly:
+	let proxy = |call: &[u8]| [&[29u8, 0][..], &bob, &[0], call].concat();
+	// Multisig.as_multi (threshold: 2, other_signatories: [], maybe_timepoint: None, call, store_call: false,
+	// max_weight: 1000), with the call wrapped up as an `OpaqueCall`:
+	let as_multi =
+		|call: &[u8]| [&[30u8, 1, 2, 0, 0, 0][..], &call.to_vec().encode(), &(false, 1000u64).encode()].concat();
+
+	for wrap in [&proxy as &dyn Fn(&[u8]) -> Vec<u8>, &as_multi] {
+		let nested = |depth: usize| (0..depth).fold(remark.to_vec(), |call, _| wrap(&call));
+
+		let call = nested(decoder::MAX_CALL_DEPTH);
+		let call_data = decoder::decode_call_data(&meta, &mut &*call).expect("can decode call");
+		let mut depth = 0;
+		let mut innermost = &call_data;
+		while let [inner] = innermost.nested_calls()[..] {
+			(depth, innermost) = (depth + 1, inner);
+		}
+		assert_eq!((depth, &*innermost.ty.name), (decoder::MAX_CALL_DEPTH, "remark"));
+
+		let call = nested(decoder::MAX_CALL_DEPTH + 1);
+		let err = decoder::decode_call_data(&meta, &mut &*call).unwrap_err();
+		assert!(matches!(err, decoder::DecodeError::CallTooDeep(decoder::MAX_CALL_DEPTH)), "{err:?}");
+	}
+}
+
+/// The scheduled call here is a batch of other calls, so the decoded value nests a few levels of the large
+/// runtime call enum inside one another.
+#[test]
+fn scheduler_schedule_named_with_nested_batch() {
+	let meta = metadata();
+
+	// Balances.transfer (Alice -> Bob, 12345), and System.remark(b"hi").
+	let transfer = to_bytes("0x0500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let remark = [vec![0u8, 1], b"hi".to_vec().encode()].concat();
+
+	// Scheduler.schedule_named (id: b"upgrade", when: 1000, maybe_periodic: Some((10, 3)), priority: 1,
+	// call: Utility.batch([remark, transfer])).
+	let batch = [vec![26u8, 0], Compact(2u32).encode(), remark, transfer].concat();
+	let call =
+		[vec![1u8, 2], b"upgrade".to_vec().encode(), (1000u32, Some((10u32, 3u32)), 1u8).encode(), batch].concat();
+	let ext = [vec![4u8], call].concat().encode();
+
+	let ext_bytes = &mut &*ext;
+	let ext = decoder::decode_extrinsic(&meta, ext_bytes).expect("can decode extrinsic");
+
+	assert!(ext_bytes.is_empty(), "No more bytes expected");
+	assert_eq!((&*ext.call_data.pallet_name, &*ext.call_data.ty.name), ("Scheduler", "schedule_named"));
+	assert_args_equal(
+		&ext.call_data.arguments[..4],
+		vec![
+			Value::from_bytes(b"upgrade"),
+			Value::u128(1000),
+			Value::variant(
+				"Some",
+				Composite::Unnamed(vec![Value::unnamed_composite([Value::u128(10), Value::u128(3)])]),
+			),
+			Value::u128(1),
+		],
+	);
+
+	let batch = ext.call_data.nested_calls();
+	assert_eq!(batch.len(), 1);
+	assert_eq!((&*batch[0].pallet_name, &*batch[0].ty.name), ("Utility", "batch"));
+	// A `Vec<Call>` isn't decoded into inner calls, but each of the calls in it is decoded as a value.
+	assert!(batch[0].nested_calls().is_empty());
+	let call_names: Vec<_> = (0..3)
+		.map(|idx| {
+			let call = batch[0].arguments[0].get(&idx.to_string())?;
+			let ValueDef::Variant(Variant { name: pallet, values }) = &call.value else { return None };
+			let ValueDef::Variant(Variant { name: call, .. }) = &values.values().next()?.value else { return None };
+			Some((pallet.as_str(), call.as_str()))
+		})
+		.collect();
+	assert_eq!(call_names, [Some(("System", "remark")), Some(("Balances", "transfer")), None]);
+	assert_eq!(batch[0].arguments[0].get("1.0.value").cloned().map(Value::remove_context), Some(Value::u128(12345)));
+}
+
+#[test]
+fn multisig_as_multi_with_undecodable_opaque_call() {
+	let meta = metadata();
+
+	// Multisig.as_multi as above, but the opaque call refers to a pallet that doesn't exist.
+	let mut call = vec![30u8, 1];
+	call.extend(2u16.encode());
+	call.extend(Vec::<[u8; 32]>::new().encode());
+	call.push(0);
+	call.extend(vec![200u8, 0].encode());
+	call.extend((false, 1000u64).encode());
+
+	let ext_bytes = &mut &*call;
+	let call = decoder::decode_call_data(&meta, ext_bytes).expect("can decode call data");
+
+	assert!(ext_bytes.is_empty(), "No more bytes expected");
+	assert_eq!(call.arguments[3].clone().remove_context(), Value::from_bytes([200u8, 0]));
+	assert!(call.opaque_calls.is_empty());
+}
+
 #[test]
 fn tips_report_awesome_unsigned() {
 	let meta = metadata();
@@ -246,6 +486,129 @@ fn can_decode_multiple_extrinsics_with_extra_bytes() {
 	assert_eq!(extrinsics.len(), 3);
 }
 
+#[test]
+fn can_decode_extrinsics_from_reader() {
+	use std::io::{Cursor, Read};
+
+	let meta = metadata();
+
+	// the same extrinsic repeated 3 times, followed by some extra bytes:
+	let mut extrinsics_bytes = to_bytes("0x0C2004480104080c10142004480104080c10142004480104080c1014");
+	extrinsics_bytes.extend(b"extra bytes!");
+
+	let mut reader = Cursor::new(&extrinsics_bytes);
+	let extrinsics = decoder::decode_extrinsics_from_reader(&meta, &mut reader).unwrap();
+	assert_eq!(extrinsics, decoder::decode_extrinsics(&meta, &mut &*extrinsics_bytes).unwrap());
+	assert_eq!(extrinsics.len(), 3);
+
+	let mut rest = Vec::new();
+	reader.read_to_end(&mut rest).unwrap();
+	assert_eq!(rest, b"extra bytes!");
+
+	// If the data ends part way through the last extrinsic, we get back the ones before it:
+	let truncated = &extrinsics_bytes[..extrinsics_bytes.len() - 14];
+	let (extrinsics, err) = decoder::decode_extrinsics_from_reader(&meta, Cursor::new(truncated)).unwrap_err();
+	assert_eq!(extrinsics.len(), 2);
+	assert!(matches!(err, decoder::DecodeError::EarlyEof(_)), "{err:?}");
+}
+
+#[test]
+fn huge_extrinsic_counts_are_not_trusted() {
+	let meta = metadata();
+
+	// Claim billions of extrinsics, but only provide one:
+	let mut data = parity_scale_codec::Compact(u32::MAX).encode();
+	data.extend(vec![to_bytes("0x04480104080c1014")].encode().into_iter().skip(1));
+
+	// Room isn't reserved for the claimed count, so this doesn't try to allocate enormous amounts of memory;
+	// no more is reserved than the data could hold:
+	let extrinsics = decoder::decode_extrinsics(&meta, &mut &*data).unwrap();
+	assert_eq!(extrinsics.len(), 1);
+	assert!(extrinsics.capacity() <= data.len() / 4, "{} extrinsics reserved", extrinsics.capacity());
+
+	// With a limit, the count is rejected before anything is reserved at all:
+	let (extrinsics, err) = decoder::decode_extrinsics_with_max(&meta, &mut &*data, 10_000).unwrap_err();
+	assert_eq!(extrinsics.capacity(), 0);
+	assert!(
+		matches!(err, decoder::DecodeError::TooManyExtrinsics { count, max: 10_000 } if count == u32::MAX as usize),
+		"{err:?}"
+	);
+
+	// Counts within the limit decode as normal:
+	let data = vec![to_bytes("0x04480104080c1014")].encode();
+	assert_eq!(decoder::decode_extrinsics_with_max(&meta, &mut &*data, 1).unwrap().len(), 1);
+}
+
+#[test]
+fn can_split_extrinsics() {
+	let meta = metadata();
+
+	// A few different unsigned extrinsics (no length prefix yet):
+	let exts = vec![
+		to_bytes("0x04480104080c1014"),
+		to_bytes("0x040000d2040000"),
+		to_bytes("0x042300485468697320706572736f6e20726f636b73211cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07c"),
+	];
+	let extrinsics_bytes = exts.encode();
+
+	let split = decoder::split_extrinsics(&extrinsics_bytes).unwrap();
+	assert_eq!(split, exts.iter().map(|ext| &ext[..]).collect::<Vec<_>>());
+
+	// Each slice can be decoded on its own:
+	let tips = decoder::decode_unwrapped_extrinsic(&meta, &mut &*split[2]).unwrap();
+	assert_eq!(tips.call_data.pallet_name, "Tips");
+
+	// Claiming more extrinsics than there are is an error:
+	let mut too_few = extrinsics_bytes.clone();
+	too_few[0] = parity_scale_codec::Compact(4u32).encode()[0];
+	assert_eq!(decoder::split_extrinsics(&too_few), Err(decoder::ExtrinsicBytesError { index: too_few.len() }));
+}
+
+#[test]
+fn can_count_extrinsics() {
+	let meta = metadata();
+
+	let exts = vec![
+		to_bytes("0x04480104080c1014"),
+		to_bytes("0x040000d2040000"),
+		to_bytes("0x042300485468697320706572736f6e20726f636b73211cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07c"),
+	];
+	let extrinsics_bytes = exts.encode();
+
+	let count = decoder::count_extrinsics(&extrinsics_bytes).unwrap();
+	assert_eq!(count, decoder::decode_extrinsics(&meta, &mut &*extrinsics_bytes).unwrap().len());
+
+	// Only the count is looked at, so it's given back even if the extrinsics after it are missing:
+	assert_eq!(decoder::count_extrinsics(&extrinsics_bytes[..1]), Ok(3));
+	assert_eq!(decoder::count_extrinsics(&[]), Err(decoder::ExtrinsicBytesError { index: 0 }));
+}
+
+#[test]
+fn can_decode_nth_extrinsic() {
+	let meta = metadata();
+
+	// A few different unsigned extrinsics (no length prefix yet):
+	let exts = vec![
+		to_bytes("0x04480104080c1014"),
+		to_bytes("0x040000d2040000"),
+		to_bytes("0x042300485468697320706572736f6e20726f636b73211cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07c"),
+		to_bytes("0x04480104080c1014"),
+	];
+	let extrinsics_bytes = exts.encode();
+
+	let all = decoder::decode_extrinsics(&meta, &mut &*extrinsics_bytes).unwrap();
+	let third = decoder::decode_nth_extrinsic(&meta, &extrinsics_bytes, 2).unwrap();
+
+	assert_eq!(third.call_data.pallet_name, "Tips");
+	assert_eq!(third, all[2]);
+
+	// Asking for an extrinsic that doesn't exist is an error:
+	assert!(matches!(
+		decoder::decode_nth_extrinsic(&meta, &extrinsics_bytes, 4),
+		Err(decoder::DecodeError::ExtrinsicNotFound(4, 4))
+	));
+}
+
 // We can decode the payload that we'd be getting signed, too.
 #[test]
 fn can_decode_signer_payload() {
@@ -279,6 +642,7 @@ fn can_decode_signer_payload() {
 		("PrevalidateAttests", empty_value(), empty_value()),
 	];
 
+	assert_eq!(r.extensions.len(), expected.len());
 	for (actual, expected) in r.extensions.into_iter().zip(expected) {
 		let (name, SignedExtensionWithAdditional { extension, additional }) = actual;
 		let (expected_name, expected_extension, expected_additional) = expected;
@@ -288,3 +652,667 @@ fn can_decode_signer_payload() {
 		assert_eq!(additional.remove_context(), expected_additional);
 	}
 }
+
+// Each signed extension in the metadata describes both its extension and additional signed types, so the two
+// halves of a signer payload can't get out of step with each other. A payload whose additional signed data is cut
+// short is an error, rather than being handed back with fewer additional values than extensions.
+#[test]
+fn signer_payload_extensions_and_additional_signed_stay_aligned() {
+	let meta = metadata();
+	let payload = to_bytes("0x0706b9340000962300000800000091b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c31c81d421f68281950ad2901291603b5e49fc5c872f129e75433f4b55f07ca072");
+
+	let r = decoder::decode_signer_payload(&meta, &mut &*payload).expect("can decode signer payload");
+	let signature_extensions = decoder::decode_signed_extensions(&meta, &mut &payload[2..]).unwrap();
+	let names: Vec<_> = r.extensions.iter().map(|(name, _)| name).collect();
+	assert_eq!(names, signature_extensions.iter().map(|(name, _)| name).collect::<Vec<_>>());
+
+	// Without the block hash that `CheckMortality` commits to:
+	let truncated = &payload[..payload.len() - 32];
+	assert!(decoder::decode_signer_payload(&meta, &mut &*truncated).is_err());
+}
+
+/// Polkadot metadata, but with the `CheckSpecVersion` and `CheckTxVersion` signed extensions aggregated into a
+/// single `CheckVersions` extension, whose types are tuples of the types of the extensions it replaces.
+fn metadata_with_aggregated_signed_extension() -> Metadata {
+	use frame_metadata::{v14::SignedExtensionMetadata, RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+	use scale_info::{form::PortableForm, Path, PortableType, Type, TypeDef, TypeDefTuple};
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let types = &mut meta_v14.types.types;
+
+	let extensions = &mut meta_v14.extrinsic.signed_extensions;
+	assert_eq!(extensions[0].identifier, "CheckSpecVersion");
+	assert_eq!(extensions[1].identifier, "CheckTxVersion");
+	let versions: Vec<_> = extensions.drain(..2).collect();
+
+	let mut tuple_of = |fields: Vec<_>| {
+		let id = types.len() as u32;
+		let ty = Type::<PortableForm> {
+			path: Path::default(),
+			type_params: vec![],
+			type_def: TypeDef::Tuple(TypeDefTuple { fields }),
+			docs: vec![],
+		};
+		types.push(PortableType { id, ty });
+		id.into()
+	};
+	let ty = tuple_of(versions.iter().map(|ext| ext.ty).collect());
+	let additional_signed = tuple_of(versions.iter().map(|ext| ext.additional_signed).collect());
+	extensions.insert(0, SignedExtensionMetadata { identifier: "CheckVersions".into(), ty, additional_signed });
+
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+#[test]
+fn signer_payload_with_tuple_additional_signed() {
+	let meta = metadata_with_aggregated_signed_extension();
+	let signer_payload = &mut &*to_bytes("0x0706b9340000962300000800000091b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c31c81d421f68281950ad2901291603b5e49fc5c872f129e75433f4b55f07ca072");
+
+	let r = decoder::decode_signer_payload(&meta, signer_payload).expect("can decode signer payload");
+	assert_eq!(signer_payload.len(), 0);
+
+	// Expected tuples of name, extension, additional.
+	let expected = vec![
+		(
+			"CheckVersions",
+			Value::unnamed_composite(vec![empty_value(), empty_value()]),
+			Value::unnamed_composite(vec![Value::u128(9110), Value::u128(8)]),
+		),
+		(
+			"CheckGenesis",
+			empty_value(),
+			hash_value(to_bytes("0x91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3")),
+		),
+		(
+			"CheckMortality",
+			singleton_value(Value::variant("Mortal185", Composite::Unnamed(vec![Value::u128(52)]))),
+			hash_value(to_bytes("0x1c81d421f68281950ad2901291603b5e49fc5c872f129e75433f4b55f07ca072")),
+		),
+		("CheckNonce", singleton_value(Value::u128(0)), empty_value()),
+		("CheckWeight", empty_value(), empty_value()),
+		("ChargeTransactionPayment", singleton_value(Value::u128(0)), empty_value()),
+		("PrevalidateAttests", empty_value(), empty_value()),
+	];
+
+	assert_eq!(r.extensions.len(), expected.len());
+	for (actual, expected) in r.extensions.into_iter().zip(expected) {
+		let (name, SignedExtensionWithAdditional { extension, additional }) = actual;
+		let (expected_name, expected_extension, expected_additional) = expected;
+
+		assert_eq!(&*name, expected_name);
+		assert_eq!(extension.remove_context(), expected_extension, "extension of {name}");
+		assert_eq!(additional.remove_context(), expected_additional, "additional signed of {name}");
+	}
+}
+
+#[test]
+fn signer_payload_extensions_by_name() {
+	let meta = metadata();
+	let signer_payload = &mut &*to_bytes("0x0706b9340000962300000800000091b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c31c81d421f68281950ad2901291603b5e49fc5c872f129e75433f4b55f07ca072");
+
+	let r = decoder::decode_signer_payload(&meta, signer_payload).expect("can decode signer payload");
+	let extensions = r.extensions_map();
+
+	let nonce = extensions["CheckNonce"];
+	assert_eq!(nonce.extension.clone().remove_context(), singleton_value(Value::u128(0)));
+	assert_eq!(nonce.additional.clone().remove_context(), empty_value());
+	assert_eq!(extensions["CheckSpecVersion"].additional.clone().remove_context(), Value::u128(9110));
+}
+
+/// Polkadot metadata, with the call information removed from the System pallet.
+fn metadata_without_system_calls() -> Metadata {
+	use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let system = meta_v14.pallets.iter_mut().find(|p| p.name == "System").expect("System pallet exists");
+	system.calls = None;
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+#[test]
+fn call_in_pallet_without_calls_is_reported() {
+	let meta = metadata_without_system_calls();
+	// System.remark(b"hi"):
+	let data = [0u8, 1, 8, b'h', b'i'];
+	let err = decoder::decode_call_data(&meta, &mut &data[..]).expect_err("System has no calls");
+	assert!(matches!(err, decoder::DecodeError::PalletHasNoCalls(0)), "{err:?}");
+}
+
+#[test]
+fn call_index_out_of_range_is_reported() {
+	let meta = metadata();
+	// System has no call at index 200:
+	let err = decoder::decode_call_data(&meta, &mut &[0u8, 200][..]).expect_err("no such call");
+	assert!(matches!(err, decoder::DecodeError::CallIndexOutOfRange(0, 200)), "{err:?}");
+	// And there's no pallet at index 250 at all:
+	let err = decoder::decode_call_data(&meta, &mut &[250u8, 0][..]).expect_err("no such pallet");
+	assert!(matches!(err, decoder::DecodeError::CannotFindCall(250, 0)), "{err:?}");
+}
+
+/// A block body holding the `Timestamp.set` inherent for the given moment, followed by a `System.remark`.
+fn block_with_timestamp(now: u64) -> Vec<u8> {
+	let mut timestamp = vec![4u8, 3, 0];
+	timestamp.extend(parity_scale_codec::Compact(now).encode());
+	let remark = vec![4u8, 0, 1, 8, b'h', b'i'];
+	vec![timestamp, remark].encode()
+}
+
+#[test]
+fn decode_block_hands_back_index_and_hash() {
+	let meta = metadata();
+
+	// The timestamp inherent, a real signed Balances.transfer, and a call to a pallet that doesn't exist.
+	let timestamp = [vec![4u8, 3, 0], parity_scale_codec::Compact(1_634_036_400_000u64).encode()].concat();
+	let transfer = to_bytes("0x8400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let bad = vec![4u8, 250, 0];
+	let block = vec![timestamp.clone(), transfer.clone(), bad.clone()].encode();
+
+	let decoded: Vec<_> = decoder::decode_block(&meta, &block).expect("can split block").collect();
+	assert_eq!(decoded.len(), 3);
+	for (idx, (index, hash, _)) in decoded.iter().enumerate() {
+		let ext = [&timestamp, &transfer, &bad][idx];
+		assert_eq!(*index, idx);
+		assert_eq!(hash.0, sp_core::blake2_256(&ext.encode()));
+	}
+
+	let timestamp = decoded[0].2.as_ref().expect("can decode timestamp");
+	assert_eq!((&*timestamp.call_data.pallet_name, &*timestamp.call_data.ty.name), ("Timestamp", "set"));
+	let transfer = decoded[1].2.as_ref().expect("can decode transfer");
+	assert!(transfer.is_signed());
+	assert_eq!(transfer.call_data.arguments[1].clone().remove_context(), Value::u128(12345));
+	// The bad extrinsic doesn't stop the others from being decoded.
+	assert!(matches!(decoded[2].2, Err(decoder::DecodeError::CannotFindCall(250, 0))));
+
+	// A block that can't be split into extrinsics is rejected up front.
+	let truncated = &block[..block.len() - 1];
+	assert!(matches!(decoder::decode_block(&meta, truncated), Err(decoder::DecodeError::UnexpectedExtrinsicsShape(_))));
+
+	// Bytes left over after the extrinsics are handed back as a final error.
+	let mut trailing = block.clone();
+	trailing.extend([1, 2, 3]);
+	let decoded: Vec<_> = decoder::decode_block(&meta, &trailing).expect("can split block").collect();
+	assert_eq!(decoded.len(), 4);
+	assert!(decoded[..3].iter().zip(0..).all(|((index, _, _), idx)| *index == idx));
+	let (index, hash, err) = &decoded[3];
+	assert_eq!((*index, hash.0), (3, sp_core::blake2_256(&[1, 2, 3])));
+	assert!(matches!(err, Err(decoder::DecodeError::ExcessBytes(3))));
+}
+
+#[test]
+fn can_decode_timestamp_from_block() {
+	let meta = metadata();
+
+	let now = 1_634_036_400_000;
+	let block = block_with_timestamp(now);
+	let exts = decoder::decode_extrinsics(&meta, &mut &*block).expect("can decode extrinsics");
+	assert_eq!(decoder::decode_timestamp_from_block(&exts), Some(now));
+	// The remark alone doesn't set a timestamp:
+	assert_eq!(decoder::decode_timestamp_from_block(&exts[1..]), None);
+
+	#[cfg(feature = "chrono")]
+	assert_eq!(
+		decoder::decode_datetime_from_block(&exts).map(|t| t.to_rfc3339()),
+		Some("2021-10-12T11:00:00+00:00".to_string())
+	);
+}
+
+/// Polkadot metadata, altered so that extrinsics are signed by a 20 byte `AccountId20`, as on Moonbeam
+/// and other EVM compatible chains.
+fn metadata_with_account_id20() -> Metadata {
+	use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+	use scale_info::{
+		form::PortableForm, Field, Path, PortableType, Type, TypeDef, TypeDefArray, TypeDefComposite, TypeDefPrimitive,
+	};
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let types = &mut meta_v14.types.types;
+
+	let u8_id = types
+		.iter()
+		.find(|t| matches!(t.ty.type_def, TypeDef::Primitive(TypeDefPrimitive::U8)))
+		.expect("u8 type exists")
+		.id;
+	let array_id = types.len() as u32;
+	let account_id = array_id + 1;
+	let array = Type::<PortableForm> {
+		path: Path::default(),
+		type_params: vec![],
+		type_def: TypeDef::Array(TypeDefArray { len: 20, type_param: u8_id.into() }),
+		docs: vec![],
+	};
+	let account = Type::<PortableForm> {
+		path: Path { segments: vec!["account".into(), "AccountId20".into()] },
+		type_params: vec![],
+		type_def: TypeDef::Composite(TypeDefComposite {
+			fields: vec![Field { name: None, ty: array_id.into(), type_name: Some("[u8; 20]".into()), docs: vec![] }],
+		}),
+		docs: vec![],
+	};
+	types.push(PortableType { id: array_id, ty: array });
+	types.push(PortableType { id: account_id, ty: account });
+
+	let extrinsic_ty = meta_v14.extrinsic.ty.id as usize;
+	let address = types[extrinsic_ty].ty.type_params.iter_mut().find(|p| p.name == "Address").expect("Address param");
+	address.ty = Some(account_id.into());
+
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+#[test]
+fn account_id20_signed_extrinsic() {
+	use desub_current::metadata::SignerFormat;
+	use sp_core::ecdsa;
+	use sp_runtime::{MultiAddress, MultiSignature};
+
+	let meta = metadata_with_account_id20();
+	assert_eq!(meta.extrinsic().signer_format(), SignerFormat::Ethereum);
+	assert_eq!(metadata().extrinsic().signer_format(), SignerFormat::Substrate);
+
+	// Signed (0x84), from a 20 byte address with a 65 byte ECDSA signature, an immortal era,
+	// nonce 5 and no tip, calling System.remark(b"hi"):
+	let mut ext = vec![0x84];
+	ext.extend([0xaa; 20]);
+	ext.extend([0xbb; 65]);
+	ext.extend([0, 5 << 2, 0]);
+	ext.extend([0, 1, 8, b'h', b'i']);
+	let ext = ext.encode();
+
+	let cursor = &mut &*ext;
+	let ext = decoder::decode_extrinsic(&meta, cursor).expect("can decode extrinsic");
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(&*ext.call_data.ty.name, "remark");
+
+	let signature = ext.signature.expect("extrinsic is signed");
+	assert_eq!(signature.address, MultiAddress::Address20([0xaa; 20]));
+	assert_eq!(signature.signature, MultiSignature::Ecdsa(ecdsa::Signature::from_raw([0xbb; 65])));
+	assert_eq!(signature.extensions_map()["CheckNonce"].clone().remove_context(), singleton_value(Value::u128(5)));
+}
+
+/// Polkadot metadata, altered so that extrinsics are signed by a bare `AccountId32` rather than a `MultiAddress`.
+fn metadata_with_account_id32_address() -> Metadata {
+	use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let types = &mut meta_v14.types.types;
+
+	let account_id =
+		types.iter().find(|t| t.ty.path.ident().as_deref() == Some("AccountId32")).expect("AccountId32").id;
+	let extrinsic_ty = meta_v14.extrinsic.ty.id as usize;
+	let address = types[extrinsic_ty].ty.type_params.iter_mut().find(|p| p.name == "Address").expect("Address param");
+	address.ty = Some(account_id.into());
+
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+#[test]
+fn account_id32_signed_extrinsic() {
+	use desub_current::metadata::SignerFormat;
+	use sp_core::sr25519;
+	use sp_runtime::{AccountId32, MultiAddress, MultiSignature};
+
+	let meta = metadata_with_account_id32_address();
+	assert_eq!(meta.extrinsic().signer_format(), SignerFormat::AccountId32);
+
+	// Signed (0x84), from a bare 32 byte address with an sr25519 signature, an immortal era,
+	// nonce 5 and no tip, calling System.remark(b"hi"):
+	let mut ext = vec![0x84];
+	ext.extend([0xaa; 32]);
+	ext.push(1);
+	ext.extend([0xbb; 64]);
+	ext.extend([0, 5 << 2, 0]);
+	ext.extend([0, 1, 8, b'h', b'i']);
+	let ext = ext.encode();
+
+	let cursor = &mut &*ext;
+	let ext = decoder::decode_extrinsic(&meta, cursor).expect("can decode extrinsic");
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(&*ext.call_data.ty.name, "remark");
+
+	let signature = ext.signature.expect("extrinsic is signed");
+	assert_eq!(signature.address, MultiAddress::Id(AccountId32::new([0xaa; 32])));
+	assert_eq!(signature.signature, MultiSignature::Sr25519(sr25519::Signature::from_raw([0xbb; 64])));
+	assert_eq!(signature.extensions_map()["CheckNonce"].clone().remove_context(), singleton_value(Value::u128(5)));
+}
+
+#[test]
+fn optional_signature_extrinsics() {
+	let mut meta = metadata();
+	meta.set_optional_signature(true);
+	assert!(meta.extrinsic().optional_signature());
+
+	// Signed (0x84), with `Some` signature from a MultiAddress::Id and an sr25519 signature, an immortal era,
+	// nonce 5 and no tip, calling System.remark(b"hi"):
+	let mut some = vec![0x84, 1, 0];
+	some.extend([0xaa; 32]);
+	some.push(1);
+	some.extend([0xbb; 64]);
+	some.extend([0, 5 << 2, 0]);
+	some.extend([0, 1, 8, b'h', b'i']);
+	let ext = decoder::decode_extrinsic(&meta, &mut &*some.encode()).expect("can decode extrinsic");
+	assert!(ext.is_signed());
+	assert_eq!(
+		ext.signature.unwrap().extensions_map()["CheckNonce"].clone().remove_context(),
+		singleton_value(Value::u128(5))
+	);
+
+	// Signed, but with a `None` signature, which is decoded as unsigned:
+	let none = vec![0x84, 0, 0, 1, 8, b'h', b'i'];
+	let cursor = &mut &*none;
+	let ext = decoder::decode_unwrapped_extrinsic(&meta, cursor).expect("can decode extrinsic");
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert!(!ext.is_signed());
+	assert_eq!(&*ext.call_data.ty.name, "remark");
+
+	// Unsigned extrinsics have no signature to wrap:
+	assert!(!decoder::decode_unwrapped_extrinsic(&meta, &mut &[4u8, 0, 1, 8, b'h', b'i'][..]).unwrap().is_signed());
+	assert!(matches!(
+		decoder::decode_unwrapped_extrinsic(&meta, &mut &[0x84, 2, 0, 1, 8, b'h', b'i'][..]),
+		Err(decoder::DecodeError::CodecError(_))
+	));
+}
+
+#[test]
+fn can_classify_extrinsics() {
+	let meta = metadata();
+
+	// Balances.transfer (amount: 12345)
+	let transfer = &mut &*to_bytes("0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0");
+	let transfer = decoder::decode_extrinsic(&meta, transfer).expect("can decode extrinsic");
+	assert!(transfer.is_signed());
+	assert!(!transfer.is_inherent(&meta));
+
+	let block = block_with_timestamp(1_634_036_400_000);
+	let exts = decoder::decode_extrinsics(&meta, &mut &*block).expect("can decode extrinsics");
+	let (timestamp, remark) = (&exts[0], &exts[1]);
+	assert!(!timestamp.is_signed());
+	assert!(timestamp.is_inherent(&meta));
+	// Unsigned, but not an inherent:
+	assert!(!remark.is_signed());
+	assert!(!remark.is_inherent(&meta));
+
+	// Inherents are recognised by the pallet providing them, whatever the runtime calls it:
+	let renamed = metadata_with_renamed_timestamp_pallet();
+	let exts = decoder::decode_extrinsics(&renamed, &mut &*block).expect("can decode extrinsics");
+	assert_eq!(exts[0].call_data.pallet_name, "Time");
+	assert!(exts[0].is_inherent(&renamed));
+	assert!(!exts[1].is_inherent(&renamed));
+}
+
+/// Polkadot metadata, altered so that the `Timestamp` pallet is called `Time`.
+fn metadata_with_renamed_timestamp_pallet() -> Metadata {
+	use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let timestamp = meta_v14.pallets.iter_mut().find(|p| p.name == "Timestamp").expect("Timestamp pallet exists");
+	timestamp.name = "Time".into();
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+/// Polkadot metadata, altered so that the argument of `System.remark` refers to a type ID that doesn't exist.
+fn metadata_with_missing_remark_type() -> Metadata {
+	use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::Decode;
+	use scale_info::TypeDef;
+
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let system = meta_v14.pallets.iter().find(|p| p.name == "System").expect("System pallet exists");
+	let calls_ty = system.calls.as_ref().expect("System has calls").ty.id as usize;
+	let TypeDef::Variant(calls) = &mut meta_v14.types.types[calls_ty].ty.type_def else {
+		panic!("calls are a variant")
+	};
+	let remark = calls.variants.iter_mut().find(|v| v.name == "remark").expect("remark call exists");
+	remark.fields[0].ty = u32::MAX.into();
+	Metadata::from_runtime_metadata(meta.1).expect("valid metadata")
+}
+
+#[test]
+fn call_with_missing_type_can_be_decoded_leniently() {
+	let meta = metadata_with_missing_remark_type();
+	// System.remark(b"hi"):
+	let data = [0u8, 1, 8, b'h', b'i'];
+
+	assert!(decoder::decode_call_data(&meta, &mut &data[..]).is_err());
+
+	let cursor = &mut &data[..];
+	let call = decoder::decode_call_data_lenient(&meta, cursor).expect("can decode leniently");
+	assert!(cursor.is_empty());
+	assert_eq!(call.pallet_name, "System");
+	assert_eq!(&*call.ty.name, "remark");
+	assert!(call.arguments.is_empty());
+	assert_eq!(call.opaque_remainder, Some(vec![8, b'h', b'i']));
+
+	// Calls whose types are all present decode as normal:
+	let meta = metadata();
+	let call = decoder::decode_call_data_lenient(&meta, &mut &data[..]).expect("can decode");
+	assert_eq!(call.arguments.len(), 1);
+	assert_eq!(call.opaque_remainder, None);
+}
+
+#[test]
+fn extrinsic_version_is_read_per_extrinsic() {
+	let meta = metadata();
+	// System.remark(b"hi") as an unsigned V4 extrinsic, and as a V5 "bare" extrinsic:
+	let v4 = vec![4u8, 0, 1, 8, b'h', b'i'];
+	let v5 = vec![5u8, 0, 1, 8, b'h', b'i'];
+	let data = vec![v4.clone(), v5.clone(), v4].encode();
+
+	let extrinsics = decoder::split_extrinsics(&data).unwrap();
+	assert!(decoder::decode_unwrapped_extrinsic(&meta, &mut &*extrinsics[0]).is_ok());
+	assert!(matches!(
+		decoder::decode_unwrapped_extrinsic(&meta, &mut &*extrinsics[1]),
+		Err(decoder::DecodeError::CannotDecodeExtrinsicVersion(5))
+	));
+	assert!(decoder::decode_unwrapped_extrinsic(&meta, &mut &*extrinsics[2]).is_ok());
+
+	// Decoding the block hands back the extrinsics before the one we can't decode:
+	let (decoded, err) = decoder::decode_extrinsics(&meta, &mut &*data).unwrap_err();
+	assert_eq!(decoded.len(), 1);
+	assert_eq!(&*decoded[0].call_data.ty.name, "remark");
+	assert!(matches!(err, decoder::DecodeError::CannotDecodeExtrinsicVersion(5)));
+}
+
+/// The `MembershipProof` given as the `key_owner_proof` of equivocation reports.
+fn key_owner_proof() -> Vec<u8> {
+	(7u32, vec![vec![1u8; 40], vec![2u8; 3]], 297u32).encode()
+}
+
+#[test]
+fn babe_equivocation_report() {
+	use sp_runtime::{generic, traits::BlakeTwo256, Digest, DigestItem};
+
+	let meta = metadata();
+	let header = |n: u8| generic::Header::<u32, BlakeTwo256> {
+		parent_hash: [n; 32].into(),
+		number: 100,
+		state_root: [2; 32].into(),
+		extrinsics_root: [3; 32].into(),
+		digest: Digest {
+			logs: vec![DigestItem::PreRuntime(*b"BABE", vec![n; 16]), DigestItem::Seal(*b"BABE", vec![n; 64])],
+		},
+	};
+
+	// Babe.report_equivocation_unsigned, as an unsigned extrinsic:
+	let mut ext = vec![4u8, 2, 1];
+	([5u8; 32], 42u64, header(1), header(2)).encode_to(&mut ext);
+	ext.extend(key_owner_proof());
+	let ext_bytes = ext.encode();
+
+	let cursor = &mut &*ext_bytes;
+	let ext = decoder::decode_extrinsic(&meta, cursor).expect("can decode extrinsic");
+	assert!(cursor.is_empty(), "No more bytes expected");
+
+	let call = ext.call_data.to_call_json();
+	assert_eq!(call["pallet"], "Babe");
+	assert_eq!(call["call"], "report_equivocation_unsigned");
+	let proof = &call["args"]["equivocation_proof"];
+	assert_eq!(proof["slot"], serde_json::json!([42]));
+	assert_eq!(proof["first_header"]["number"], 100);
+	assert_eq!(proof["second_header"]["digest"]["logs"].as_array().unwrap().len(), 2);
+	assert_eq!(call["args"]["key_owner_proof"]["validator_count"], 297);
+}
+
+#[test]
+fn grandpa_equivocation_report() {
+	let meta = metadata();
+	// A precommit for some block, and the signature on it.
+	let precommit = |n: u8| (([n; 32], 1000u32), [n; 64]);
+
+	// Grandpa.report_equivocation_unsigned, as an unsigned extrinsic. The proof is
+	// `EquivocationProof { set_id, equivocation: Equivocation::Precommit(..) }`:
+	let mut ext = vec![4u8, 11, 1];
+	(3u64, 1u8, 9u64, [5u8; 32], precommit(1), precommit(2)).encode_to(&mut ext);
+	ext.extend(key_owner_proof());
+	let ext_bytes = ext.encode();
+
+	let cursor = &mut &*ext_bytes;
+	let ext = decoder::decode_extrinsic(&meta, cursor).expect("can decode extrinsic");
+	assert!(cursor.is_empty(), "No more bytes expected");
+
+	let call = ext.call_data.to_call_json();
+	assert_eq!(call["pallet"], "Grandpa");
+	assert_eq!(call["call"], "report_equivocation_unsigned");
+	let proof = &call["args"]["equivocation_proof"];
+	assert_eq!(proof["set_id"], 3);
+	assert_eq!(proof["equivocation"]["name"], "Precommit");
+	assert_eq!(proof["equivocation"]["values"][0]["round_number"], 9);
+	assert_eq!(call["args"]["key_owner_proof"]["session"], 7);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn observer_counts_decoded_extrinsics_and_errors() {
+	use decoder::{CallData, DecodeError, DecodeObserver, Extrinsic};
+	use std::cell::{Cell, RefCell};
+	use std::collections::BTreeMap;
+
+	#[derive(Default)]
+	struct Counter {
+		extrinsics: Cell<usize>,
+		calls: Cell<usize>,
+		bytes: Cell<usize>,
+		call_bytes: Cell<usize>,
+		errors: RefCell<BTreeMap<&'static str, usize>>,
+	}
+
+	impl DecodeObserver for Counter {
+		fn extrinsic_decoded(&self, _extrinsic: &Extrinsic<'_>, bytes: usize) {
+			self.extrinsics.set(self.extrinsics.get() + 1);
+			self.bytes.set(self.bytes.get() + bytes);
+		}
+
+		fn call_decoded(&self, _call: &CallData<'_>, bytes: usize) {
+			self.calls.set(self.calls.get() + 1);
+			self.call_bytes.set(self.call_bytes.get() + bytes);
+		}
+
+		fn decode_failed(&self, error: &DecodeError) {
+			let kind = match error {
+				DecodeError::CannotFindCall(..) => "CannotFindCall",
+				DecodeError::TooManyExtrinsics { .. } | DecodeError::UnexpectedExtrinsicsShape(_) => "BadShape",
+				_ => "Other",
+			};
+			*self.errors.borrow_mut().entry(kind).or_default() += 1;
+		}
+	}
+
+	let meta = metadata();
+	let counter = Counter::default();
+
+	// Bytes are counted per extrinsic, so the count and the length prefix of each aren't included. The call of
+	// each extrinsic is counted too, which leaves out the version byte of each.
+	let block = block_with_timestamp(1_634_036_400_000);
+	let exts = decoder::decode_extrinsics_observed(&meta, &mut &*block, &counter).expect("can decode extrinsics");
+	assert_eq!(exts.len(), 2);
+	assert_eq!((counter.extrinsics.get(), counter.bytes.get()), (2, block.len() - 3));
+	assert_eq!((counter.calls.get(), counter.call_bytes.get()), (2, block.len() - 5));
+
+	// A call to a pallet that doesn't exist fails part way through the block.
+	let bad_block = vec![vec![4u8, 0, 1, 0], vec![4, 250, 0]].encode();
+	let (exts, _) = decoder::decode_extrinsics_observed(&meta, &mut &*bad_block, &counter).unwrap_err();
+	assert_eq!(exts.len(), 1);
+	assert_eq!((counter.extrinsics.get(), counter.calls.get()), (3, 3));
+
+	let remark = [0u8, 1, 8, b'h', b'i'];
+	decoder::decode_call_data_observed(&meta, &mut &remark[..], &counter).expect("can decode call");
+	decoder::decode_call_data_observed(&meta, &mut &[250u8, 0][..], &counter).expect_err("no such pallet");
+	assert_eq!(counter.calls.get(), 4);
+	assert_eq!(counter.bytes.get(), block.len() - 3 + 4);
+	assert_eq!(counter.call_bytes.get(), block.len() - 5 + 3 + remark.len());
+	assert_eq!(*counter.errors.borrow(), BTreeMap::from([("CannotFindCall", 2)]));
+}
+
+#[test]
+fn extrinsics_can_be_encoded_again() {
+	let meta = metadata();
+
+	for ext_hex in [
+		// Signed Balances.transfer and Balances.transfer_all:
+		"0x31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0",
+		"0x2d028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d01f0431ffe387134b4f84d92d3c3f1ac18c0f42237ad7dbd455bb0cf8a18efb1760528f052b2219ad1601d9a4719e1a446cf307bf6d7e9c56175bfe6e7bf8cbe81450304000504001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07c00",
+		// Unsigned Auctions.bid and TechnicalCommittee.execute:
+		"0x2004480104080c1014",
+		"0xa80410010500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0d107",
+	] {
+		let ext_bytes = to_bytes(ext_hex);
+		let ext = decoder::decode_extrinsic(&meta, &mut &*ext_bytes).expect("can decode extrinsic");
+		assert_eq!(decoder::encode_extrinsic(&meta, &ext).expect("can encode extrinsic"), ext_bytes);
+	}
+
+	// Modifying the decoded extrinsic modifies the bytes it is encoded to. Here, the fourth argument of
+	// Auctions.bid goes from 4 to 7.
+	let ext_bytes = to_bytes("0x2004480104080c1014");
+	let mut ext = decoder::decode_extrinsic(&meta, &mut &*ext_bytes).expect("can decode extrinsic");
+	ext.call_data.arguments[3].value = ValueDef::Primitive(scale_value::Primitive::u128(7));
+	let encoded = decoder::encode_extrinsic(&meta, &ext).expect("can encode extrinsic");
+	assert_eq!(encoded, to_bytes("0x2004480104080c1c14"));
+
+	// Adding or removing an argument is an error rather than being silently ignored:
+	let mut extra = ext.clone();
+	extra.call_data.arguments.push(extra.call_data.arguments[0].clone());
+	let mut missing = ext;
+	missing.call_data.arguments.pop();
+	for (ext, got) in [(extra, 6), (missing, 4)] {
+		assert!(matches!(
+			decoder::encode_extrinsic(&meta, &ext),
+			Err(decoder::EncodeExtrinsicError::WrongArgumentCount { call, expected: 5, got: n }) if call == "bid" && n == got
+		));
+	}
+}
+
+#[test]
+fn votes_can_be_encoded_again() {
+	let meta = metadata();
+
+	// Democracy.vote(5, Standard { vote: aye with Locked1x conviction, balance: 1000 }). The vote is packed into a
+	// single byte, which is left as it is when decoding so that it can be encoded again.
+	let mut call = vec![4u8, 14, 2];
+	call.extend(parity_scale_codec::Compact(5u32).encode());
+	call.extend([0, 0b1000_0001]);
+	call.extend(1000u128.encode());
+	let ext_bytes = call.encode();
+
+	let mut ext = decoder::decode_extrinsic(&meta, &mut &*ext_bytes).expect("can decode extrinsic");
+	assert_eq!((&*ext.call_data.pallet_name, &*ext.call_data.ty.name), ("Democracy", "vote"));
+	assert_eq!(decoder::encode_extrinsic(&meta, &ext).expect("can encode extrinsic"), ext_bytes);
+
+	// Changing the vote byte to nay (with the same conviction) changes the encoded extrinsic to match.
+	let ValueDef::Variant(standard) = &mut ext.call_data.arguments[1].value else { panic!("AccountVote is a variant") };
+	let scale_value::Composite::Named(fields) = &mut standard.values else { panic!("Standard has named fields") };
+	let vote = &mut fields[0].1;
+	*vote = decoder::decode_value_by_id(&meta, vote.context, &mut &[0b0000_0001u8][..]).expect("can decode vote");
+	let mut expected = ext_bytes.clone();
+	let vote_idx = expected.len() - 17;
+	expected[vote_idx] = 0b0000_0001;
+	assert_eq!(decoder::encode_extrinsic(&meta, &ext).expect("can encode extrinsic"), expected);
+}
@@ -15,10 +15,11 @@
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
 use desub_current::{
-	decoder::{self, StorageHasher},
-	Metadata, Value,
+	decoder::{self, StorageDecodeError, StorageHasher},
+	Metadata, Value, ValueExt,
 };
 use parity_scale_codec::Encode;
+use sp_core::hashing::{twox_128, twox_64};
 
 static V14_METADATA_POLKADOT_SCALE: &[u8] = include_bytes!("data/v14_metadata_polkadot.scale");
 
@@ -52,7 +53,7 @@ macro_rules! bytes {
 #[test]
 fn timestamp_now() {
 	let meta = metadata();
-	let storage = decoder::decode_storage(&meta);
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
 
 	// Timestamp.Now(): u64
 	bytes!(storage_key = "0xf0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb");
@@ -68,11 +69,67 @@ fn timestamp_now() {
 	assert_eq!(val.remove_context(), Value::u128(123));
 }
 
+// Decode a value for a known storage entry, without a key.
+#[test]
+fn system_number_value_by_name() {
+	let meta = metadata();
+
+	// System.Number(): u32
+	let bytes = 1234u32.encode();
+	let cursor = &mut &*bytes;
+	let val = decoder::decode_storage_value_by_name(&meta, "System", "Number", cursor).unwrap();
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(val.remove_context(), Value::u128(1234));
+
+	// Map entries decode their value type, too. System.BlockHash(u32): H256
+	let val = decoder::decode_storage_value_by_name(&meta, "System", "BlockHash", &mut &[7u8; 32][..]).unwrap();
+	assert_eq!(val.remove_context(), Value::unnamed_composite(vec![Value::from_bytes([7u8; 32])]));
+
+	assert!(matches!(
+		decoder::decode_storage_value_by_name(&meta, "System", "Nope", &mut &*bytes),
+		Err(decoder::DecodeError::CannotFindStorageEntry(..))
+	));
+}
+
+// Some runtimes have stored values compactly encoded, for instance `Balances.TotalIssuance` as a `Compact<u128>`.
+#[test]
+fn compact_storage_value() {
+	use frame_metadata::{v14::StorageEntryType, RuntimeMetadata, RuntimeMetadataPrefixed};
+	use parity_scale_codec::{Compact, Decode};
+	use scale_info::{form::PortableForm, PortableType, Type, TypeDef, TypeDefCompact};
+
+	// Polkadot metadata, altered so that `Balances.TotalIssuance` is stored as a `Compact<u128>`.
+	let mut meta = RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let RuntimeMetadata::V14(meta_v14) = &mut meta.1 else { panic!("expected V14 metadata") };
+	let balances = meta_v14.pallets.iter_mut().find(|p| p.name == "Balances").expect("Balances pallet");
+	let entry = balances.storage.as_mut().unwrap().entries.iter_mut().find(|e| e.name == "TotalIssuance").unwrap();
+	let StorageEntryType::Plain(u128_ty) = entry.ty else { panic!("expected a plain storage entry") };
+	let types = &mut meta_v14.types.types;
+	let id = types.len() as u32;
+	let ty = Type::<PortableForm> {
+		path: Default::default(),
+		type_params: vec![],
+		type_def: TypeDef::Compact(TypeDefCompact { type_param: u128_ty }),
+		docs: vec![],
+	};
+	types.push(PortableType { id, ty });
+	entry.ty = StorageEntryType::Plain(id.into());
+	let meta = Metadata::from_runtime_metadata(meta.1).expect("valid metadata");
+
+	let total_issuance = 12_345_678_901_234_567_890u128;
+	let bytes = Compact(total_issuance).encode();
+	assert!(bytes.len() < 16, "compact encoding is smaller than the full width");
+	let cursor = &mut &*bytes;
+	let val = decoder::decode_storage_value_by_name(&meta, "Balances", "TotalIssuance", cursor).unwrap();
+	assert!(cursor.is_empty(), "No more bytes expected");
+	assert_eq!(val.remove_context(), Value::u128(total_issuance));
+}
+
 // A simple map lookup with an Identity hash (ie just the key itself)
 #[test]
 fn democracy_blacklist() {
 	let meta = metadata();
-	let storage = decoder::decode_storage(&meta);
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
 
 	// Democracy.Blacklist([1u8; 32]: H256): ..
 	bytes!(storage_key = "0xf2794c22e353e9a839f12faab03a911bb7612c99e31defd01cd5a28e9967e2080101010101010101010101010101010101010101010101010101010101010101");
@@ -98,7 +155,7 @@ fn democracy_blacklist() {
 #[test]
 fn system_blockhash() {
 	let meta = metadata();
-	let storage = decoder::decode_storage(&meta);
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
 
 	// System.BlockHash(1000): [u8; 32]
 	bytes!(storage_key = "0x26aa394eea5630e07c48ae0c9558cef7a44704b568d21667356a5a050c118746b6ff6f7d467b87a9e8030000");
@@ -128,7 +185,7 @@ fn system_blockhash() {
 #[test]
 fn balances_account() {
 	let meta = metadata();
-	let storage = decoder::decode_storage(&meta);
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
 
 	// Balances.Account(BOB: AccountId32): PalletBalancesAccountData
 	bytes!(storage_key = "0xc2261276cc9d1f8598ea4b6a74b15c2fb99d880ec681799c0cf30e8886371da94f9aea1afa791265fae359272badc1cf8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48");
@@ -151,7 +208,7 @@ fn balances_account() {
 #[test]
 fn imonline_authoredblocks() {
 	let meta = metadata();
-	let storage = decoder::decode_storage(&meta);
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
 
 	// ImOnline.AuthoredBlocks(1234: u32, BOB:AccountId32): u32
 	bytes!(storage_key = "0x2b06af9719ac64d755623cda8ddd9b94b1c371ded9e9c565e89ba783c4d5f5f9548491cbfe725727d2040000a647e755c30521d38eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48");
@@ -176,3 +233,250 @@ fn imonline_authoredblocks() {
 	let val = decoder::decode_value_by_id(&meta, entry.ty, &mut &*bytes).unwrap();
 	assert_eq!(val.remove_context(), Value::u128(5678));
 }
+
+// A map storage entry keyed by a single Twox64Concat hash of a tuple containing a `Vec`.
+#[test]
+fn session_keyowner() {
+	let meta = metadata();
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
+
+	// Session.KeyOwner((KeyTypeId(*b"gran"), vec![1, 2, 3]): (KeyTypeId, Vec<u8>)): AccountId32
+	let key = (*b"gran", vec![1u8, 2, 3]).encode();
+	let mut storage_key = [twox_128(b"Session"), twox_128(b"KeyOwner")].concat();
+	storage_key.extend(twox_64(&key));
+	storage_key.extend(&key);
+	let storage_key = &mut &*storage_key;
+
+	let entry = storage.decode_key(&meta, storage_key).expect("can decode storage");
+	assert!(storage_key.is_empty(), "No more bytes expected");
+	assert_eq!(entry.prefix, "Session");
+	assert_eq!(entry.name, "KeyOwner");
+
+	// The tuple is decoded in full as the one key:
+	let keys = entry.details.map_keys();
+	assert_eq!(keys.len(), 1);
+	assert_eq!(keys[0].bytes.len(), 8 + key.len());
+	assert_hasher_eq!(
+		keys[0].hasher,
+		StorageHasher::Twox64Concat,
+		Value::unnamed_composite(vec![
+			Value::unnamed_composite(vec![Value::from_bytes(b"gran")]),
+			Value::from_bytes([1u8, 2, 3]),
+		])
+	);
+}
+
+// A map storage entry whose value is a tuple of a `BoundedVec` of structs holding enums, and a balance.
+#[test]
+fn proxy_proxies() {
+	let meta = metadata();
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
+
+	// Proxy.Proxies(ALICE: AccountId32): (BoundedVec<ProxyDefinition<AccountId32, ProxyType, u32>>, u128)
+	let alice = sp_keyring::AccountKeyring::Alice.to_account_id();
+	let bob = sp_keyring::AccountKeyring::Bob.to_account_id();
+	let mut storage_key = [twox_128(b"Proxy"), twox_128(b"Proxies")].concat();
+	storage_key.extend(twox_64(alice.as_ref()));
+	storage_key.extend(alice.as_ref() as &[u8]);
+	let storage_key = &mut &*storage_key;
+
+	let entry = storage.decode_key(&meta, storage_key).expect("can decode storage");
+	assert!(storage_key.is_empty(), "No more bytes expected");
+	assert_eq!((&*entry.prefix, &*entry.name), ("Proxy", "Proxies"));
+	let keys = entry.details.map_keys();
+	assert_eq!(keys.len(), 1);
+	assert_hasher_eq!(keys[0].hasher, StorageHasher::Twox64Concat, account_id_to_value(&alice));
+
+	// Bob may make any call (ProxyType::Any) for Alice straight away, and staking calls (ProxyType::Staking)
+	// after a delay of 100 blocks. Alice has reserved a deposit of 1000 for this.
+	let proxies = vec![(bob.clone(), 0u8, 0u32), (bob.clone(), 3u8, 100u32)];
+	let bytes = (proxies, 1000u128).encode();
+	let cursor = &mut &*bytes;
+	let val = decoder::decode_value_by_id(&meta, entry.ty, cursor).unwrap();
+	assert!(cursor.is_empty(), "No more bytes expected");
+
+	let proxy = |proxy_type: &str, delay: u128| {
+		Value::named_composite(vec![
+			("delegate", account_id_to_value(&bob)),
+			("proxy_type", Value::unnamed_variant(proxy_type, vec![])),
+			("delay", Value::u128(delay)),
+		])
+	};
+	// The `BoundedVec` is presented as the sequence that it wraps.
+	assert_eq!(
+		val.remove_context(),
+		Value::unnamed_composite(vec![
+			Value::unnamed_composite(vec![proxy("Any", 0), proxy("Staking", 100)]),
+			Value::u128(1000),
+		])
+	);
+}
+
+// A storage key that ends partway through the hash of a map key is an error.
+#[test]
+fn truncated_map_key() {
+	let meta = metadata();
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
+
+	// System.BlockHash(1000), with only half of the Twox64Concat hash present.
+	bytes!(storage_key = "0x26aa394eea5630e07c48ae0c9558cef7a44704b568d21667356a5a050c118746b6ff6f7d");
+
+	assert!(matches!(
+		storage.decode_key(&meta, storage_key),
+		Err(decoder::StorageDecodeError::NotEnoughBytesForKey { key: 0, .. })
+	));
+}
+
+// A malformed key in a stream of keys can be skipped over, and the keys after it still decoded.
+#[test]
+fn skip_malformed_key_in_stream() {
+	let meta = metadata();
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
+
+	let timestamp_now = [twox_128(b"Timestamp"), twox_128(b"Now")].concat();
+	let mut block_hash = [twox_128(b"System"), twox_128(b"BlockHash")].concat();
+	block_hash.extend(twox_64(&1000u32.encode()));
+	block_hash.extend(1000u32.encode());
+	// System.BlockHash, with the map key cut short:
+	let truncated_block_hash = block_hash[..36].to_vec();
+
+	let stream: Vec<u8> = [timestamp_now, truncated_block_hash, block_hash].iter().flat_map(|k| k.encode()).collect();
+	let bytes = &mut &*stream;
+
+	let entry = storage.decode_key_checkpointed(&meta, bytes).expect("can decode first key");
+	assert_eq!((&*entry.prefix, &*entry.name), ("Timestamp", "Now"));
+
+	// The bad key leaves the input untouched, and tells us where the next key begins:
+	let remaining = bytes.len();
+	let err = storage.decode_key_checkpointed(&meta, bytes).expect_err("second key is malformed");
+	assert!(matches!(err.error, decoder::StorageDecodeError::NotEnoughBytesForKey { key: 0, .. }));
+	assert_eq!(bytes.len(), remaining);
+	assert_eq!(err.next_key_offset, Some(1 + 36));
+	*bytes = &bytes[err.next_key_offset.unwrap()..];
+
+	let entry = storage.decode_key_checkpointed(&meta, bytes).expect("can decode third key");
+	assert_eq!((&*entry.prefix, &*entry.name), ("System", "BlockHash"));
+	assert_hasher_eq!(entry.details.map_keys()[0].hasher, StorageHasher::Twox64Concat, Value::u128(1000));
+	assert!(bytes.is_empty(), "No more bytes expected");
+}
+
+// Decoded values are `scale_value` values, so they can be used wherever those are expected.
+#[test]
+fn decoded_values_are_scale_values() {
+	let meta = metadata();
+	let value: desub_current::scale_value::Value<u32> =
+		decoder::decode_storage_value_by_name(&meta, "Timestamp", "Now", &mut &*123u64.encode()).unwrap();
+	assert_eq!(value.remove_context(), scale_value::Value::u128(123));
+}
+
+// A Twox64Concat map keyed by a `Vec<u8>`; the whole vector is decoded from after the hash.
+#[test]
+fn scheduler_lookup() {
+	let meta = metadata();
+	let storage = decoder::decode_storage(&meta).expect("no prefix collisions");
+
+	// Scheduler.Lookup(b"task-id".to_vec(): Vec<u8>): (BlockNumber, u32)
+	let key = b"task-id".to_vec().encode();
+	let mut storage_key = [twox_128(b"Scheduler"), twox_128(b"Lookup")].concat();
+	storage_key.extend(twox_64(&key));
+	storage_key.extend(&key);
+	let storage_key = &mut &*storage_key;
+
+	let entry = storage.decode_key(&meta, storage_key).expect("can decode storage");
+	assert!(storage_key.is_empty(), "No more bytes expected");
+	assert_eq!(entry.prefix, "Scheduler");
+	assert_eq!(entry.name, "Lookup");
+
+	let keys = entry.details.map_keys();
+	assert_eq!(keys.len(), 1);
+	assert_eq!(keys[0].bytes.len(), 8 + key.len());
+	assert_hasher_eq!(keys[0].hasher, StorageHasher::Twox64Concat, Value::from_bytes(b"task-id"));
+}
+
+// Each scheduled call carries the origin to dispatch it with, which is a variant of the runtime's `OriginCaller`
+// enum. Its variant indexes follow the pallet indexes, so they are sparse and not in the order they are declared in.
+#[test]
+fn scheduler_agenda_origins() {
+	let meta = metadata();
+
+	// Balances.transfer (Alice -> Bob, 12345).
+	let transfer =
+		hex::decode("0500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0").expect("valid hex");
+	let bob = [7u8; 32];
+
+	// A ScheduledV2 with no id, priority 1 and no period, dispatched from the given origin.
+	let scheduled = |origin: Vec<u8>| [vec![1u8, 0, 1], transfer.clone(), vec![0], origin].concat();
+	let agenda = vec![
+		Some(scheduled(vec![0, 1].into_iter().chain(bob).collect())),
+		None,
+		Some(scheduled([vec![15u8, 0], (2u32, 3u32).encode()].concat())),
+		Some(scheduled([vec![50u8, 0], 2000u32.encode()].concat())),
+		Some(scheduled(vec![16, 1].into_iter().chain(bob).collect())),
+	];
+	// Each scheduled call is already encoded, so encode the Vec<Option<_>> around them by hand.
+	let mut bytes = parity_scale_codec::Compact(agenda.len() as u32).encode();
+	for entry in &agenda {
+		match entry {
+			Some(scheduled) => bytes.extend([&[1u8][..], scheduled].concat()),
+			None => bytes.push(0),
+		}
+	}
+
+	let cursor = &mut &*bytes;
+	let val = decoder::decode_storage_value_by_name(&meta, "Scheduler", "Agenda", cursor).unwrap();
+	assert!(cursor.is_empty(), "No more bytes expected");
+
+	let origin = |idx: usize| val.get(&format!("{idx}.Some.0.origin")).cloned().map(|v| v.remove_context());
+	let variant = |name: &str, inner: Value<()>| Value::unnamed_variant(name, [inner]);
+	assert_eq!(origin(0), Some(variant("system", Value::unnamed_variant("Signed", [account_id_to_value(bob)]))));
+	assert_eq!(origin(1), None);
+	assert_eq!(
+		origin(2),
+		Some(variant("Council", Value::unnamed_variant("Members", [Value::u128(2), Value::u128(3)])))
+	);
+	assert_eq!(
+		origin(3),
+		Some(variant(
+			"ParachainsOrigin",
+			Value::unnamed_variant("Parachain", [Value::unnamed_composite([Value::u128(2000)])])
+		))
+	);
+	assert_eq!(
+		origin(4),
+		Some(variant("TechnicalCommittee", Value::unnamed_variant("Member", [account_id_to_value(bob)])))
+	);
+}
+
+// Finding two prefixes with colliding twox128 hashes isn't practical, but two pallets given the same storage
+// prefix collide in the same way, and keys under either would be ambiguous.
+#[test]
+fn colliding_storage_prefixes_are_rejected() {
+	use frame_metadata::v14::{ExtrinsicMetadata, PalletMetadata, PalletStorageMetadata, RuntimeMetadataV14};
+	use frame_metadata::RuntimeMetadata;
+	use scale_info::meta_type;
+
+	let pallet = |name, index| PalletMetadata {
+		name,
+		storage: Some(PalletStorageMetadata { prefix: "Shared", entries: vec![] }),
+		calls: None,
+		event: None,
+		constants: vec![],
+		error: None,
+		index,
+	};
+	let extrinsic = ExtrinsicMetadata { ty: meta_type::<()>(), version: 4, signed_extensions: vec![] };
+	let runtime_metadata =
+		RuntimeMetadataV14::new(vec![pallet("First", 0), pallet("Second", 1)], extrinsic, meta_type::<()>());
+	let meta = Metadata::from_runtime_metadata(RuntimeMetadata::V14(runtime_metadata)).expect("valid metadata");
+
+	match decoder::decode_storage(&meta) {
+		Err(StorageDecodeError::PrefixCollision { first, second }) => {
+			assert_eq!((first.as_str(), second.as_str()), ("Shared", "Shared"));
+		}
+		Err(e) => panic!("expected a prefix collision, got {e}"),
+		Ok(_) => panic!("expected a prefix collision"),
+	}
+
+	// The real metadata has no such collisions:
+	assert!(decoder::decode_storage(&metadata()).is_ok());
+}
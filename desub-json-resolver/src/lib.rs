@@ -7,12 +7,14 @@ mod modules;
 mod overrides;
 mod resolver;
 pub mod runtimes;
+mod scale_info_types;
 
 pub use self::error::*;
 pub use self::extrinsics::*;
 pub use self::modules::*;
 pub use self::overrides::*;
 pub use self::resolver::{Builder as TypeResolverBuilder, TypeResolver};
+pub use self::scale_info_types::*;
 
 use serde::{Deserialize, Serialize};
 /// An overrides for a range of runtime versions
@@ -13,7 +13,7 @@
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::error::Error;
-use desub_legacy::{regex, EnumField, RustTypeMarker, SetField, StructField};
+use desub_legacy::{parse_type, EnumField, RustTypeMarker, SetField, StructField};
 use serde::{
 	de::{self, Deserializer, MapAccess, Visitor},
 	Deserialize, Serialize,
@@ -175,7 +175,7 @@ fn parse_mod_types(
 ) -> Result<(), Error> {
 	match val {
 		Value::String(s) => {
-			module_types.insert(key.to_string(), regex::parse(s).ok_or_else(|| Error::from(s.to_string()))?);
+			module_types.insert(key.to_string(), parse_type(s).ok_or_else(|| Error::from(s.to_string()))?);
 		}
 		Value::Object(ref mut obj) => {
 			if obj.len() == 1 && obj.keys().any(|k| k == "_enum" || k == "_set") {
@@ -209,7 +209,7 @@ fn clean_struct(map: &mut Map<String, Value>) -> Result<Option<RustTypeMarker>,
 
 	if let Some(fallback) = map.remove("_fallback") {
 		let ty = match fallback {
-			Value::String(s) => regex::parse(&s).ok_or_else(|| Error::from(s.to_string()))?,
+			Value::String(s) => parse_type(&s).ok_or_else(|| Error::from(s.to_string()))?,
 			Value::Object(o) => parse_struct(&o)?,
 			Value::Array(a) => parse_tuple(&a)?,
 			Value::Null => RustTypeMarker::Null,
@@ -264,7 +264,7 @@ fn parse_enum(value: &Value) -> Result<RustTypeMarker, Error> {
 				match value {
 					Value::Null => rust_enum.push(EnumField::new(key.into(), Some(RustTypeMarker::Null))),
 					Value::String(s) => {
-						let field = regex::parse(s).ok_or_else(|| Error::from(s.to_string()))?;
+						let field = parse_type(s).ok_or_else(|| Error::from(s.to_string()))?;
 						rust_enum.push(EnumField::new(key.into(), Some(field)));
 					}
 					Value::Object(o) => {
@@ -293,7 +293,7 @@ fn parse_struct(rust_struct: &Map<String, Value>) -> Result<RustTypeMarker, Erro
 			}
 			Value::String(s) => {
 				// points to some other type
-				let ty = regex::parse(s).ok_or_else(|| s.to_string())?;
+				let ty = parse_type(s).ok_or_else(|| s.to_string())?;
 				let field = StructField::new(key, ty);
 				fields.push(field);
 			}
@@ -320,7 +320,7 @@ fn parse_tuple(json_tuple: &[Value]) -> Result<RustTypeMarker, Error> {
 		match value {
 			Value::Null => tuple.push(RustTypeMarker::Null),
 			Value::String(s) => {
-				let ty = regex::parse(s).ok_or_else(|| s.to_string())?;
+				let ty = parse_type(s).ok_or_else(|| s.to_string())?;
 				tuple.push(ty);
 			}
 			_ => return Err(Error::UnexpectedType),
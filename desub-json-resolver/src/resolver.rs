@@ -14,8 +14,8 @@
 
 //! Resolves types based on the JSON
 
-use crate::{Extrinsics, Modules, Overrides, Result};
-use desub_legacy::{regex, RustTypeMarker, TypeDetective};
+use crate::{Extrinsics, Modules, Overrides, Result, ScaleInfoTypes};
+use desub_legacy::{sanitize_type, RustTypeMarker, TypeDetective};
 
 #[cfg(feature = "default-definitions")]
 mod default {
@@ -28,11 +28,12 @@ pub struct Builder {
 	mods: Modules,
 	overrides: Overrides,
 	extrinsics: Extrinsics,
+	scale_info_types: ScaleInfoTypes,
 }
 
 impl Builder {
 	pub fn new(mods: Modules, extrinsics: Extrinsics, overrides: Overrides) -> Self {
-		Self { mods, overrides, extrinsics }
+		Self { mods, overrides, extrinsics, scale_info_types: ScaleInfoTypes::default() }
 	}
 
 	pub fn modules(mut self, modules: Modules) -> Self {
@@ -65,8 +66,23 @@ impl Builder {
 		Ok(self)
 	}
 
+	pub fn scale_info_types(mut self, types: ScaleInfoTypes) -> Self {
+		self.scale_info_types = types;
+		self
+	}
+
+	pub fn scale_info_types_from_json(mut self, json: &str) -> Result<Self> {
+		self.scale_info_types = ScaleInfoTypes::new(json)?;
+		Ok(self)
+	}
+
 	pub fn build(self) -> TypeResolver {
-		TypeResolver { mods: self.mods, overrides: self.overrides, extrinsics: self.extrinsics }
+		TypeResolver {
+			mods: self.mods,
+			overrides: self.overrides,
+			extrinsics: self.extrinsics,
+			scale_info_types: self.scale_info_types,
+		}
 	}
 }
 
@@ -77,6 +93,7 @@ impl Default for Builder {
 			mods: Modules::new(default::DEFINITIONS).expect("Included definitions should not panic"),
 			overrides: Overrides::new(default::OVERRIDES).expect("Included overrides should not panic"),
 			extrinsics: Extrinsics::new(default::EXTRINSICS).expect("Included extrinsics should not panic"),
+			scale_info_types: ScaleInfoTypes::default(),
 		}
 	}
 }
@@ -92,6 +109,7 @@ pub struct TypeResolver {
 	mods: Modules,
 	overrides: Overrides,
 	extrinsics: Extrinsics,
+	scale_info_types: ScaleInfoTypes,
 }
 
 impl TypeResolver {
@@ -103,11 +121,12 @@ impl TypeResolver {
 
 	/// Construct the TypeResolver from its parts
 	pub fn new(modules: Modules, extrinsics: Extrinsics, overrides: Overrides) -> Self {
-		Self { mods: modules, extrinsics, overrides }
+		Self { mods: modules, extrinsics, overrides, scale_info_types: ScaleInfoTypes::default() }
 	}
 
 	/// Try to resolve a type
-	/// First, tries to resolve from the overrides (overrides.json),
+	/// First, tries to resolve from the scale-info types given to the builder, if any were,
+	/// then from the overrides (overrides.json),
 	/// then checks if extrinsics includes the type (extrinsics.json).
 	/// If none of the above contains the type that we are looking for,
 	/// we check if the modules includes it (definitions.json)
@@ -117,7 +136,10 @@ impl TypeResolver {
 	pub fn get(&self, chain: &str, spec: u32, module: &str, ty: &str) -> Option<&RustTypeMarker> {
 		log::trace!("Getting Type: {}, module: {}, spec: {}", ty, module, spec);
 
-		if let Some(t) = self.check_overrides(module, ty, spec, chain) {
+		if let Some(t) = self.scale_info_types.get(ty) {
+			log::trace!("Resolving to scale-info Type");
+			Some(t)
+		} else if let Some(t) = self.check_overrides(module, ty, spec, chain) {
 			log::trace!("Resolving to Override");
 			Some(t)
 		} else if let Some(t) = self.extrinsics.get(ty, spec, chain) {
@@ -133,6 +155,17 @@ impl TypeResolver {
 		self.mods.try_fallback(module, ty)
 	}
 
+	/// Resolve a type name to its ID in the scale-info types given to the builder, if any were.
+	/// The ID can be resolved in the registry given by [`TypeResolver::scale_info_types`].
+	pub fn scale_info_type_id(&self, ty: &str) -> Option<u32> {
+		self.scale_info_types.type_id(ty)
+	}
+
+	/// The scale-info types given to the builder
+	pub fn scale_info_types(&self) -> &ScaleInfoTypes {
+		&self.scale_info_types
+	}
+
 	/// Get type for decoding an Extrinsic
 	pub fn get_ext_ty(&self, chain: &str, spec: u32, ty: &str) -> Option<&RustTypeMarker> {
 		if let Some(t) = self.extrinsics.get(ty, spec, chain) {
@@ -187,21 +220,29 @@ impl TypeResolver {
 impl TypeDetective for TypeResolver {
 	fn get(&self, chain: &str, spec: u32, module: &str, ty: &str) -> Option<&RustTypeMarker> {
 		log::trace!("Getting type {}", ty);
-		let ty = regex::sanitize_ty(ty)?;
+		let ty = sanitize_type(ty)?;
 		let module = module.to_ascii_lowercase();
 		let chain = chain.to_ascii_lowercase();
 		TypeResolver::get(self, &chain, spec, &module, &ty)
 	}
 
+	fn get_many(&self, chain: &str, spec: u32, module: &str, tys: &[&str]) -> Vec<Option<&RustTypeMarker>> {
+		let module = module.to_ascii_lowercase();
+		let chain = chain.to_ascii_lowercase();
+		tys.iter()
+			.map(|ty| sanitize_type(ty).and_then(|ty| TypeResolver::get(self, &chain, spec, &module, &ty)))
+			.collect()
+	}
+
 	fn try_fallback(&self, module: &str, ty: &str) -> Option<&RustTypeMarker> {
-		let ty = regex::sanitize_ty(ty)?;
+		let ty = sanitize_type(ty)?;
 		let module = module.to_ascii_lowercase();
 
 		TypeResolver::try_fallback(self, &module, &ty)
 	}
 
 	fn get_extrinsic_ty(&self, chain: &str, spec: u32, ty: &str) -> Option<&RustTypeMarker> {
-		let ty = regex::sanitize_ty(ty)?;
+		let ty = sanitize_type(ty)?;
 		let chain = chain.to_ascii_lowercase();
 
 		TypeResolver::get_ext_ty(self, &chain, spec, &ty)
@@ -247,6 +288,66 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn should_resolve_scale_info_type_ids() -> Result<()> {
+		let json = r#"{ "types": [{ "id": 0, "type": { "path": ["sp_runtime", "DispatchError"], "def": { "variant": {} } } }] }"#;
+		let types = TypeResolver::builder().scale_info_types_from_json(json)?.build();
+		assert_eq!(types.scale_info_type_id("DispatchError"), Some(0));
+		assert_eq!(types.scale_info_type_id("sp_runtime::DispatchError"), Some(0));
+		assert!(types.scale_info_types().registry().resolve(0).is_some());
+
+		// Without any scale-info types, nothing resolves.
+		assert_eq!(TypeResolver::default().scale_info_type_id("DispatchError"), None);
+		Ok(())
+	}
+
+	#[test]
+	fn should_prefer_scale_info_types_to_definitions() -> Result<()> {
+		let json = r#"
+        {
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u128" } } },
+                {
+                    "id": 1,
+                    "type": {
+                        "path": ["pallet_balances", "BalanceLock"],
+                        "def": { "composite": { "fields": [{ "name": "amount", "type": 0 }] } }
+                    }
+                }
+            ]
+        }
+        "#;
+		let overridden = RustTypeMarker::Struct(vec![StructField { name: "amount".into(), ty: RustTypeMarker::U128 }]);
+
+		let types = TypeResolver::default();
+		assert_ne!(types.get("kusama", 1040, "balances", "BalanceLock"), Some(&overridden));
+
+		let types = TypeResolver::builder().scale_info_types_from_json(json)?.build();
+		assert_eq!(types.get("kusama", 1040, "balances", "BalanceLock"), Some(&overridden));
+		assert_eq!(TypeDetective::get(&types, "Kusama", 1040, "Balances", "BalanceLock"), Some(&overridden));
+		// Types that aren't given are resolved as before:
+		assert_eq!(
+			types.get("kusama", 1040, "balances", "BalanceLockTo212"),
+			TypeResolver::default().get("kusama", 1040, "balances", "BalanceLockTo212")
+		);
+		Ok(())
+	}
+
+	#[test]
+	fn should_get_many_types_at_once() -> Result<()> {
+		let types = TypeResolver::default();
+		let tys = ["DispatchError", "T::AccountId", "NotARealType", "Vec<Balance>"];
+		let many = TypeDetective::get_many(&types, "Kusama", 1040, "System", &tys);
+
+		assert_eq!(many.len(), tys.len());
+		assert!(many[0].is_some());
+		assert!(many[2].is_none());
+		let one_by_one: Vec<_> =
+			tys.iter().map(|ty| TypeDetective::get(&types, "Kusama", 1040, "System", ty)).collect();
+		assert_eq!(many, one_by_one);
+		Ok(())
+	}
+
 	#[test]
 	fn should_get_duplicated_types() -> Result<()> {
 		let types = TypeResolver::default();
@@ -287,6 +388,45 @@ mod tests {
 		assert_ne!(dot_t, ksm_t);
 	}
 
+	#[test]
+	fn should_decode_bare_account_id_signers_on_early_kusama() {
+		use desub_legacy::{
+			decoder::{Chain, Decoder, Metadata},
+			SubstrateType,
+		};
+		use parity_scale_codec::{Compact, Encode};
+
+		// Before the old indices based address (and long before `MultiAddress`), Kusama signers were bare account IDs.
+		// The extrinsic types for those runtimes point the address straight at 32 bytes.
+		let types = TypeResolver::default();
+		assert_eq!(
+			types.get("kusama", 1000, "runtime", "Address"),
+			Some(&RustTypeMarker::TypePointer("AccountId".into()))
+		);
+		assert_eq!(
+			types.get("kusama", 1000, "runtime", "AccountId"),
+			Some(&RustTypeMarker::Array { size: 32, ty: Box::new(RustTypeMarker::U8) })
+		);
+
+		let mut decoder = Decoder::new(types, Chain::Kusama);
+		let metadata = Metadata::new(include_bytes!("../../integration-tests/data/metadata_v9_block6.bin")).unwrap();
+		decoder.register_version(1000, metadata).unwrap();
+
+		// A System.remark(b"hi") signed by a bare account ID, with an immortal era and a nonce of 5. None of the
+		// other signed extensions in use then have any data.
+		let mut ext = vec![0x84u8];
+		ext.extend([7u8; 32]);
+		ext.extend([9u8; 64]);
+		ext.push(0);
+		ext.extend(Compact(5u32).encode());
+		ext.extend([0, 1, 8, b'h', b'i']);
+		let ext = decoder.decode_single_extrinsic(1000, &ext.encode()).unwrap();
+
+		assert_eq!(ext.to_string(), "System.remark(_remark: 6869)");
+		let (address, _, _) = ext.signature().expect("extrinsic is signed").parts();
+		assert_eq!(address, &SubstrateType::Composite(vec![SubstrateType::U8(7); 32]));
+	}
+
 	#[test]
 	fn should_deserialize_overrides() {
 		let overrides = Overrides::new(OVERRIDES).unwrap();
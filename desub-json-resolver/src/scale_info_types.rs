@@ -0,0 +1,266 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::Result;
+use desub_legacy::{CommonTypes, EnumField, RustTypeMarker, StructField};
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef, TypeDefPrimitive};
+use std::collections::HashMap;
+
+/// Types given as a scale-info [`PortableRegistry`], which some newer polkadot-js bundles ship in place of
+/// the string definitions understood by [Modules](struct.Modules.html). Types are looked up by name,
+/// either by their full path (like `sp_runtime::DispatchError`) or just the last segment of it (like
+/// `DispatchError`), and resolve to their ID in the registry, or to a [`RustTypeMarker`] describing
+/// the same shape so that they can stand in for the string definitions.
+#[derive(Debug, Clone)]
+pub struct ScaleInfoTypes {
+	registry: PortableRegistry,
+	/// Type names (full paths, and the last segment of each path) to the ID of the type.
+	ids_by_name: HashMap<String, u32>,
+	/// The named types, converted to markers.
+	markers: HashMap<u32, RustTypeMarker>,
+}
+
+impl Default for ScaleInfoTypes {
+	fn default() -> Self {
+		Self::from_registry(PortableRegistry { types: Vec::new() })
+	}
+}
+
+impl ScaleInfoTypes {
+	/// Construct the types from a JSON serialized [`PortableRegistry`]
+	pub fn new(raw_json: &str) -> Result<Self> {
+		let registry: PortableRegistry = serde_json::from_str(raw_json)?;
+		Ok(Self::from_registry(registry))
+	}
+
+	/// Construct the types from a [`PortableRegistry`]
+	pub fn from_registry(registry: PortableRegistry) -> Self {
+		let mut ids_by_name = HashMap::new();
+		let mut markers = HashMap::new();
+		for ty in registry.types.iter() {
+			let Some(ident) = ty.ty.path.ident() else { continue };
+			ids_by_name.insert(ty.ty.path.segments.join("::"), ty.id);
+			// If several types share a name, the first one wins:
+			ids_by_name.entry(ident).or_insert(ty.id);
+			markers.insert(ty.id, to_marker(&registry, ty.id, &mut Vec::new()));
+		}
+		Self { registry, ids_by_name, markers }
+	}
+
+	/// Get the ID of the type with the given name, if there is one
+	pub fn type_id(&self, name: &str) -> Option<u32> {
+		self.ids_by_name.get(name).copied()
+	}
+
+	/// Get the type with the given name as a [`RustTypeMarker`], if there is one. The types that it refers to are
+	/// given inline, except for those that refer back to a type that contains them, which are given as a
+	/// [`RustTypeMarker::TypePointer`] to the name of that type.
+	pub fn get(&self, name: &str) -> Option<&RustTypeMarker> {
+		self.markers.get(&self.type_id(name)?)
+	}
+
+	/// The registry that type IDs can be resolved in
+	pub fn registry(&self) -> &PortableRegistry {
+		&self.registry
+	}
+}
+
+/// Convert a type in the registry to the marker that decodes the same bytes. `visiting` holds the types that we're
+/// part way through converting, so that recursive types end in a pointer rather than recursing forever.
+fn to_marker(registry: &PortableRegistry, id: u32, visiting: &mut Vec<u32>) -> RustTypeMarker {
+	let Some(ty) = registry.resolve(id) else { return RustTypeMarker::Null };
+	if visiting.contains(&id) {
+		return RustTypeMarker::TypePointer(ty.path.ident().unwrap_or_default());
+	}
+	visiting.push(id);
+	let mut marker = |id: u32| Box::new(to_marker(registry, id, visiting));
+	let converted = match &ty.type_def {
+		TypeDef::Variant(v) if ty.path.segments == ["Option"] && v.variants.len() == 2 => {
+			let some = v.variants.iter().find(|v| v.name == "Some").and_then(|v| v.fields.first());
+			match some {
+				Some(field) => RustTypeMarker::Std(CommonTypes::Option(marker(field.ty.id))),
+				None => RustTypeMarker::Null,
+			}
+		}
+		TypeDef::Composite(c) => fields_marker(registry, &c.fields, visiting),
+		TypeDef::Variant(v) => {
+			// Legacy enums are indexed by position, so fill any gaps in the variant indexes.
+			let len = v.variants.iter().map(|v| v.index as usize + 1).max().unwrap_or(0);
+			let mut fields: Vec<_> =
+				(0..len).map(|idx| EnumField::new(format!("__Unused{idx}"), Some(RustTypeMarker::Null))).collect();
+			for variant in &v.variants {
+				let value = fields_marker(registry, &variant.fields, visiting);
+				fields[variant.index as usize] = EnumField::new(variant.name.clone(), Some(value));
+			}
+			RustTypeMarker::Enum(fields)
+		}
+		TypeDef::Sequence(seq) => RustTypeMarker::Std(CommonTypes::Vec(marker(seq.type_param.id))),
+		TypeDef::Array(arr) => RustTypeMarker::Array { size: arr.len as usize, ty: marker(arr.type_param.id) },
+		TypeDef::Tuple(t) if t.fields.is_empty() => RustTypeMarker::Null,
+		TypeDef::Tuple(t) => RustTypeMarker::Tuple(t.fields.iter().map(|f| *marker(f.id)).collect()),
+		TypeDef::Compact(c) => RustTypeMarker::Std(CommonTypes::Compact(marker(c.type_param.id))),
+		TypeDef::BitSequence(_) => RustTypeMarker::TypePointer("BitVec".to_string()),
+		TypeDef::Primitive(p) => match p {
+			TypeDefPrimitive::Bool => RustTypeMarker::Bool,
+			TypeDefPrimitive::Char => RustTypeMarker::U32,
+			TypeDefPrimitive::Str => RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::U8))),
+			TypeDefPrimitive::U8 => RustTypeMarker::U8,
+			TypeDefPrimitive::U16 => RustTypeMarker::U16,
+			TypeDefPrimitive::U32 => RustTypeMarker::U32,
+			TypeDefPrimitive::U64 => RustTypeMarker::U64,
+			TypeDefPrimitive::U128 => RustTypeMarker::U128,
+			TypeDefPrimitive::I8 => RustTypeMarker::I8,
+			TypeDefPrimitive::I16 => RustTypeMarker::I16,
+			TypeDefPrimitive::I32 => RustTypeMarker::I32,
+			TypeDefPrimitive::I64 => RustTypeMarker::I64,
+			TypeDefPrimitive::I128 => RustTypeMarker::I128,
+			TypeDefPrimitive::U256 | TypeDefPrimitive::I256 => {
+				RustTypeMarker::Array { size: 32, ty: Box::new(RustTypeMarker::U8) }
+			}
+		},
+	};
+	visiting.pop();
+	converted
+}
+
+/// The marker for the fields of a struct or of an enum variant: a struct if they're named, the one field's type for a
+/// newtype, a tuple for several unnamed fields and nothing at all if there are no fields.
+fn fields_marker(
+	registry: &PortableRegistry,
+	fields: &[Field<PortableForm>],
+	visiting: &mut Vec<u32>,
+) -> RustTypeMarker {
+	match fields {
+		[] => RustTypeMarker::Null,
+		[field] if field.name.is_none() => to_marker(registry, field.ty.id, visiting),
+		_ if fields.iter().all(|f| f.name.is_some()) => RustTypeMarker::Struct(
+			fields
+				.iter()
+				.map(|f| StructField {
+					name: f.name.clone().unwrap_or_default(),
+					ty: to_marker(registry, f.ty.id, visiting),
+				})
+				.collect(),
+		),
+		_ => RustTypeMarker::Tuple(fields.iter().map(|f| to_marker(registry, f.ty.id, visiting)).collect()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use scale_info::{TypeDef, TypeDefPrimitive};
+
+	const TYPES: &str = r#"
+        {
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u32" } } },
+                {
+                    "id": 1,
+                    "type": {
+                        "path": ["pallet_foo", "Thing"],
+                        "def": { "composite": { "fields": [{ "name": "a", "type": 0, "typeName": "u32" }] } }
+                    }
+                },
+                {
+                    "id": 2,
+                    "type": {
+                        "path": ["pallet_bar", "Thing"],
+                        "def": { "composite": { "fields": [{ "type": 0 }] } }
+                    }
+                }
+            ]
+        }
+        "#;
+
+	#[test]
+	fn should_resolve_scale_info_types_by_name() {
+		let types = ScaleInfoTypes::new(TYPES).unwrap();
+
+		assert_eq!(types.type_id("pallet_foo::Thing"), Some(1));
+		assert_eq!(types.type_id("pallet_bar::Thing"), Some(2));
+		// The first type with a given name is found when the path isn't given.
+		assert_eq!(types.type_id("Thing"), Some(1));
+		assert_eq!(types.type_id("Other"), None);
+
+		let ty = types.registry().resolve(0).unwrap();
+		assert_eq!(ty.type_def, TypeDef::Primitive(TypeDefPrimitive::U32));
+	}
+
+	#[test]
+	fn should_convert_scale_info_types_to_markers() {
+		let json = r#"
+        {
+            "types": [
+                { "id": 0, "type": { "def": { "primitive": "u8" } } },
+                { "id": 1, "type": { "def": { "sequence": { "type": 0 } } } },
+                {
+                    "id": 2,
+                    "type": {
+                        "path": ["pallet_foo", "Action"],
+                        "def": {
+                            "variant": {
+                                "variants": [
+                                    { "name": "Stop", "index": 0 },
+                                    { "name": "Say", "index": 2, "fields": [{ "type": 1 }] },
+                                    { "name": "Move", "index": 3, "fields": [{ "name": "x", "type": 0 }, { "name": "y", "type": 0 }] }
+                                ]
+                            }
+                        }
+                    }
+                },
+                {
+                    "id": 3,
+                    "type": {
+                        "path": ["pallet_foo", "Plan"],
+                        "def": { "composite": { "fields": [{ "name": "steps", "type": 4 }] } }
+                    }
+                },
+                { "id": 4, "type": { "def": { "sequence": { "type": 3 } } } }
+            ]
+        }
+        "#;
+		let types = ScaleInfoTypes::new(json).unwrap();
+
+		let bytes = RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::U8)));
+		let action = RustTypeMarker::Enum(vec![
+			EnumField::new("Stop".into(), Some(RustTypeMarker::Null)),
+			// Gaps in the variant indexes are filled, so that variants are found by position:
+			EnumField::new("__Unused1".into(), Some(RustTypeMarker::Null)),
+			EnumField::new("Say".into(), Some(bytes)),
+			EnumField::new(
+				"Move".into(),
+				Some(RustTypeMarker::Struct(vec![
+					StructField { name: "x".into(), ty: RustTypeMarker::U8 },
+					StructField { name: "y".into(), ty: RustTypeMarker::U8 },
+				])),
+			),
+		]);
+		assert_eq!(types.get("Action"), Some(&action));
+		assert_eq!(types.get("pallet_foo::Action"), Some(&action));
+
+		// Recursive types refer back to themselves by name:
+		let plan = RustTypeMarker::Struct(vec![StructField {
+			name: "steps".into(),
+			ty: RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::TypePointer("Plan".into())))),
+		}]);
+		assert_eq!(types.get("Plan"), Some(&plan));
+		assert_eq!(types.get("Other"), None);
+	}
+
+	#[test]
+	fn should_fail_on_invalid_registry() {
+		assert!(ScaleInfoTypes::new(r#"{ "types": [{ "id": 0 }] }"#).is_err());
+	}
+}
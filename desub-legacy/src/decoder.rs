@@ -23,10 +23,12 @@
 //! Theoretically, one could upload the deserialized decoder JSON to distribute
 //! to different applications that need the type data
 
+mod events;
 mod extrinsics;
 pub mod metadata;
 mod storage;
 
+pub use self::events::GenericEvent;
 pub use self::extrinsics::{ExtrinsicArgument, GenericCall, GenericExtrinsic, GenericSignature};
 pub use self::storage::{GenericStorage, StorageInfo, StorageKey, StorageKeyData, StorageLookupTable, StorageValue};
 
@@ -34,8 +36,8 @@ pub use self::storage::{GenericStorage, StorageInfo, StorageKey, StorageKeyData,
 pub use self::metadata::test_suite;
 
 pub use self::metadata::{
-	CallMetadata, Error as MetadataError, Metadata, ModuleIndex, ModuleMetadata, StorageEntryModifier, StorageHasher,
-	StorageType,
+	CallMetadata, Error as MetadataError, Metadata, ModuleEventMetadata, ModuleIndex, ModuleMetadata,
+	StorageEntryModifier, StorageHasher, StorageType,
 };
 pub use frame_metadata::v14::StorageEntryType;
 
@@ -61,17 +63,35 @@ use std::{
 /// hold information about the Runtime Metadata
 /// and maps types inside the runtime metadata to self-describing types in
 /// type-metadata
+///
+/// Decoding only needs `&self`, and a `Decoder` is `Send + Sync`, so to share one decoder between threads
+/// or async tasks, wrap it in an [`Arc`](std::sync::Arc) rather than cloning it; cloning deep-copies every
+/// registered version of metadata and the type detective.
 #[derive(Debug)]
 pub struct Decoder {
 	// reference to an item in 'versions' vector
 	versions: HashMap<SpecVersion, Metadata>,
 	types: Box<dyn TypeDetective>,
 	chain: String,
+	ss58_prefix: u16,
+	genesis_hash: Option<[u8; 32]>,
 }
 
+// A `Decoder` is expected to be shared across threads, so make sure that it stays `Send + Sync`.
+const _: fn() = || {
+	fn assert_send_sync<T: Send + Sync>() {}
+	assert_send_sync::<Decoder>();
+};
+
 impl Clone for Decoder {
 	fn clone(&self) -> Self {
-		Self { versions: self.versions.clone(), types: dyn_clone::clone_box(&*self.types), chain: self.chain.clone() }
+		Self {
+			versions: self.versions.clone(),
+			types: dyn_clone::clone_box(&*self.types),
+			chain: self.chain.clone(),
+			ss58_prefix: self.ss58_prefix,
+			genesis_hash: self.genesis_hash,
+		}
 	}
 }
 
@@ -89,7 +109,7 @@ pub enum Entry {
 	Constant,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Chain {
 	Polkadot,
 	Kusama,
@@ -112,6 +132,43 @@ impl std::fmt::Display for Chain {
 	}
 }
 
+impl Chain {
+	/// The ss58 prefix of the generic Substrate address format, which [`Chain::Custom`] chains are assumed to use.
+	pub const DEFAULT_SS58_PREFIX: u16 = 42;
+
+	/// The ss58 prefix that addresses on this chain are encoded with. [`Chain::Custom`] chains use
+	/// [`Chain::DEFAULT_SS58_PREFIX`]; see [`Decoder::set_ss58_prefix`] to use another.
+	pub fn ss58_prefix(&self) -> u16 {
+		match self {
+			Chain::Polkadot => 0,
+			Chain::Kusama => 2,
+			Chain::Centrifuge => 36,
+			Chain::Westend | Chain::Rococo | Chain::Custom(_) => Self::DEFAULT_SS58_PREFIX,
+		}
+	}
+
+	/// The hash of the genesis block of this chain, which signed extrinsics commit to via the `CheckGenesis` signed
+	/// extension. This is `None` for chains whose genesis hash isn't known (including those, like Rococo, which
+	/// have been restarted from a new genesis); see [`Decoder::set_genesis_hash`] to provide one.
+	pub fn genesis_hash(&self) -> Option<[u8; 32]> {
+		match self {
+			Chain::Polkadot => Some([
+				0x91, 0xb1, 0x71, 0xbb, 0x15, 0x8e, 0x2d, 0x38, 0x48, 0xfa, 0x23, 0xa9, 0xf1, 0xc2, 0x51, 0x82, 0xfb,
+				0x8e, 0x20, 0x31, 0x3b, 0x2c, 0x1e, 0xb4, 0x92, 0x19, 0xda, 0x7a, 0x70, 0xce, 0x90, 0xc3,
+			]),
+			Chain::Kusama => Some([
+				0xb0, 0xa8, 0xd4, 0x93, 0x28, 0x5c, 0x2d, 0xf7, 0x32, 0x90, 0xdf, 0xb7, 0xe6, 0x1f, 0x87, 0x0f, 0x17,
+				0xb4, 0x18, 0x01, 0x19, 0x7a, 0x14, 0x9c, 0xa9, 0x36, 0x54, 0x49, 0x9e, 0xa3, 0xda, 0xfe,
+			]),
+			Chain::Westend => Some([
+				0xe1, 0x43, 0xf2, 0x38, 0x03, 0xac, 0x50, 0xe8, 0xf6, 0xf8, 0xe6, 0x26, 0x95, 0xd1, 0xce, 0x9e, 0x4e,
+				0x1d, 0x68, 0xaa, 0x36, 0xc1, 0xcd, 0x2c, 0xfd, 0x15, 0x34, 0x02, 0x13, 0xf3, 0x42, 0x3e,
+			]),
+			Chain::Centrifuge | Chain::Rococo | Chain::Custom(_) => None,
+		}
+	}
+}
+
 impl FromStr for Chain {
 	type Err = Error;
 
@@ -154,6 +211,10 @@ impl<'a> Module<'a> {
 	fn call(&self, index: u8) -> Result<Option<&'a CallMetadata>, MetadataError> {
 		self.module.map(|m| m.call(index)).transpose()
 	}
+
+	fn event(&self, index: u8) -> Result<Option<&'a ModuleEventMetadata>, MetadataError> {
+		self.module.map(|m| m.event(index)).transpose()
+	}
 }
 
 #[derive(Debug)]
@@ -198,6 +259,19 @@ impl<'a> DecodeState<'a> {
 		Ok(())
 	}
 
+	/// Loads the module at the current index of the outer event enum.
+	/// Increments the cursor by 1.
+	fn load_event_module(&mut self) -> Result<(), Error> {
+		log::trace!("Loading event module in index {}", self.index());
+		let module = self
+			.metadata
+			.module_by_index(ModuleIndex::Event(self.index()))
+			.map_err(|e| Error::DetailedMetaFail(e, self.cursor(), hex::encode(self.data)))?;
+		self.increment();
+		self.module.set(module);
+		Ok(())
+	}
+
 	// Gets the call at the current index. Increments cursor by 1.
 	// Sets the call for the state.
 	// Panics if there is no module loaded
@@ -221,10 +295,13 @@ impl<'a> DecodeState<'a> {
 
 	/// Get the scale length at the current point in time.
 	/// Increment cursor accordingly to the length.
-	fn scale_length(&mut self) -> Result<usize, Error> {
+	/// Errors if `length` items of at least `min_item_len` bytes each can't fit in the rest of the data,
+	/// so that a bogus length can't have us decode (or allocate space for) far more items than exist.
+	fn scale_length(&mut self, min_item_len: usize) -> Result<usize, Error> {
 		let length = Decoder::scale_length(&self.data[self.cursor.load(Ordering::Relaxed)..])?;
 		log::trace!("Scale Byte Length {}, actual items: {}", length.1, length.0);
 		self.cursor.fetch_add(length.1, Ordering::Relaxed);
+		check_length_fits(length.0, min_item_len, self.data.len().saturating_sub(self.cursor()))?;
 		Ok(length.0)
 	}
 
@@ -281,11 +358,12 @@ impl<'a> DecodeState<'a> {
 	fn observe(&self, line: u32) {
 		let module = self.module.name();
 		let cursor = self.cursor.load(Ordering::Relaxed);
-		let value_at_cursor = &self.data[cursor];
-		let data_at_cursor = &self.data[cursor..];
+		// The cursor can sit at the end of the data, after zero sized values.
+		let value_at_cursor = self.data.get(cursor);
+		let data_at_cursor = self.data.get(cursor..).unwrap_or_default();
 
 		log::trace!(
-			"line: {}, module = {}, call = {:?}, cursor = {}, data[cursor] = {}, data[cursor..] = {:?}",
+			"line: {}, module = {}, call = {:?}, cursor = {}, data[cursor] = {:?}, data[cursor..] = {:?}",
 			line,
 			module,
 			self.call.borrow().as_ref().map(|c| c.name()),
@@ -306,13 +384,18 @@ impl<'a> ChunkedExtrinsic<'a> {
 	fn new(data: &'a [u8]) -> Self {
 		Self { data, cursor: 0 }
 	}
+
+	/// The bytes after the last extrinsic returned so far.
+	fn remaining(&self) -> &'a [u8] {
+		&self.data[self.cursor..]
+	}
 }
 
 impl<'a> Iterator for ChunkedExtrinsic<'a> {
 	type Item = &'a [u8];
 	fn next(&mut self) -> Option<&'a [u8]> {
 		let (length, prefix) = Decoder::scale_length(&self.data[self.cursor..]).ok()?;
-		let extrinsic = &self.data[(self.cursor + prefix)..(self.cursor + length + prefix)];
+		let extrinsic = self.data.get((self.cursor + prefix)..(self.cursor + length + prefix))?;
 		self.cursor += length + prefix;
 		Some(extrinsic)
 	}
@@ -321,7 +404,58 @@ impl<'a> Iterator for ChunkedExtrinsic<'a> {
 impl Decoder {
 	/// Create new Decoder with specified types.
 	pub fn new(types: impl TypeDetective + 'static, chain: Chain) -> Self {
-		Self { versions: HashMap::default(), types: Box::new(types), chain: chain.to_string() }
+		let ss58_prefix = chain.ss58_prefix();
+		let genesis_hash = chain.genesis_hash();
+		Self {
+			versions: HashMap::default(),
+			types: Box::new(types),
+			chain: chain.to_string(),
+			ss58_prefix,
+			genesis_hash,
+		}
+	}
+
+	/// Create a new Decoder for another chain, which uses the same types as this one but has
+	/// no metadata versions registered.
+	pub fn for_chain(&self, chain: Chain) -> Self {
+		let ss58_prefix = chain.ss58_prefix();
+		let genesis_hash = chain.genesis_hash();
+		Self {
+			versions: HashMap::default(),
+			types: dyn_clone::clone_box(&*self.types),
+			chain: chain.to_string(),
+			ss58_prefix,
+			genesis_hash,
+		}
+	}
+
+	/// The name of the chain this decoder was created for, as passed to [`TypeDetective`] when looking up types.
+	pub fn chain(&self) -> &str {
+		&self.chain
+	}
+
+	/// The ss58 prefix that addresses in decoded extrinsics are formatted with. This defaults to the prefix of
+	/// the chain given when creating the decoder.
+	pub fn ss58_prefix(&self) -> u16 {
+		self.ss58_prefix
+	}
+
+	/// Set the ss58 prefix that addresses in decoded extrinsics are formatted with, for instance to give
+	/// a [`Chain::Custom`] chain its own prefix.
+	pub fn set_ss58_prefix(&mut self, prefix: u16) {
+		self.ss58_prefix = prefix;
+	}
+
+	/// The hash of the genesis block of the chain being decoded, if known. This defaults to the genesis hash of the
+	/// chain given when creating the decoder.
+	pub fn genesis_hash(&self) -> Option<[u8; 32]> {
+		self.genesis_hash
+	}
+
+	/// Set the hash of the genesis block of the chain being decoded, for instance to give a [`Chain::Custom`] chain
+	/// its own.
+	pub fn set_genesis_hash(&mut self, hash: [u8; 32]) {
+		self.genesis_hash = Some(hash);
 	}
 
 	/// Check if a metadata version has already been registered
@@ -336,6 +470,33 @@ impl Decoder {
 		Ok(())
 	}
 
+	/// Remove the metadata registered for a version, freeing the memory it used. Returns whether there was
+	/// any metadata registered for that version.
+	pub fn remove_version(&mut self, version: SpecVersion) -> bool {
+		self.versions.remove(&version).is_some()
+	}
+
+	/// Register the SCALE encoded metadata of several versions at once. Registration stops at the first metadata
+	/// that can't be decoded, with an [`Error::RegisterVersion`] naming its version; any versions before it
+	/// remain registered.
+	pub fn register_versions<B: AsRef<[u8]>>(
+		&mut self,
+		items: impl IntoIterator<Item = (SpecVersion, B)>,
+	) -> Result<(), Error> {
+		for (version, bytes) in items {
+			Metadata::new(bytes.as_ref())
+				.map_err(Error::from)
+				.and_then(|metadata| self.register_version(version, metadata))
+				.map_err(|e| Error::RegisterVersion { version, source: Box::new(e) })?;
+		}
+		Ok(())
+	}
+
+	/// Consume the decoder, handing back the metadata registered for each version.
+	pub fn into_versions(self) -> HashMap<SpecVersion, Metadata> {
+		self.versions
+	}
+
 	/// internal api to get metadata from runtime version.
 	///
 	/// # Note
@@ -344,6 +505,16 @@ impl Decoder {
 		self.versions.get(&version)
 	}
 
+	/// Get the metadata registered for the highest spec version that is at or below `version`,
+	/// along with that spec version. This is useful when the exact spec version of a block was not
+	/// registered, but the metadata of an earlier runtime is known to be compatible.
+	///
+	/// # Note
+	/// Returns None if no version at or below `version` has been registered
+	pub fn metadata_at_or_below(&self, version: SpecVersion) -> Option<(SpecVersion, &Metadata)> {
+		self.versions.iter().filter(|(v, _)| **v <= version).max_by_key(|(v, _)| **v).map(|(v, m)| (*v, m))
+	}
+
 	fn decode_key_len(&self, key: &[u8], hasher: &StorageHasher) -> Vec<u8> {
 		match hasher {
 			StorageHasher::Blake2_128 | StorageHasher::Twox128 | StorageHasher::Blake2_128Concat => key[..16].to_vec(),
@@ -460,22 +631,227 @@ impl Decoder {
 	}
 
 	/// Decode a Vec<Extrinsic>. (Vec<Vec<u8>>)
+	/// Any bytes left over after the number of extrinsics given by the length prefix are an error.
 	pub fn decode_extrinsics(&self, spec: SpecVersion, data: &[u8]) -> Result<Vec<GenericExtrinsic>, Error> {
-		let mut ext = Vec::new();
+		self.decode_extrinsics_allow_trailing(spec, data, 0).map(|(ext, _)| ext)
+	}
+
+	/// Like [`Decoder::decode_extrinsics`], but allow up to `max_trailing` bytes to be left over after the
+	/// extrinsics, returning them alongside the decoded extrinsics. Some sources of block bodies tack on extra
+	/// bytes, which can be inspected (or ignored) this way.
+	pub fn decode_extrinsics_allow_trailing<'b>(
+		&self,
+		spec: SpecVersion,
+		data: &'b [u8],
+		max_trailing: usize,
+	) -> Result<(Vec<GenericExtrinsic>, &'b [u8]), Error> {
+		let mut exts = Vec::new();
+		let trailing = self.decode_each_extrinsic(spec, data, max_trailing, |ext| {
+			exts.push(ext?);
+			Ok(())
+		})?;
+		Ok((exts, trailing))
+	}
+
+	/// Like [`Decoder::decode_extrinsics`], but an extrinsic which can't be decoded doesn't stop the rest from being
+	/// decoded; the result of decoding each one is handed back in order. The whole thing is still an error if the
+	/// data can't be split up into the number of extrinsics that it claims to hold, or if there are bytes left over.
+	pub fn decode_all_extrinsics(
+		&self,
+		spec: SpecVersion,
+		data: &[u8],
+	) -> Result<Vec<Result<GenericExtrinsic, Error>>, Error> {
+		let mut exts = Vec::new();
+		self.decode_each_extrinsic(spec, data, 0, |ext| {
+			exts.push(ext);
+			Ok(())
+		})?;
+		Ok(exts)
+	}
+
+	/// Split a Vec<Extrinsic> into extrinsics, handing the result of decoding each to `on_extrinsic` (which
+	/// can stop things early by returning an error), and return any trailing bytes.
+	fn decode_each_extrinsic<'b>(
+		&self,
+		spec: SpecVersion,
+		data: &'b [u8],
+		max_trailing: usize,
+		mut on_extrinsic: impl FnMut(Result<GenericExtrinsic, Error>) -> Result<(), Error>,
+	) -> Result<&'b [u8], Error> {
 		let (length, prefix) = Self::scale_length(data)?;
+		// Every extrinsic needs at least a byte for its length.
+		check_length_fits(length, 1, data.len() - prefix)?;
 		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
 		log::trace!("Decoding {} Total Extrinsics. CALLS: {:#?}", length, meta.modules_by_call_index);
 		log::trace!("Extrinsics bytes: {data:?}");
+		let mut count = 0;
 		let mut state = DecodeState::new(None, None, meta, prefix, spec, data);
-		for (idx, extrinsic) in ChunkedExtrinsic::new(&data[prefix..]).enumerate() {
+		let mut chunks = ChunkedExtrinsic::new(&data[prefix..]);
+		for (idx, extrinsic) in chunks.by_ref().take(length).enumerate() {
 			log::trace!("Extrinsic {}:{:?}", idx, extrinsic);
 			state.reset(extrinsic);
-			ext.push(self.decode_extrinsic(&mut state)?);
+			on_extrinsic(self.decode_extrinsic(&mut state))?;
+			count += 1;
+		}
+		if count < length {
+			// The next extrinsic couldn't be split off; either the data ends where its length should be, or it
+			// says that it's longer than the bytes that are left.
+			let rest = chunks.remaining();
+			if rest.is_empty() {
+				return Err(Error::LengthExceedsInput { length: length - count, remaining: 0 });
+			}
+			let (ext_length, ext_prefix) = Self::scale_length(rest)?;
+			return Err(Error::LengthExceedsInput { length: ext_length, remaining: rest.len() - ext_prefix });
 		}
 
+		let trailing = chunks.remaining();
+		if trailing.len() > max_trailing {
+			return Err(Error::ExcessBytes(trailing.len()));
+		}
+		Ok(trailing)
+	}
+
+	/// Decode extrinsics which have already been split apart, such as those returned in a block from the
+	/// `chain_getBlock` RPC call. Each one is expected to begin with its compact encoded length.
+	pub fn decode_extrinsic_list<B: AsRef<[u8]>>(
+		&self,
+		spec: SpecVersion,
+		exts: &[B],
+	) -> Result<Vec<GenericExtrinsic>, Error> {
+		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
+		let mut decoded = Vec::with_capacity(exts.len());
+		let mut state = DecodeState::new(None, None, meta, 0, spec, &[]);
+		for (idx, ext) in exts.iter().enumerate() {
+			let ext = ext.as_ref();
+			log::trace!("Extrinsic {}:{:?}", idx, ext);
+			let (length, prefix) = Self::scale_length(ext)?;
+			check_length_fits(length, 1, ext.len() - prefix)?;
+			state.reset(&ext[prefix..prefix + length]);
+			decoded.push(self.decode_extrinsic(&mut state)?);
+			let excess = ext.len() - prefix - state.cursor();
+			if excess > 0 {
+				return Err(Error::ExcessBytes(excess));
+			}
+		}
+
+		Ok(decoded)
+	}
+
+	/// Decode a single extrinsic, which is expected to begin with its compact encoded length. Any bytes in `data`
+	/// beyond that length, or left undecoded within it, are an error.
+	pub fn decode_single_extrinsic(&self, spec: SpecVersion, data: &[u8]) -> Result<GenericExtrinsic, Error> {
+		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
+		let (length, prefix) = Self::scale_length(data)?;
+		check_length_fits(length, 1, data.len() - prefix)?;
+		let mut state = DecodeState::new(None, None, meta, 0, spec, &data[prefix..prefix + length]);
+		let ext = self.decode_extrinsic(&mut state)?;
+		let excess = data.len() - prefix - state.cursor();
+		if excess > 0 {
+			return Err(Error::ExcessBytes(excess));
+		}
 		Ok(ext)
 	}
 
+	/// Decode a single call (module index, call index and arguments) that is not wrapped in an extrinsic,
+	/// such as a scheduled call. `data` is advanced past the bytes consumed.
+	pub fn decode_call_data(&self, spec: SpecVersion, data: &mut &[u8]) -> Result<GenericCall, Error> {
+		if data.len() < 2 {
+			// The module index and call index are a byte each.
+			return Err(Error::LengthExceedsInput { length: 2, remaining: data.len() });
+		}
+		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
+		let mut state = DecodeState::new(None, None, meta, 0, spec, data);
+		state.load_module()?;
+		let args = self.decode_call(&mut state)?;
+		let call = state.call.borrow().as_ref().map(|c| c.name()).unwrap_or_else(|| "unknown".into());
+		let call = GenericCall::new(state.module_name().into(), call, args);
+		*data = &data[state.cursor()..];
+		Ok(call)
+	}
+
+	/// Decode a Vec<EventRecord>, as found in the `System.Events` storage entry. Exactly as many records
+	/// as the compact count prefixing them are decoded, and it is an error if any bytes are left over.
+	pub fn decode_events(&self, spec: SpecVersion, data: &[u8]) -> Result<Vec<GenericEvent>, Error> {
+		let (length, prefix) = Self::scale_length(data)?;
+		// Every event record starts with at least a byte for its phase.
+		check_length_fits(length, 1, data.len() - prefix)?;
+		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
+		log::trace!("Decoding {} Total Events.", length);
+		let mut state = DecodeState::new(None, None, meta, prefix, spec, data);
+		let mut events = Vec::with_capacity(length);
+		for idx in 0..length {
+			log::trace!("Event {}", idx);
+			if state.cursor() >= data.len() {
+				return Err(Error::LengthExceedsInput { length: length - idx, remaining: 0 });
+			}
+			events.push(self.decode_event(&mut state)?);
+		}
+		if state.cursor() < data.len() {
+			return Err(Error::ExcessBytes(data.len() - state.cursor()));
+		}
+		Ok(events)
+	}
+
+	/// Decode the arguments of a single event, given the module and event index it was emitted with. This is
+	/// useful if the phase and indexes of an `EventRecord` have already been read; `data` should start at the
+	/// first argument of the event, and is advanced past the bytes consumed. Since they are not part of
+	/// `data`, the phase of the returned event is `Null` and it has no topics.
+	pub fn decode_single_event(
+		&self,
+		spec: SpecVersion,
+		module_index: u8,
+		event_index: u8,
+		data: &mut &[u8],
+	) -> Result<GenericEvent, Error> {
+		let meta = self.versions.get(&spec).ok_or(Error::MissingSpec(spec))?;
+		let module = meta.module_by_index(ModuleIndex::Event(module_index))?;
+		let mut state = DecodeState::new(Some(module), None, meta, 0, spec, data);
+		let (name, args) = self.decode_event_args(&mut state, event_index)?;
+		let event = GenericEvent::new(SubstrateType::Null, module.name().into(), name, args, Vec::new());
+		*data = &data[state.cursor()..];
+		Ok(event)
+	}
+
+	/// Decode a single EventRecord (phase, event and topics)
+	fn decode_event(&self, state: &mut DecodeState) -> Result<GenericEvent, Error> {
+		state.module.reset();
+		let phase = self.decode_single(state, &RustTypeMarker::TypePointer("Phase".into()), false)?;
+
+		state.load_event_module()?;
+		let event_index = state.do_index();
+		let (name, args) = self.decode_event_args(state, event_index)?;
+
+		let topics = match self.decode_single(
+			state,
+			&RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::TypePointer("Hash".into())))),
+			false,
+		)? {
+			SubstrateType::Composite(topics) => topics,
+			other => vec![other],
+		};
+
+		Ok(GenericEvent::new(phase, state.module_name().into(), name, args, topics))
+	}
+
+	/// Decode the arguments of the event at `event_index` in the module currently loaded into `state`.
+	fn decode_event_args(
+		&self,
+		state: &mut DecodeState,
+		event_index: u8,
+	) -> Result<(String, Vec<SubstrateType>), Error> {
+		let event = state
+			.module
+			.event(event_index)?
+			.ok_or_else(|| Error::from("No module loaded while decoding event"))?
+			.clone();
+		log::trace!("Decoding event {} in module {}", event.name, state.module_name());
+		let args = event
+			.types()
+			.map(|ty| self.decode_single(state, ty, false))
+			.collect::<Result<Vec<SubstrateType>, Error>>()?;
+		Ok((event.name, args))
+	}
+
 	/// Decode an extrinsic
 	fn decode_extrinsic(&self, state: &mut DecodeState) -> Result<GenericExtrinsic, Error> {
 		let signature = if state.interpret_version() { Some(self.decode_signature(state)?) } else { None };
@@ -484,7 +860,7 @@ impl Decoder {
 		let types = self.decode_call(state)?;
 		log::debug!("Finished cursor length={}", state.cursor());
 		let call = state.call.borrow().as_ref().map(|c| c.name()).unwrap_or_else(|| "unknown".into());
-		Ok(GenericExtrinsic::new(signature, types, call, state.module_name().into()))
+		Ok(GenericExtrinsic::new(signature, types, call, state.module_name().into()).with_ss58_prefix(self.ss58_prefix))
 	}
 
 	/// Decode the signature part of an UncheckedExtrinsic
@@ -606,7 +982,7 @@ impl Decoder {
 			RustTypeMarker::Std(v) => match v {
 				CommonTypes::Vec(v) => {
 					log::trace!("Vec::cursor={}", state.cursor());
-					let length = state.scale_length()?;
+					let length = state.scale_length(min_encoded_len(v))?;
 					let mut vec = Vec::new();
 					if length == 0 {
 						return Ok(SubstrateType::Composite(Vec::new()));
@@ -652,8 +1028,26 @@ impl Decoder {
 						}
 					}
 				}
+				CommonTypes::Map(k, v) => {
+					log::trace!("Map::cursor={}", state.cursor());
+					let length = state.scale_length(min_encoded_len(k) + min_encoded_len(v))?;
+					let mut entries = Vec::new();
+					for _ in 0..length {
+						state.observe(line!());
+						let key = self.decode_single(state, k, is_compact)?;
+						let value = self.decode_single(state, v, is_compact)?;
+						entries.push((key, value));
+					}
+					SubstrateType::Map(entries)
+				}
 				CommonTypes::Compact(v) => {
-					log::trace!("COMPACT SWITCHED! Compact::cursor={}", state.cursor());
+					// Only the innermost integer of a `Compact` is compact encoded, so a `Compact` nested in
+					// another one (like `Compact<Compact<u32>>`) is decoded exactly the same as a single `Compact`.
+					if is_compact {
+						log::trace!("Nested Compact, already decoding compactly. cursor={}", state.cursor());
+					} else {
+						log::trace!("COMPACT SWITCHED! Compact::cursor={}", state.cursor());
+					}
 					self.decode_single(state, v, true)?
 				}
 			},
@@ -764,6 +1158,10 @@ impl Decoder {
 				boo.into()
 			}
 			RustTypeMarker::Null => SubstrateType::Null,
+			RustTypeMarker::DoNotConstruct => {
+				log::warn!("Skipping `DoNotConstruct` type at cursor={}; no bytes consumed", state.cursor());
+				SubstrateType::Null
+			}
 		};
 		Ok(ty)
 	}
@@ -942,6 +1340,35 @@ impl Decoder {
 	}
 }
 
+/// The fewest bytes that a value of the given type can be encoded in (compactly or not). Types whose
+/// size can't be cheaply told, like structs or pointers to other types, are assumed to take up no bytes.
+fn min_encoded_len(ty: &RustTypeMarker) -> usize {
+	match ty {
+		RustTypeMarker::U8
+		| RustTypeMarker::U16
+		| RustTypeMarker::U32
+		| RustTypeMarker::U64
+		| RustTypeMarker::U128
+		| RustTypeMarker::I8
+		| RustTypeMarker::I16
+		| RustTypeMarker::I32
+		| RustTypeMarker::I64
+		| RustTypeMarker::I128
+		| RustTypeMarker::Bool
+		| RustTypeMarker::Enum(_)
+		| RustTypeMarker::Std(_) => 1,
+		_ => 0,
+	}
+}
+
+/// Check that `length` items of at least `min_item_len` bytes each can fit in `remaining` bytes.
+fn check_length_fits(length: usize, min_item_len: usize, remaining: usize) -> Result<(), Error> {
+	if length.saturating_mul(min_item_len) > remaining {
+		return Err(Error::LengthExceedsInput { length, remaining });
+	}
+	Ok(())
+}
+
 /// Decodes old address pre-refactor (<https://github.com/paritytech/substrate/pull/7380>)
 /// and converts it to a MultiAddress, where "old" here means anything before v0.8.26 or 26/2026/46 on polkadot/kusama/westend respectively.
 fn decode_old_address(state: &DecodeState) -> Result<substrate_types::Address, Error> {
@@ -1015,6 +1442,45 @@ mod tests {
 		}
 	}
 
+	/// Resolves the types used by the events in the mock metadata.
+	#[derive(Debug, Clone)]
+	struct EventTypes {
+		phase: RustTypeMarker,
+		hash: RustTypeMarker,
+	}
+
+	impl EventTypes {
+		fn new() -> Self {
+			let phase = RustTypeMarker::Enum(vec![
+				RustEnumField::new("ApplyExtrinsic".to_string(), Some(RustTypeMarker::U32)),
+				RustEnumField::new("Finalization".to_string(), None),
+				RustEnumField::new("Initialization".to_string(), None),
+			]);
+			Self { phase, hash: RustTypeMarker::TypePointer("H256".to_string()) }
+		}
+	}
+
+	impl TypeDetective for EventTypes {
+		fn get(&self, _chain: &str, _spec: u32, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+			match ty {
+				"Phase" => Some(&self.phase),
+				"TestEvent0" => Some(&RustTypeMarker::U32),
+				"TestEvent1" => Some(&RustTypeMarker::Bool),
+				"TestEvent2" => Some(&RustTypeMarker::U64),
+				"Hash" => Some(&self.hash),
+				_ => None,
+			}
+		}
+
+		fn try_fallback(&self, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+
+		fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+	}
+
 	#[test]
 	fn should_insert_metadata() {
 		let mut decoder = Decoder::new(GenericTypes, Chain::Kusama);
@@ -1026,6 +1492,82 @@ mod tests {
 		assert!(decoder.versions.contains_key(&test_suite::mock_runtime(2).spec_version))
 	}
 
+	#[test]
+	fn should_use_chain_ss58_prefix() {
+		assert_eq!(Chain::Polkadot.ss58_prefix(), 0);
+		assert_eq!(Chain::Kusama.ss58_prefix(), 2);
+		assert_eq!(Chain::Westend.ss58_prefix(), 42);
+		assert_eq!(Chain::Custom("dev".into()).ss58_prefix(), Chain::DEFAULT_SS58_PREFIX);
+
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		assert_eq!(decoder.ss58_prefix(), 2);
+		assert_eq!(decoder.for_chain(Chain::Polkadot).ss58_prefix(), 0);
+
+		let mut decoder = decoder.for_chain(Chain::Custom("dev".into()));
+		decoder.set_ss58_prefix(7);
+		assert_eq!(decoder.ss58_prefix(), 7);
+		assert_eq!(decoder.clone().ss58_prefix(), 7);
+	}
+
+	#[test]
+	fn should_use_chain_genesis_hash() {
+		let genesis_hash = |chain: Chain| chain.genesis_hash().map(hex::encode);
+		assert_eq!(
+			genesis_hash(Chain::Polkadot).as_deref(),
+			Some("91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3")
+		);
+		assert_eq!(
+			genesis_hash(Chain::Kusama).as_deref(),
+			Some("b0a8d493285c2df73290dfb7e61f870f17b41801197a149ca93654499ea3dafe")
+		);
+		assert_eq!(
+			genesis_hash(Chain::Westend).as_deref(),
+			Some("e143f23803ac50e8f6f8e62695d1ce9e4e1d68aa36c1cd2cfd15340213f3423e")
+		);
+		assert_eq!(Chain::Custom("dev".into()).genesis_hash(), None);
+
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		assert_eq!(decoder.genesis_hash(), Chain::Kusama.genesis_hash());
+		assert_eq!(decoder.for_chain(Chain::Polkadot).genesis_hash(), Chain::Polkadot.genesis_hash());
+
+		let mut decoder = decoder.for_chain(Chain::Custom("dev".into()));
+		assert_eq!(decoder.genesis_hash(), None);
+		decoder.set_genesis_hash([1; 32]);
+		assert_eq!(decoder.genesis_hash(), Some([1; 32]));
+		assert_eq!(decoder.clone().genesis_hash(), Some([1; 32]));
+	}
+
+	#[test]
+	fn should_expose_chain() {
+		let decoder = Decoder::new(GenericTypes, Chain::Centrifuge);
+		assert_eq!(decoder.chain(), "centrifuge-chain");
+		assert_eq!(decoder.for_chain(Chain::Custom("dev".into())).chain(), "dev");
+	}
+
+	#[test]
+	fn should_remove_versions() {
+		let mut decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		decoder.register_version(1, meta_test_suite::test_metadata()).unwrap();
+		assert!(decoder.has_version(&1));
+
+		assert!(decoder.remove_version(1));
+		assert!(!decoder.has_version(&1));
+		assert!(!decoder.remove_version(1));
+	}
+
+	#[test]
+	fn should_register_versions_in_bulk() {
+		let bytes = include_bytes!("../../integration-tests/data/metadata_v12_block4643974.bin");
+		let mut decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		decoder.register_versions([(1, bytes.to_vec()), (2, bytes.to_vec()), (3, bytes.to_vec())]).unwrap();
+		assert!([1, 2, 3].iter().all(|v| decoder.has_version(v)));
+
+		let res = decoder.register_versions([(4, &bytes[..]), (5, &[0u8; 4][..]), (6, &bytes[..])]);
+		assert!(matches!(res, Err(Error::RegisterVersion { version: 5, .. })));
+		assert!(decoder.has_version(&4));
+		assert!(!decoder.has_version(&6));
+	}
+
 	#[test]
 	fn should_get_version_metadata() {
 		let mut decoder = Decoder::new(GenericTypes, Chain::Kusama);
@@ -1036,6 +1578,18 @@ mod tests {
 		assert_eq!(Some(&meta), _other_meta.clone())
 	}
 
+	#[test]
+	fn should_get_metadata_at_or_below() {
+		let mut decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		for version in [10, 20, 30] {
+			decoder.register_version(version, meta_test_suite::test_metadata()).unwrap();
+		}
+		assert_eq!(decoder.metadata_at_or_below(25).map(|(v, _)| v), Some(20));
+		assert_eq!(decoder.metadata_at_or_below(30).map(|(v, _)| v), Some(30));
+		assert_eq!(decoder.metadata_at_or_below(u32::MAX).map(|(v, _)| v), Some(30));
+		assert!(decoder.metadata_at_or_below(9).is_none());
+	}
+
 	#[test]
 	fn should_get_scale_length() {
 		let encoded = vec![32, 4].encode();
@@ -1046,6 +1600,30 @@ mod tests {
 		assert_eq!(len.0, 2);
 	}
 
+	#[test]
+	fn should_get_scale_length_in_every_compact_mode() {
+		// The largest and smallest lengths of each mode, and how many bytes each mode takes up:
+		let lengths = [(0, 1), (63, 1), (64, 2), ((1 << 14) - 1, 2), (1 << 14, 4), ((1 << 30) - 1, 4), (1 << 30, 5)];
+		for (length, prefix) in lengths.into_iter().chain([(u32::MAX, 5)]) {
+			let mut encoded = Compact(length).encode();
+			encoded.push(0xff);
+			assert_eq!(Decoder::scale_length(&encoded).unwrap(), (length as usize, prefix), "length {length}");
+		}
+
+		// The cursor moves past the whole of a big integer mode prefix before the length is checked:
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let mut data = Compact(1u32 << 30).encode();
+		data.extend([1u8; 3]);
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, data.as_slice());
+		let res = decoder.decode_single(
+			&mut state,
+			&RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::U8))),
+			false,
+		);
+		assert!(matches!(res, Err(Error::LengthExceedsInput { length, remaining: 3 }) if length == 1 << 30), "{res:?}");
+	}
+
 	macro_rules! decode_test {
 		( $v: expr, $x:expr, $r: expr) => {{
 			let val = $v.encode();
@@ -1090,6 +1668,106 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_decode_nested_compact() {
+		let compact = |ty| RustTypeMarker::Std(CommonTypes::Compact(Box::new(ty)));
+
+		let val = Compact(0x1337u32);
+		decode_test!(val, compact(RustTypeMarker::U32), SubstrateType::U32(0x1337));
+		// The integer is only compact encoded once, however many `Compact`s it is wrapped in.
+		decode_test!(val, compact(compact(RustTypeMarker::U32)), SubstrateType::U32(0x1337));
+		decode_test!(val, compact(compact(compact(RustTypeMarker::U32))), SubstrateType::U32(0x1337));
+	}
+
+	#[test]
+	fn should_reject_lengths_longer_than_the_input() {
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let vec_of = |ty| RustTypeMarker::Std(CommonTypes::Vec(Box::new(ty)));
+
+		// A length close to `u32::MAX` fits in a `usize` even on 32 bit targets, but not in the input.
+		let mut data = Compact(u32::MAX - 1).encode();
+		data.extend([1u8; 8]);
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, data.as_slice());
+		let res = decoder.decode_single(&mut state, &vec_of(RustTypeMarker::U32), false);
+		assert!(
+			matches!(res, Err(Error::LengthExceedsInput { length, remaining: 8 }) if length == (u32::MAX - 1) as usize)
+		);
+
+		// Items that can take up no bytes at all aren't limited by the input length.
+		let data = Compact(3u32).encode();
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, data.as_slice());
+		let res = decoder.decode_single(&mut state, &vec_of(RustTypeMarker::Null), false).unwrap();
+		assert_eq!(res, SubstrateType::Composite(vec![SubstrateType::Null; 3]));
+	}
+
+	/// Resolves `Weight` to a type which fails to decode part way through, with a fallback that decodes.
+	#[derive(Debug, Clone)]
+	struct FallbackTypes {
+		weight: RustTypeMarker,
+	}
+
+	impl TypeDetective for FallbackTypes {
+		fn get(&self, _chain: &str, _spec: u32, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+			match ty {
+				"Weight" => Some(&self.weight),
+				_ => None,
+			}
+		}
+
+		fn try_fallback(&self, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+			match ty {
+				"Weight" => Some(&RustTypeMarker::U64),
+				_ => None,
+			}
+		}
+
+		fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+	}
+
+	#[test]
+	fn should_fall_back_for_each_vec_element() {
+		// The first half of a `Weight` decodes before the second half fails to resolve, so each element
+		// only decodes properly if the fallback starts again from the beginning of that element.
+		let weight =
+			RustTypeMarker::Tuple(vec![RustTypeMarker::U32, RustTypeMarker::TypePointer("Unknown".to_string())]);
+		let decoder = Decoder::new(FallbackTypes { weight }, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let ty = RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::TypePointer("Weight".to_string()))));
+
+		let data = vec![1u64, 0x1337, u64::MAX].encode();
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, data.as_slice());
+		let res = decoder.decode_single(&mut state, &ty, false).unwrap();
+		assert_eq!(
+			res,
+			SubstrateType::Composite(vec![
+				SubstrateType::U64(1),
+				SubstrateType::U64(0x1337),
+				SubstrateType::U64(u64::MAX)
+			])
+		);
+		assert_eq!(state.cursor(), data.len());
+	}
+
+	#[test]
+	fn should_decode_map() {
+		let val: Vec<(u32, bool)> = vec![(1, true), (0x1337, false)];
+		let map = RustTypeMarker::Std(CommonTypes::Map(Box::new(RustTypeMarker::U32), Box::new(RustTypeMarker::Bool)));
+		let expected = SubstrateType::Map(vec![
+			(SubstrateType::U32(1), SubstrateType::Bool(true)),
+			(SubstrateType::U32(0x1337), SubstrateType::Bool(false)),
+		]);
+		decode_test!(val, map, expected);
+
+		assert_eq!(
+			serde_json::to_value(&expected).unwrap(),
+			serde_json::json!([{ "key": 1, "value": true }, { "key": 0x1337, "value": false }])
+		);
+		assert_eq!(expected.to_string(), "{1: true, 4919: false}");
+	}
+
 	#[test]
 	fn should_decode_vector() {
 		let val: Vec<u32> = vec![12, 32, 0x1337, 62];
@@ -1117,6 +1795,38 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_decode_nested_byte_vectors() {
+		// The outer length prefix counts elements, and each element has a length prefix of its own.
+		let val: Vec<Vec<u8>> = vec![vec![], vec![1], vec![2; 70]];
+		let bytes = |v: &[u8]| SubstrateType::Composite(v.iter().copied().map(SubstrateType::U8).collect());
+		decode_test!(
+			val,
+			RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::Std(CommonTypes::Vec(Box::new(
+				RustTypeMarker::U8
+			)))))),
+			SubstrateType::Composite(vec![bytes(&[]), bytes(&[1]), bytes(&[2; 70])])
+		);
+	}
+
+	#[test]
+	fn should_decode_raw_multi_address() {
+		// 70 bytes needs a two byte compact length prefix:
+		let address = substrate_types::Address::Raw(vec![7; 70]);
+		let mut encoded = address.encode();
+		assert_eq!(encoded.len(), 1 + 2 + 70);
+		encoded.push(0xff);
+
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, encoded.as_slice());
+		let ty = RustTypeMarker::TypePointer("GenericMultiAddress".into());
+		let res = decoder.decode_single(&mut state, &ty, false).unwrap();
+
+		assert_eq!(res, SubstrateType::Address(address));
+		assert_eq!(state.cursor(), encoded.len() - 1);
+	}
+
 	#[test]
 	fn should_decode_array() {
 		let val: [u32; 4] = [12, 32, 0x1337, 62];
@@ -1132,6 +1842,23 @@ mod tests {
 		)
 	}
 
+	#[test]
+	fn should_decode_64_byte_array() {
+		// ie an sr25519 signature, which must not be truncated
+		let val: Vec<u8> = (0..64).collect();
+		let encoded = <[u8; 64]>::try_from(val.as_slice()).unwrap().encode();
+		assert_eq!(encoded.len(), 64);
+
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, encoded.as_slice());
+		let ty = crate::parse_type("[u8; 64]").unwrap();
+		let res = decoder.decode_single(&mut state, &ty, false).unwrap();
+
+		assert_eq!(res, SubstrateType::Composite(val.into_iter().map(SubstrateType::U8).collect()));
+		assert_eq!(state.cursor(), 64);
+	}
+
 	#[test]
 	fn should_decode_struct() {
 		#[derive(Encode, Decode)]
@@ -1164,6 +1891,25 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_skip_do_not_construct() {
+		let val: (u32, u32) = (0x1337, 42);
+		let encoded = val.encode();
+		let decoder = Decoder::new(GenericTypes, Chain::Kusama);
+		let meta = meta_test_suite::test_metadata();
+		let mut state = DecodeState::new(None, None, &meta, 0, 1031, encoded.as_slice());
+		let res = decoder
+			.decode_single(
+				&mut state,
+				&RustTypeMarker::Tuple(vec![RustTypeMarker::U32, RustTypeMarker::DoNotConstruct]),
+				false,
+			)
+			.unwrap();
+		assert_eq!(res, SubstrateType::Composite(vec![SubstrateType::U32(0x1337), SubstrateType::Null]));
+		// the bytes of the `DoNotConstruct` type are left for the caller
+		assert_eq!(&encoded[state.cursor()..], &42u32.encode()[..]);
+	}
+
 	#[test]
 	fn should_decode_tuple() {
 		let val: (u32, u32, u32, u32) = (18, 32, 42, 0x1337);
@@ -1281,4 +2027,411 @@ mod tests {
 		assert_eq!(chunked.next(), Some(vec![3, 4, 5].as_slice()));
 		assert_eq!(chunked.next(), Some(vec![6, 7, 8].as_slice()));
 	}
+
+	#[test]
+	fn should_decode_events() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		let mut first = Vec::new();
+		// Phase::ApplyExtrinsic(1)
+		first.push(0u8);
+		first.extend(1u32.encode());
+		// TestModule1, TestEvent0(0x1337, true, 42)
+		first.extend([1u8, 0u8]);
+		first.extend((0x1337u32, true, 42u64).encode());
+		first.extend(vec![[7u8; 32]].encode());
+
+		let mut second = Vec::new();
+		// Phase::Finalization
+		second.push(1u8);
+		// TestModule2, TestEvent0(1, false, 2)
+		second.extend([2u8, 0u8]);
+		second.extend((1u32, false, 2u64).encode());
+		second.extend(Vec::<[u8; 32]>::new().encode());
+
+		let mut events_blob = Compact(2u32).encode();
+		events_blob.extend(first);
+		events_blob.extend(second);
+
+		let events = decoder.decode_events(1031, &events_blob).unwrap();
+		assert_eq!(events.len(), 2);
+
+		assert_eq!(
+			events[0].phase(),
+			&SubstrateType::Enum(EnumField::new("ApplyExtrinsic".to_string(), Some(SubstrateType::U32(1).into())))
+		);
+		assert_eq!(events[0].module(), "TestModule1");
+		assert_eq!(events[0].name(), "TestEvent0");
+		assert_eq!(events[0].args(), &[SubstrateType::U32(0x1337), SubstrateType::Bool(true), SubstrateType::U64(42)]);
+		assert_eq!(events[0].topics(), &[SubstrateType::H256([7u8; 32].into())]);
+
+		assert_eq!(events[1].phase(), &SubstrateType::Enum(EnumField::new("Finalization".to_string(), None)));
+		assert_eq!(events[1].module(), "TestModule2");
+		assert_eq!(events[1].args(), &[SubstrateType::U32(1), SubstrateType::Bool(false), SubstrateType::U64(2)]);
+		assert!(events[1].topics().is_empty());
+	}
+
+	#[test]
+	fn should_decode_exactly_the_number_of_events_given() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// Phase::Finalization, TestModule2, TestEvent0(1, false, 2), no topics
+		let mut record = vec![1u8, 2u8, 0u8];
+		record.extend((1u32, false, 2u64).encode());
+		record.extend(Vec::<[u8; 32]>::new().encode());
+
+		let mut events_blob = Compact(1u32).encode();
+		events_blob.extend(&record);
+		assert_eq!(decoder.decode_events(1031, &events_blob).unwrap().len(), 1);
+
+		// Bytes after the last record are an error:
+		let mut trailing = events_blob.clone();
+		trailing.push(0xff);
+		assert!(matches!(decoder.decode_events(1031, &trailing), Err(Error::ExcessBytes(1))));
+
+		// As is claiming more records than there are:
+		let mut too_few = Compact(2u32).encode();
+		too_few.extend(&record);
+		let res = decoder.decode_events(1031, &too_few);
+		assert!(matches!(res, Err(Error::LengthExceedsInput { length: 1, remaining: 0 })), "{res:?}");
+		// Or more records than could possibly fit in the data:
+		let mut too_many = Compact(u32::MAX).encode();
+		too_many.extend(&record);
+		assert!(matches!(decoder.decode_events(1031, &too_many), Err(Error::LengthExceedsInput { .. })));
+	}
+
+	#[test]
+	fn should_fail_on_unknown_event_module() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		let mut events_blob = Compact(1u32).encode();
+		// Phase::Initialization, module 9 does not exist
+		events_blob.extend([2u8, 9u8, 0u8]);
+		assert!(decoder.decode_events(1031, &events_blob).is_err());
+	}
+
+	#[test]
+	fn should_decode_call_data() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// TestModule1, foo_function1(42), followed by a trailing byte that is not part of the call.
+		let mut data = vec![1u8, 2u8];
+		data.extend(42u64.encode());
+		data.push(0xff);
+
+		let mut cursor = &data[..];
+		let call = decoder.decode_call_data(1031, &mut cursor).unwrap();
+		assert_eq!(call.module(), "TestModule1");
+		assert_eq!(call.name(), "foo_function1");
+		assert_eq!(call.args().len(), 1);
+		assert_eq!(call.args()[0].name, "foo_arg");
+		assert_eq!(call.args()[0].arg, SubstrateType::U64(42));
+		assert_eq!(cursor, &[0xff]);
+
+		let res = decoder.decode_call_data(1031, &mut &[1u8][..]);
+		assert!(matches!(res, Err(Error::LengthExceedsInput { length: 2, remaining: 1 })), "{res:?}");
+		assert!(decoder.decode_call_data(1, &mut &data[..]).is_err());
+	}
+
+	#[tokio::test(flavor = "multi_thread")]
+	async fn should_decode_concurrently_with_shared_decoder() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+		let decoder = std::sync::Arc::new(decoder);
+
+		let tasks = (0..8u64)
+			.map(|n| {
+				let decoder = decoder.clone();
+				tokio::spawn(async move {
+					// TestModule1, foo_function1(n)
+					let mut data = vec![1u8, 2u8];
+					data.extend(n.encode());
+					decoder.decode_call_data(1031, &mut &data[..]).unwrap()
+				})
+			})
+			.collect::<Vec<_>>();
+
+		for (n, task) in tasks.into_iter().enumerate() {
+			let call = task.await.unwrap();
+			assert_eq!(call.args()[0].arg, SubstrateType::U64(n as u64));
+		}
+	}
+
+	#[test]
+	fn should_decode_single_event() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// TestEvent0(0x1337, true, 42), followed by the (empty) topics of the record.
+		let mut data = (0x1337u32, true, 42u64).encode();
+		data.extend(Vec::<[u8; 32]>::new().encode());
+
+		let mut cursor = &data[..];
+		let event = decoder.decode_single_event(1031, 2, 0, &mut cursor).unwrap();
+		assert_eq!(event.module(), "TestModule2");
+		assert_eq!(event.name(), "TestEvent0");
+		assert_eq!(event.args(), &[SubstrateType::U32(0x1337), SubstrateType::Bool(true), SubstrateType::U64(42)]);
+		assert_eq!(event.phase(), &SubstrateType::Null);
+		assert!(event.topics().is_empty());
+		assert_eq!(cursor, &[0u8]);
+
+		assert!(decoder.decode_single_event(1031, 9, 0, &mut &data[..]).is_err());
+	}
+
+	#[test]
+	fn should_decode_extrinsic_accessors() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// An unsigned extrinsic calling TestModule1.foo_function1(42)
+		let mut ext = vec![4u8, 1u8, 2u8];
+		ext.extend(42u64.encode());
+		let data = vec![ext].encode();
+
+		let extrinsics = decoder.decode_extrinsics(1031, &data).unwrap();
+		assert_eq!(extrinsics.len(), 1);
+		let ext = &extrinsics[0];
+		assert!(ext.signature().is_none());
+		assert_eq!(ext.module_name(), "TestModule1");
+		assert_eq!(ext.call_name(), "foo_function1");
+		assert_eq!(ext.args().len(), 1);
+		assert_eq!((ext.args()[0].name.as_str(), &ext.args()[0].arg), ("foo_arg", &SubstrateType::U64(42)));
+		assert_eq!(ext.to_string(), "TestModule1.foo_function1(foo_arg: 42)");
+	}
+
+	#[test]
+	fn should_only_allow_trailing_bytes_when_asked() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// An unsigned extrinsic calling TestModule1.foo_function1(42), and a junk byte after it.
+		let mut ext = vec![4u8, 1u8, 2u8];
+		ext.extend(42u64.encode());
+		let mut data = vec![ext].encode();
+		data.push(4);
+
+		assert!(matches!(decoder.decode_extrinsics(1031, &data), Err(Error::ExcessBytes(1))));
+		let (extrinsics, trailing) = decoder.decode_extrinsics_allow_trailing(1031, &data, 1).unwrap();
+		assert_eq!(extrinsics.len(), 1);
+		assert_eq!(trailing, &[4]);
+		assert!(matches!(decoder.decode_extrinsics_allow_trailing(1031, &data, 0), Err(Error::ExcessBytes(1))));
+
+		// Claiming more extrinsics than there are is still an error:
+		data[0] = Compact(2u32).encode()[0];
+		let res = decoder.decode_extrinsics_allow_trailing(1031, &data, 1);
+		assert!(matches!(res, Err(Error::LengthExceedsInput { length: 1, remaining: 0 })), "{res:?}");
+		data.pop();
+		let res = decoder.decode_extrinsics_allow_trailing(1031, &data, 1);
+		assert!(matches!(res, Err(Error::LengthExceedsInput { length: 1, remaining: 0 })), "{res:?}");
+	}
+
+	#[test]
+	fn should_decode_all_extrinsics_despite_failures() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// TestModule1.foo_function1(42), a call to a module that doesn't exist, and TestModule1.foo_function1(7).
+		let call = |arg: u64| [vec![4u8, 1u8, 2u8], arg.encode()].concat();
+		let data = vec![call(42), vec![4u8, 200u8, 0u8], call(7)].encode();
+
+		assert!(decoder.decode_extrinsics(1031, &data).is_err());
+		let extrinsics = decoder.decode_all_extrinsics(1031, &data).unwrap();
+		assert_eq!(extrinsics.len(), 3);
+		assert_eq!(extrinsics[0].as_ref().unwrap().args()[0].arg, SubstrateType::U64(42));
+		assert!(extrinsics[1].is_err());
+		assert_eq!(extrinsics[2].as_ref().unwrap().args()[0].arg, SubstrateType::U64(7));
+
+		// Data which can't be split into extrinsics is still an error as a whole.
+		assert!(decoder.decode_all_extrinsics(1031, &data[..data.len() - 1]).is_err());
+		assert!(matches!(
+			decoder.decode_all_extrinsics(1031, &[data.clone(), vec![0]].concat()),
+			Err(Error::ExcessBytes(1))
+		));
+	}
+
+	#[test]
+	fn should_record_unresolved_types_while_decoding() {
+		let types = crate::RecordingTypeDetective::new(EventTypes::new());
+		let mut decoder = Decoder::new(types.clone(), Chain::Kusama);
+		decoder.register_version(1031, meta_test_suite::test_metadata()).unwrap();
+
+		// TestModule0.foo_function2 takes a `SomeType`, which the types don't know about.
+		let data = [0u8, 1u8, 0u8];
+		assert!(decoder.decode_call_data(1031, &mut &data[..]).is_err());
+		assert_eq!(types.unresolved(), vec![("TestModule0".to_string(), "SomeType".to_string())]);
+	}
+
+	/// V13 metadata for a runtime with a single `System` module and the signed extensions given, encoded as it
+	/// would be fetched from a node.
+	fn v13_metadata_bytes(signed_extensions: &[&str]) -> Vec<u8> {
+		use frame_metadata::{
+			decode_different::DecodeDifferent::Decoded,
+			v13::{
+				EventMetadata, ExtrinsicMetadata, FunctionArgumentMetadata, FunctionMetadata, ModuleMetadata,
+				RuntimeMetadataV13, StorageEntryMetadata, StorageEntryModifier, StorageEntryType, StorageHasher,
+				StorageMetadata,
+			},
+			RuntimeMetadata, RuntimeMetadataPrefixed,
+		};
+
+		let entry = |name: &str, ty| StorageEntryMetadata {
+			name: Decoded(name.to_string()),
+			modifier: StorageEntryModifier::Default,
+			ty,
+			default: Decoded(vec![0; 4]),
+			documentation: Decoded(vec![]),
+		};
+		let system = ModuleMetadata {
+			name: Decoded("System".to_string()),
+			storage: Some(Decoded(StorageMetadata {
+				prefix: Decoded("System".to_string()),
+				entries: Decoded(vec![
+					entry("Number", StorageEntryType::Plain(Decoded("u32".to_string()))),
+					entry(
+						"BlockHash",
+						StorageEntryType::Map {
+							hasher: StorageHasher::Twox64Concat,
+							key: Decoded("u32".to_string()),
+							value: Decoded("[u8; 32]".to_string()),
+							unused: false,
+						},
+					),
+				]),
+			})),
+			calls: Some(Decoded(vec![FunctionMetadata {
+				name: Decoded("remark".to_string()),
+				arguments: Decoded(vec![FunctionArgumentMetadata {
+					name: Decoded("_remark".to_string()),
+					ty: Decoded("Vec<u8>".to_string()),
+				}]),
+				documentation: Decoded(vec![]),
+			}])),
+			event: Some(Decoded(vec![EventMetadata {
+				name: Decoded("Remarked".to_string()),
+				arguments: Decoded(vec!["u32".to_string(), "bool".to_string()]),
+				documentation: Decoded(vec![]),
+			}])),
+			constants: Decoded(vec![]),
+			errors: Decoded(vec![]),
+			index: 0,
+		};
+		let metadata = RuntimeMetadataV13 {
+			modules: Decoded(vec![system]),
+			extrinsic: ExtrinsicMetadata {
+				version: 4,
+				signed_extensions: signed_extensions.iter().map(|ext| Decoded(ext.to_string())).collect(),
+			},
+		};
+		RuntimeMetadataPrefixed(frame_metadata::META_RESERVED, RuntimeMetadata::V13(metadata)).encode()
+	}
+
+	#[test]
+	fn should_decode_with_v13_metadata() {
+		let mut decoder = Decoder::new(EventTypes::new(), Chain::Kusama);
+		decoder.register_version(9050, Metadata::new(&v13_metadata_bytes(&[])).unwrap()).unwrap();
+
+		// An unsigned System.remark(b"hi") extrinsic:
+		let data = vec![vec![4u8, 0, 0, 8, b'h', b'i']].encode();
+		let extrinsics = decoder.decode_extrinsics(9050, &data).unwrap();
+		assert_eq!(extrinsics.len(), 1);
+		assert_eq!(extrinsics[0].to_string(), "System.remark(_remark: 6869)");
+
+		// Phase::Finalization, System.Remarked(7, true), no topics:
+		let mut events_blob = Compact(1u32).encode();
+		events_blob.extend([1u8, 0, 0]);
+		events_blob.extend((7u32, true).encode());
+		events_blob.extend(Vec::<[u8; 32]>::new().encode());
+		let events = decoder.decode_events(9050, &events_blob).unwrap();
+		assert_eq!((events[0].module(), events[0].name()), ("System", "Remarked"));
+		assert_eq!(events[0].args(), &[SubstrateType::U32(7), SubstrateType::Bool(true)]);
+
+		let mut key = sp_core::twox_128(b"System").to_vec();
+		key.extend(sp_core::twox_128(b"Number"));
+		let storage = decoder.decode_storage(9050, (&key, Some(1234u32.encode()))).unwrap();
+		assert_eq!(storage.value().unwrap().ty(), &SubstrateType::U32(1234));
+
+		let mut key = sp_core::twox_128(b"System").to_vec();
+		key.extend(sp_core::twox_128(b"BlockHash"));
+		key.extend(sp_core::twox_64(&5u32.encode()));
+		key.extend(5u32.encode());
+		let storage = decoder.decode_storage(9050, (&key, Some([9u8; 32].encode()))).unwrap();
+		assert_eq!(storage.key().prefix, "System BlockHash");
+		assert!(matches!(storage.key().extra, Some(StorageKeyData::Map { .. })));
+	}
+
+	/// Resolves a `SignedExtra` whose extensions point to further tuples and type pointers.
+	#[derive(Debug, Clone)]
+	struct NestedExtensionTypes {
+		signature: RustTypeMarker,
+		asset_payment: RustTypeMarker,
+		asset_id: RustTypeMarker,
+	}
+
+	impl NestedExtensionTypes {
+		fn new() -> Self {
+			let signature = RustTypeMarker::Tuple(vec![
+				RustTypeMarker::U32,
+				RustTypeMarker::U64,
+				RustTypeMarker::TypePointer("SignedExtra".to_string()),
+			]);
+			let asset_payment = RustTypeMarker::Tuple(vec![
+				RustTypeMarker::TypePointer("Tip".to_string()),
+				RustTypeMarker::TypePointer("AssetId".to_string()),
+			]);
+			let asset_id = RustTypeMarker::Std(CommonTypes::Option(Box::new(RustTypeMarker::U32)));
+			Self { signature, asset_payment, asset_id }
+		}
+	}
+
+	impl TypeDetective for NestedExtensionTypes {
+		fn get(&self, _chain: &str, _spec: u32, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+			match ty {
+				"CheckNonce" => Some(&RustTypeMarker::U32),
+				"ChargeAssetTxPayment" => Some(&self.asset_payment),
+				"Tip" => Some(&RustTypeMarker::U128),
+				"AssetId" => Some(&self.asset_id),
+				_ => None,
+			}
+		}
+
+		fn try_fallback(&self, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+
+		fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, ty: &str) -> Option<&RustTypeMarker> {
+			match ty {
+				"signature" => Some(&self.signature),
+				_ => None,
+			}
+		}
+	}
+
+	#[test]
+	fn should_decode_nested_signed_extensions() {
+		let mut decoder = Decoder::new(NestedExtensionTypes::new(), Chain::Kusama);
+		let metadata = v13_metadata_bytes(&["CheckNonce", "ChargeAssetTxPayment"]);
+		decoder.register_version(9050, Metadata::new(&metadata).unwrap()).unwrap();
+
+		// A System.remark(b"hi") extrinsic from address 1, with nonce 5, a tip of 10 and asset ID 7:
+		let mut ext = vec![0x84u8];
+		ext.extend((1u32, 2u64, 5u32, (10u128, Some(7u32))).encode());
+		ext.extend([0, 0, 8, b'h', b'i']);
+		let data = vec![ext].encode();
+
+		let extrinsics = decoder.decode_extrinsics(9050, &data).unwrap();
+		assert_eq!(extrinsics[0].to_string(), "System.remark(_remark: 6869)");
+		let extra = SubstrateType::Composite(vec![
+			SubstrateType::U32(5),
+			SubstrateType::Composite(vec![
+				SubstrateType::U128(10),
+				SubstrateType::Option(Box::new(Some(SubstrateType::U32(7)))),
+			]),
+		]);
+		let signature = extrinsics[0].signature().expect("extrinsic is signed");
+		assert_eq!(signature.parts(), (&SubstrateType::U32(1), &SubstrateType::U64(2), &extra));
+	}
 }
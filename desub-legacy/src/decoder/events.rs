@@ -0,0 +1,79 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Generic Event Type and Functions
+use crate::substrate_types::SubstrateType;
+use serde::Serialize;
+use std::fmt;
+
+/// A decoded event, along with the phase it was emitted in and any topics attached to it.
+#[derive(Debug, Serialize)]
+pub struct GenericEvent {
+	phase: SubstrateType,
+	module: String,
+	name: String,
+	args: Vec<SubstrateType>,
+	topics: Vec<SubstrateType>,
+}
+
+impl fmt::Display for GenericEvent {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut s = String::from("");
+		s.push_str(&format!("{}.{}:   ", self.module, self.name));
+		for arg in self.args.iter() {
+			s.push_str(&format!(" {} ", arg));
+		}
+		write!(f, "{}", s)
+	}
+}
+
+impl GenericEvent {
+	/// create a new generic event type
+	pub fn new(
+		phase: SubstrateType,
+		module: String,
+		name: String,
+		args: Vec<SubstrateType>,
+		topics: Vec<SubstrateType>,
+	) -> Self {
+		Self { phase, module, name, args, topics }
+	}
+
+	/// The phase of block execution in which the event was emitted
+	pub fn phase(&self) -> &SubstrateType {
+		&self.phase
+	}
+
+	/// The module which emitted the event
+	pub fn module(&self) -> &str {
+		&self.module
+	}
+
+	/// The name of the event
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// The decoded arguments of the event, in the order they appear in the metadata
+	pub fn args(&self) -> &[SubstrateType] {
+		&self.args
+	}
+
+	/// The topics the event was indexed under
+	pub fn topics(&self) -> &[SubstrateType] {
+		&self.topics
+	}
+}
@@ -15,6 +15,7 @@
 
 //! Generic Extrinsic Type and Functions
 
+use super::Chain;
 use crate::substrate_types::SubstrateType;
 use serde::Serialize;
 use std::fmt;
@@ -37,15 +38,37 @@ pub struct GenericCall {
 	args: Vec<ExtrinsicArgument>,
 }
 
+impl GenericCall {
+	/// create a new generic call type
+	pub fn new(module: String, name: String, args: Vec<(String, SubstrateType)>) -> Self {
+		let args = args.into_iter().map(|(name, arg)| ExtrinsicArgument { name, arg }).collect();
+		Self { name, module, args }
+	}
+
+	pub fn module(&self) -> &str {
+		&self.module
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn args(&self) -> &[ExtrinsicArgument] {
+		&self.args
+	}
+}
+
+/// Renders the call as `Module.call(arg_name: arg, ...)`.
 impl fmt::Display for GenericCall {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let mut s = String::from("");
-		s.push_str(&self.name);
-		s.push_str(":   ");
-		for val in self.args.iter() {
-			s.push_str(&format!("{}", val));
+		write!(f, "{}.{}(", self.module, self.name)?;
+		for (i, val) in self.args.iter().enumerate() {
+			if i > 0 {
+				write!(f, ", ")?;
+			}
+			write!(f, "{}: {}", val.name, val.arg)?;
 		}
-		write!(f, "{}", s)
+		write!(f, ")")
 	}
 }
 
@@ -54,31 +77,30 @@ impl fmt::Display for GenericCall {
 pub struct GenericExtrinsic {
 	signature: Option<GenericSignature>,
 	call: GenericCall,
+	/// The ss58 prefix used by [`GenericExtrinsic::address_ss58`].
+	#[serde(skip)]
+	ss58_prefix: u16,
 }
 
+/// Renders the call of the extrinsic as `Module.call(arg_name: arg, ...)`; the signature, if there is one,
+/// can be displayed separately.
 impl fmt::Display for GenericExtrinsic {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let mut s = String::from("");
-		if let Some(v) = &self.signature {
-			s.push_str(&format!("{}", v));
-		} else {
-			s.push_str("None");
-		}
-		s.push('\n');
-		s.push_str("CALL");
-		s.push('\n');
-		s.push_str(&format!("{}", self.call));
-		write!(f, "{}", s)
+		write!(f, "{}", self.call)
 	}
 }
 
 impl GenericExtrinsic {
 	/// create a new generic extrinsic type
 	pub fn new(sig: Option<SubstrateType>, call: Vec<(String, SubstrateType)>, name: String, module: String) -> Self {
-		let call =
-			call.into_iter().map(|c| ExtrinsicArgument { name: c.0, arg: c.1 }).collect::<Vec<ExtrinsicArgument>>();
-		let call = GenericCall { name, module, args: call };
-		Self { signature: sig.map(GenericSignature::new), call }
+		let call = GenericCall::new(module, name, call);
+		Self { signature: sig.map(GenericSignature::new), call, ss58_prefix: Chain::DEFAULT_SS58_PREFIX }
+	}
+
+	/// Set the ss58 prefix that [`GenericExtrinsic::address_ss58`] formats the signer's address with.
+	pub fn with_ss58_prefix(mut self, prefix: u16) -> Self {
+		self.ss58_prefix = prefix;
+		self
 	}
 
 	pub fn is_signed(&self) -> bool {
@@ -93,14 +115,34 @@ impl GenericExtrinsic {
 		&self.call
 	}
 
-	pub fn ext_module(&self) -> &str {
+	/// The address of the signer, ss58 encoded with the prefix of the chain that the extrinsic was decoded for.
+	/// Returns `None` if the extrinsic is unsigned, or its address isn't an account ID.
+	pub fn address_ss58(&self) -> Option<String> {
+		let (address, _, _) = self.signature.as_ref()?.parts();
+		crate::util::to_ss58_address(address, self.ss58_prefix)
+	}
+
+	/// The name of the module that the extrinsic calls into
+	pub fn module_name(&self) -> &str {
 		&self.call.module
 	}
 
-	pub fn ext_call(&self) -> &str {
+	/// The name of the call
+	pub fn call_name(&self) -> &str {
 		&self.call.name
 	}
 
+	/// Same as [`GenericExtrinsic::module_name`]
+	pub fn ext_module(&self) -> &str {
+		self.module_name()
+	}
+
+	/// Same as [`GenericExtrinsic::call_name`]
+	pub fn ext_call(&self) -> &str {
+		self.call_name()
+	}
+
+	/// The name and decoded value of each argument of the call, in the order they appear in the metadata
 	pub fn args(&self) -> &[ExtrinsicArgument] {
 		&self.call.args
 	}
@@ -196,6 +238,7 @@ mod tests {
 				SubstrateType::U128(128),
 			]))),
 			call,
+			ss58_prefix: Chain::DEFAULT_SS58_PREFIX,
 		};
 		let serialized = serde_json::to_string(&ext).unwrap();
 		assert_eq!(
@@ -203,4 +246,33 @@ mod tests {
 			r#"{"signature":{"address":"5C4hrfjw9DjXZTzV3MwzrrAr9P1MJhSrvWGWqi1eSuyUpnhM","signature":64,"extra":128},"call":{"name":"set","module":"Timestamp","args":[{"name":"Some Arg","arg":32}]}}"#
 		);
 	}
+
+	#[test]
+	fn should_display_generic_extrinsic() {
+		let args = vec![("now".to_string(), SubstrateType::U64(42)), ("flag".to_string(), SubstrateType::Bool(true))];
+		let ext = GenericExtrinsic::new(None, args, "set".to_string(), "Timestamp".to_string());
+		assert_eq!(ext.to_string(), "Timestamp.set(now: 42, flag: true)");
+
+		let ext = GenericExtrinsic::new(None, vec![], "noop".to_string(), "Test".to_string());
+		assert_eq!(ext.to_string(), "Test.noop()");
+	}
+
+	#[test]
+	fn should_format_address_with_ss58_prefix() {
+		let alice = hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap();
+		let alice = sp_core::crypto::AccountId32::new(alice.try_into().unwrap());
+		let sig = SubstrateType::Composite(vec![
+			SubstrateType::Address(sp_runtime::MultiAddress::Id(alice)),
+			SubstrateType::U64(64),
+			SubstrateType::U128(128),
+		]);
+		let ext = GenericExtrinsic::new(Some(sig), vec![], "noop".to_string(), "Test".to_string());
+		assert_eq!(ext.address_ss58().unwrap(), "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+
+		let ext = ext.with_ss58_prefix(Chain::Polkadot.ss58_prefix());
+		assert_eq!(ext.address_ss58().unwrap(), "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5");
+
+		let unsigned = GenericExtrinsic::new(None, vec![], "noop".to_string(), "Test".to_string());
+		assert_eq!(unsigned.address_ss58(), None);
+	}
 }
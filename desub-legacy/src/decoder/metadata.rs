@@ -44,7 +44,7 @@ use serde::{Deserialize, Serialize};
 use sp_core::{storage::StorageKey, twox_128};
 
 use std::{
-	collections::{HashMap, HashSet},
+	collections::HashMap,
 	convert::{TryFrom, TryInto},
 	fmt,
 	marker::PhantomData,
@@ -174,6 +174,11 @@ impl<'a> Metadata {
 		self.modules.get(&name).ok_or(Error::ModuleNotFound(name)).map(|m| (*m).clone())
 	}
 
+	/// The version of the extrinsic format, if the metadata says (only metadata version 11+ does).
+	pub fn extrinsic_version(&self) -> Option<u8> {
+		self.extrinsics.as_ref().map(|e| e.version)
+	}
+
 	pub fn signed_extensions(&self) -> Option<&[RustTypeMarker]> {
 		self.extrinsics.as_ref().map(|e| e.extensions.as_slice())
 	}
@@ -482,12 +487,19 @@ impl<K: Encode, V: Decode + Clone> StorageMap<K, V> {
 #[derive(Clone, Debug, PartialEq)]
 pub struct ModuleEventMetadata {
 	pub name: String,
-	pub(crate) arguments: HashSet<EventArg>,
+	pub(crate) arguments: Vec<EventArg>,
+	/// The type of each argument, in the order that they are encoded
+	pub(crate) types: Vec<RustTypeMarker>,
 }
 
 impl ModuleEventMetadata {
 	pub fn arguments(&self) -> Vec<EventArg> {
-		self.arguments.iter().cloned().collect()
+		self.arguments.clone()
+	}
+
+	/// Returns an iterator over the types of each argument, in the order that they are encoded
+	pub fn types(&self) -> impl Iterator<Item = &RustTypeMarker> {
+		self.types.iter()
 	}
 }
 
@@ -23,8 +23,8 @@ use std::sync::Arc;
 pub fn test_metadata() -> Metadata {
 	Metadata {
 		modules: module_metadata_mock(),
-		modules_by_event_index: HashMap::new(),
-		modules_by_call_index: HashMap::new(),
+		modules_by_event_index: (0..3).map(|i| (i, format!("TestModule{}", i))).collect(),
+		modules_by_call_index: (0..3).map(|i| (i, format!("TestModule{}", i))).collect(),
 		extrinsics: None,
 	}
 }
@@ -160,11 +160,13 @@ fn event_mock() -> HashMap<u8, ModuleEventMetadata> {
 	let event_arg_1 = EventArg::Primitive("TestEvent1".to_string());
 	let event_arg_2 = EventArg::Primitive("TestEvent2".to_string());
 
-	let mut arguments = HashSet::new();
-	arguments.insert(event_arg_0);
-	arguments.insert(event_arg_1);
-	arguments.insert(event_arg_2);
-	let module_event_metadata = ModuleEventMetadata { name: "TestEvent0".to_string(), arguments };
+	let arguments = vec![event_arg_0, event_arg_1, event_arg_2];
+	let types = vec![
+		RustTypeMarker::TypePointer("TestEvent0".to_string()),
+		RustTypeMarker::TypePointer("TestEvent1".to_string()),
+		RustTypeMarker::TypePointer("TestEvent2".to_string()),
+	];
+	let module_event_metadata = ModuleEventMetadata { name: "TestEvent0".to_string(), arguments, types };
 
 	map.insert(0, module_event_metadata);
 	map
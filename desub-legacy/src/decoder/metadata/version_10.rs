@@ -19,10 +19,10 @@ use super::{
 	StorageEntryModifier as DesubStorageEntryModifier, StorageHasher as DesubStorageHasher, StorageMetadata,
 	StorageType,
 };
-use crate::regex;
+use crate::parse_type;
 use frame_metadata::v10::{self, RuntimeMetadataV10, StorageEntryModifier, StorageEntryType, StorageHasher};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::HashMap,
 	convert::{TryFrom, TryInto},
 };
 
@@ -39,7 +39,7 @@ impl TryFrom<RuntimeMetadataV10> for Metadata {
 				modules_by_call_index.insert(call_index, module_name.clone());
 				call_index += 1;
 			}
-			if module.event.is_none() {
+			if module.event.is_some() {
 				modules_by_event_index.insert(event_index, module_name.clone());
 				event_index += 1;
 			}
@@ -71,7 +71,7 @@ fn convert_module(index: usize, module: v10::ModuleMetadata) -> Result<ModuleMet
 				.map(|a| {
 					let ty = convert(a.ty.clone())?;
 					let name = convert(a.name.clone())?;
-					let arg = CallArgMetadata { name, ty: regex::parse(&ty).ok_or(Error::InvalidType(ty))? };
+					let arg = CallArgMetadata { name, ty: parse_type(&ty).ok_or(Error::InvalidType(ty))? };
 					Ok(arg)
 				})
 				.collect::<Result<Vec<CallArgMetadata>, Error>>()?;
@@ -97,12 +97,13 @@ fn convert_module(index: usize, module: v10::ModuleMetadata) -> Result<ModuleMet
 
 fn convert_event(event: v10::EventMetadata) -> Result<ModuleEventMetadata, Error> {
 	let name = convert(event.name)?;
-	let mut arguments = HashSet::new();
+	let mut arguments = Vec::new();
+	let mut types = Vec::new();
 	for arg in convert(event.arguments)? {
-		let arg = arg.parse::<EventArg>()?;
-		arguments.insert(arg);
+		types.push(parse_type(&arg).ok_or_else(|| Error::InvalidType(arg.clone()))?);
+		arguments.push(arg.parse::<EventArg>()?);
 	}
-	Ok(ModuleEventMetadata { name, arguments })
+	Ok(ModuleEventMetadata { name, arguments, types })
 }
 
 fn convert_entry(prefix: String, entry: v10::StorageEntryMetadata) -> Result<StorageMetadata, Error> {
@@ -153,15 +154,15 @@ impl TryFrom<StorageEntryType> for StorageType {
 		let entry = match entry {
 			StorageEntryType::Plain(v) => {
 				let ty = convert(v)?;
-				StorageType::Plain(regex::parse(&ty).ok_or(Error::InvalidType(ty))?)
+				StorageType::Plain(parse_type(&ty).ok_or(Error::InvalidType(ty))?)
 			}
 			StorageEntryType::Map { hasher, key, value, is_linked } => {
 				let key = convert(key)?;
 				let value = convert(value)?;
 				StorageType::Map {
 					hasher: TempStorageHasher(hasher).into(),
-					key: regex::parse(&key).ok_or(Error::InvalidType(key))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key: parse_type(&key).ok_or(Error::InvalidType(key))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					unused: is_linked,
 				}
 			}
@@ -171,9 +172,9 @@ impl TryFrom<StorageEntryType> for StorageType {
 				let value = convert(value)?;
 				StorageType::DoubleMap {
 					hasher: TempStorageHasher(hasher).into(),
-					key1: regex::parse(&key1).ok_or(Error::InvalidType(key1))?,
-					key2: regex::parse(&key2).ok_or(Error::InvalidType(key2))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key1: parse_type(&key1).ok_or(Error::InvalidType(key1))?,
+					key2: parse_type(&key2).ok_or(Error::InvalidType(key2))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					key2_hasher: TempStorageHasher(key2_hasher).into(),
 				}
 			}
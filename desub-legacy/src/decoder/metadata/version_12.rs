@@ -34,7 +34,7 @@ use super::{
 	ModuleMetadata, StorageEntryModifier as DesubStorageEntryModifier, StorageHasher as DesubStorageHasher,
 	StorageMetadata, StorageType,
 };
-use crate::{regex, RustTypeMarker};
+use crate::{parse_type, RustTypeMarker};
 
 use frame_metadata::v12::{
 	EventMetadata as EventMetadatav12, ModuleMetadata as ModuleMetadatav12, RuntimeMetadataV12,
@@ -43,7 +43,7 @@ use frame_metadata::v12::{
 };
 
 use std::{
-	collections::{HashMap, HashSet},
+	collections::HashMap,
 	convert::{TryFrom, TryInto},
 };
 
@@ -52,15 +52,13 @@ impl TryFrom<RuntimeMetadataV12> for Metadata {
 	fn try_from(metadata: RuntimeMetadataV12) -> Result<Self, Self::Error> {
 		let mut modules = HashMap::new();
 		let (mut modules_by_event_index, mut modules_by_call_index) = (HashMap::new(), HashMap::new());
-		let mut event_index = 0;
 		for module in convert(metadata.modules)?.into_iter() {
 			let module_name = convert(module.name.clone())?;
 			if module.calls.is_some() {
 				modules_by_call_index.insert(module.index, module_name.clone());
 			}
-			if module.event.is_none() {
-				modules_by_event_index.insert(event_index, module_name.clone());
-				event_index += 1;
+			if module.event.is_some() {
+				modules_by_event_index.insert(module.index, module_name.clone());
 			}
 			let module_metadata = convert_module(module)?;
 			modules.insert(module_name, std::sync::Arc::new(module_metadata));
@@ -69,7 +67,7 @@ impl TryFrom<RuntimeMetadataV12> for Metadata {
 		let mut extensions: Vec<RustTypeMarker> = Vec::new();
 		for ext in metadata.extrinsic.signed_extensions.iter() {
 			let name: String = convert(ext.clone())?;
-			let ty = regex::parse(&name).ok_or(Error::InvalidType(name))?;
+			let ty = parse_type(&name).ok_or(Error::InvalidType(name))?;
 			extensions.push(ty);
 		}
 
@@ -100,7 +98,7 @@ fn convert_module(module: ModuleMetadatav12) -> Result<ModuleMetadata, Error> {
 				.map(|a| {
 					let ty = convert(a.ty.clone())?;
 					let name = convert(a.name.clone())?;
-					let arg = CallArgMetadata { name, ty: regex::parse(&ty).ok_or(Error::InvalidType(ty))? };
+					let arg = CallArgMetadata { name, ty: parse_type(&ty).ok_or(Error::InvalidType(ty))? };
 					Ok(arg)
 				})
 				.collect::<Result<Vec<CallArgMetadata>, Error>>()?;
@@ -126,12 +124,13 @@ fn convert_module(module: ModuleMetadatav12) -> Result<ModuleMetadata, Error> {
 
 fn convert_event(event: EventMetadatav12) -> Result<ModuleEventMetadata, Error> {
 	let name = convert(event.name)?;
-	let mut arguments = HashSet::new();
+	let mut arguments = Vec::new();
+	let mut types = Vec::new();
 	for arg in convert(event.arguments)? {
-		let arg = arg.parse::<EventArg>()?;
-		arguments.insert(arg);
+		types.push(parse_type(&arg).ok_or_else(|| Error::InvalidType(arg.clone()))?);
+		arguments.push(arg.parse::<EventArg>()?);
 	}
-	Ok(ModuleEventMetadata { name, arguments })
+	Ok(ModuleEventMetadata { name, arguments, types })
 }
 
 fn convert_entry(prefix: String, entry: StorageEntryMetadatav12) -> Result<StorageMetadata, Error> {
@@ -183,15 +182,15 @@ impl TryFrom<StorageEntryType> for StorageType {
 		let entry = match entry {
 			StorageEntryType::Plain(v) => {
 				let ty = convert(v)?;
-				StorageType::Plain(regex::parse(&ty).ok_or(Error::InvalidType(ty))?)
+				StorageType::Plain(parse_type(&ty).ok_or(Error::InvalidType(ty))?)
 			}
 			StorageEntryType::Map { hasher, key, value, unused } => {
 				let key = convert(key)?;
 				let value = convert(value)?;
 				StorageType::Map {
 					hasher: TempStorageHasher(hasher).into(),
-					key: regex::parse(&key).ok_or(Error::InvalidType(key))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key: parse_type(&key).ok_or(Error::InvalidType(key))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					unused,
 				}
 			}
@@ -201,9 +200,9 @@ impl TryFrom<StorageEntryType> for StorageType {
 				let value = convert(value)?;
 				StorageType::DoubleMap {
 					hasher: TempStorageHasher(hasher).into(),
-					key1: regex::parse(&key1).ok_or(Error::InvalidType(key1))?,
-					key2: regex::parse(&key2).ok_or(Error::InvalidType(key2))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key1: parse_type(&key1).ok_or(Error::InvalidType(key1))?,
+					key2: parse_type(&key2).ok_or(Error::InvalidType(key2))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					key2_hasher: TempStorageHasher(key2_hasher).into(),
 				}
 			}
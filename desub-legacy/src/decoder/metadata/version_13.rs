@@ -34,7 +34,7 @@ use super::{
 	ModuleMetadata, StorageEntryModifier as DesubStorageEntryModifier, StorageHasher as DesubStorageHasher,
 	StorageMetadata, StorageType,
 };
-use crate::{regex, RustTypeMarker};
+use crate::{parse_type, RustTypeMarker};
 
 use frame_metadata::v13::{
 	EventMetadata as EventMetadataV13, ModuleMetadata as ModuleMetadataV13, RuntimeMetadataV13,
@@ -43,7 +43,7 @@ use frame_metadata::v13::{
 };
 
 use std::{
-	collections::{HashMap, HashSet},
+	collections::HashMap,
 	convert::{TryFrom, TryInto},
 };
 
@@ -53,15 +53,13 @@ impl TryFrom<RuntimeMetadataV13> for Metadata {
 	fn try_from(metadata: RuntimeMetadataV13) -> Result<Self, Self::Error> {
 		let mut modules = HashMap::new();
 		let (mut modules_by_event_index, mut modules_by_call_index) = (HashMap::new(), HashMap::new());
-		let mut event_index = 0;
 		for module in convert(metadata.modules)?.into_iter() {
 			let module_name = convert(module.name.clone())?;
 			if module.calls.is_some() {
 				modules_by_call_index.insert(module.index, module_name.clone());
 			}
-			if module.event.is_none() {
-				modules_by_event_index.insert(event_index, module_name.clone());
-				event_index += 1;
+			if module.event.is_some() {
+				modules_by_event_index.insert(module.index, module_name.clone());
 			}
 			let module_metadata = convert_module(module)?;
 			modules.insert(module_name, std::sync::Arc::new(module_metadata));
@@ -70,7 +68,7 @@ impl TryFrom<RuntimeMetadataV13> for Metadata {
 		let mut extensions: Vec<RustTypeMarker> = Vec::new();
 		for ext in metadata.extrinsic.signed_extensions.iter() {
 			let name: String = convert(ext.clone())?;
-			let ty = regex::parse(&name).ok_or(Error::InvalidType(name))?;
+			let ty = parse_type(&name).ok_or(Error::InvalidType(name))?;
 			extensions.push(ty);
 		}
 
@@ -101,7 +99,7 @@ fn convert_module(module: ModuleMetadataV13) -> Result<ModuleMetadata, Error> {
 				.map(|a| {
 					let ty = convert(a.ty.clone())?;
 					let name = convert(a.name.clone())?;
-					let arg = CallArgMetadata { name, ty: regex::parse(&ty).ok_or(Error::InvalidType(ty))? };
+					let arg = CallArgMetadata { name, ty: parse_type(&ty).ok_or(Error::InvalidType(ty))? };
 					Ok(arg)
 				})
 				.collect::<Result<Vec<CallArgMetadata>, Error>>()?;
@@ -127,12 +125,13 @@ fn convert_module(module: ModuleMetadataV13) -> Result<ModuleMetadata, Error> {
 
 fn convert_event(event: EventMetadataV13) -> Result<ModuleEventMetadata, Error> {
 	let name = convert(event.name)?;
-	let mut arguments = HashSet::new();
+	let mut arguments = Vec::new();
+	let mut types = Vec::new();
 	for arg in convert(event.arguments)? {
-		let arg = arg.parse::<EventArg>()?;
-		arguments.insert(arg);
+		types.push(parse_type(&arg).ok_or_else(|| Error::InvalidType(arg.clone()))?);
+		arguments.push(arg.parse::<EventArg>()?);
 	}
-	Ok(ModuleEventMetadata { name, arguments })
+	Ok(ModuleEventMetadata { name, arguments, types })
 }
 
 fn convert_entry(prefix: String, entry: StorageEntryMetadataV13) -> Result<StorageMetadata, Error> {
@@ -184,15 +183,15 @@ impl TryFrom<StorageEntryType> for StorageType {
 		let entry = match entry {
 			StorageEntryType::Plain(v) => {
 				let ty = convert(v)?;
-				StorageType::Plain(regex::parse(&ty).ok_or(Error::InvalidType(ty))?)
+				StorageType::Plain(parse_type(&ty).ok_or(Error::InvalidType(ty))?)
 			}
 			StorageEntryType::Map { hasher, key, value, unused } => {
 				let key = convert(key)?;
 				let value = convert(value)?;
 				StorageType::Map {
 					hasher: TempStorageHasher(hasher).into(),
-					key: regex::parse(&key).ok_or(Error::InvalidType(key))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key: parse_type(&key).ok_or(Error::InvalidType(key))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					unused,
 				}
 			}
@@ -202,9 +201,9 @@ impl TryFrom<StorageEntryType> for StorageType {
 				let value = convert(value)?;
 				StorageType::DoubleMap {
 					hasher: TempStorageHasher(hasher).into(),
-					key1: regex::parse(&key1).ok_or(Error::InvalidType(key1))?,
-					key2: regex::parse(&key2).ok_or(Error::InvalidType(key2))?,
-					value: regex::parse(&value).ok_or(Error::InvalidType(value))?,
+					key1: parse_type(&key1).ok_or(Error::InvalidType(key1))?,
+					key2: parse_type(&key2).ok_or(Error::InvalidType(key2))?,
+					value: parse_type(&value).ok_or(Error::InvalidType(value))?,
 					key2_hasher: TempStorageHasher(key2_hasher).into(),
 				}
 			}
@@ -216,9 +215,9 @@ impl TryFrom<StorageEntryType> for StorageType {
 					hashers.into_iter().map(|h| TempStorageHasher(h).into()).collect::<Vec<DesubStorageHasher>>();
 				let keys = keys
 					.into_iter()
-					.map(|k| regex::parse(&k).ok_or(Error::InvalidType(k)))
+					.map(|k| parse_type(&k).ok_or(Error::InvalidType(k)))
 					.collect::<Result<_, Error>>()?;
-				let value = regex::parse(&value).ok_or(Error::InvalidType(value))?;
+				let value = parse_type(&value).ok_or(Error::InvalidType(value))?;
 				StorageType::NMap { hashers, keys, value }
 			}
 		};
@@ -14,12 +14,25 @@ pub enum Error {
 	DecodeFail,
 	#[error("error: {0}")]
 	Fail(String),
+	#[cfg(feature = "regex")]
 	#[error("parse error {0}")]
 	Regex(#[from] onig::Error),
 	#[error("Conversion from {0} to {1} not possible")]
 	Conversion(String, String),
 	#[error("Spec version {0} not present in Decoder")]
 	MissingSpec(u32),
+	#[error("Cannot parse type `{0}`")]
+	InvalidType(String),
+	#[error("{0} bytes of the input were not consumed")]
+	ExcessBytes(usize),
+	#[error("Failed to register spec version {version}")]
+	RegisterVersion {
+		version: u32,
+		#[source]
+		source: Box<Error>,
+	},
+	#[error("Encoded length of {length} items cannot fit in the {remaining} bytes remaining")]
+	LengthExceedsInput { length: usize, remaining: usize },
 }
 
 impl From<&str> for Error {
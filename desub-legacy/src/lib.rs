@@ -18,22 +18,51 @@
 #[deny(unused)]
 pub mod decoder;
 mod error;
+mod recording_detective;
+#[cfg(feature = "regex")]
 pub mod regex;
 mod substrate_types;
+pub mod type_parser;
 mod util;
 
 #[cfg(test)]
 pub mod test_suite;
 
 pub use self::error::Error;
-pub use self::substrate_types::SubstrateType;
+pub use self::recording_detective::RecordingTypeDetective;
+pub use self::substrate_types::{Data, SubstrateType};
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display};
 
+/// Parse a type string into a [`RustTypeMarker`]. This uses the pure Rust [`type_parser`] if the `pure-parser`
+/// feature is enabled (or the `regex` feature isn't), and the Oniguruma backed `regex` parser otherwise.
+pub fn parse_type(s: &str) -> Option<RustTypeMarker> {
+	#[cfg(any(feature = "pure-parser", not(feature = "regex")))]
+	return type_parser::parse(s);
+	#[cfg(all(feature = "regex", not(feature = "pure-parser")))]
+	return regex::parse(s);
+}
+
+/// Sanitize a type string, leaving the parts that might correspond to polkadot.js types. As with [`parse_type`],
+/// this is done by the [`type_parser`] or the `regex` parser depending on the features enabled.
+pub fn sanitize_type(ty: &str) -> Option<String> {
+	#[cfg(any(feature = "pure-parser", not(feature = "regex")))]
+	return type_parser::sanitize_ty(ty);
+	#[cfg(all(feature = "regex", not(feature = "pure-parser")))]
+	return regex::sanitize_ty(ty);
+}
+
 pub trait TypeDetective: fmt::Debug + dyn_clone::DynClone + Send + Sync {
 	/// Get a 'RustTypeMarker'
 	fn get(&self, chain: &str, spec: u32, module: &str, ty: &str) -> Option<&RustTypeMarker>;
 
+	/// Get a 'RustTypeMarker' for each of the types given, in order. By default this calls
+	/// [`TypeDetective::get`] for each type, but resolvers can override it to do any work that
+	/// is common to every lookup (like normalizing the chain and module names) just once.
+	fn get_many(&self, chain: &str, spec: u32, module: &str, tys: &[&str]) -> Vec<Option<&RustTypeMarker>> {
+		tys.iter().map(|ty| self.get(chain, spec, module, ty)).collect()
+	}
+
 	/// Some types have a fallback type that may be decoded into if the original
 	/// type fails.
 	fn try_fallback(&self, module: &str, ty: &str) -> Option<&RustTypeMarker>;
@@ -115,6 +144,8 @@ pub enum CommonTypes {
 	Result(Box<RustTypeMarker>, Box<RustTypeMarker>),
 	/// parity-scale-codec Compact<T> type
 	Compact(Box<RustTypeMarker>),
+	/// A map, encoded like a `Vec<(K, V)>`, ie `BTreeMap<K, V>`
+	Map(Box<RustTypeMarker>, Box<RustTypeMarker>),
 }
 
 impl Display for CommonTypes {
@@ -133,6 +164,9 @@ impl Display for CommonTypes {
 			CommonTypes::Compact(t) => {
 				common_types.push_str(&format!("Compact<{}>", t));
 			}
+			CommonTypes::Map(k, v) => {
+				common_types.push_str(&format!("BTreeMap<{},{}>", k, v));
+			}
 		}
 		write!(f, "{}", common_types)
 	}
@@ -150,6 +184,7 @@ impl CommonTypes {
 			CommonTypes::Option(ref o_inner) => vec![o_inner],
 			CommonTypes::Result(ref r_inner1, ref r_inner2) => vec![r_inner1, r_inner2],
 			CommonTypes::Compact(ref c_inner) => vec![c_inner],
+			CommonTypes::Map(ref k_inner, ref v_inner) => vec![k_inner, v_inner],
 		}
 	}
 }
@@ -219,6 +254,10 @@ pub enum RustTypeMarker {
 	/// Used for fields that don't exist (ex Unit variant in an enum with both
 	/// units/structs)
 	Null,
+
+	/// The polkadot-js `DoNotConstruct` placeholder, used for types that cannot be decoded.
+	/// Decoding this type consumes no bytes; any bytes that belong to it are left to the caller.
+	DoNotConstruct,
 }
 
 fn display_types(fields: &[RustTypeMarker]) -> String {
@@ -273,6 +312,7 @@ impl Display for RustTypeMarker {
 			RustTypeMarker::I128 => type_marker.push_str("i128"),
 			RustTypeMarker::Bool => type_marker.push_str("bool"),
 			RustTypeMarker::Null => type_marker.push_str("null"),
+			RustTypeMarker::DoNotConstruct => type_marker.push_str("DoNotConstruct"),
 		}
 		write!(f, "{}", type_marker)
 	}
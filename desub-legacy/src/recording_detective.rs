@@ -0,0 +1,109 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A [`TypeDetective`] that records the types it fails to resolve.
+
+use crate::{RustTypeMarker, TypeDetective};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// Wraps another [`TypeDetective`], recording the `(module, type)` of every lookup that the inner detective
+/// could not resolve. This is handy when writing type definitions for a new chain: decode a batch of blocks,
+/// and then check [`RecordingTypeDetective::unresolved`] to see which definitions are missing.
+///
+/// Clones share the same record, so a clone can be kept around to inspect the record after the original has
+/// been handed to a [`crate::decoder::Decoder`].
+#[derive(Debug, Clone)]
+pub struct RecordingTypeDetective<T> {
+	inner: T,
+	unresolved: Arc<Mutex<BTreeSet<(String, String)>>>,
+}
+
+impl<T: TypeDetective> RecordingTypeDetective<T> {
+	/// Wrap a type detective, recording its failed lookups.
+	pub fn new(inner: T) -> Self {
+		Self { inner, unresolved: Default::default() }
+	}
+
+	/// The `(module, type)` of every lookup that has failed so far, sorted and without duplicates.
+	pub fn unresolved(&self) -> Vec<(String, String)> {
+		self.unresolved.lock().expect("lock is never poisoned; qed").iter().cloned().collect()
+	}
+
+	/// Forget about any failed lookups recorded so far.
+	pub fn clear(&self) {
+		self.unresolved.lock().expect("lock is never poisoned; qed").clear();
+	}
+}
+
+impl<T: TypeDetective + Clone> TypeDetective for RecordingTypeDetective<T> {
+	fn get(&self, chain: &str, spec: u32, module: &str, ty: &str) -> Option<&RustTypeMarker> {
+		let resolved = self.inner.get(chain, spec, module, ty);
+		if resolved.is_none() {
+			log::debug!("Unresolved type {} in module {} (chain={}, spec={})", ty, module, chain, spec);
+			self.unresolved.lock().expect("lock is never poisoned; qed").insert((module.to_string(), ty.to_string()));
+		}
+		resolved
+	}
+
+	fn try_fallback(&self, module: &str, ty: &str) -> Option<&RustTypeMarker> {
+		self.inner.try_fallback(module, ty)
+	}
+
+	fn get_extrinsic_ty(&self, chain: &str, spec: u32, ty: &str) -> Option<&RustTypeMarker> {
+		self.inner.get_extrinsic_ty(chain, spec, ty)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, Clone)]
+	struct OnlyU32;
+
+	impl TypeDetective for OnlyU32 {
+		fn get(&self, _chain: &str, _spec: u32, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+			(ty == "u32").then_some(&RustTypeMarker::U32)
+		}
+
+		fn try_fallback(&self, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+
+		fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, _ty: &str) -> Option<&RustTypeMarker> {
+			None
+		}
+	}
+
+	#[test]
+	fn should_record_unresolved_types() {
+		let types = RecordingTypeDetective::new(OnlyU32);
+		let record = types.clone();
+
+		assert_eq!(types.get("kusama", 1031, "Balances", "u32"), Some(&RustTypeMarker::U32));
+		assert!(types.get("kusama", 1031, "Balances", "Missing").is_none());
+		assert!(types.get("kusama", 1031, "System", "Missing").is_none());
+		assert!(types.get("kusama", 1032, "Balances", "Missing").is_none());
+
+		let expected =
+			vec![("Balances".to_string(), "Missing".to_string()), ("System".to_string(), "Missing".to_string())];
+		assert_eq!(record.unresolved(), expected);
+
+		record.clear();
+		assert!(types.unresolved().is_empty());
+	}
+}
@@ -14,8 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
-use super::{CommonTypes, RustTypeMarker};
+use super::{type_parser::split_top_level, CommonTypes, Error, RustTypeMarker};
 use onig::{Regex, Region, SearchOptions};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum RegexSet {
@@ -32,6 +33,57 @@ enum RegexSet {
 	Generic,
 }
 
+/// The shape of a type string, as recognised by the regex parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeClass {
+	/// An array of primitives, ie `[u8; 32]`
+	ArrayPrimitive,
+	/// An array of primitives with an extra type name, ie `[u8; 20; H160]`
+	ArrayPrimitiveExtra,
+	/// A number declared by its size in bits, ie `UInt<128, Balance>`
+	BitSize,
+	/// An array of some non-primitive type, ie `[Balance; 4]`
+	ArrayStruct,
+	/// `Vec<T>`
+	Vec,
+	/// `Option<T>`
+	Option,
+	/// `Result<T, E>`
+	Result,
+	/// `Compact<T>`
+	Compact,
+	/// `Box<T>`
+	Box,
+	/// A tuple, ie `(u32, AccountId)`
+	Tuple,
+	/// Any other generic type, ie `BTreeMap<K, V>`
+	Generic,
+}
+
+impl From<&RegexSet> for TypeClass {
+	fn from(set: &RegexSet) -> TypeClass {
+		match set {
+			RegexSet::ArrayPrimitive => TypeClass::ArrayPrimitive,
+			RegexSet::ArrayPrimitiveExtra => TypeClass::ArrayPrimitiveExtra,
+			RegexSet::BitSize => TypeClass::BitSize,
+			RegexSet::ArrayStruct => TypeClass::ArrayStruct,
+			RegexSet::Vec => TypeClass::Vec,
+			RegexSet::Option => TypeClass::Option,
+			RegexSet::Result => TypeClass::Result,
+			RegexSet::Compact => TypeClass::Compact,
+			RegexSet::Box => TypeClass::Box,
+			RegexSet::Tuple => TypeClass::Tuple,
+			RegexSet::Generic => TypeClass::Generic,
+		}
+	}
+}
+
+/// Classifies a type string by its outermost shape, as the parser would see it.
+/// Returns `None` for anything that isn't a compound type (primitives and plain type names).
+pub fn classify_type(s: &str) -> Option<TypeClass> {
+	RegexSet::get_type(s).as_ref().map(TypeClass::from)
+}
+
 impl RegexSet {
 	/// Checks if string matches any of the patterns defined
 	/// Returns none if it does not match
@@ -425,6 +477,16 @@ fn parse_generic(s: &str) -> Option<RustTypeMarker> {
 	Some(RustTypeMarker::Generic(Box::new(ty_outer), Box::new(ty_inner)))
 }
 
+/// Parse a `BTreeMap<K, V>` (or `HashMap<K, V>`). Maps are SCALE encoded like a `Vec<(K, V)>`,
+/// but are worth presenting as maps.
+fn parse_map(s: &str) -> Option<RustTypeMarker> {
+	let inner = s.strip_prefix("BTreeMap<").or_else(|| s.strip_prefix("HashMap<"))?.strip_suffix('>')?;
+	let [key, value] = split_top_level(inner, ',')[..] else { return None };
+	let key = parse(key).expect("Must be a type; qed");
+	let value = parse(value).expect("Must be a type; qed");
+	Some(RustTypeMarker::Std(CommonTypes::Map(Box::new(key), Box::new(value))))
+}
+
 fn parse_bit_size(s: &str) -> Option<RustTypeMarker> {
 	let re = rust_bit_size();
 	if !re.is_match(s) {
@@ -478,9 +540,14 @@ pub fn parse(s: &str) -> Option<RustTypeMarker> {
 		"bool" => Some(RustTypeMarker::Bool),
 		"Null" => Some(RustTypeMarker::Null),
 
+		// polkadot-js placeholder (optionally with the type it stands in for, ie `DoNotConstruct<Origin>`)
+		_ if s == "DoNotConstruct" || s.starts_with("DoNotConstruct<") => Some(RustTypeMarker::DoNotConstruct),
+
 		_ => {
 			// check if nested type
-			if let Some(m) = RegexSet::get_type(s) {
+			if let Some(map) = parse_map(s) {
+				Some(map)
+			} else if let Some(m) = RegexSet::get_type(s) {
 				m.parse_type(s)
 			} else {
 				// if not a primitive, then a type pointer
@@ -490,6 +557,41 @@ pub fn parse(s: &str) -> Option<RustTypeMarker> {
 	}
 }
 
+impl FromStr for RustTypeMarker {
+	type Err = Error;
+
+	/// Parse a type string (ie `Vec<u8>`) with [`parse`]. Empty strings, and strings
+	/// with unbalanced brackets, are rejected rather than treated as type pointers.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if s.is_empty() || !has_balanced_brackets(s) {
+			return Err(Error::InvalidType(s.to_string()));
+		}
+		parse(s).ok_or_else(|| Error::InvalidType(s.to_string()))
+	}
+}
+
+/// Checks that every `<`, `(` and `[` is closed in the right order
+fn has_balanced_brackets(s: &str) -> bool {
+	let mut open = Vec::new();
+	for c in s.chars() {
+		let opener = match c {
+			'<' | '(' | '[' => {
+				open.push(c);
+				continue;
+			}
+			'>' => '<',
+			')' => '(',
+			']' => '[',
+			_ => continue,
+		};
+		if open.pop() != Some(opener) {
+			return false;
+		}
+	}
+	open.is_empty()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -831,6 +933,8 @@ mod tests {
 
 		assert_eq!(parse("bool").unwrap(), RustTypeMarker::Bool);
 		assert_eq!(parse("Null").unwrap(), RustTypeMarker::Null);
+		assert_eq!(parse("DoNotConstruct").unwrap(), RustTypeMarker::DoNotConstruct);
+		assert_eq!(parse("DoNotConstruct<Origin>").unwrap(), RustTypeMarker::DoNotConstruct);
 
 		assert_eq!(
 			parse("Option<Foo>").unwrap(),
@@ -898,6 +1002,27 @@ mod tests {
 		assert_eq!(parse("Box<T::Proposal>").unwrap(), RustTypeMarker::TypePointer("T::Proposal".to_string()))
 	}
 
+	#[test]
+	fn should_parse_maps() {
+		assert_eq!(
+			parse("BTreeMap<AccountId, RewardPoint>").unwrap(),
+			RustTypeMarker::Std(CommonTypes::Map(
+				Box::new(RustTypeMarker::TypePointer("AccountId".into())),
+				Box::new(RustTypeMarker::TypePointer("RewardPoint".into()))
+			))
+		);
+		assert_eq!(
+			parse("BTreeMap<u32, Vec<(u8, u16)>>").unwrap(),
+			RustTypeMarker::Std(CommonTypes::Map(
+				Box::new(RustTypeMarker::U32),
+				Box::new(RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::Tuple(vec![
+					RustTypeMarker::U8,
+					RustTypeMarker::U16
+				])))))
+			))
+		);
+	}
+
 	#[test]
 	fn should_parse_vec_of_tuples() {
 		let _ = pretty_env_logger::try_init();
@@ -939,4 +1064,62 @@ mod tests {
 		let ty = parse_array_with_extra_type(ty);
 		assert_eq!(ty, Some(RustTypeMarker::Array { size: 20, ty: Box::new(RustTypeMarker::U8) }));
 	}
+
+	#[test]
+	fn should_parse_from_str() {
+		assert_eq!("u64".parse::<RustTypeMarker>().unwrap(), RustTypeMarker::U64);
+		assert_eq!(
+			"Vec<u8>".parse::<RustTypeMarker>().unwrap(),
+			RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::U8)))
+		);
+		assert_eq!(
+			" Option<(u32, Balance)> ".parse::<RustTypeMarker>().unwrap(),
+			RustTypeMarker::Std(CommonTypes::Option(Box::new(RustTypeMarker::Tuple(vec![
+				RustTypeMarker::U32,
+				RustTypeMarker::TypePointer("Balance".into())
+			]))))
+		);
+		assert_eq!(
+			"[u8; 32]".parse::<RustTypeMarker>().unwrap(),
+			RustTypeMarker::Array { size: 32, ty: Box::new(RustTypeMarker::U8) }
+		);
+		assert_eq!("AccountId".parse::<RustTypeMarker>().unwrap(), RustTypeMarker::TypePointer("AccountId".into()));
+	}
+
+	#[test]
+	fn should_not_parse_malformed_from_str() {
+		for ty in ["", "   ", "Vec<u8", "Vec<u8>>", "(u8, u16", "[u8; 32", "Option<(u8>)", "u8]"] {
+			assert!(ty.parse::<RustTypeMarker>().is_err(), "`{}` should not parse", ty);
+		}
+	}
+
+	#[test]
+	fn should_classify_type() {
+		assert_eq!(classify_type("[u8; 32]"), Some(TypeClass::ArrayPrimitive));
+		assert_eq!(classify_type("[u8; 20; H160]"), Some(TypeClass::ArrayPrimitiveExtra));
+		assert_eq!(classify_type("UInt<128, Balance>"), Some(TypeClass::BitSize));
+		assert_eq!(classify_type("[Balance; 4]"), Some(TypeClass::ArrayStruct));
+		assert_eq!(classify_type("Vec<(u32, AccountId)>"), Some(TypeClass::Vec));
+		assert_eq!(classify_type("Option<Vec<u8>>"), Some(TypeClass::Option));
+		assert_eq!(classify_type("Result<u8, DispatchError>"), Some(TypeClass::Result));
+		assert_eq!(classify_type("Compact<Balance>"), Some(TypeClass::Compact));
+		assert_eq!(classify_type("Box<Call>"), Some(TypeClass::Box));
+		assert_eq!(classify_type("(u32, AccountId)"), Some(TypeClass::Tuple));
+		assert_eq!(classify_type("BTreeMap<u32, Balance>"), Some(TypeClass::Generic));
+		assert_eq!(classify_type("u32"), None);
+		assert_eq!(classify_type("AccountId"), None);
+	}
+
+	#[test]
+	fn should_parse_large_primitive_arrays() {
+		for size in [33, 64, 65, 256, 1024] {
+			let ty = format!("[u8; {}]", size);
+			assert_eq!(parse(&ty), Some(RustTypeMarker::Array { size, ty: Box::new(RustTypeMarker::U8) }));
+		}
+		assert_eq!(parse("[u64; 64]"), Some(RustTypeMarker::Array { size: 64, ty: Box::new(RustTypeMarker::U64) }));
+		assert_eq!(
+			parse("[Balance; 64]"),
+			Some(RustTypeMarker::Array { size: 64, ty: Box::new(RustTypeMarker::TypePointer("Balance".into())) })
+		);
+	}
 }
@@ -152,6 +152,11 @@ pub enum SubstrateType {
 	#[serde(serialize_with = "crate::util::as_hex")]
 	Composite(Vec<SubstrateType>),
 
+	/// Maps, decoded from a `Vec<(K, V)>` that is known to be a map; serialized as a sequence of
+	/// `{ "key": K, "value": V }` entries
+	#[serde(serialize_with = "crate::util::as_map_entries")]
+	Map(Vec<(SubstrateType, SubstrateType)>),
+
 	/// C-Like Enum Type
 	Set(SetField),
 	/// Enum
@@ -238,6 +243,16 @@ impl fmt::Display for SubstrateType {
 				}
 				write!(f, "{}", s)
 			}
+			SubstrateType::Map(entries) => {
+				write!(f, "{{")?;
+				for (i, (k, v)) in entries.iter().enumerate() {
+					if i > 0 {
+						write!(f, ", ")?;
+					}
+					write!(f, "{}: {}", k, v)?;
+				}
+				write!(f, "}}")
+			}
 			SubstrateType::Set(v) => write!(f, "{}", v),
 			SubstrateType::Enum(v) => write!(f, "{}", v),
 			SubstrateType::Struct(v) => {
@@ -341,6 +356,96 @@ impl TryFrom<&SubstrateType> for Vec<u8> {
 	}
 }
 
+impl SubstrateType {
+	/// The value of any of the integer variants as a `u128`, if it is one and isn't negative.
+	fn as_u128(&self) -> Option<u128> {
+		match *self {
+			SubstrateType::U8(n) => Some(n.into()),
+			SubstrateType::U16(n) => Some(n.into()),
+			SubstrateType::U32(n) => Some(n.into()),
+			SubstrateType::U64(n) => Some(n.into()),
+			SubstrateType::U128(n) => Some(n),
+			SubstrateType::USize(n) => n.try_into().ok(),
+			SubstrateType::I8(n) => n.try_into().ok(),
+			SubstrateType::I16(n) => n.try_into().ok(),
+			SubstrateType::I32(n) => n.try_into().ok(),
+			SubstrateType::I64(n) => n.try_into().ok(),
+			SubstrateType::I128(n) => n.try_into().ok(),
+			SubstrateType::ISize(n) => n.try_into().ok(),
+			_ => None,
+		}
+	}
+
+	/// The value of any of the integer variants as an `i128`, if it is one and fits.
+	fn as_i128(&self) -> Option<i128> {
+		match *self {
+			SubstrateType::I8(n) => Some(n.into()),
+			SubstrateType::I16(n) => Some(n.into()),
+			SubstrateType::I32(n) => Some(n.into()),
+			SubstrateType::I64(n) => Some(n.into()),
+			SubstrateType::I128(n) => Some(n),
+			SubstrateType::ISize(n) => n.try_into().ok(),
+			_ => self.as_u128().and_then(|n| n.try_into().ok()),
+		}
+	}
+
+	/// Compare two values, treating integers as equal if they have the same numeric value, whatever
+	/// their width or signedness (so `U32(5)` is equal to `U64(5)`), including integers nested in
+	/// composites. Everything else is compared with `==`.
+	///
+	/// Compact integers are decoded into their plain integer variants, so decode assertions that
+	/// shouldn't care how an integer was encoded can use this.
+	pub fn value_eq(&self, other: &SubstrateType) -> bool {
+		match (self, other) {
+			(SubstrateType::Composite(a), SubstrateType::Composite(b)) => {
+				a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.value_eq(b))
+			}
+			_ => match (self.as_u128(), other.as_u128()) {
+				(Some(a), Some(b)) => a == b,
+				_ => match (self.as_i128(), other.as_i128()) {
+					(Some(a), Some(b)) => a == b,
+					_ => self == other,
+				},
+			},
+		}
+	}
+}
+
+/// Convert any of the integer variants into an unsigned integer, as long as the value fits.
+macro_rules! try_from_integer {
+	($($int:ty),*) => {$(
+		impl TryFrom<&SubstrateType> for $int {
+			type Error = Error;
+			fn try_from(ty: &SubstrateType) -> Result<$int, Error> {
+				ty.as_u128()
+					.and_then(|n| n.try_into().ok())
+					.ok_or_else(|| Error::Conversion(format!("{}", ty), stringify!($int).to_string()))
+			}
+		}
+	)*};
+}
+
+try_from_integer!(u32, u64, u128);
+
+impl TryFrom<&SubstrateType> for bool {
+	type Error = Error;
+	fn try_from(ty: &SubstrateType) -> Result<bool, Error> {
+		match ty {
+			SubstrateType::Bool(b) => Ok(*b),
+			_ => Err(Error::Conversion(format!("{}", ty), "bool".to_string())),
+		}
+	}
+}
+
+/// Bytes (ie a `Vec<u8>`) which are valid UTF-8 can be converted into a `String`.
+impl TryFrom<&SubstrateType> for String {
+	type Error = Error;
+	fn try_from(ty: &SubstrateType) -> Result<String, Error> {
+		let bytes = Vec::<u8>::try_from(ty)?;
+		String::from_utf8(bytes).map_err(|_| Error::Conversion(format!("{}", ty), "String".to_string()))
+	}
+}
+
 impl From<u8> for SubstrateType {
 	fn from(num: u8) -> SubstrateType {
 		SubstrateType::U8(num)
@@ -430,3 +535,65 @@ impl From<bool> for SubstrateType {
 		SubstrateType::Bool(val)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn should_convert_integers() {
+		assert_eq!(u128::try_from(&SubstrateType::U128(u128::MAX)).unwrap(), u128::MAX);
+		assert_eq!(u128::try_from(&SubstrateType::U8(7)).unwrap(), 7);
+		assert_eq!(u64::try_from(&SubstrateType::U32(0x1337)).unwrap(), 0x1337);
+		assert_eq!(u64::try_from(&SubstrateType::I64(42)).unwrap(), 42);
+		assert_eq!(u32::try_from(&SubstrateType::U128(u32::MAX.into())).unwrap(), u32::MAX);
+		assert_eq!(u32::try_from(&SubstrateType::USize(3)).unwrap(), 3);
+
+		// Values which don't fit, negative values and other types can't be converted:
+		assert!(u32::try_from(&SubstrateType::U64(u64::from(u32::MAX) + 1)).is_err());
+		assert!(u64::try_from(&SubstrateType::I8(-1)).is_err());
+		assert!(u128::try_from(&SubstrateType::Bool(true)).is_err());
+	}
+
+	#[test]
+	fn should_convert_bools() {
+		assert!(bool::try_from(&SubstrateType::Bool(true)).unwrap());
+		assert!(!bool::try_from(&SubstrateType::Bool(false)).unwrap());
+		assert!(bool::try_from(&SubstrateType::U8(1)).is_err());
+	}
+
+	#[test]
+	fn should_convert_bytes_and_strings() {
+		let bytes = SubstrateType::Composite(b"hi".iter().map(|b| SubstrateType::U8(*b)).collect());
+		assert_eq!(Vec::<u8>::try_from(&bytes).unwrap(), b"hi".to_vec());
+		assert_eq!(String::try_from(&bytes).unwrap(), "hi");
+
+		let invalid_utf8 = SubstrateType::Composite(vec![SubstrateType::U8(0xff)]);
+		assert_eq!(Vec::<u8>::try_from(&invalid_utf8).unwrap(), vec![0xff]);
+		assert!(String::try_from(&invalid_utf8).is_err());
+
+		let not_bytes = SubstrateType::Composite(vec![SubstrateType::U32(1)]);
+		assert!(Vec::<u8>::try_from(&not_bytes).is_err());
+		assert!(String::try_from(&SubstrateType::U32(1)).is_err());
+	}
+
+	#[test]
+	fn should_compare_integers_by_value() {
+		// `PartialEq` cares about the width of the integer:
+		assert_ne!(SubstrateType::U32(5), SubstrateType::U64(5));
+		assert!(SubstrateType::U32(5).value_eq(&SubstrateType::U64(5)));
+		assert!(SubstrateType::U8(5).value_eq(&SubstrateType::I128(5)));
+		assert!(SubstrateType::I8(-1).value_eq(&SubstrateType::I64(-1)));
+		assert!(SubstrateType::U128(u128::MAX).value_eq(&SubstrateType::U128(u128::MAX)));
+
+		assert!(!SubstrateType::U32(5).value_eq(&SubstrateType::U32(6)));
+		assert!(!SubstrateType::U128(u128::MAX).value_eq(&SubstrateType::I8(-1)));
+		assert!(!SubstrateType::U8(1).value_eq(&SubstrateType::Bool(true)));
+
+		let a = SubstrateType::Composite(vec![SubstrateType::U16(1), SubstrateType::Bool(true)]);
+		let b = SubstrateType::Composite(vec![SubstrateType::U64(1), SubstrateType::Bool(true)]);
+		assert_ne!(a, b);
+		assert!(a.value_eq(&b));
+		assert!(!a.value_eq(&SubstrateType::Composite(vec![SubstrateType::U64(1)])));
+	}
+}
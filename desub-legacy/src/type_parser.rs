@@ -0,0 +1,404 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A recursive-descent parser for type strings, written in pure Rust.
+//!
+//! This produces the same [`RustTypeMarker`]s as the `regex` parser for the type strings found in
+//! legacy metadata, without depending on a regex engine.
+
+use super::{CommonTypes, RustTypeMarker};
+
+/// Recursively parses a type string (ie `Vec<(u32, AccountId)>`), returning a
+/// `RustTypeMarker` with all of the nested types. Anything that isn't a primitive
+/// or a recognised compound type is returned as a [`RustTypeMarker::TypePointer`].
+pub fn parse(s: &str) -> Option<RustTypeMarker> {
+	let ty = match s {
+		"u8" => RustTypeMarker::U8,
+		"u16" => RustTypeMarker::U16,
+		"u32" => RustTypeMarker::U32,
+		"u64" => RustTypeMarker::U64,
+		"u128" => RustTypeMarker::U128,
+
+		"i8" => RustTypeMarker::I8,
+		"i16" => RustTypeMarker::I16,
+		"i32" => RustTypeMarker::I32,
+		"i64" => RustTypeMarker::I64,
+		"i128" => RustTypeMarker::I128,
+
+		"bool" => RustTypeMarker::Bool,
+		"Null" => RustTypeMarker::Null,
+
+		// polkadot-js placeholder (optionally with the type it stands in for, ie `DoNotConstruct<Origin>`)
+		_ if s == "DoNotConstruct" || s.starts_with("DoNotConstruct<") => RustTypeMarker::DoNotConstruct,
+
+		_ => parse_compound(s).unwrap_or_else(|| RustTypeMarker::TypePointer(s.to_string())),
+	};
+	Some(ty)
+}
+
+/// Parse arrays, tuples and generic types. Returns `None` if `s` isn't one of these.
+fn parse_compound(s: &str) -> Option<RustTypeMarker> {
+	if let Some(inner) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+		return parse_array(inner);
+	}
+	if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+		return parse_tuple(inner);
+	}
+
+	let (outer, inner) = s.strip_suffix('>')?.split_once('<')?;
+	let ty = match (outer, &split_top_level(inner, ',')[..]) {
+		("Vec", _) => RustTypeMarker::Std(CommonTypes::Vec(Box::new(parse(inner)?))),
+		("Option", _) => RustTypeMarker::Std(CommonTypes::Option(Box::new(parse(inner)?))),
+		("Compact", _) => RustTypeMarker::Std(CommonTypes::Compact(Box::new(parse(inner)?))),
+		// We only care about the underlying data, not the fact that it's boxed.
+		("Box", _) => parse(inner)?,
+		("Result", [ok, err]) => RustTypeMarker::Std(CommonTypes::Result(Box::new(parse(ok)?), Box::new(parse(err)?))),
+		("BTreeMap" | "HashMap", [key, value]) => {
+			RustTypeMarker::Std(CommonTypes::Map(Box::new(parse(key)?), Box::new(parse(value)?)))
+		}
+		("UInt", [bits, _]) => match bits.parse::<usize>().ok()? {
+			8 => RustTypeMarker::U8,
+			16 => RustTypeMarker::U16,
+			32 => RustTypeMarker::U32,
+			64 => RustTypeMarker::U64,
+			128 => RustTypeMarker::U128,
+			size => RustTypeMarker::Array { size, ty: Box::new(RustTypeMarker::U8) },
+		},
+		("Int", [bits, _]) => match bits.parse::<usize>().ok()? {
+			8 => RustTypeMarker::I8,
+			16 => RustTypeMarker::I16,
+			32 => RustTypeMarker::I32,
+			64 => RustTypeMarker::I64,
+			128 => RustTypeMarker::I128,
+			size => RustTypeMarker::Array { size, ty: Box::new(RustTypeMarker::U8) },
+		},
+		// Generic types with a path (ie `schedule::Period<T::BlockNumber>`) are left as type pointers,
+		// to be resolved by name.
+		_ if outer.is_empty() || !outer.chars().all(|c| c.is_alphanumeric() || c == '_') => return None,
+		_ => RustTypeMarker::Generic(Box::new(parse(outer)?), Box::new(parse(inner)?)),
+	};
+	Some(ty)
+}
+
+/// Parse the inside of an array declaration: `u8; 32`, `Balance; 4` or `u8; 20; H160`.
+/// Any extra type name after the size is discarded.
+fn parse_array(s: &str) -> Option<RustTypeMarker> {
+	let (ty, size) = match split_top_level(s, ';')[..] {
+		[ty, size] => (ty, size),
+		[ty, size, _] if is_integer(ty) => (ty, size),
+		_ => return None,
+	};
+	let size = size.parse::<usize>().ok()?;
+	Some(RustTypeMarker::Array { size, ty: Box::new(parse(ty)?) })
+}
+
+/// Parse the inside of a tuple, ie `u32, AccountId`. A trailing comma is allowed.
+/// The unit type `()` isn't treated as a tuple, and so ends up as a type pointer.
+fn parse_tuple(s: &str) -> Option<RustTypeMarker> {
+	let types = split_top_level(s, ',');
+	let types = match types.split_last() {
+		Some((&"", rest)) => rest,
+		_ => &types[..],
+	};
+	if types.is_empty() {
+		return None;
+	}
+	types.iter().map(|ty| parse(ty)).collect::<Option<Vec<_>>>().map(RustTypeMarker::Tuple)
+}
+
+fn is_integer(ty: &str) -> bool {
+	matches!(ty, "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128")
+}
+
+/// Split a list of types (ie `u32, Vec<(u8, u16)>`) at each `sep` which isn't nested
+/// inside of any brackets, trimming whitespace from each type.
+pub(crate) fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+	let mut types = Vec::new();
+	let (mut depth, mut start) = (0, 0);
+	for (i, c) in s.char_indices() {
+		match c {
+			'<' | '(' | '[' => depth += 1,
+			'>' | ')' | ']' => depth -= 1,
+			c if c == sep && depth == 0 => {
+				types.push(s[start..i].trim());
+				start = i + c.len_utf8();
+			}
+			_ => {}
+		}
+	}
+	types.push(s[start..].trim());
+	types
+}
+
+/// Strip the parts of a type string (like `<T as Trait>::`, `T::` and any generic parameters) that get in the way of
+/// finding it among the polkadot.js definitions, giving the same results as `regex::sanitize_ty`.
+pub fn sanitize_ty(ty: &str) -> Option<String> {
+	log::trace!("sanitizing ty {}", ty);
+	let ty = remove_empty_generic(ty).unwrap_or(ty);
+	let ty = remove_trait(ty).unwrap_or(ty);
+	let ty = remove_path(ty).unwrap_or(ty);
+	let ty = remove_prefix(ty).unwrap_or(ty);
+	log::trace!("Possibly sanitized type: {}", ty);
+	Some(ty.to_string())
+}
+
+/// The name in front of the first empty generic, ie `Foo` from `Vec<Foo<()>>`.
+fn remove_empty_generic(s: &str) -> Option<&str> {
+	let end = s.find("<()>")?;
+	let start = s[..end].trim_end_matches(is_word).len();
+	Some(&s[start..end])
+}
+
+/// Whatever follows the trait that a type is taken from, ie `Call` from `<T as Trait>::Call`.
+fn remove_trait(s: &str) -> Option<&str> {
+	let rest = match s.strip_prefix("<T as Trait") {
+		Some(rest) => rest,
+		None => {
+			let mut rest = s.strip_prefix("<T as Config<")?.chars();
+			rest.next().filter(|c| is_word(*c))?;
+			rest.as_str().strip_prefix('>')?
+		}
+	};
+	let after = rest.trim_start_matches(|c| is_word(c) || c == '<' || c == '>');
+	(after.len() < rest.len()).then(|| after.trim_start_matches(':'))
+}
+
+/// The first type with generic parameters, less its path and parameters, ie `Period` from
+/// `schedule::Period<T::BlockNumber>`.
+fn remove_path(s: &str) -> Option<&str> {
+	let mut start = None;
+	for (i, c) in s.char_indices() {
+		if is_word(c) {
+			start.get_or_insert(i);
+		} else if let Some(start) = start.take() {
+			let mut params = s[i + c.len_utf8()..].chars().take_while(|c| is_word(*c) || "<>,: ".contains(*c));
+			if c == '<' && params.by_ref().next().is_some() && params.any(|c| c == '>') {
+				return Some(&s[start..i]);
+			}
+		}
+	}
+	None
+}
+
+/// The name after the first `::`, ie `Moment` from `T::Moment`.
+fn remove_prefix(s: &str) -> Option<&str> {
+	let is_name = |c: char| is_word(c) || c == '<' || c == '>';
+	s.match_indices("::").find_map(|(i, _)| {
+		let after = &s[i + 2..];
+		let name = &after[..after.find(|c| !is_name(c)).unwrap_or(after.len())];
+		(s[..i].chars().next_back().is_some_and(is_name) && !name.is_empty()).then_some(name)
+	})
+}
+
+/// Does this character match `\w` in a regex?
+fn is_word(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::{
+		decode_different::DecodeDifferent, v12::StorageEntryType, RuntimeMetadata, RuntimeMetadataPrefixed,
+	};
+	use parity_scale_codec::Decode;
+
+	/// Type strings taken from the regex parser tests.
+	#[cfg(feature = "regex")]
+	const TYPE_STRINGS: &[&str] = &[
+		"u8",
+		"i128",
+		"bool",
+		"Null",
+		"DoNotConstruct",
+		"DoNotConstruct<Origin>",
+		"[u8; 32]",
+		"[i128; 9999]",
+		"[u64; 64]",
+		"[   u8;   32 ]",
+		"[u8; 20; H160]",
+		"[Balance; 4]",
+		"[Foo; 99]",
+		"[Vec<u8>; 10]",
+		"UInt<128, Balance>",
+		"Int<64, Balance>",
+		"Vec<u8>",
+		"Vec<Foo>",
+		"Vec<Option<Foo>>",
+		"Vec<(u32, AccountId)>",
+		"Vec<Vec<(Foo, Bar, T::SystemMarker)>>",
+		"Vec<(NominatorIndexCompact, CompactScoreCompact, ValidatorIndexCompact)>",
+		"Vec<(NominatorIndexCompact, [CompactScoreCompact; 2], ValidatorIndexCompact)>",
+		"Option<BlockNumber>",
+		"Option<Vec<u8>>",
+		"Option<Vec<(Foo, Bar, u8)>>",
+		"Option<(u32, Balance)>",
+		"Result<u8, DispatchError>",
+		"Result<Foo, Bar>",
+		"Result<(), FooError>",
+		"Result<Foo, (WeirdError, PogError)>",
+		"Result<Vec<WeirdType>, FooError>",
+		"Result<(Foo, Zoo), (Bar, Car)>",
+		"Result<(Vec<WeirdType>, Weird), (FooError, WeirdErrorFormat)>",
+		"Compact<Balance>",
+		"Compact<Vec<Option<Foo>>>",
+		"Compact<Vec<(Foo, Bar, u8)>>",
+		"Box<Call>",
+		"Box<T::Proposal>",
+		"(u32, AccountId)",
+		"(u32,ApiKey,AnotherType)",
+		"(StorageKey, Foo<Bar>)",
+		"(StorageKey, Option<StorageData>)",
+		"(ParaId, Option<(CollatorId, Retriable)>)",
+		"(StorageKey, Option<StorageData>, Foo, Bar, Aoo, Raw, Car, Dar, Eoo, Foo, Goo, Foo, Foo, Foo, Foo, Foo,\
+		 Hoo, Ioo, Joo, Koo, Loo, Moo, Noo, Ooo, Poo, Qoo, Roo, Soo, Too, Uoo, Xoo)",
+		"GenericOuterType<GenericInnerType>",
+		"GenericOutT<GenericOutInT<InnerT>>",
+		"T::Generic<Runtime>",
+		"schedule::Period<T::BlockNumber>",
+		"BTreeMap<AccountId, RewardPoint>",
+		"BTreeMap<u32, Vec<(u8, u16)>>",
+		"T::Moment",
+		"RuntimeVersionApi",
+		"CompactScoreCompact",
+	];
+
+	/// Every type string used by the calls, events, storage entries and signed extensions of some V12 metadata.
+	fn v12_type_strings() -> Vec<String> {
+		fn decoded<B: 'static, O: 'static>(dd: DecodeDifferent<B, O>) -> O {
+			match dd {
+				DecodeDifferent::Decoded(value) => value,
+				_ => panic!("metadata should be decoded"),
+			}
+		}
+
+		let bytes = include_bytes!("../../integration-tests/data/metadata_v12_block4643974.bin");
+		let meta = RuntimeMetadataPrefixed::decode(&mut &bytes[..]).unwrap();
+		let RuntimeMetadata::V12(meta) = meta.1 else { panic!("expected V12 metadata") };
+
+		let mut types: Vec<String> = meta.extrinsic.signed_extensions.into_iter().map(decoded).collect();
+		for module in decoded(meta.modules) {
+			for call in module.calls.map(decoded).unwrap_or_default() {
+				types.extend(decoded(call.arguments).into_iter().map(|arg| decoded(arg.ty)));
+			}
+			for event in module.event.map(decoded).unwrap_or_default() {
+				types.extend(decoded(event.arguments));
+			}
+			for entry in module.storage.map(|s| decoded(decoded(s).entries)).unwrap_or_default() {
+				match entry.ty {
+					StorageEntryType::Plain(ty) => types.push(decoded(ty)),
+					StorageEntryType::Map { key, value, .. } => types.extend([decoded(key), decoded(value)]),
+					StorageEntryType::DoubleMap { key1, key2, value, .. } => {
+						types.extend([decoded(key1), decoded(key2), decoded(value)])
+					}
+				}
+			}
+		}
+		types
+	}
+
+	/// The regex parser splits tuples at every comma, even those nested inside of another type
+	/// (ie `(BalanceOf<T, I>, u32)`), and doesn't expect line breaks within a type.
+	/// We don't expect to reproduce its output for these.
+	#[cfg(feature = "regex")]
+	fn regex_misparses(s: &str) -> bool {
+		let mut open = Vec::new();
+		s.contains('\n')
+			|| s.chars().any(|c| {
+				match c {
+					'<' | '(' | '[' => open.push(c),
+					'>' | ')' | ']' => {
+						open.pop();
+					}
+					_ => {}
+				}
+				c == ',' && open.split_last().is_some_and(|(_, outer)| outer.contains(&'('))
+			})
+	}
+
+	#[test]
+	#[cfg(feature = "regex")]
+	fn should_match_regex_parser_on_test_types() {
+		for s in TYPE_STRINGS.iter().filter(|s| !regex_misparses(s)) {
+			assert_eq!(parse(s), crate::regex::parse(s), "parsing `{}`", s);
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "regex")]
+	fn should_match_regex_parser_on_metadata_types() {
+		let types = v12_type_strings();
+		let (misparsed, types): (Vec<_>, Vec<_>) = types.iter().partition(|s| regex_misparses(s));
+		assert!(types.len() > 100);
+		assert!(misparsed.len() < 10);
+		for s in types {
+			assert_eq!(parse(s), crate::regex::parse(s), "parsing `{}`", s);
+		}
+	}
+
+	#[test]
+	#[cfg(feature = "regex")]
+	fn should_sanitize_like_regex_parser() {
+		let extra = ["<T as Trait>::Call", "<T as Trait<I>>::Balance", "<T as Config<I>>::Proposal", "Vec<Foo<()>>"];
+		let types = v12_type_strings();
+		for s in TYPE_STRINGS.iter().copied().chain(extra).chain(types.iter().map(|s| s.as_str())) {
+			assert_eq!(sanitize_ty(s), crate::regex::sanitize_ty(s), "sanitizing `{}`", s);
+		}
+	}
+
+	#[test]
+	fn should_parse_every_metadata_type() {
+		for s in v12_type_strings() {
+			assert!(parse(&s).is_some(), "parsing `{}`", s);
+		}
+	}
+
+	#[test]
+	fn should_sanitize_types() {
+		let sanitized = |s| sanitize_ty(s).unwrap();
+		assert_eq!(sanitized("<T as Trait>::Call"), "Call");
+		assert_eq!(sanitized("<T as Config<I>>::Proposal"), "Proposal");
+		assert_eq!(sanitized("schedule::Period<T::BlockNumber>"), "Period");
+		assert_eq!(sanitized("T::Moment"), "Moment");
+		assert_eq!(sanitized("Vec<Foo<()>>"), "Foo");
+		assert_eq!(sanitized("AccountId"), "AccountId");
+	}
+
+	#[test]
+	fn should_parse_nested_types() {
+		assert_eq!(
+			parse("Vec<(u32, [u8; 4], Option<Compact<Balance>>)>"),
+			Some(RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::Tuple(vec![
+				RustTypeMarker::U32,
+				RustTypeMarker::Array { size: 4, ty: Box::new(RustTypeMarker::U8) },
+				RustTypeMarker::Std(CommonTypes::Option(Box::new(RustTypeMarker::Std(CommonTypes::Compact(
+					Box::new(RustTypeMarker::TypePointer("Balance".to_string()))
+				)))))
+			])))))
+		);
+		assert_eq!(
+			parse("(BalanceOf<T, I>, u32)"),
+			Some(RustTypeMarker::Tuple(vec![
+				RustTypeMarker::Generic(
+					Box::new(RustTypeMarker::TypePointer("BalanceOf".to_string())),
+					Box::new(RustTypeMarker::TypePointer("T, I".to_string()))
+				),
+				RustTypeMarker::U32
+			]))
+		);
+		assert_eq!(parse("()"), Some(RustTypeMarker::TypePointer("()".to_string())));
+	}
+}
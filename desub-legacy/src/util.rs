@@ -16,11 +16,30 @@
 use crate::{Error, SubstrateType};
 use serde::{
 	ser::{self, SerializeSeq},
-	Serializer,
+	Serialize, Serializer,
 };
-use sp_core::crypto::Ss58Codec;
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
 use std::convert::TryFrom;
 
+// Utility function to serialize map entries as a sequence of `{ "key": K, "value": V }` objects,
+// since keys are not necessarily strings
+pub fn as_map_entries<S: Serializer>(
+	entries: &[(SubstrateType, SubstrateType)],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	#[derive(Serialize)]
+	struct Entry<'a> {
+		key: &'a SubstrateType,
+		value: &'a SubstrateType,
+	}
+
+	let mut seq = serializer.serialize_seq(Some(entries.len()))?;
+	for (key, value) in entries.iter() {
+		seq.serialize_element(&Entry { key, value })?;
+	}
+	seq.end()
+}
+
 // Utility function to serialize from slice/vec to hex
 // If the SubstrateType is a collection of u8s, will serialize as hex
 pub fn as_hex<S: Serializer>(elements: &[SubstrateType], serializer: S) -> Result<S::Ok, S::Error> {
@@ -44,6 +63,20 @@ pub fn as_hex<S: Serializer>(elements: &[SubstrateType], serializer: S) -> Resul
 	}
 }
 
+/// Format a Substrate Type holding an account ID (either 32 bytes, or a `MultiAddress::Id`) as an ss58 address with
+/// the given prefix. Returns `None` if the type doesn't hold an account ID.
+pub fn to_ss58_address(ty: &SubstrateType, prefix: u16) -> Option<String> {
+	let account = match ty {
+		SubstrateType::Composite(_) => {
+			let bytes: Vec<u8> = TryFrom::try_from(ty).ok()?;
+			AccountId32::new(bytes.try_into().ok()?)
+		}
+		SubstrateType::Address(sp_runtime::MultiAddress::Id(i)) => i.clone(),
+		_ => return None,
+	};
+	Some(account.to_ss58check_with_version(Ss58AddressFormat::custom(prefix)))
+}
+
 /// Serialize a Substrate Type as a ss58 Address
 /// # Panics
 /// Panics if a SubstrateType can not be serialized into an ss58 address type
@@ -0,0 +1,71 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Facade crate for decoding data that uses any version of metadata (V8+)
+
+//! Compares decoding a block of 1000 signed `Balances.transfer` extrinsics with [`Decoder::decode_block`] against
+//! [`Decoder::decode_extrinsics`], and against the V14 decoder used directly. Run with `cargo bench -p desub`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use desub::{Chain, Decoder};
+use desub_current::{decoder, Metadata};
+use desub_json_resolver::TypeResolver;
+use parity_scale_codec::{Compact, Encode};
+
+static V14_METADATA_POLKADOT_SCALE: &[u8] =
+	include_bytes!("../../desub-current/tests/data/v14_metadata_polkadot.scale");
+
+const EXTRINSICS: u32 = 1000;
+
+fn block() -> Vec<u8> {
+	// Balances.transfer (amount: 12345), signed by Alice:
+	let ext = hex::decode("31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0").expect("valid hex");
+	let mut block = Compact(EXTRINSICS).encode();
+	for _ in 0..EXTRINSICS {
+		block.extend(&ext);
+	}
+	block
+}
+
+fn decode_block(c: &mut Criterion) {
+	let mut desub = Decoder::with_custom_types(TypeResolver::default(), Chain::Polkadot);
+	desub.register_version(9110, V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let metadata = Metadata::from_bytes(V14_METADATA_POLKADOT_SCALE).expect("valid metadata");
+	let block = block();
+
+	let mut group = c.benchmark_group("decode_block");
+	group.throughput(Throughput::Elements(EXTRINSICS as u64));
+	group.bench_function("Decoder::decode_block", |b| {
+		b.iter(|| desub.decode_block(9110, black_box(&block)).expect("can decode block"))
+	});
+	group.bench_function("Decoder::decode_extrinsics", |b| {
+		b.iter(|| desub.decode_extrinsics(9110, black_box(&block)).expect("can decode extrinsics"))
+	});
+	group.bench_function("decoder::decode_block", |b| {
+		b.iter(|| {
+			let exts = decoder::decode_block(&metadata, black_box(&block)).expect("can split block");
+			for (_, _, ext) in exts {
+				black_box(ext.expect("can decode extrinsic"));
+			}
+		})
+	});
+	group.bench_function("decoder::decode_extrinsics", |b| {
+		b.iter(|| decoder::decode_extrinsics(&metadata, &mut black_box(&*block)).expect("can decode extrinsics"))
+	});
+	group.finish();
+}
+
+criterion_group!(benches, decode_block);
+criterion_main!(benches);
@@ -15,7 +15,7 @@
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
 use desub_current::{
-	decoder::{DecodeError, Extrinsic},
+	decoder::{DecodeError, Extrinsic, ExtrinsicBytesError},
 	metadata::MetadataError,
 };
 use desub_legacy::{decoder::metadata::Error as LegacyMetadataError, Error as LegacyError};
@@ -29,16 +29,40 @@ pub enum Error {
 		source: DecodeError,
 		ext: Vec<Extrinsic<'static>>,
 	},
+	#[error("Decoding v14 events failed")]
+	V14Events(#[source] DecodeError),
+	#[error("Decoding v14 call failed")]
+	V14Call(#[source] DecodeError),
 	#[error(transparent)]
 	Legacy(#[from] LegacyError),
 	#[error(transparent)]
 	Codec(#[from] parity_scale_codec::Error),
 	#[error(transparent)]
+	ExtrinsicBytes(#[from] ExtrinsicBytesError),
+	#[error(transparent)]
 	MetadataError(#[from] MetadataError),
 	#[error(transparent)]
 	LegacyMetadataError(#[from] LegacyMetadataError),
+	#[error("Failed to register spec version {version}")]
+	RegisterVersion {
+		version: u32,
+		#[source]
+		source: Box<Error>,
+	},
 	#[error("Spec Version {0} not registered with decoder")]
 	SpecVersionNotFound(u32),
+	#[error("Chain {0} not registered with decoder")]
+	ChainNotFound(String),
+	#[error("None of the candidate chains could decode the data: {0:?}")]
+	NoCandidateChain(Vec<(String, Error)>),
 	#[error(transparent)]
 	Serialization(#[from] serde_json::Error),
+	#[error("Invalid chain_getBlock response: {0}")]
+	InvalidRpcBlock(String),
+	#[error("Spec Version {0} was registered with legacy metadata")]
+	LegacyMetadata(u32),
+	#[error("Metadata refers to types which aren't in its type registry: {0:?}")]
+	UnresolvedTypes(Vec<u32>),
+	#[error("Failed to fetch metadata: {0}")]
+	Rpc(#[source] Box<dyn std::error::Error + Send + Sync>),
 }
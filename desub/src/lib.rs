@@ -18,28 +18,35 @@
 #![forbid(unsafe_code)]
 #[deny(unused)]
 mod error;
+#[cfg(feature = "rpc")]
+mod metadata_cache;
+mod types;
 
 use desub_current::{
-	decoder::{self, Extrinsic},
+	decoder::{self, CallData, DecodeError, EventRecord, Extrinsic},
 	Metadata as DesubMetadata,
 };
 use desub_legacy::{
-	decoder::{Decoder as LegacyDecoder, Metadata as LegacyDesubMetadata},
+	decoder::{Decoder as LegacyDecoder, GenericCall, GenericEvent, GenericExtrinsic, Metadata as LegacyDesubMetadata},
 	RustTypeMarker, TypeDetective,
 };
 use frame_metadata::RuntimeMetadataPrefixed;
-use parity_scale_codec::Decode;
+use parity_scale_codec::{Compact, Decode, Encode};
+use serde::Serialize;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 #[cfg(feature = "polkadot-js")]
 use desub_json_resolver::TypeResolver as PolkadotJsResolver;
 
 pub use self::error::Error;
+pub use self::types::{values_match, NormalizedValue};
 pub use desub_common::SpecVersion;
 #[cfg(feature = "polkadot-js")]
 pub use desub_json_resolver::runtimes;
 pub use desub_legacy::decoder::Chain;
+#[cfg(feature = "rpc")]
+pub use metadata_cache::{AsyncMetadataCache, BoxFuture, MetadataRpc};
 
 /// Struct That implements TypeDetective but refuses to resolve anything
 /// that is not of metadata v14+.
@@ -61,66 +68,572 @@ impl TypeDetective for NoLegacyTypes {
 	}
 }
 
+/// An event decoded against either V14+ or legacy metadata.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DecodedEvent {
+	/// An event decoded against V14+ metadata.
+	Current(EventRecord<'static>),
+	/// An event decoded against legacy (pre-V14) metadata.
+	Legacy(GenericEvent),
+}
+
+impl DecodedEvent {
+	/// The name of the pallet (or module) which emitted the event.
+	pub fn pallet_name(&self) -> &str {
+		match self {
+			DecodedEvent::Current(e) => &e.event.pallet_name,
+			DecodedEvent::Legacy(e) => e.module(),
+		}
+	}
+
+	/// The name of the event.
+	pub fn event_name(&self) -> &str {
+		match self {
+			DecodedEvent::Current(e) => &e.event.ty.name,
+			DecodedEvent::Legacy(e) => e.name(),
+		}
+	}
+}
+
+/// A call decoded against either V14+ or legacy metadata.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DecodedCall {
+	/// A call decoded against V14+ metadata.
+	Current(CallData<'static>),
+	/// A call decoded against legacy (pre-V14) metadata.
+	Legacy(GenericCall),
+}
+
+impl DecodedCall {
+	/// The name of the pallet (or module) that the call belongs to.
+	pub fn pallet_name(&self) -> &str {
+		match self {
+			DecodedCall::Current(c) => &c.pallet_name,
+			DecodedCall::Legacy(c) => c.module(),
+		}
+	}
+
+	/// The name of the call.
+	pub fn call_name(&self) -> &str {
+		match self {
+			DecodedCall::Current(c) => &c.ty.name,
+			DecodedCall::Legacy(c) => c.name(),
+		}
+	}
+}
+
+/// An extrinsic decoded against either V14+ or legacy metadata.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DecodedExtrinsic {
+	/// An extrinsic decoded against V14+ metadata.
+	Current(Extrinsic<'static>),
+	/// An extrinsic decoded against legacy (pre-V14) metadata.
+	Legacy(GenericExtrinsic),
+}
+
+impl DecodedExtrinsic {
+	/// The name of the pallet (or module) that the extrinsic's call belongs to.
+	pub fn pallet_name(&self) -> &str {
+		match self {
+			DecodedExtrinsic::Current(e) => &e.call_data.pallet_name,
+			DecodedExtrinsic::Legacy(e) => e.module_name(),
+		}
+	}
+
+	/// The name of the extrinsic's call.
+	pub fn call_name(&self) -> &str {
+		match self {
+			DecodedExtrinsic::Current(e) => &e.call_data.ty.name,
+			DecodedExtrinsic::Legacy(e) => e.call_name(),
+		}
+	}
+}
+
+/// Which of the internal decoders some registered metadata is handled by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataKind {
+	/// Metadata before V14, decoded with the help of the legacy type definitions.
+	Legacy,
+	/// V14+ metadata, which carries its own type information.
+	Current,
+}
+
+/// A summary of the metadata registered for a spec version; see [`Decoder::describe_version`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionInfo {
+	/// Which decoder handles this version.
+	pub kind: MetadataKind,
+	/// The version of the extrinsic format. This is `None` for legacy metadata older than V11, which doesn't
+	/// record it.
+	pub extrinsic_version: Option<u8>,
+	/// The number of pallets (or modules, in legacy terms) in the runtime.
+	pub pallet_count: usize,
+}
+
 pub struct Decoder {
+	chain: Chain,
 	legacy_decoder: LegacyDecoder,
-	current_metadata: HashMap<SpecVersion, DesubMetadata>,
+	/// Legacy decoders for any chains other than `chain` that metadata has been registered for. Each holds the
+	/// legacy metadata of its chain, since legacy types are looked up by chain name.
+	other_legacy_decoders: HashMap<Chain, LegacyDecoder>,
+	/// V14+ metadata for every chain, keyed by chain and spec version.
+	current_metadata: HashMap<(Chain, SpecVersion), Arc<DesubMetadata>>,
 }
 
 impl Decoder {
 	#[cfg(feature = "polkadot-js")]
 	pub fn new(chain: Chain) -> Self {
-		let legacy_decoder = LegacyDecoder::new(PolkadotJsResolver::default(), chain);
-		let current_metadata = HashMap::new();
-
-		Self { legacy_decoder, current_metadata }
+		let legacy_decoder = LegacyDecoder::new(PolkadotJsResolver::default(), chain.clone());
+		Self { chain, legacy_decoder, other_legacy_decoders: HashMap::new(), current_metadata: HashMap::new() }
 	}
 
 	#[cfg(not(feature = "polkadot-js"))]
 	pub fn new() -> Self {
-		let legacy_decoder = LegacyDecoder::new(NoLegacyTypes, Chain::Custom("none".to_string()));
-		let current_metadata = HashMap::new();
-
-		Self { legacy_decoder, current_metadata }
+		let chain = Chain::Custom("none".to_string());
+		let legacy_decoder = LegacyDecoder::new(NoLegacyTypes, chain.clone());
+		Self { chain, legacy_decoder, other_legacy_decoders: HashMap::new(), current_metadata: HashMap::new() }
 	}
 
 	/// Create a new general Decoder
 	pub fn with_custom_types(types: impl TypeDetective + 'static, chain: Chain) -> Self {
-		let legacy_decoder = LegacyDecoder::new(types, chain);
-		let current_decoder = HashMap::new();
-		Self { legacy_decoder, current_metadata: current_decoder }
+		let legacy_decoder = LegacyDecoder::new(types, chain.clone());
+		Self { chain, legacy_decoder, other_legacy_decoders: HashMap::new(), current_metadata: HashMap::new() }
 	}
 
 	/// Register a runtime version with the decoder.
-	pub fn register_version(&mut self, version: SpecVersion, mut metadata: &[u8]) -> Result<(), Error> {
+	pub fn register_version(&mut self, version: SpecVersion, metadata: &[u8]) -> Result<(), Error> {
+		self.register_chain_version(self.chain.clone(), version, metadata)
+	}
+
+	/// Register the metadata of several runtime versions at once. Registration stops at the first metadata that
+	/// can't be registered, with an [`Error::RegisterVersion`] naming its version; any versions before it remain
+	/// registered.
+	pub fn register_versions<B: AsRef<[u8]>>(
+		&mut self,
+		items: impl IntoIterator<Item = (SpecVersion, B)>,
+	) -> Result<(), Error> {
+		for (version, metadata) in items {
+			self.register_version(version, metadata.as_ref())
+				.map_err(|e| Error::RegisterVersion { version, source: Box::new(e) })?;
+		}
+		Ok(())
+	}
+
+	/// Register a runtime version of a specific chain with the decoder, so that the same spec version can be
+	/// registered for several chains. Legacy metadata registered for a chain other than the one this decoder was
+	/// created for is decoded using the same types, looked up under the name of that chain. Use
+	/// [`Decoder::decode_chain_extrinsics`] to decode data from that chain.
+	pub fn register_chain_version(
+		&mut self,
+		chain: Chain,
+		version: SpecVersion,
+		mut metadata: &[u8],
+	) -> Result<(), Error> {
 		let metadata: RuntimeMetadataPrefixed = Decode::decode(&mut metadata)?;
+		// Even chains with only V14+ metadata get a legacy decoder, which marks them as known.
+		let legacy_decoder = if chain == self.chain {
+			&mut self.legacy_decoder
+		} else {
+			let default_decoder = &self.legacy_decoder;
+			self.other_legacy_decoders.entry(chain.clone()).or_insert_with(|| default_decoder.for_chain(chain.clone()))
+		};
 		if metadata.1.version() >= 14 {
 			let meta = DesubMetadata::from_runtime_metadata(metadata.1)?;
-			self.current_metadata.insert(version, meta);
+			self.current_metadata.insert((chain, version), Arc::new(meta));
 		} else {
-			self.legacy_decoder.register_version(version, LegacyDesubMetadata::from_runtime_metadata(metadata.1)?)?;
+			legacy_decoder.register_version(version, LegacyDesubMetadata::from_runtime_metadata(metadata.1)?)?;
 		}
 		Ok(())
 	}
 
-	pub fn decode_extrinsics(&self, version: SpecVersion, mut data: &[u8]) -> Result<Value, Error> {
-		if self.current_metadata.contains_key(&version) {
-			let metadata = self.current_metadata.get(&version).expect("Checked if key is contained; qed");
-			match decoder::decode_extrinsics(metadata, &mut data) {
-				Ok(v) => Ok(serde_json::to_value(v)?),
-				Err((ext, e)) => {
-					Err(Error::V14 { source: e, ext: ext.into_iter().map(Extrinsic::into_owned).collect() })
+	/// Is there any metadata registered for the given version of the given chain?
+	pub fn has_chain_version(&self, chain: &Chain, version: SpecVersion) -> bool {
+		self.current_metadata.contains_key(&(chain.clone(), version))
+			|| self.legacy_decoder_for(chain).is_some_and(|d| d.has_version(&version))
+	}
+
+	/// Decode extrinsics from the given chain; see [`Decoder::decode_extrinsics`]. This is an
+	/// [`Error::ChainNotFound`] if no metadata has been registered for the chain.
+	pub fn decode_chain_extrinsics(&self, chain: &Chain, version: SpecVersion, data: &[u8]) -> Result<Value, Error> {
+		self.decode_extrinsics_on(chain, version, data, 0).map(|(ext, _)| ext)
+	}
+
+	/// Decode a SCALE encoded vector of extrinsics (in the same shape as expected by [`Decoder::decode_extrinsics`])
+	/// whose chain isn't known, by trying each of the `candidates` in turn and handing back the first chain that
+	/// decodes every extrinsic without errors or left over bytes, along with the decoded extrinsics. Candidates
+	/// without metadata for the given version are skipped. If no candidate succeeds, the error that each one
+	/// failed with is returned in [`Error::NoCandidateChain`].
+	pub fn try_decode_extrinsics(
+		&self,
+		version: SpecVersion,
+		data: &[u8],
+		candidates: &[Chain],
+	) -> Result<(Chain, Vec<DecodedExtrinsic>), Error> {
+		// Splitting up the extrinsics doesn't depend on the chain, so do it once up front.
+		let exts: Vec<Vec<u8>> = decoder::split_extrinsics(data)?.into_iter().map(|ext| ext.encode()).collect();
+		let consumed = Compact(exts.len() as u32).encoded_size() + exts.iter().map(Vec::len).sum::<usize>();
+		if data.len() > consumed {
+			return Err(Error::V14 { source: DecodeError::ExcessBytes(data.len() - consumed), ext: Vec::new() });
+		}
+
+		let mut errors = Vec::with_capacity(candidates.len());
+		for chain in candidates {
+			match self.decode_extrinsics_from_list_on(chain, version, &exts) {
+				Ok(exts) => return Ok((chain.clone(), exts)),
+				Err(e) => errors.push((chain.to_string(), e)),
+			}
+		}
+		Err(Error::NoCandidateChain(errors))
+	}
+
+	/// Decode a SCALE encoded vector of extrinsics, such as a block body, routing to the current or legacy
+	/// decoder depending on the metadata registered for the given version. Any bytes left over after the
+	/// extrinsics are an error; see [`Decoder::decode_extrinsics_allow_trailing`] to tolerate them.
+	pub fn decode_extrinsics(&self, version: SpecVersion, data: &[u8]) -> Result<Value, Error> {
+		self.decode_extrinsics_allow_trailing(version, data, 0).map(|(ext, _)| ext)
+	}
+
+	/// Like [`Decoder::decode_extrinsics`], but allow up to `max_trailing` bytes to be left over after the
+	/// extrinsics, returning them alongside the decoded extrinsics for inspection. Some sources of block
+	/// bodies tack extra bytes on to the end.
+	pub fn decode_extrinsics_allow_trailing<'b>(
+		&self,
+		version: SpecVersion,
+		data: &'b [u8],
+		max_trailing: usize,
+	) -> Result<(Value, &'b [u8]), Error> {
+		self.decode_extrinsics_on(&self.chain, version, data, max_trailing)
+	}
+
+	fn decode_extrinsics_on<'b>(
+		&self,
+		chain: &Chain,
+		version: SpecVersion,
+		mut data: &'b [u8],
+		max_trailing: usize,
+	) -> Result<(Value, &'b [u8]), Error> {
+		let legacy_decoder = self.legacy_decoder_for(chain).ok_or_else(|| Error::ChainNotFound(chain.to_string()))?;
+		if let Some(metadata) = self.current_metadata.get(&(chain.clone(), version)) {
+			let to_error = |source, ext: Vec<Extrinsic>| Error::V14 {
+				source,
+				ext: ext.into_iter().map(Extrinsic::into_owned).collect(),
+			};
+			let ext = decoder::decode_extrinsics(metadata, &mut data).map_err(|(ext, e)| to_error(e, ext))?;
+			if data.len() > max_trailing {
+				return Err(to_error(DecodeError::ExcessBytes(data.len()), ext));
+			}
+			Ok((serde_json::to_value(ext)?, data))
+		} else {
+			if !legacy_decoder.has_version(&version) {
+				return Err(Error::SpecVersionNotFound(version));
+			}
+			let (ext, trailing) = legacy_decoder.decode_extrinsics_allow_trailing(version, data, max_trailing)?;
+			Ok((serde_json::to_value(&ext)?, trailing))
+		}
+	}
+
+	/// The arguments of the call of an extrinsic decoded for the given version, in a form which can be compared with
+	/// the arguments of an extrinsic decoded by the other decoder; see [`NormalizedValue`]. V14+ extrinsics are
+	/// normalized with the metadata registered for the version, which is an [`Error::SpecVersionNotFound`] if there
+	/// is none.
+	pub fn normalized_args(
+		&self,
+		version: SpecVersion,
+		extrinsic: &DecodedExtrinsic,
+	) -> Result<Vec<NormalizedValue>, Error> {
+		match extrinsic {
+			DecodedExtrinsic::Current(e) => {
+				let metadata = self.current_metadata(version).ok_or(Error::SpecVersionNotFound(version))?;
+				Ok(e.call_data.arguments.iter().map(|arg| NormalizedValue::from_value(arg, metadata)).collect())
+			}
+			DecodedExtrinsic::Legacy(e) => Ok(e.args().iter().map(|a| NormalizedValue::from(&a.arg)).collect()),
+		}
+	}
+
+	/// Decode a single extrinsic, routing to the current or legacy decoder depending on the metadata registered
+	/// for the given version. `data` should begin with the compact encoded length of the extrinsic, and must be
+	/// exactly as long as that length says.
+	pub fn decode_extrinsic(&self, version: SpecVersion, data: &[u8]) -> Result<DecodedExtrinsic, Error> {
+		if let Some(metadata) = self.current_metadata(version) {
+			let ext =
+				decode_current_extrinsic(metadata, data).map_err(|source| Error::V14 { source, ext: Vec::new() })?;
+			Ok(DecodedExtrinsic::Current(ext.into_owned()))
+		} else {
+			if !self.legacy_decoder.has_version(&version) {
+				return Err(Error::SpecVersionNotFound(version));
+			}
+			let ext = self.legacy_decoder.decode_single_extrinsic(version, data)?;
+			Ok(DecodedExtrinsic::Legacy(ext))
+		}
+	}
+
+	/// Decode extrinsics which have already been split apart, such as the list of extrinsics in a block
+	/// returned by the `chain_getBlock` RPC call, routing to the current or legacy decoder depending on
+	/// the metadata registered for the given version. Each extrinsic should begin with its compact encoded
+	/// length, and must be exactly as long as that length says.
+	pub fn decode_extrinsics_from_list<B: AsRef<[u8]>>(
+		&self,
+		version: SpecVersion,
+		exts: &[B],
+	) -> Result<Vec<DecodedExtrinsic>, Error> {
+		self.decode_extrinsics_from_list_on(&self.chain, version, exts)
+	}
+
+	fn decode_extrinsics_from_list_on<B: AsRef<[u8]>>(
+		&self,
+		chain: &Chain,
+		version: SpecVersion,
+		exts: &[B],
+	) -> Result<Vec<DecodedExtrinsic>, Error> {
+		let legacy_decoder = self.legacy_decoder_for(chain).ok_or_else(|| Error::ChainNotFound(chain.to_string()))?;
+		if let Some(metadata) = self.current_metadata.get(&(chain.clone(), version)) {
+			let mut out = Vec::with_capacity(exts.len());
+			for ext in exts {
+				match decode_current_extrinsic(metadata, ext.as_ref()) {
+					Ok(ext) => out.push(ext.into_owned()),
+					Err(e) => return Err(Error::V14 { source: e, ext: out }),
 				}
 			}
+			Ok(out.into_iter().map(DecodedExtrinsic::Current).collect())
+		} else {
+			if !legacy_decoder.has_version(&version) {
+				return Err(Error::SpecVersionNotFound(version));
+			}
+			let exts = legacy_decoder.decode_extrinsic_list(version, exts)?;
+			Ok(exts.into_iter().map(DecodedExtrinsic::Legacy).collect())
+		}
+	}
+
+	/// Decode the extrinsics in the JSON response to a `chain_getBlock` RPC call, which lists them as hex strings
+	/// under `block.extrinsics`. Either the whole JSON-RPC response or just its `result` can be given.
+	pub fn decode_rpc_block(&self, version: SpecVersion, block_json: &Value) -> Result<Vec<DecodedExtrinsic>, Error> {
+		let result = block_json.get("result").unwrap_or(block_json);
+		let exts = result
+			.pointer("/block/extrinsics")
+			.and_then(Value::as_array)
+			.ok_or_else(|| Error::InvalidRpcBlock("expected an array at block.extrinsics".into()))?;
+		let exts = exts
+			.iter()
+			.enumerate()
+			.map(|(idx, ext)| {
+				let hex_str = ext.as_str().and_then(|ext| ext.strip_prefix("0x"));
+				let hex_str = hex_str
+					.ok_or_else(|| Error::InvalidRpcBlock(format!("extrinsic {idx} is not a 0x prefixed string")))?;
+				hex::decode(hex_str)
+					.map_err(|e| Error::InvalidRpcBlock(format!("extrinsic {idx} is not valid hex: {e}")))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		self.decode_extrinsics_from_list(version, &exts)
+	}
+
+	/// Decode a SCALE encoded vector of extrinsics, such as a block body, routing to the current or legacy decoder
+	/// depending on the metadata registered for the given version. Unlike [`Decoder::decode_extrinsics`], the
+	/// extrinsics are handed back as they were decoded rather than serialized to JSON. Any bytes left over after
+	/// the extrinsics are an error.
+	pub fn decode_block(&self, version: SpecVersion, data: &[u8]) -> Result<Vec<DecodedExtrinsic>, Error> {
+		if let Some(metadata) = self.current_metadata(version) {
+			let to_error = |source, ext: Vec<Extrinsic>| Error::V14 {
+				source,
+				ext: ext.into_iter().map(Extrinsic::into_owned).collect(),
+			};
+			let cursor = &mut &*data;
+			let exts = decoder::decode_extrinsics(metadata, cursor).map_err(|(ext, e)| to_error(e, ext))?;
+			if !cursor.is_empty() {
+				return Err(to_error(DecodeError::ExcessBytes(cursor.len()), exts));
+			}
+			Ok(exts.into_iter().map(|ext| DecodedExtrinsic::Current(ext.into_owned())).collect())
+		} else {
+			if !self.legacy_decoder.has_version(&version) {
+				return Err(Error::SpecVersionNotFound(version));
+			}
+			let exts = self.legacy_decoder.decode_extrinsics(version, data)?;
+			Ok(exts.into_iter().map(DecodedExtrinsic::Legacy).collect())
+		}
+	}
+
+	/// Decode only some of the extrinsics in a SCALE encoded vector of extrinsics (in the same shape as expected by
+	/// [`Decoder::decode_extrinsics`]): the first `skip` extrinsics are stepped over without being decoded, and then
+	/// at most `count` are decoded. This is useful to page through very large blocks.
+	pub fn decode_extrinsics_window(
+		&self,
+		version: SpecVersion,
+		data: &[u8],
+		skip: usize,
+		count: usize,
+	) -> Result<Vec<DecodedExtrinsic>, Error> {
+		let window: Vec<Vec<u8>> =
+			decoder::split_extrinsics(data)?.into_iter().skip(skip).take(count).map(|ext| ext.encode()).collect();
+		self.decode_extrinsics_from_list(version, &window)
+	}
+
+	/// Decode the events stored in `System.Events`, routing to the current or legacy decoder
+	/// depending on the metadata registered for the given version. `data` should hold exactly the
+	/// compact count of events followed by that many records; any bytes left over are an error.
+	pub fn decode_events(&self, version: SpecVersion, mut data: &[u8]) -> Result<Vec<DecodedEvent>, Error> {
+		if let Some(metadata) = self.current_metadata(version) {
+			let events = decoder::decode_events(metadata, &mut data).map_err(Error::V14Events)?;
+			Ok(events.into_iter().map(|e| DecodedEvent::Current(e.into_owned())).collect())
+		} else {
+			if !self.legacy_decoder.has_version(&version) {
+				return Err(Error::SpecVersionNotFound(version));
+			}
+			let events = self.legacy_decoder.decode_events(version, data)?;
+			Ok(events.into_iter().map(DecodedEvent::Legacy).collect())
+		}
+	}
+
+	/// Decode a bare call (pallet index, call index and arguments) that is not wrapped in an extrinsic,
+	/// such as a scheduled call, routing to the current or legacy decoder depending on the metadata
+	/// registered for the given version. `data` is advanced past the bytes consumed.
+	pub fn decode_call(&self, version: SpecVersion, data: &mut &[u8]) -> Result<DecodedCall, Error> {
+		if let Some(metadata) = self.current_metadata(version) {
+			let call = decoder::decode_call_data(metadata, data).map_err(Error::V14Call)?;
+			Ok(DecodedCall::Current(call.into_owned()))
 		} else {
 			if !self.legacy_decoder.has_version(&version) {
 				return Err(Error::SpecVersionNotFound(version));
 			}
-			let ext = self.legacy_decoder.decode_extrinsics(version, data)?;
-			Ok(serde_json::to_value(&ext)?)
+			let call = self.legacy_decoder.decode_call_data(version, data)?;
+			Ok(DecodedCall::Legacy(call))
 		}
 	}
 
+	/// The chain this decoder was created for. This is the chain whose metadata [`Decoder::register_version`]
+	/// registers, and which [`Decoder::decode_extrinsics`] and friends decode for.
+	pub fn chain(&self) -> &Chain {
+		&self.chain
+	}
+
+	/// Get the V14+ metadata registered for a version, if there is any. The metadata is shared rather than
+	/// cloned, so this is cheap to call and the result can be handed to other threads, for instance to
+	/// decode with [`desub_current::decoder`] directly.
+	///
+	/// Returns `None` if the version is not registered, or if it was registered with legacy metadata.
+	pub fn metadata(&self, version: SpecVersion) -> Option<Arc<DesubMetadata>> {
+		self.current_metadata(version).cloned()
+	}
+
+	/// Check that every type referred to by the V14+ metadata registered for a version is in its type registry,
+	/// so that bad metadata is caught up front rather than part way through decoding. See
+	/// [`DesubMetadata::validate`]. Legacy metadata has no type registry to check, and is reported as
+	/// [`Error::LegacyMetadata`].
+	pub fn validate_metadata(&self, version: SpecVersion) -> Result<(), Error> {
+		if let Some(metadata) = self.current_metadata(version) {
+			metadata.validate().map_err(Error::UnresolvedTypes)
+		} else if self.legacy_decoder.has_version(&version) {
+			Err(Error::LegacyMetadata(version))
+		} else {
+			Err(Error::SpecVersionNotFound(version))
+		}
+	}
+
+	/// Summarise the metadata registered for a version, whichever decoder it was registered with. Returns `None` if
+	/// the version is not registered.
+	pub fn describe_version(&self, version: SpecVersion) -> Option<VersionInfo> {
+		if let Some(metadata) = self.current_metadata(version) {
+			return Some(VersionInfo {
+				kind: MetadataKind::Current,
+				extrinsic_version: Some(metadata.extrinsic().version()),
+				pallet_count: metadata.pallet_count(),
+			});
+		}
+		let metadata = self.legacy_decoder.get_version_metadata(version)?;
+		Some(VersionInfo {
+			kind: MetadataKind::Legacy,
+			extrinsic_version: metadata.extrinsic_version(),
+			pallet_count: metadata.modules().count(),
+		})
+	}
+
+	/// Remove the metadata registered for a version (whether V14+ or legacy), freeing the memory it used once
+	/// any [`Decoder::metadata`] handed out for it has been dropped. Returns whether there was any metadata
+	/// registered for that version. Only the chain this decoder was created for is affected; use
+	/// [`Decoder::remove_chain_version`] for other chains.
+	pub fn remove_version(&mut self, version: SpecVersion) -> bool {
+		let chain = self.chain.clone();
+		self.remove_chain_version(&chain, version)
+	}
+
+	/// Remove the metadata registered for a version of the given chain; see [`Decoder::remove_version`].
+	pub fn remove_chain_version(&mut self, chain: &Chain, version: SpecVersion) -> bool {
+		let removed_current = self.current_metadata.remove(&(chain.clone(), version)).is_some();
+		let removed_legacy = if *chain == self.chain {
+			self.legacy_decoder.remove_version(version)
+		} else {
+			self.other_legacy_decoders.get_mut(chain).is_some_and(|d| d.remove_version(version))
+		};
+		removed_current || removed_legacy
+	}
+
+	/// Consume the decoder, handing back the V14+ metadata registered for each chain and version so that it can be
+	/// used with [`desub_current::decoder`] directly. As with [`Decoder::metadata`], the metadata is shared, so any
+	/// handed out before this call still refer to the same instances. Legacy metadata is left out; see
+	/// [`Decoder::into_legacy_metadata_map`] for that.
+	pub fn into_metadata_map(self) -> HashMap<(Chain, SpecVersion), Arc<DesubMetadata>> {
+		self.current_metadata
+	}
+
+	/// Consume the decoder, handing back the legacy (pre V14) metadata registered for each chain and version so that
+	/// it can be used with [`desub_legacy`] directly. V14+ metadata is left out; see [`Decoder::into_metadata_map`]
+	/// for that.
+	pub fn into_legacy_metadata_map(self) -> HashMap<(Chain, SpecVersion), LegacyDesubMetadata> {
+		std::iter::once((self.chain, self.legacy_decoder))
+			.chain(self.other_legacy_decoders)
+			.flat_map(|(chain, decoder)| {
+				decoder.into_versions().into_iter().map(move |(version, meta)| ((chain.clone(), version), meta))
+			})
+			.collect()
+	}
+
 	pub fn has_version(&self, version: SpecVersion) -> bool {
-		self.current_metadata.contains_key(&version) || self.legacy_decoder.has_version(&version)
+		self.current_metadata(version).is_some() || self.legacy_decoder.has_version(&version)
+	}
+
+	/// Find the highest registered spec version that is at or below `version`, whether its metadata is
+	/// V14+ or legacy. This is useful when the exact spec version of a block was not registered, but the
+	/// metadata of an earlier runtime is known to be compatible; the version returned can then be passed
+	/// to any of the decode methods.
+	///
+	/// Returns `None` if no version at or below `version` has been registered.
+	pub fn metadata_at_or_below(&self, version: SpecVersion) -> Option<SpecVersion> {
+		let current =
+			self.current_metadata.keys().filter(|(c, v)| *c == self.chain && *v <= version).map(|(_, v)| *v).max();
+		let legacy = self.legacy_decoder.metadata_at_or_below(version).map(|(v, _)| v);
+		current.max(legacy)
+	}
+
+	/// The V14+ metadata registered for a version of the chain this decoder was created for.
+	fn current_metadata(&self, version: SpecVersion) -> Option<&Arc<DesubMetadata>> {
+		self.current_metadata.get(&(self.chain.clone(), version))
+	}
+
+	/// The legacy decoder for the given chain, if it's the chain this decoder was created for or if any metadata has
+	/// been registered for it.
+	fn legacy_decoder_for(&self, chain: &Chain) -> Option<&LegacyDecoder> {
+		if *chain == self.chain {
+			Some(&self.legacy_decoder)
+		} else {
+			self.other_legacy_decoders.get(chain)
+		}
+	}
+}
+
+/// Decode a single length prefixed extrinsic against V14+ metadata, checking that the length
+/// prefix matches the number of bytes that the extrinsic actually takes up.
+fn decode_current_extrinsic<'a>(metadata: &'a DesubMetadata, mut data: &[u8]) -> Result<Extrinsic<'a>, DecodeError> {
+	let len = <Compact<u32>>::decode(&mut data)?.0 as usize;
+	if data.len() < len {
+		return Err(DecodeError::EarlyEof("extrinsic is shorter than its length prefix"));
+	}
+	if data.len() > len {
+		return Err(DecodeError::ExcessBytes(data.len() - len));
+	}
+	let ext = decoder::decode_unwrapped_extrinsic(metadata, &mut data)?;
+	if !data.is_empty() {
+		return Err(DecodeError::ExcessBytes(data.len()));
 	}
+	Ok(ext)
 }
@@ -0,0 +1,83 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A cache of metadata which is fetched over RPC the first time that each runtime version is needed.
+
+use crate::{Decoder, Error, SpecVersion};
+use desub_current::Metadata as DesubMetadata;
+use std::{
+	collections::HashMap,
+	future::Future,
+	pin::Pin,
+	sync::{Arc, Mutex},
+};
+use tokio::sync::{OnceCell, RwLock, RwLockReadGuard};
+
+/// A boxed future, as returned from [`MetadataRpc`] methods.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Something which can fetch the SCALE encoded metadata of a chain, such as a client connected to
+/// a node's RPC endpoint (where this would be a call to `state_getMetadata`).
+pub trait MetadataRpc: Send + Sync {
+	/// Fetch the metadata of the runtime in use at the block with the given hash. The bytes should
+	/// be the SCALE encoded `RuntimeMetadataPrefixed`, as [`Decoder::register_version`] expects.
+	fn metadata_at(&self, at_block: [u8; 32]) -> BoxFuture<'_, Result<Vec<u8>, Error>>;
+}
+
+/// Wraps a [`Decoder`], registering the metadata for each spec version lazily as it's asked for by
+/// fetching it over RPC. Concurrent requests for a version that is not yet registered share a single
+/// fetch, so metadata is only fetched once for each version.
+pub struct AsyncMetadataCache<R> {
+	rpc: R,
+	decoder: RwLock<Decoder>,
+	fetches: Mutex<HashMap<SpecVersion, Arc<OnceCell<()>>>>,
+}
+
+impl<R: MetadataRpc> AsyncMetadataCache<R> {
+	/// Create a new cache. Versions already registered with the decoder are never fetched.
+	pub fn new(decoder: Decoder, rpc: R) -> Self {
+		Self { rpc, decoder: RwLock::new(decoder), fetches: Mutex::new(HashMap::new()) }
+	}
+
+	/// Get the V14+ metadata for a spec version. If the version is not registered yet, its metadata is fetched
+	/// from the given block, which should be one that the runtime with that spec version was in use at.
+	///
+	/// Legacy (pre-V14) metadata is registered too, but can only be used through [`AsyncMetadataCache::decoder`];
+	/// asking for it here returns [`Error::LegacyMetadata`] once it has been registered.
+	pub async fn get(&self, spec: SpecVersion, at_block: [u8; 32]) -> Result<Arc<DesubMetadata>, Error> {
+		if !self.decoder.read().await.has_version(spec) {
+			let fetch = self.fetches.lock().expect("lock is not poisoned").entry(spec).or_default().clone();
+			// If fetching fails, the cell is left empty, so the next call for this version tries again.
+			fetch
+				.get_or_try_init(|| async {
+					let metadata = self.rpc.metadata_at(at_block).await?;
+					self.decoder.write().await.register_version(spec, &metadata)
+				})
+				.await?;
+		}
+		self.decoder.read().await.metadata(spec).ok_or(Error::LegacyMetadata(spec))
+	}
+
+	/// The RPC client that metadata is fetched with.
+	pub fn rpc(&self) -> &R {
+		&self.rpc
+	}
+
+	/// Access the wrapped decoder, to decode things using any of the versions that have been registered so far.
+	pub async fn decoder(&self) -> RwLockReadGuard<'_, Decoder> {
+		self.decoder.read().await
+	}
+}
@@ -0,0 +1,210 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A common form for the values produced by the legacy and the V14+ decoders, so that the output of the two can be
+//! compared against each other.
+
+use desub_current::{scale_info::TypeDef, scale_value::Primitive, Metadata as DesubMetadata, TypeId, Value, ValueDef};
+use desub_legacy::{Data, SubstrateType};
+use parity_scale_codec::Encode;
+use sp_runtime::MultiAddress;
+
+/// A decoded value with the differences between the legacy [`SubstrateType`] and the V14+ [`Value`] representations
+/// smoothed over. A [`SubstrateType`] can be converted into this with [`From`], and a [`Value`] with
+/// [`NormalizedValue::from_value`], given the metadata it was decoded with. Two values which hold the same data
+/// compare equal once converted, even if one decoder produced, say, a `U32` where the other produced a `u128`. In
+/// particular:
+///
+/// - Integers of every width (and whether or not they were compact encoded) are represented the same way. Only
+///   negative numbers are [`NormalizedValue::Int`]s.
+/// - Structs, tuples, arrays and sequences are all [`NormalizedValue::Sequence`]s of their values, with field names
+///   dropped. A struct with a single field is replaced by the value of that field, so a newtype (like `AccountId32`,
+///   which wraps a `[u8; 32]`, or a democracy `Vote`, which wraps the byte that it's packed into) matches its
+///   contents. Sequences holding a single value are left as they are.
+/// - Enums, options and results are [`NormalizedValue::Variant`]s holding the value of their field if they have one,
+///   a sequence of the values of their fields if they have several, and an empty sequence for unit variants.
+/// - Hashes, addresses and the other special cased types of the legacy decoder are broken down into the shape that
+///   the V14+ decoder gives them.
+///
+/// Calls nested inside other calls don't match: the legacy decoder keeps their arguments but not the names of their
+/// pallet and call, so they are normalized to a sequence of their arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NormalizedValue {
+	Bool(bool),
+	UInt(u128),
+	Int(i128),
+	Str(String),
+	Sequence(Vec<NormalizedValue>),
+	Variant(String, Box<NormalizedValue>),
+}
+
+impl NormalizedValue {
+	/// Normalize a value decoded by the V14+ decoder, given the metadata that it was decoded with. The metadata is
+	/// used to tell newtypes, which are replaced by the value that they wrap, from sequences of a single value.
+	pub fn from_value(value: &Value<TypeId>, metadata: &DesubMetadata) -> NormalizedValue {
+		match &value.value {
+			ValueDef::Composite(composite) if is_newtype(metadata, value.context) => {
+				NormalizedValue::fields(composite.values().map(|v| NormalizedValue::from_value(v, metadata)))
+			}
+			ValueDef::Composite(composite) => {
+				NormalizedValue::sequence(composite.values().map(|v| NormalizedValue::from_value(v, metadata)))
+			}
+			ValueDef::Variant(variant) => NormalizedValue::variant(
+				&variant.name,
+				NormalizedValue::fields(variant.values.values().map(|v| NormalizedValue::from_value(v, metadata))),
+			),
+			ValueDef::BitSequence(bits) => NormalizedValue::sequence(bits.iter().map(NormalizedValue::Bool)),
+			ValueDef::Primitive(primitive) => match primitive {
+				Primitive::Bool(b) => NormalizedValue::Bool(*b),
+				Primitive::Char(c) => NormalizedValue::Str(c.to_string()),
+				Primitive::String(s) => NormalizedValue::Str(s.clone()),
+				Primitive::U128(n) => NormalizedValue::UInt(*n),
+				Primitive::I128(n) => NormalizedValue::int(*n),
+				Primitive::U256(bytes) | Primitive::I256(bytes) => NormalizedValue::bytes(bytes),
+			},
+		}
+	}
+
+	fn sequence(values: impl IntoIterator<Item = NormalizedValue>) -> NormalizedValue {
+		NormalizedValue::Sequence(values.into_iter().collect())
+	}
+
+	/// The fields of a struct or of an enum variant: the value itself if there is just one, or a sequence of them.
+	fn fields(values: impl IntoIterator<Item = NormalizedValue>) -> NormalizedValue {
+		let mut values: Vec<_> = values.into_iter().collect();
+		if values.len() == 1 {
+			values.remove(0)
+		} else {
+			NormalizedValue::Sequence(values)
+		}
+	}
+
+	fn bytes(bytes: &[u8]) -> NormalizedValue {
+		NormalizedValue::sequence(bytes.iter().map(|b| NormalizedValue::UInt(*b as u128)))
+	}
+
+	fn variant(name: impl Into<String>, value: NormalizedValue) -> NormalizedValue {
+		NormalizedValue::Variant(name.into(), Box::new(value))
+	}
+
+	fn unit() -> NormalizedValue {
+		NormalizedValue::Sequence(Vec::new())
+	}
+
+	fn int(n: i128) -> NormalizedValue {
+		if n < 0 {
+			NormalizedValue::Int(n)
+		} else {
+			NormalizedValue::UInt(n as u128)
+		}
+	}
+}
+
+/// Is the given type a struct with a single field? The collections which the V14+ decoder presents as the sequence
+/// or map that they wrap are composites with a single field too, but aren't newtypes.
+fn is_newtype(metadata: &DesubMetadata, id: TypeId) -> bool {
+	let Some(ty) = metadata.resolve(id) else { return false };
+	let is_collection = matches!(
+		ty.path.segments.last().map(String::as_str),
+		Some("BTreeMap" | "BTreeSet" | "BoundedBTreeMap" | "BoundedBTreeSet" | "BoundedVec" | "WeakBoundedVec")
+	);
+	matches!(&ty.type_def, TypeDef::Composite(c) if c.fields.len() == 1) && !is_collection
+}
+
+impl From<&SubstrateType> for NormalizedValue {
+	fn from(value: &SubstrateType) -> Self {
+		match value {
+			SubstrateType::H512(hash) => NormalizedValue::bytes(hash.as_bytes()),
+			SubstrateType::H256(hash) => NormalizedValue::bytes(hash.as_bytes()),
+			SubstrateType::BitVec(bits) => NormalizedValue::sequence(bits.iter().map(|b| NormalizedValue::Bool(*b))),
+			SubstrateType::Call(args) => NormalizedValue::sequence(args.iter().map(|(_, arg)| arg.into())),
+			// V14+ metadata describes an era as a variant per mortal period, named after the first encoded byte.
+			SubstrateType::Era(era) => match era.encode().as_slice() {
+				[first, second] => {
+					NormalizedValue::variant(format!("Mortal{first}"), NormalizedValue::UInt(*second as u128))
+				}
+				_ => NormalizedValue::variant("Immortal", NormalizedValue::unit()),
+			},
+			SubstrateType::GenericVote(vote) => {
+				NormalizedValue::UInt(((vote.aye as u8) << 7 | vote.conviction as u8) as u128)
+			}
+			SubstrateType::Address(address) => match address {
+				MultiAddress::Id(id) => NormalizedValue::variant("Id", NormalizedValue::bytes(id.as_ref())),
+				MultiAddress::Index(index) => NormalizedValue::variant("Index", NormalizedValue::UInt(*index as u128)),
+				MultiAddress::Raw(bytes) => NormalizedValue::variant("Raw", NormalizedValue::bytes(bytes)),
+				MultiAddress::Address32(bytes) => NormalizedValue::variant("Address32", NormalizedValue::bytes(bytes)),
+				MultiAddress::Address20(bytes) => NormalizedValue::variant("Address20", NormalizedValue::bytes(bytes)),
+			},
+			SubstrateType::Data(data) => normalize_data(data),
+			SubstrateType::IdentityField(fields) => NormalizedValue::UInt(*fields as u128),
+			SubstrateType::SignedExtra(s) => NormalizedValue::Str(s.clone()),
+			SubstrateType::Unit(name) => NormalizedValue::variant(name, NormalizedValue::unit()),
+			SubstrateType::Composite(values) => NormalizedValue::sequence(values.iter().map(NormalizedValue::from)),
+			SubstrateType::Map(entries) => NormalizedValue::Sequence(
+				entries.iter().map(|(k, v)| NormalizedValue::Sequence(vec![k.into(), v.into()])).collect(),
+			),
+			SubstrateType::Set(field) => NormalizedValue::variant(&field.name, NormalizedValue::unit()),
+			SubstrateType::Enum(field) => NormalizedValue::variant(
+				&field.name,
+				field.value.as_deref().map(NormalizedValue::from).unwrap_or_else(NormalizedValue::unit),
+			),
+			SubstrateType::Struct(fields) => NormalizedValue::fields(fields.iter().map(|f| (&f.ty).into())),
+			SubstrateType::Option(option) => match &**option {
+				Some(value) => NormalizedValue::variant("Some", value.into()),
+				None => NormalizedValue::variant("None", NormalizedValue::unit()),
+			},
+			SubstrateType::Result(result) => match &**result {
+				Ok(value) => NormalizedValue::variant("Ok", value.into()),
+				Err(value) => NormalizedValue::variant("Err", value.into()),
+			},
+			SubstrateType::U8(n) => NormalizedValue::UInt(*n as u128),
+			SubstrateType::U16(n) => NormalizedValue::UInt(*n as u128),
+			SubstrateType::U32(n) => NormalizedValue::UInt(*n as u128),
+			SubstrateType::U64(n) => NormalizedValue::UInt(*n as u128),
+			SubstrateType::U128(n) => NormalizedValue::UInt(*n),
+			SubstrateType::USize(n) => NormalizedValue::UInt(*n as u128),
+			SubstrateType::I8(n) => NormalizedValue::int(*n as i128),
+			SubstrateType::I16(n) => NormalizedValue::int(*n as i128),
+			SubstrateType::I32(n) => NormalizedValue::int(*n as i128),
+			SubstrateType::I64(n) => NormalizedValue::int(*n as i128),
+			SubstrateType::I128(n) => NormalizedValue::int(*n),
+			SubstrateType::ISize(n) => NormalizedValue::int(*n as i128),
+			// Floats can't be SCALE encoded, so will never match anything from the V14+ decoder.
+			SubstrateType::F32(n) => NormalizedValue::Str(n.to_string()),
+			SubstrateType::F64(n) => NormalizedValue::Str(n.to_string()),
+			SubstrateType::Bool(b) => NormalizedValue::Bool(*b),
+			SubstrateType::Null => NormalizedValue::unit(),
+		}
+	}
+}
+
+/// V14+ metadata describes identity data as a variant per length of raw data, each holding an array of that length.
+fn normalize_data(data: &Data) -> NormalizedValue {
+	match data {
+		Data::None => NormalizedValue::variant("None", NormalizedValue::unit()),
+		Data::Raw(bytes) => NormalizedValue::variant(format!("Raw{}", bytes.len()), NormalizedValue::bytes(bytes)),
+		Data::BlakeTwo256(hash) => NormalizedValue::variant("BlakeTwo256", NormalizedValue::bytes(hash)),
+		Data::Sha256(hash) => NormalizedValue::variant("Sha256", NormalizedValue::bytes(hash)),
+		Data::Keccak256(hash) => NormalizedValue::variant("Keccak256", NormalizedValue::bytes(hash)),
+		Data::ShaThree256(hash) => NormalizedValue::variant("ShaThree256", NormalizedValue::bytes(hash)),
+	}
+}
+
+/// Do a value decoded by the legacy decoder and one decoded by the V14+ decoder (with the given metadata) hold the
+/// same data? See [`NormalizedValue`] for the differences in representation that are allowed for.
+pub fn values_match(legacy: &SubstrateType, current: &Value<TypeId>, metadata: &DesubMetadata) -> bool {
+	NormalizedValue::from(legacy) == NormalizedValue::from_value(current, metadata)
+}
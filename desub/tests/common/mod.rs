@@ -0,0 +1,45 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Helpers shared by the integration tests. Not every test uses all of them.
+#![allow(dead_code)]
+
+use desub_legacy::{RustTypeMarker, TypeDetective};
+
+#[path = "../../../desub-current/tests/common/v15.rs"]
+pub mod v15;
+
+pub static V14_METADATA_POLKADOT_SCALE: &[u8] =
+	include_bytes!("../../../desub-current/tests/data/v14_metadata_polkadot.scale");
+pub static V12_METADATA_KUSAMA: &[u8] = include_bytes!("../../../integration-tests/data/metadata_v12_block4643974.bin");
+
+/// A [`TypeDetective`] which knows of no legacy types at all, for tests which don't need any.
+#[derive(Debug, Clone)]
+pub struct NoTypes;
+
+impl TypeDetective for NoTypes {
+	fn get(&self, _chain: &str, _spec: u32, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+		None
+	}
+
+	fn try_fallback(&self, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+		None
+	}
+
+	fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, _ty: &str) -> Option<&RustTypeMarker> {
+		None
+	}
+}
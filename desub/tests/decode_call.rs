@@ -0,0 +1,55 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{NoTypes, V12_METADATA_KUSAMA, V14_METADATA_POLKADOT_SCALE};
+use desub::{Chain, DecodedCall, Decoder, Error};
+
+/// System.remark(b"hi"), followed by a byte that isn't part of the call.
+static REMARK_CALL: &[u8] = &[0, 1, 8, b'h', b'i', 0xff];
+
+#[test]
+fn decode_call_from_v14_chain() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	let mut data = REMARK_CALL;
+	let call = decoder.decode_call(9110, &mut data).unwrap();
+	assert!(matches!(call, DecodedCall::Current(_)));
+	assert_eq!(call.pallet_name(), "System");
+	assert_eq!(call.call_name(), "remark");
+	assert_eq!(data, &[0xff]);
+}
+
+#[test]
+fn decode_call_from_legacy_chain() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let mut data = REMARK_CALL;
+	let call = decoder.decode_call(2027, &mut data).unwrap();
+	assert!(matches!(call, DecodedCall::Legacy(_)));
+	assert_eq!(call.pallet_name(), "System");
+	assert_eq!(call.call_name(), "remark");
+	assert_eq!(data, &[0xff]);
+}
+
+#[test]
+fn decode_call_for_unknown_version() {
+	let decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	assert!(matches!(decoder.decode_call(1, &mut { REMARK_CALL }), Err(Error::SpecVersionNotFound(1))));
+}
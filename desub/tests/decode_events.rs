@@ -0,0 +1,137 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use desub::{Chain, Decoder};
+use desub_legacy::{CommonTypes, EnumField, RustTypeMarker, StructField, TypeDetective};
+use parity_scale_codec::{Compact, Encode};
+use std::collections::HashMap;
+
+static V14_METADATA_POLKADOT_SCALE: &[u8] =
+	include_bytes!("../../desub-current/tests/data/v14_metadata_polkadot.scale");
+static V12_METADATA_KUSAMA: &[u8] = include_bytes!("../../integration-tests/data/metadata_v12_block4643974.bin");
+
+/// Just enough legacy type definitions to decode a `System.ExtrinsicSuccess` event.
+#[derive(Debug, Clone)]
+struct EventTypes(HashMap<&'static str, RustTypeMarker>);
+
+impl EventTypes {
+	fn new() -> Self {
+		let unit_enum =
+			|names: &[&str]| RustTypeMarker::Enum(names.iter().map(|n| EnumField::new(n.to_string(), None)).collect());
+		let mut types = HashMap::new();
+		types.insert(
+			"Phase",
+			RustTypeMarker::Enum(vec![
+				EnumField::new("ApplyExtrinsic".into(), Some(RustTypeMarker::U32)),
+				EnumField::new("Finalization".into(), None),
+				EnumField::new("Initialization".into(), None),
+			]),
+		);
+		types.insert(
+			"DispatchInfo",
+			RustTypeMarker::Struct(vec![
+				StructField::new("weight", RustTypeMarker::U64),
+				StructField::new("class", unit_enum(&["Normal", "Operational", "Mandatory"])),
+				StructField::new("paysFee", unit_enum(&["Yes", "No"])),
+			]),
+		);
+		types.insert("Hash", RustTypeMarker::TypePointer("H256".into()));
+		types.insert("AccountId", RustTypeMarker::Array { size: 32, ty: Box::new(RustTypeMarker::U8) });
+		types.insert("Balance", RustTypeMarker::U128);
+		types.insert("Vec<u8>", RustTypeMarker::Std(CommonTypes::Vec(Box::new(RustTypeMarker::U8))));
+		Self(types)
+	}
+}
+
+impl TypeDetective for EventTypes {
+	fn get(&self, _chain: &str, _spec: u32, _module: &str, ty: &str) -> Option<&RustTypeMarker> {
+		self.0.get(ty)
+	}
+
+	fn try_fallback(&self, _module: &str, _ty: &str) -> Option<&RustTypeMarker> {
+		None
+	}
+
+	fn get_extrinsic_ty(&self, _chain: &str, _spec: u32, _ty: &str) -> Option<&RustTypeMarker> {
+		None
+	}
+}
+
+/// `Vec<EventRecord>` containing a single `System.ExtrinsicSuccess` event emitted while
+/// applying the first extrinsic, with no topics.
+fn extrinsic_success_events() -> Vec<u8> {
+	let mut bytes = Compact(1u32).encode();
+	// Phase::ApplyExtrinsic(0)
+	bytes.push(0);
+	bytes.extend(0u32.encode());
+	// System.ExtrinsicSuccess(DispatchInfo { weight: 1000, class: Normal, pays_fee: Yes })
+	bytes.extend([0u8, 0u8]);
+	bytes.extend((1000u64, 0u8, 0u8).encode());
+	// No topics
+	bytes.extend(Vec::<[u8; 32]>::new().encode());
+	bytes
+}
+
+#[test]
+fn decode_events_from_v14_chain() {
+	let mut decoder = Decoder::with_custom_types(EventTypes::new(), Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	let events = decoder.decode_events(9110, &extrinsic_success_events()).unwrap();
+
+	assert_eq!(events.len(), 1);
+	assert!(matches!(events[0], desub::DecodedEvent::Current(_)));
+	assert_eq!(events[0].pallet_name(), "System");
+	assert_eq!(events[0].event_name(), "ExtrinsicSuccess");
+}
+
+#[test]
+fn decode_events_from_legacy_chain() {
+	let mut decoder = Decoder::with_custom_types(EventTypes::new(), Chain::Kusama);
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let events = decoder.decode_events(2027, &extrinsic_success_events()).unwrap();
+
+	assert_eq!(events.len(), 1);
+	assert!(matches!(events[0], desub::DecodedEvent::Legacy(_)));
+	assert_eq!(events[0].pallet_name(), "System");
+	assert_eq!(events[0].event_name(), "ExtrinsicSuccess");
+}
+
+#[test]
+fn trailing_event_bytes_are_an_error() {
+	let mut decoder = Decoder::with_custom_types(EventTypes::new(), Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let mut events = extrinsic_success_events();
+	events.push(0);
+
+	assert!(matches!(
+		decoder.decode_events(9110, &events),
+		Err(desub::Error::V14Events(desub_current::decoder::DecodeError::ExcessBytes(1)))
+	));
+	assert!(matches!(
+		decoder.decode_events(2027, &events),
+		Err(desub::Error::Legacy(desub_legacy::Error::ExcessBytes(1)))
+	));
+}
+
+#[test]
+fn decode_events_for_unknown_version() {
+	let decoder = Decoder::with_custom_types(EventTypes::new(), Chain::Polkadot);
+	assert!(matches!(decoder.decode_events(1, &extrinsic_success_events()), Err(desub::Error::SpecVersionNotFound(1))));
+}
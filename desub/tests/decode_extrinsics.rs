@@ -0,0 +1,278 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{NoTypes, V12_METADATA_KUSAMA, V14_METADATA_POLKADOT_SCALE};
+use desub::{values_match, Chain, DecodedExtrinsic, Decoder, Error, NormalizedValue};
+use desub_current::{decoder::DecodeError, ValueExt};
+use desub_json_resolver::TypeResolver;
+use desub_legacy::Error as LegacyError;
+use parity_scale_codec::{Compact, Encode};
+
+/// Unsigned System.remark extrinsics, each with its own length prefix, as they'd
+/// appear in the list of extrinsics of a block.
+fn remark_extrinsics() -> Vec<Vec<u8>> {
+	vec![vec![4u8, 0, 1, 8, b'h', b'i'].encode(), vec![4u8, 0, 1, 12, b'b', b'y', b'e'].encode()]
+}
+
+#[test]
+fn decode_extrinsic_list_from_v14_chain() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	let exts = decoder.decode_extrinsics_from_list(9110, &remark_extrinsics()).unwrap();
+	assert_eq!(exts.len(), 2);
+	for ext in &exts {
+		assert!(matches!(ext, DecodedExtrinsic::Current(_)));
+		assert_eq!(ext.pallet_name(), "System");
+		assert_eq!(ext.call_name(), "remark");
+	}
+}
+
+#[test]
+fn decode_extrinsic_list_from_legacy_chain() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let mut exts = remark_extrinsics();
+	let decoded = decoder.decode_extrinsics_from_list(2027, &exts).unwrap();
+	assert_eq!(decoded.len(), 2);
+	for ext in &decoded {
+		assert!(matches!(ext, DecodedExtrinsic::Legacy(_)));
+		assert_eq!(ext.pallet_name(), "System");
+		assert_eq!(ext.call_name(), "remark");
+	}
+
+	// Every byte of each extrinsic must be used:
+	exts[1].push(0xff);
+	assert!(matches!(
+		decoder.decode_extrinsics_from_list(2027, &exts),
+		Err(Error::Legacy(LegacyError::ExcessBytes(1)))
+	));
+}
+
+#[test]
+fn legacy_and_current_decodes_can_be_compared() {
+	let mut decoder = Decoder::with_custom_types(TypeResolver::default(), Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+	let normalized_args = |version, ext: &DecodedExtrinsic| decoder.normalized_args(version, ext).unwrap();
+
+	// Unsigned Timestamp.set(1_600_000_000_000); the Timestamp pallet has a different index on each chain. The
+	// legacy decoder gives back the compact encoded moment as a U64, and the current one as a u128.
+	let timestamp = |pallet: u8| [vec![4u8, pallet, 0], Compact(1_600_000_000_000u64).encode()].concat().encode();
+	let current = decoder.decode_extrinsic(9110, &timestamp(3)).unwrap();
+	let legacy = decoder.decode_extrinsic(2027, &timestamp(2)).unwrap();
+	assert!(matches!(current, DecodedExtrinsic::Current(_)));
+	assert!(matches!(legacy, DecodedExtrinsic::Legacy(_)));
+	assert_eq!((legacy.pallet_name(), legacy.call_name()), (current.pallet_name(), current.call_name()));
+	assert_eq!(normalized_args(2027, &legacy), vec![NormalizedValue::UInt(1_600_000_000_000)]);
+	assert_eq!(normalized_args(2027, &legacy), normalized_args(9110, &current));
+
+	// Democracy.vote(5, Standard { vote: aye with Locked1x conviction, balance: 1000 }). The legacy decoder gives
+	// back the vote as a `GenericVote`, and the current one as the `Vote` newtype wrapping the byte that it's packed
+	// into, which both normalize to that byte.
+	let vote = |pallet: u8| {
+		[vec![4u8, pallet, 2], Compact(5u32).encode(), vec![0, 0b1000_0001], 1000u128.encode()].concat().encode()
+	};
+	let current = decoder.decode_extrinsic(9110, &vote(14)).unwrap();
+	let legacy = decoder.decode_extrinsic(2027, &vote(13)).unwrap();
+	assert_eq!((legacy.pallet_name(), legacy.call_name()), (current.pallet_name(), current.call_name()));
+	let standard = NormalizedValue::Sequence(vec![NormalizedValue::UInt(0b1000_0001), NormalizedValue::UInt(1000)]);
+	let expected = vec![NormalizedValue::UInt(5), NormalizedValue::Variant("Standard".into(), Box::new(standard))];
+	assert_eq!(normalized_args(2027, &legacy), expected);
+	assert_eq!(normalized_args(9110, &current), expected);
+
+	let remark = &remark_extrinsics()[0];
+	let current = decoder.decode_extrinsic(9110, remark).unwrap();
+	let legacy = decoder.decode_extrinsic(2027, remark).unwrap();
+	assert_eq!(normalized_args(2027, &legacy), normalized_args(9110, &current));
+	// Normalizing a V14+ extrinsic needs the metadata that it was decoded with:
+	assert!(matches!(decoder.normalized_args(2027, &current), Err(Error::SpecVersionNotFound(2027))));
+
+	let (DecodedExtrinsic::Legacy(legacy), DecodedExtrinsic::Current(current)) = (&legacy, &current) else {
+		panic!("expected one extrinsic from each decoder");
+	};
+	let metadata = decoder.metadata(9110).unwrap();
+	assert!(values_match(&legacy.args()[0].arg, &current.call_data.arguments[0], &metadata));
+	let hello = desub_current::Value::from_bytes(b"hello").map_context(|_| current.call_data.arguments[0].context);
+	assert!(!values_match(&legacy.args()[0].arg, &hello, &metadata));
+
+	// Only newtypes are replaced by the value they hold, so a single byte remark doesn't match the byte itself:
+	let remark = [vec![4u8, 0, 1], vec![7u8].encode()].concat().encode();
+	let DecodedExtrinsic::Current(current) = decoder.decode_extrinsic(9110, &remark).unwrap() else {
+		panic!("expected a V14 extrinsic")
+	};
+	let byte = NormalizedValue::UInt(7);
+	assert_eq!(
+		NormalizedValue::from_value(&current.call_data.arguments[0], &metadata),
+		NormalizedValue::Sequence(vec![byte])
+	);
+}
+
+#[test]
+fn decode_rpc_block() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	// The response to a chain_getBlock call, holding the Timestamp.set inherent followed by a signed
+	// Balances.transfer and Balances.transfer_all, each from Alice to Bob on a Polkadot 9110 runtime.
+	let response: serde_json::Value = serde_json::from_str(include_str!("data/chain_get_block_polkadot.json")).unwrap();
+
+	for block in [&response, &response["result"]] {
+		let exts = decoder.decode_rpc_block(9110, block).unwrap();
+		let names: Vec<_> = exts.iter().map(|ext| (ext.pallet_name(), ext.call_name())).collect();
+		assert_eq!(names, [("Timestamp", "set"), ("Balances", "transfer"), ("Balances", "transfer_all")]);
+		let [DecodedExtrinsic::Current(timestamp), DecodedExtrinsic::Current(transfer), _] = &exts[..] else {
+			panic!("expected three V14 extrinsics")
+		};
+		assert_eq!(timestamp.call_data.arguments[0].as_u128(), Some(1_634_036_400_000));
+		assert!(!timestamp.is_signed());
+		assert!(transfer.is_signed());
+		assert_eq!(transfer.call_data.arguments[1].as_u128(), Some(12345));
+	}
+
+	let mut bad = response.clone();
+	bad["result"]["block"]["extrinsics"][1] = "0xzz".into();
+	assert!(matches!(decoder.decode_rpc_block(9110, &bad), Err(Error::InvalidRpcBlock(_))));
+	assert!(matches!(
+		decoder.decode_rpc_block(9110, &serde_json::json!({ "block": {} })),
+		Err(Error::InvalidRpcBlock(_))
+	));
+}
+
+#[test]
+fn extrinsic_longer_than_its_prefix_is_an_error() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	let mut exts = remark_extrinsics();
+	exts[1].push(0xff);
+	match decoder.decode_extrinsics_from_list(9110, &exts) {
+		Err(Error::V14 { ext, .. }) => assert_eq!(ext.len(), 1),
+		other => panic!("expected a V14 error, got {other:?}"),
+	}
+}
+
+#[test]
+fn trailing_bytes_are_only_allowed_when_asked_for() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	// A block body with a single remark in it, and a junk byte on the end:
+	let mut data = vec![vec![4u8, 0, 1, 8, b'h', b'i']].encode();
+	data.push(4);
+
+	for version in [9110, 2027] {
+		let (ext, trailing) = decoder.decode_extrinsics_allow_trailing(version, &data, 1).unwrap();
+		assert_eq!(ext.as_array().map(Vec::len), Some(1));
+		assert_eq!(trailing, &[4]);
+	}
+
+	assert!(matches!(
+		decoder.decode_extrinsics(9110, &data),
+		Err(Error::V14 { source: DecodeError::ExcessBytes(1), ext }) if ext.len() == 1
+	));
+	assert!(matches!(decoder.decode_extrinsics(2027, &data), Err(Error::Legacy(LegacyError::ExcessBytes(1)))));
+}
+
+#[test]
+fn decode_single_extrinsic_from_either_era() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let ext = &remark_extrinsics()[0];
+	let current = decoder.decode_extrinsic(9110, ext).unwrap();
+	assert!(matches!(current, DecodedExtrinsic::Current(_)));
+	let legacy = decoder.decode_extrinsic(2027, ext).unwrap();
+	assert!(matches!(legacy, DecodedExtrinsic::Legacy(_)));
+	for ext in [current, legacy] {
+		assert_eq!(ext.pallet_name(), "System");
+		assert_eq!(ext.call_name(), "remark");
+	}
+
+	let mut longer = ext.clone();
+	longer.push(0xff);
+	assert!(matches!(
+		decoder.decode_extrinsic(9110, &longer),
+		Err(Error::V14 { source: DecodeError::ExcessBytes(1), .. })
+	));
+	assert!(matches!(decoder.decode_extrinsic(2027, &longer), Err(Error::Legacy(LegacyError::ExcessBytes(1)))));
+	assert!(matches!(decoder.decode_extrinsic(1, ext), Err(Error::SpecVersionNotFound(1))));
+}
+
+#[test]
+fn decode_a_block_from_either_era() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let exts = remark_extrinsics();
+	let mut data = [Compact(exts.len() as u32).encode(), exts.concat()].concat();
+	for version in [9110, 2027] {
+		let exts = decoder.decode_block(version, &data).unwrap();
+		assert_eq!(exts.len(), remark_extrinsics().len());
+		assert!(exts.iter().all(|ext| ext.call_name() == "remark"));
+	}
+
+	// Bytes left over after the extrinsics are an error:
+	data.push(4);
+	assert!(matches!(
+		decoder.decode_block(9110, &data),
+		Err(Error::V14 { source: DecodeError::ExcessBytes(1), ext }) if ext.len() == remark_extrinsics().len()
+	));
+	assert!(matches!(decoder.decode_block(2027, &data), Err(Error::Legacy(LegacyError::ExcessBytes(1)))));
+	assert!(matches!(decoder.decode_block(1, &data), Err(Error::SpecVersionNotFound(1))));
+}
+
+#[test]
+fn decode_a_window_of_extrinsics() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	// A block body of remarks with 0..5 bytes in them:
+	let remarks: Vec<Vec<u8>> = (0..5u8).map(|n| [vec![4u8, 0, 1], vec![n; n as usize].encode()].concat()).collect();
+	let data = remarks.encode();
+
+	for version in [9110, 2027] {
+		let window = decoder.decode_extrinsics_window(version, &data, 1, 2).unwrap();
+		assert_eq!(window.len(), 2);
+		assert!(window.iter().all(|ext| ext.call_name() == "remark"));
+		assert_eq!(decoder.decode_extrinsics_window(version, &data, 3, usize::MAX).unwrap().len(), 2);
+		assert!(decoder.decode_extrinsics_window(version, &data, 5, 1).unwrap().is_empty());
+	}
+
+	// The window starts after the skipped extrinsics:
+	let window = decoder.decode_extrinsics_window(9110, &data, 1, 2).unwrap();
+	let metadata = decoder.metadata(9110).unwrap();
+	let remarks: Vec<_> = window
+		.iter()
+		.map(|ext| match ext {
+			DecodedExtrinsic::Current(ext) => ext.call_data.arguments[0].as_bytes(&metadata).unwrap(),
+			DecodedExtrinsic::Legacy(_) => panic!("expected a V14 extrinsic"),
+		})
+		.collect();
+	assert_eq!(remarks, vec![vec![1], vec![2, 2]]);
+
+	assert!(matches!(
+		decoder.decode_extrinsics_window(9110, &data[..data.len() - 1], 0, 1),
+		Err(Error::ExtrinsicBytes(_))
+	));
+}
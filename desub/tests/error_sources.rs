@@ -0,0 +1,56 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{NoTypes, V14_METADATA_POLKADOT_SCALE};
+use desub::{Chain, Decoder, Error};
+use desub_current::decoder::DecodeError;
+use parity_scale_codec::{Compact, Encode};
+use std::error::Error as _;
+
+fn decoder() -> Decoder {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder
+}
+
+#[test]
+fn v14_extrinsic_errors_expose_their_source() {
+	// An unsigned extrinsic calling a System call that doesn't exist.
+	let mut data = Compact(1u32).encode();
+	data.extend(vec![4u8, 0, 200].encode());
+
+	let err = decoder().decode_extrinsics(9110, &data).unwrap_err();
+	assert!(matches!(err, Error::V14 { .. }), "{err:?}");
+	let source = err.source().and_then(|e| e.downcast_ref::<DecodeError>());
+	assert!(matches!(source, Some(DecodeError::CallIndexOutOfRange(0, 200))), "{source:?}");
+}
+
+#[test]
+fn v14_event_errors_expose_their_source() {
+	let err = decoder().decode_events(9110, &[0, 0xff]).unwrap_err();
+	let source = err.source().and_then(|e| e.downcast_ref::<DecodeError>());
+	assert!(matches!(source, Some(DecodeError::ExcessBytes(1))), "{source:?}");
+}
+
+#[test]
+fn registration_errors_expose_their_source() {
+	let err = decoder().register_versions([(1, &[1u8, 2, 3][..])]).unwrap_err();
+	// The failed registration's own error is boxed, since it's another `Error`.
+	let source = err.source().and_then(|e| e.downcast_ref::<Box<Error>>());
+	assert!(matches!(source.map(|e| &**e), Some(Error::Codec(_))), "{source:?}");
+}
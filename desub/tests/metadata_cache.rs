@@ -0,0 +1,97 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{NoTypes, V12_METADATA_KUSAMA, V14_METADATA_POLKADOT_SCALE};
+use desub::{AsyncMetadataCache, BoxFuture, Chain, Decoder, Error, MetadataRpc};
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+/// Hands back the same metadata for every block, counting how many times it's asked for it.
+/// The first `failures` fetches fail.
+struct MockRpc {
+	metadata: &'static [u8],
+	fetches: AtomicUsize,
+	failures: usize,
+}
+
+impl MockRpc {
+	fn new(metadata: &'static [u8]) -> Self {
+		MockRpc { metadata, fetches: AtomicUsize::new(0), failures: 0 }
+	}
+}
+
+impl MetadataRpc for MockRpc {
+	fn metadata_at(&self, _at_block: [u8; 32]) -> BoxFuture<'_, Result<Vec<u8>, Error>> {
+		Box::pin(async move {
+			let fetch = self.fetches.fetch_add(1, Ordering::SeqCst);
+			// Give other requests for the same version a chance to pile up while this one is in flight:
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			if fetch < self.failures {
+				return Err(Error::Rpc("connection reset".into()));
+			}
+			Ok(self.metadata.to_vec())
+		})
+	}
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_gets_fetch_metadata_once() {
+	let decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	let cache = Arc::new(AsyncMetadataCache::new(decoder, MockRpc::new(V14_METADATA_POLKADOT_SCALE)));
+
+	let gets: Vec<_> = (0..8)
+		.map(|_| {
+			let cache = cache.clone();
+			tokio::spawn(async move { cache.get(9110, [0; 32]).await })
+		})
+		.collect();
+	for get in gets {
+		let metadata = get.await.unwrap().unwrap();
+		assert!(Arc::ptr_eq(&metadata, &cache.get(9110, [0; 32]).await.unwrap()));
+	}
+
+	assert_eq!(cache.rpc().fetches.load(Ordering::SeqCst), 1);
+	assert!(cache.decoder().await.has_version(9110));
+}
+
+#[tokio::test]
+async fn failed_fetches_are_retried() {
+	let decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	let rpc = MockRpc { failures: 1, ..MockRpc::new(V14_METADATA_POLKADOT_SCALE) };
+	let cache = AsyncMetadataCache::new(decoder, rpc);
+
+	assert!(matches!(cache.get(9110, [0; 32]).await, Err(Error::Rpc(_))));
+	assert!(cache.get(9110, [0; 32]).await.is_ok());
+	assert_eq!(cache.rpc().fetches.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn legacy_metadata_is_registered_but_not_returned() {
+	let decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	let cache = AsyncMetadataCache::new(decoder, MockRpc::new(V12_METADATA_KUSAMA));
+
+	assert!(matches!(cache.get(2027, [0; 32]).await, Err(Error::LegacyMetadata(2027))));
+	assert!(cache.decoder().await.has_version(2027));
+	assert!(matches!(cache.get(2027, [0; 32]).await, Err(Error::LegacyMetadata(2027))));
+	assert_eq!(cache.rpc().fetches.load(Ordering::SeqCst), 1);
+}
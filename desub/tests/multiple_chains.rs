@@ -0,0 +1,109 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{NoTypes, V12_METADATA_KUSAMA, V14_METADATA_POLKADOT_SCALE};
+use desub::{Chain, Decoder, Error};
+use desub_json_resolver::TypeResolver;
+use desub_legacy::Error as LegacyError;
+use parity_scale_codec::{Compact, Encode};
+
+/// A block body holding a single unsigned System.remark(b"hi") extrinsic.
+fn remark_extrinsics() -> Vec<u8> {
+	let mut data = Compact(1u32).encode();
+	data.extend(vec![4u8, 0, 1, 8, b'h', b'i'].encode());
+	data
+}
+
+#[test]
+fn decode_extrinsics_from_two_chains() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	// The same spec version is registered for both chains, with different metadata.
+	decoder.register_chain_version(Chain::Polkadot, 9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_chain_version(Chain::Kusama, 9110, V12_METADATA_KUSAMA).unwrap();
+
+	let polkadot = decoder.decode_chain_extrinsics(&Chain::Polkadot, 9110, &remark_extrinsics()).unwrap();
+	let kusama = decoder.decode_chain_extrinsics(&Chain::Kusama, 9110, &remark_extrinsics()).unwrap();
+
+	// V14 extrinsics have a `call_data` field, whereas legacy extrinsics have a `call`.
+	assert_eq!(polkadot[0]["call_data"]["pallet_name"], "System");
+	assert_eq!(kusama[0]["call"]["module"], "System");
+
+	// The default chain is the one the decoder was created for.
+	assert_eq!(decoder.decode_extrinsics(9110, &remark_extrinsics()).unwrap(), polkadot);
+	assert_eq!(decoder.chain(), &Chain::Polkadot);
+	assert!(decoder.has_chain_version(&Chain::Kusama, 9110));
+	assert!(!decoder.has_chain_version(&Chain::Kusama, 2027));
+
+	// Removing the version from the default chain leaves the other chain's metadata alone.
+	assert!(decoder.remove_version(9110));
+	assert!(!decoder.has_version(9110));
+	assert!(decoder.has_chain_version(&Chain::Kusama, 9110));
+	assert_eq!(decoder.decode_chain_extrinsics(&Chain::Kusama, 9110, &remark_extrinsics()).unwrap(), kusama);
+
+	// Which can be removed by naming its chain.
+	assert!(decoder.remove_chain_version(&Chain::Kusama, 9110));
+	assert!(!decoder.has_chain_version(&Chain::Kusama, 9110));
+	assert!(!decoder.remove_chain_version(&Chain::Kusama, 9110));
+	assert!(!decoder.remove_chain_version(&Chain::Westend, 9110));
+}
+
+#[test]
+fn decode_extrinsics_from_unknown_chain() {
+	let decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	assert!(!decoder.has_chain_version(&Chain::Westend, 9110));
+	assert!(matches!(
+		decoder.decode_chain_extrinsics(&Chain::Westend, 9110, &remark_extrinsics()),
+		Err(Error::ChainNotFound(name)) if name == "westend"
+	));
+}
+
+#[test]
+fn try_decode_extrinsics_picks_the_chain_that_decodes_cleanly() {
+	// Real type definitions, so that Kusama fails for the bytes it leaves over rather than for a missing type.
+	let mut decoder = Decoder::with_custom_types(TypeResolver::default(), Chain::Polkadot);
+	decoder.register_chain_version(Chain::Polkadot, 9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_chain_version(Chain::Kusama, 9110, V12_METADATA_KUSAMA).unwrap();
+
+	// Timestamp.set(1_600_000_000_000) on Polkadot. On Kusama, pallet 3 is Indices, whose call 0 takes a u32
+	// `AccountIndex` and so leaves 3 of the 7 bytes of the compact encoded moment unused.
+	let mut call = vec![4u8, 3, 0];
+	call.extend(Compact(1_600_000_000_000u64).encode());
+	let mut data = Compact(1u32).encode();
+	data.extend(call.encode());
+
+	for candidates in [[Chain::Kusama, Chain::Polkadot], [Chain::Polkadot, Chain::Kusama]] {
+		let (chain, exts) = decoder.try_decode_extrinsics(9110, &data, &candidates).unwrap();
+		assert_eq!(chain, Chain::Polkadot);
+		assert_eq!(exts.len(), 1);
+		assert_eq!(exts[0].pallet_name(), "Timestamp");
+		assert_eq!(exts[0].call_name(), "set");
+	}
+
+	// When no candidate decodes the data, we get back why each of them failed.
+	match decoder.try_decode_extrinsics(9110, &data, &[Chain::Kusama, Chain::Westend]) {
+		Err(Error::NoCandidateChain(errors)) => {
+			assert_eq!(errors.len(), 2);
+			assert!(
+				matches!(&errors[0], (name, Error::Legacy(LegacyError::ExcessBytes(3))) if name == "kusama"),
+				"{errors:?}"
+			);
+			assert!(matches!(&errors[1], (name, Error::ChainNotFound(_)) if name == "westend"));
+		}
+		other => panic!("expected no candidate chain to decode the data, got {other:?}"),
+	}
+}
@@ -0,0 +1,199 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+mod common;
+
+use common::{v15::v14_as_v15, NoTypes, V12_METADATA_KUSAMA, V14_METADATA_POLKADOT_SCALE};
+use desub::{Chain, Decoder, Error, MetadataKind, VersionInfo};
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use parity_scale_codec::{Compact, Decode, Encode};
+
+#[test]
+fn metadata_at_or_below_finds_closest_registered_version() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	decoder.register_version(10, V12_METADATA_KUSAMA).unwrap();
+	decoder.register_version(20, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(30, V12_METADATA_KUSAMA).unwrap();
+
+	assert_eq!(decoder.metadata_at_or_below(25), Some(20));
+	assert_eq!(decoder.metadata_at_or_below(20), Some(20));
+	assert_eq!(decoder.metadata_at_or_below(19), Some(10));
+	assert_eq!(decoder.metadata_at_or_below(35), Some(30));
+	assert_eq!(decoder.metadata_at_or_below(9), None);
+}
+
+#[test]
+fn versions_can_be_registered_in_bulk() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	decoder
+		.register_versions([
+			(10, V12_METADATA_KUSAMA.to_vec()),
+			(20, V14_METADATA_POLKADOT_SCALE.to_vec()),
+			(30, V12_METADATA_KUSAMA.to_vec()),
+		])
+		.unwrap();
+	assert!([10, 20, 30].into_iter().all(|v| decoder.has_version(v)));
+	assert!(decoder.metadata(20).is_some());
+
+	let res = decoder.register_versions([(40, V12_METADATA_KUSAMA), (50, &[1, 2, 3][..])]);
+	assert!(matches!(res, Err(Error::RegisterVersion { version: 50, .. })), "{res:?}");
+	// The cause is left to `source()` rather than repeated in the message:
+	let err = res.unwrap_err();
+	assert_eq!(err.to_string(), "Failed to register spec version 50");
+	assert!(std::error::Error::source(&err).is_some());
+	assert!(decoder.has_version(40));
+	assert!(!decoder.has_version(50));
+}
+
+#[test]
+fn versions_can_be_removed() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Kusama);
+	decoder.register_version(10, V12_METADATA_KUSAMA).unwrap();
+	decoder.register_version(20, V14_METADATA_POLKADOT_SCALE).unwrap();
+
+	assert!(decoder.remove_version(20));
+	assert!(!decoder.has_version(20));
+	assert!(decoder.metadata(20).is_none());
+	assert!(decoder.has_version(10));
+
+	assert!(decoder.remove_version(10));
+	assert!(!decoder.has_version(10));
+	assert!(!decoder.remove_version(10));
+	assert_eq!(decoder.metadata_at_or_below(30), None);
+}
+
+#[test]
+fn metadata_can_be_shared_across_threads() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	assert!(decoder.metadata(2027).is_none(), "legacy metadata is not handed out");
+	assert!(decoder.metadata(1).is_none());
+
+	let metadata = decoder.metadata(9110).unwrap();
+	let handles: Vec<_> = (0..2)
+		.map(|_| {
+			let metadata = metadata.clone();
+			std::thread::spawn(move || {
+				// Unsigned System.remark(b"hi").
+				let ext = vec![4u8, 0, 1, 8, b'h', b'i'].encode();
+				let ext = desub_current::decoder::decode_extrinsic(&metadata, &mut &*ext).unwrap();
+				ext.call_data.ty.name.clone()
+			})
+		})
+		.collect();
+
+	for handle in handles {
+		assert_eq!(handle.join().unwrap(), "remark");
+	}
+	// The decoder still holds its own reference, alongside ours.
+	assert_eq!(std::sync::Arc::strong_count(&metadata), 2);
+}
+
+#[test]
+fn registered_metadata_can_be_extracted() {
+	let decoder = || {
+		let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+		decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+		decoder.register_version(9111, V14_METADATA_POLKADOT_SCALE).unwrap();
+		decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+		decoder.register_chain_version(Chain::Kusama, 9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+		decoder.register_chain_version(Chain::Kusama, 2028, V12_METADATA_KUSAMA).unwrap();
+		decoder
+	};
+
+	let decoder_to_extract = decoder();
+	let shared = decoder_to_extract.metadata(9110).unwrap();
+	let current = decoder_to_extract.into_metadata_map();
+	let mut versions: Vec<_> = current.keys().cloned().collect();
+	versions.sort_by_key(|(chain, version)| (chain.to_string(), *version));
+	assert_eq!(versions, [(Chain::Kusama, 9110), (Chain::Polkadot, 9110), (Chain::Polkadot, 9111)]);
+	assert!(std::sync::Arc::ptr_eq(&current[&(Chain::Polkadot, 9110)], &shared));
+	assert!(!std::sync::Arc::ptr_eq(&current[&(Chain::Kusama, 9110)], &shared));
+
+	// Unsigned System.remark(b"hi") decodes with the extracted metadata.
+	let ext = vec![4u8, 0, 1, 8, b'h', b'i'].encode();
+	let ext = desub_current::decoder::decode_extrinsic(&current[&(Chain::Polkadot, 9111)], &mut &*ext).unwrap();
+	assert_eq!(ext.call_data.ty.name, "remark");
+
+	// Legacy metadata of every chain is handed back too.
+	let legacy = decoder().into_legacy_metadata_map();
+	let mut versions: Vec<_> = legacy.keys().cloned().collect();
+	versions.sort_by_key(|(chain, version)| (chain.to_string(), *version));
+	assert_eq!(versions, [(Chain::Kusama, 2028), (Chain::Polkadot, 2027)]);
+	assert!(legacy[&(Chain::Kusama, 2028)].modules().count() > 0);
+}
+
+#[test]
+fn registered_metadata_can_be_validated() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	assert!(decoder.validate_metadata(9110).is_ok());
+	assert!(matches!(decoder.validate_metadata(2027), Err(Error::LegacyMetadata(2027))));
+	assert!(matches!(decoder.validate_metadata(1), Err(Error::SpecVersionNotFound(1))));
+}
+
+#[test]
+fn registered_versions_can_be_described() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(9110, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(2027, V12_METADATA_KUSAMA).unwrap();
+
+	let RuntimeMetadataPrefixed(_, RuntimeMetadata::V14(v14)) =
+		RuntimeMetadataPrefixed::decode(&mut &*V14_METADATA_POLKADOT_SCALE).unwrap()
+	else {
+		panic!("expected V14 metadata");
+	};
+	assert_eq!(
+		decoder.describe_version(9110),
+		Some(VersionInfo {
+			kind: MetadataKind::Current,
+			extrinsic_version: Some(v14.extrinsic.version),
+			pallet_count: v14.pallets.len()
+		})
+	);
+	let legacy = decoder.describe_version(2027).unwrap();
+	assert_eq!(legacy.kind, MetadataKind::Legacy);
+	assert_eq!(legacy.extrinsic_version, Some(4));
+	assert!(legacy.pallet_count > 0);
+	assert_eq!(decoder.describe_version(1), None);
+}
+
+/// Re-encode the V14 Polkadot metadata as V15, which carries the same pallets and types.
+fn v15_metadata_polkadot() -> Vec<u8> {
+	RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V15(v14_as_v15(V14_METADATA_POLKADOT_SCALE))).encode()
+}
+
+#[test]
+fn v15_metadata_decodes_like_v14() {
+	let mut decoder = Decoder::with_custom_types(NoTypes, Chain::Polkadot);
+	decoder.register_version(14, V14_METADATA_POLKADOT_SCALE).unwrap();
+	decoder.register_version(15, &v15_metadata_polkadot()).unwrap();
+	assert!(decoder.metadata(15).is_some(), "V15 metadata is handled by the current decoder");
+
+	// Signed Balances.transfer (amount: 12345).
+	let ext = hex::decode("31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0").unwrap();
+	let mut data = Compact(1u32).encode();
+	data.extend(ext);
+
+	let v14 = decoder.decode_extrinsics(14, &data).unwrap();
+	let v15 = decoder.decode_extrinsics(15, &data).unwrap();
+	assert_eq!(v15[0]["call_data"]["pallet_name"], "Balances");
+	assert_eq!(v15, v14);
+}